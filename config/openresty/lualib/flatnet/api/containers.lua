@@ -6,6 +6,10 @@
 --   GET    /api/containers/:id     - Get container by ID
 --   POST   /api/containers         - Register container
 --   DELETE /api/containers/:id     - Deregister container
+--
+-- Requests from flatnet-cni carry an X-Flatnet-Operation-ID header. It is
+-- logged as "op=<id>" and stored with the container, so one container's
+-- CNI and Gateway log lines can be found together (flatnet logs --op <id>).
 
 local cjson = require("cjson.safe")
 local registry = require("flatnet.registry")
@@ -41,6 +45,20 @@ local function get_container_id()
     return id
 end
 
+-- Helper: Get the CNI operation ID of this request
+-- Peers forward it in the body (opId) since their requests carry no header
+local function get_operation_id(data)
+    local op_id = ngx.req.get_headers()["X-Flatnet-Operation-ID"]
+    if type(op_id) ~= "string" and data then
+        op_id = data.opId
+    end
+    -- Only accept short alphanumeric IDs (they are written to the log)
+    if type(op_id) ~= "string" or #op_id > 64 or not op_id:match("^[a-zA-Z0-9-]+$") then
+        return nil
+    end
+    return op_id
+end
+
 -- Helper: Read JSON body
 local function read_json_body()
     ngx.req.read_body()
@@ -101,6 +119,8 @@ local function handle_register()
         return json_response(400, {error = "missing required fields: id, ip"})
     end
 
+    local op_id = get_operation_id(data)
+
     -- Register container
     -- Note: hostId defaults to 1 (single-host default) if not provided
     local ok, err = registry.register({
@@ -109,13 +129,18 @@ local function handle_register()
         hostname = data.hostname,
         ports = data.ports,
         hostId = data.hostId or 1,
-        createdAt = data.createdAt
+        createdAt = data.createdAt,
+        opId = op_id
     })
 
     if not ok then
+        ngx.log(ngx.ERR, "flatnet api: op=", op_id or "-", " registration of container ", data.id, " failed: ", err)
         return json_response(500, {error = "registration failed", details = err})
     end
 
+    ngx.log(ngx.NOTICE, "flatnet api: op=", op_id or "-", " registered container ", data.id,
+        " ip=", data.ip, " host=", data.hostId or 1)
+
     -- Notify peers asynchronously to avoid blocking the response
     local peer_host_id = ngx.req.get_headers()["X-Flatnet-Host-ID"]
     if not peer_host_id then
@@ -127,7 +152,8 @@ local function handle_register()
             hostname = data.hostname,
             ports = data.ports,
             hostId = data.hostId or 1,
-            createdAt = data.createdAt
+            createdAt = data.createdAt,
+            opId = op_id
         }
         local ok, err = ngx.timer.at(0, function(premature)
             if premature then return end
@@ -144,17 +170,24 @@ end
 
 -- DELETE /api/containers/:id - Deregister container
 local function handle_delete(container_id)
+    local op_id = get_operation_id(nil)
     local container = registry.get(container_id)
     if not container then
+        ngx.log(ngx.NOTICE, "flatnet api: op=", op_id or "-", " container ", container_id, " already deregistered")
         -- Idempotent: return success even if not found
         return json_response(200, {success = true, id = container_id, note = "already deleted"})
     end
 
     local ok, err = registry.deregister(container_id)
     if not ok then
+        ngx.log(ngx.ERR, "flatnet api: op=", op_id or "-", " deregistration of container ", container_id, " failed: ", err)
         return json_response(500, {error = "deregistration failed", details = err})
     end
 
+    -- Also log the ADD that registered it, linking the two operations
+    ngx.log(ngx.NOTICE, "flatnet api: op=", op_id or "-", " deregistered container ", container_id,
+        " (registered by op=", container.opId or "-", ")")
+
     -- Notify peers asynchronously
     local peer_host_id = ngx.req.get_headers()["X-Flatnet-Host-ID"]
     if not peer_host_id then
@@ -162,7 +195,7 @@ local function handle_delete(container_id)
         local ok, err = ngx.timer.at(0, function(premature)
             if premature then return end
             local sync_module = require("flatnet.sync")
-            sync_module.notify_deletion(delete_id)
+            sync_module.notify_deletion(delete_id, op_id)
         end)
         if not ok then
             ngx.log(ngx.WARN, "flatnet api: failed to schedule deletion notification: ", err)
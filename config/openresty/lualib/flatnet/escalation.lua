@@ -15,6 +15,7 @@
 
 local _M = {}
 local cjson = require("cjson.safe")
+local metrics = require("flatnet.metrics")
 
 -- Shared dict for escalation state (configured in nginx.conf)
 local SHARED_DICT_NAME = "flatnet_escalation"
@@ -381,6 +382,7 @@ function _M.attempt_p2p(ip, target_gateway)
         attempt_started = ngx.now()
     })
 
+    metrics.record_escalation("attempt")
     ngx.log(ngx.INFO, "flatnet escalation: starting P2P attempt for ", ip, " via ", target_gateway or "direct")
 
     return true
@@ -407,6 +409,7 @@ function _M.activate_p2p(ip)
         return false, err
     end
 
+    metrics.record_escalation("success")
     ngx.log(ngx.INFO, "flatnet escalation: P2P activated for ", ip)
     return true
 end
@@ -430,6 +433,10 @@ function _M.fallback_to_gateway(ip, reason)
         return false, err
     end
 
+    if current_state == _M.STATE.P2P_ATTEMPTING then
+        metrics.record_escalation("failure")
+    end
+
     ngx.log(ngx.WARN, "flatnet escalation: fallback to gateway for ", ip,
         ", reason: ", reason or "unknown",
         ", retry_count: ", retry_count)
@@ -446,11 +453,17 @@ function _M.reset_to_gateway_only(ip, reason)
         return false, "ip is required"
     end
 
+    local current_state = _M.get_state(ip)
+
     local ok, err = _M.set_state(ip, _M.STATE.GATEWAY_ONLY)
     if not ok then
         return false, err
     end
 
+    if current_state == _M.STATE.P2P_ATTEMPTING then
+        metrics.record_escalation("failure")
+    end
+
     -- Clear metadata
     local dict = get_shared_dict()
     if dict then
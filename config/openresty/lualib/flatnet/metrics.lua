@@ -5,6 +5,7 @@
 -- - Request counters (total, by status code)
 -- - Response time histogram
 -- - Active connections gauge
+-- - P2P escalation attempts and results
 --
 -- Usage:
 --   local metrics = require("flatnet.metrics")
@@ -12,6 +13,7 @@
 --   metrics.record_request(status, duration)  -- Record request
 --   metrics.inc_connections()         -- Increment active connections
 --   metrics.dec_connections()         -- Decrement active connections
+--   metrics.record_escalation(result) -- Record "attempt", "success" or "failure"
 --   local output = metrics.export()   -- Export Prometheus format
 
 local _M = {}
@@ -32,6 +34,10 @@ local KEY_DURATION_SUM = "duration_sum"
 local KEY_DURATION_COUNT = "duration_count"
 local KEY_DURATION_BUCKET = "duration_bucket_"
 local KEY_ACTIVE_CONNECTIONS = "active_connections"
+local KEY_ESCALATION = "escalation_"
+
+-- Escalation results exported as flatnet_escalation_results_total{result=...}
+local ESCALATION_RESULTS = {"success", "failure"}
 
 -- Get shared dict with error handling
 local function get_shared_dict()
@@ -55,6 +61,10 @@ function _M.init()
     dict:set(KEY_DURATION_SUM, 0)
     dict:set(KEY_DURATION_COUNT, 0)
     dict:set(KEY_ACTIVE_CONNECTIONS, 0)
+    dict:set(KEY_ESCALATION .. "attempt", 0)
+    for _, result in ipairs(ESCALATION_RESULTS) do
+        dict:set(KEY_ESCALATION .. result, 0)
+    end
 
     -- Initialize histogram buckets
     for _, bucket in ipairs(DURATION_BUCKETS) do
@@ -128,6 +138,18 @@ function _M.dec_connections()
     end
 end
 
+-- Record a P2P escalation event
+-- Called from the escalation module, which also runs where metrics are not
+-- configured, so a missing shared dict is ignored silently.
+-- @param result string: "attempt", "success" or "failure"
+function _M.record_escalation(result)
+    local dict = ngx.shared[SHARED_DICT_NAME]
+    if not dict then
+        return
+    end
+    dict:incr(KEY_ESCALATION .. result, 1, 0)
+end
+
 -- Get current active connections
 function _M.get_connections()
     local dict = get_shared_dict()
@@ -231,6 +253,21 @@ function _M.export()
     table.insert(output, string.format("flatnet_http_request_duration_seconds_count %d", duration_count))
     table.insert(output, "")
 
+    -- P2P escalation (counters)
+    local attempts = dict:get(KEY_ESCALATION .. "attempt") or 0
+    table.insert(output, "# HELP flatnet_escalation_attempts_total P2P escalation attempts")
+    table.insert(output, "# TYPE flatnet_escalation_attempts_total counter")
+    table.insert(output, string.format("flatnet_escalation_attempts_total %d", attempts))
+    table.insert(output, "")
+
+    table.insert(output, "# HELP flatnet_escalation_results_total Finished P2P escalation attempts by result")
+    table.insert(output, "# TYPE flatnet_escalation_results_total counter")
+    for _, result in ipairs(ESCALATION_RESULTS) do
+        local count = dict:get(KEY_ESCALATION .. result) or 0
+        table.insert(output, string.format('flatnet_escalation_results_total{result="%s"} %d', result, count))
+    end
+    table.insert(output, "")
+
     return table.concat(output, "\n") .. "\n"
 end
 
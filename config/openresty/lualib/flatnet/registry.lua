@@ -86,7 +86,7 @@ local function remove_from_list(list, id)
 end
 
 -- Register a container
--- @param info table: Container info {id, ip, hostname, ports, hostId, createdAt}
+-- @param info table: Container info {id, ip, hostname, ports, hostId, createdAt, opId}
 -- @param ttl number: Optional TTL in seconds (default: 300)
 -- @return boolean, string: success, error message
 function _M.register(info, ttl)
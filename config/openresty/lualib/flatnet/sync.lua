@@ -6,12 +6,24 @@
 --   sync.configure(peer_endpoints)
 --   sync.pull_from_peers()
 --   sync.push_to_peers(container_info)
+--   sync.peer_status()
 
 local _M = {}
 local cjson = require("cjson.safe")
 local http = require("resty.http")
 local registry = require("flatnet.registry")
 
+-- Shared dict for peer list and per-peer sync state (shared with registry)
+local SHARED_DICT_NAME = "flatnet_containers"
+
+-- Shared dict keys
+local PEER_LIST_KEY = "sync:peers"
+local PEER_STATE_PREFIX = "sync:peer:"
+local LAST_SYNC_KEY = "sync:last_sync"
+
+-- Worker flag to prevent multiple timers
+local timer_started = false
+
 -- Configuration
 local config = {
     -- List of peer Gateway endpoints
@@ -56,33 +68,158 @@ function _M.get_config()
     return config
 end
 
+-- Get shared dict (nil if not configured)
+local function get_shared_dict()
+    return ngx.shared[SHARED_DICT_NAME]
+end
+
+-- Get the current peer list
+-- Peers added or removed at runtime are kept in the shared dict so that
+-- every worker sees the same list; otherwise the configured list is used.
+-- @return table: Array of peer endpoint URLs
+function _M.get_peers()
+    local dict = get_shared_dict()
+    if dict then
+        local data = dict:get(PEER_LIST_KEY)
+        if data then
+            local peers = cjson.decode(data)
+            if peers then
+                return peers
+            end
+        end
+    end
+    return config.peers
+end
+
+-- Store the peer list in the shared dict
+-- @param peers table: Array of peer endpoint URLs
+local function save_peers(peers)
+    config.peers = peers
+
+    local dict = get_shared_dict()
+    if not dict then
+        return
+    end
+
+    -- Encode an empty list as [] rather than {}
+    local data = #peers == 0 and "[]" or cjson.encode(peers)
+    local ok, err = dict:set(PEER_LIST_KEY, data, 0)
+    if not ok then
+        ngx.log(ngx.WARN, "flatnet sync: failed to store peer list: ", err)
+    end
+end
+
 -- Add a peer endpoint
 -- @param endpoint string: Peer endpoint URL (e.g., "http://10.100.2.1:8080")
+-- @return boolean: True if the peer was added, false if it already existed
 function _M.add_peer(endpoint)
-    if not endpoint then return end
+    if not endpoint then return false end
+
+    local peers = _M.get_peers()
 
     -- Check for duplicates
-    for _, peer in ipairs(config.peers) do
+    for _, peer in ipairs(peers) do
         if peer == endpoint then
-            return
+            return false
         end
     end
 
-    table.insert(config.peers, endpoint)
+    local updated = {}
+    for _, peer in ipairs(peers) do
+        table.insert(updated, peer)
+    end
+    table.insert(updated, endpoint)
+    save_peers(updated)
+
     ngx.log(ngx.INFO, "flatnet sync: added peer ", endpoint)
+
+    -- Sync may have been disabled at startup because no peers were configured
+    _M.start_sync_timer()
+    return true
 end
 
 -- Remove a peer endpoint
 -- @param endpoint string: Peer endpoint URL
+-- @return boolean: True if the peer was removed
 function _M.remove_peer(endpoint)
-    for i, peer in ipairs(config.peers) do
+    local peers = _M.get_peers()
+    local updated = {}
+    local found = false
+
+    for _, peer in ipairs(peers) do
         if peer == endpoint then
-            table.remove(config.peers, i)
-            ngx.log(ngx.INFO, "flatnet sync: removed peer ", endpoint)
-            return true
+            found = true
+        else
+            table.insert(updated, peer)
         end
     end
-    return false
+
+    if not found then
+        return false
+    end
+
+    save_peers(updated)
+
+    local dict = get_shared_dict()
+    if dict then
+        dict:delete(PEER_STATE_PREFIX .. endpoint)
+    end
+
+    ngx.log(ngx.INFO, "flatnet sync: removed peer ", endpoint)
+    return true
+end
+
+-- Get the recorded sync state of a peer
+-- @param endpoint string: Peer endpoint URL
+-- @return table: State {endpoint, last_attempt, last_success, last_error, container_count, consecutive_failures}
+local function get_peer_state(endpoint)
+    local dict = get_shared_dict()
+    if dict then
+        local data = dict:get(PEER_STATE_PREFIX .. endpoint)
+        if data then
+            local state = cjson.decode(data)
+            if state then
+                return state
+            end
+        end
+    end
+
+    return {
+        endpoint = endpoint,
+        consecutive_failures = 0,
+        container_count = 0
+    }
+end
+
+-- Record the result of a pull from a peer
+-- @param endpoint string: Peer endpoint URL
+-- @param ok boolean: Whether the pull succeeded
+-- @param container_count number: Containers registered from the peer
+-- @param err string: Error message if the pull failed
+local function record_peer_result(endpoint, ok, container_count, err)
+    local dict = get_shared_dict()
+    if not dict then
+        return
+    end
+
+    local state = get_peer_state(endpoint)
+    local now = ngx.time()
+
+    state.last_attempt = now
+    if ok then
+        state.last_success = now
+        state.last_error = nil
+        state.container_count = container_count
+        state.consecutive_failures = 0
+    else
+        state.last_error = err
+        state.consecutive_failures = (state.consecutive_failures or 0) + 1
+    end
+
+    local set_ok, set_err = dict:set(PEER_STATE_PREFIX .. endpoint, cjson.encode(state), 0)
+    if not set_ok then
+        ngx.log(ngx.WARN, "flatnet sync: failed to store state for peer ", endpoint, ": ", set_err)
+    end
 end
 
 -- Pull container info from a single peer
@@ -119,30 +256,52 @@ local function pull_from_peer(endpoint)
 end
 
 -- Pull container info from all peers
+-- @param only_endpoint string: Optional endpoint to restrict the pull to a single peer
 -- @return number, number: success count, failure count
-function _M.pull_from_peers()
+function _M.pull_from_peers(only_endpoint)
     local success_count = 0
     local failure_count = 0
     local total_containers = 0
 
-    for _, endpoint in ipairs(config.peers) do
-        local containers, err = pull_from_peer(endpoint)
-        if containers then
-            -- Register all containers from this peer
-            local registered, failed = registry.bulk_register(containers, config.sync_ttl)
-            total_containers = total_containers + registered
-            success_count = success_count + 1
-            ngx.log(ngx.INFO, "flatnet sync: pulled ", registered, " containers from ", endpoint)
-        else
-            failure_count = failure_count + 1
-            ngx.log(ngx.WARN, "flatnet sync: failed to pull from ", endpoint, ": ", err)
+    for _, endpoint in ipairs(_M.get_peers()) do
+        if not only_endpoint or only_endpoint == endpoint then
+            local containers, err = pull_from_peer(endpoint)
+            if containers then
+                -- Register all containers from this peer
+                local registered, failed = registry.bulk_register(containers, config.sync_ttl)
+                total_containers = total_containers + registered
+                success_count = success_count + 1
+                record_peer_result(endpoint, true, registered)
+                ngx.log(ngx.INFO, "flatnet sync: pulled ", registered, " containers from ", endpoint)
+            else
+                failure_count = failure_count + 1
+                record_peer_result(endpoint, false, 0, err)
+                ngx.log(ngx.WARN, "flatnet sync: failed to pull from ", endpoint, ": ", err)
+            end
         end
     end
 
+    local dict = get_shared_dict()
+    if dict then
+        dict:set(LAST_SYNC_KEY, ngx.utctime(), 0)
+    end
+
     ngx.log(ngx.INFO, "flatnet sync: pull complete, ", total_containers, " containers from ", success_count, " peers")
     return success_count, failure_count
 end
 
+-- Check whether an endpoint is a configured peer
+-- @param endpoint string: Peer endpoint URL
+-- @return boolean
+function _M.has_peer(endpoint)
+    for _, peer in ipairs(_M.get_peers()) do
+        if peer == endpoint then
+            return true
+        end
+    end
+    return false
+end
+
 -- Push container info to a single peer
 -- @param endpoint string: Peer endpoint URL
 -- @param container_info table: Container info to push
@@ -185,7 +344,7 @@ function _M.push_to_peers(container_info)
     local success_count = 0
     local failure_count = 0
 
-    for _, endpoint in ipairs(config.peers) do
+    for _, endpoint in ipairs(_M.get_peers()) do
         local ok, err = push_to_peer(endpoint, container_info)
         if ok then
             success_count = success_count + 1
@@ -200,12 +359,13 @@ end
 
 -- Notify peers of container deletion
 -- @param container_id string: Container ID to delete
+-- @param op_id string: Optional CNI operation ID of the deletion
 -- @return number, number: success count, failure count
-function _M.notify_deletion(container_id)
+function _M.notify_deletion(container_id, op_id)
     local success_count = 0
     local failure_count = 0
 
-    for _, endpoint in ipairs(config.peers) do
+    for _, endpoint in ipairs(_M.get_peers()) do
         local httpc = http.new()
         httpc:set_timeout(config.timeout)
 
@@ -214,7 +374,9 @@ function _M.notify_deletion(container_id)
         local res, err = httpc:request_uri(uri, {
             method = "DELETE",
             headers = {
-                ["X-Flatnet-Host-ID"] = config.host_id or "unknown"
+                ["X-Flatnet-Host-ID"] = config.host_id or "unknown",
+                -- Lets peers log the deletion under the same CNI operation
+                ["X-Flatnet-Operation-ID"] = op_id
             }
         })
 
@@ -255,7 +417,12 @@ end
 -- Start periodic sync timer
 -- Should be called from init_worker_by_lua
 function _M.start_sync_timer()
-    if #config.peers == 0 then
+    if timer_started then
+        return
+    end
+
+    local peers = _M.get_peers()
+    if #peers == 0 then
         ngx.log(ngx.INFO, "flatnet sync: no peers configured, sync disabled")
         return
     end
@@ -266,15 +433,31 @@ function _M.start_sync_timer()
         return
     end
 
-    ngx.log(ngx.INFO, "flatnet sync: started periodic sync, interval=", config.sync_interval, "s, peers=", #config.peers)
+    timer_started = true
+    ngx.log(ngx.INFO, "flatnet sync: started periodic sync, interval=", config.sync_interval, "s, peers=", #peers)
+end
+
+-- Get per-peer sync status
+-- @return table: Array of peer states {endpoint, last_attempt, last_success, last_error, container_count, consecutive_failures}
+function _M.peer_status()
+    local result = {}
+    for _, endpoint in ipairs(_M.get_peers()) do
+        table.insert(result, get_peer_state(endpoint))
+    end
+    return result
 end
 
 -- Get sync status
 function _M.status()
+    local peers = _M.get_peers()
+    local dict = get_shared_dict()
+
     return {
+        enabled = #peers > 0,
         host_id = config.host_id,
-        peer_count = #config.peers,
-        peers = config.peers,
+        peer_count = #peers,
+        peers = peers,
+        last_sync = dict and dict:get(LAST_SYNC_KEY) or nil,
         sync_interval = config.sync_interval,
         sync_ttl = config.sync_ttl,
         local_containers = registry.count()
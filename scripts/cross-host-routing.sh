@@ -23,6 +23,9 @@
 #
 #   # Remove routes
 #   ./cross-host-routing.sh --del --peers "2,3" --gateway 172.17.0.1
+#
+# `flatnet routes sync --watch` derives the peers from the Gateway registry
+# and keeps these routes up to date automatically.
 
 set -e
 
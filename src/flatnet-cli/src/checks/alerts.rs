@@ -0,0 +1,118 @@
+//! Alert checks
+//!
+//! Reports firing Flatnet alerts from Alertmanager: critical alerts fail,
+//! other severities warn, and silenced alerts pass.
+
+use super::CheckResult;
+use crate::clients::alertmanager::{Alert, AlertmanagerClient};
+use crate::config::Config;
+use std::time::Duration;
+
+const CATEGORY: &str = "Alerts";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run all alert checks
+pub async fn run_checks(config: &Config) -> Vec<CheckResult> {
+    let url = &config.monitoring.alertmanager_url;
+    let client = match AlertmanagerClient::new(url, CHECK_TIMEOUT) {
+        Ok(c) => c,
+        Err(e) => {
+            return vec![CheckResult::fail(
+                CATEGORY,
+                "Alertmanager",
+                format!("Failed to create HTTP client: {}", e),
+                "Check system configuration",
+            )];
+        }
+    };
+
+    match client.alerts().await {
+        Ok(alerts) => alert_results(&alerts),
+        Err(e) => vec![CheckResult::warning(
+            CATEGORY,
+            "Alertmanager",
+            format!("Cannot list alerts: {}", e),
+            "Start monitoring stack: podman-compose up -d",
+        )],
+    }
+}
+
+/// Turn alerts into check results (one passing result when none fire)
+fn alert_results(alerts: &[Alert]) -> Vec<CheckResult> {
+    if alerts.is_empty() {
+        return vec![CheckResult::pass(
+            CATEGORY,
+            "Alertmanager",
+            "No active alerts",
+        )];
+    }
+
+    alerts
+        .iter()
+        .map(|alert| {
+            let message = format!("{} ({})", alert.summary(), alert.severity());
+            let suggestion = alert
+                .annotations
+                .get("runbook")
+                .cloned()
+                .unwrap_or_else(|| "See monitoring/README.md".to_string());
+
+            if alert.is_silenced() {
+                CheckResult::pass(CATEGORY, alert.name(), format!("{} - silenced", message))
+            } else if !alert.is_active() {
+                // Inhibited by a more severe alert, which is reported itself
+                CheckResult::pass(CATEGORY, alert.name(), format!("{} - inhibited", message))
+            } else if alert.severity() == "critical" {
+                CheckResult::fail(CATEGORY, alert.name(), message, suggestion)
+            } else {
+                CheckResult::warning(CATEGORY, alert.name(), message, suggestion)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::CheckStatus;
+
+    fn alert(name: &str, severity: &str, state: &str, silenced: bool) -> Alert {
+        serde_json::from_value(serde_json::json!({
+            "labels": {"alertname": name, "severity": severity, "component": "gateway"},
+            "annotations": {"summary": "summary", "runbook": "Check the Gateway"},
+            "startsAt": "2026-10-18T10:00:00Z",
+            "status": {
+                "state": state,
+                "silencedBy": if silenced { vec!["id"] } else { vec![] },
+                "inhibitedBy": []
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_alert_results() {
+        let results = alert_results(&[]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, CheckStatus::Pass);
+
+        let results = alert_results(&[
+            alert("GatewayDown", "critical", "active", false),
+            alert("HighLatency", "warning", "active", false),
+            alert("HighErrorRate", "critical", "suppressed", true),
+            alert("GatewayNoRequests", "warning", "suppressed", false),
+        ]);
+        let statuses: Vec<CheckStatus> = results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                CheckStatus::Fail,
+                CheckStatus::Warning,
+                CheckStatus::Pass,
+                CheckStatus::Pass
+            ]
+        );
+        assert_eq!(results[0].suggestion.as_deref(), Some("Check the Gateway"));
+        assert!(results[2].message.ends_with("silenced"));
+    }
+}
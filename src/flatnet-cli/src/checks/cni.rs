@@ -1,67 +1,669 @@
 //! CNI Plugin health checks
 //!
-//! Checks for CNI bridge and IPAM state.
+//! Checks for the CNI plugin installation, bridge, and IPAM state.
 
-use super::CheckResult;
-use crate::clients::gateway::GatewayClient;
+use super::cni_config::{self, PLUGIN_TYPE};
+use super::{CheckResult, Remediation};
+use crate::clients::cni_plugin::{CniPlugin, PluginError, ERR_INVALID_ENVIRONMENT};
+use crate::clients::gateway::{ContainerInfo, ContainerRegistration, GatewayClient};
+use crate::clients::ipam::{IpamState, IpamStore, IPAM_DIR};
+use crate::clients::netlink::{BridgeInfo, Link, NetlinkClient};
+use crate::clients::podman::{PodmanClient, PodmanContainer};
 use crate::config::Config;
-use std::process::Stdio;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 
 const CATEGORY: &str = "CNI Plugin";
+const BRIDGE_NAME: &str = "flatnet-br0";
+
+/// Directories where container runtimes look for CNI plugin binaries
+pub const CNI_BIN_DIRS: &[&str] = &["/opt/cni/bin", "/usr/libexec/cni", "/usr/lib/cni"];
+
+/// Directory where CNI network configurations are installed
+const CNI_CONF_DIR: &str = "/etc/cni/net.d";
+
+/// Host-side veth prefix used by flatnet-cni
+const HOST_VETH_PREFIX: &str = "fn-";
+
+/// Maximum interface name length (Linux IFNAMSIZ - 1)
+const MAX_IFNAME_LEN: usize = 15;
 
 /// Run all CNI checks
 pub async fn run_checks(config: &Config) -> Vec<CheckResult> {
     // Run CNI checks in parallel
-    let (bridge_check, ipam_check) = tokio::join!(check_cni_bridge(), check_ipam_state(config));
-
-    vec![bridge_check, ipam_check]
-}
-
-/// Check if the CNI bridge exists
-async fn check_cni_bridge() -> CheckResult {
-    // Check if flatnet bridge exists using ip command
-    let output = tokio::process::Command::new("ip")
-        .args(["link", "show", "flatnet-br0"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output()
-        .await;
-
-    match output {
-        Ok(output) if output.status.success() => {
-            // Bridge exists, try to get more info
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            if stdout.contains("state UP") {
-                CheckResult::pass(CATEGORY, "Bridge exists", "Bridge exists (flatnet-br0)")
-            } else if stdout.contains("state DOWN") {
-                CheckResult::warning(
+    let (bridge_checks, ipam_check, allocation_checks) = tokio::join!(
+        check_bridge(),
+        check_ipam_state(config),
+        check_allocations(config),
+    );
+
+    let mut results = vec![check_plugin_binary(), check_conflist()];
+    results.extend(bridge_checks);
+    results.push(ipam_check);
+    results.push(check_ipam_writable());
+    results.extend(allocation_checks);
+    results
+}
+
+/// Find the installed flatnet-cni binary
+pub fn find_plugin_binary() -> Option<PathBuf> {
+    CNI_BIN_DIRS
+        .iter()
+        .map(|dir| Path::new(dir).join(PLUGIN_TYPE))
+        .find(|path| path.exists())
+}
+
+/// Check that the flatnet-cni binary is installed in a CNI plugin directory
+fn check_plugin_binary() -> CheckResult {
+    match find_plugin_binary() {
+        Some(path) => {
+            let executable = fs::metadata(&path)
+                .map(|m| m.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false);
+            if executable {
+                CheckResult::pass(
                     CATEGORY,
-                    "Bridge exists",
-                    "Bridge exists but is DOWN (flatnet-br0)",
-                    "Run: sudo ip link set flatnet-br0 up",
+                    "Plugin installed",
+                    format!("Plugin installed ({})", path.display()),
                 )
             } else {
-                CheckResult::pass(CATEGORY, "Bridge exists", "Bridge exists (flatnet-br0)")
+                CheckResult::fail(
+                    CATEGORY,
+                    "Plugin installed",
+                    format!("Plugin is not executable ({})", path.display()),
+                    format!("Run: sudo chmod +x {}", path.display()),
+                )
             }
         }
+        None => CheckResult::fail(
+            CATEGORY,
+            "Plugin installed",
+            format!("flatnet plugin not found in {}", CNI_BIN_DIRS.join(", ")),
+            "Install it: sudo cp target/release/flatnet /opt/cni/bin/",
+        ),
+    }
+}
+
+/// Check that an installed conflist configures the flatnet plugin and parses
+fn check_conflist() -> CheckResult {
+    let entries = match fs::read_dir(CNI_CONF_DIR) {
+        Ok(entries) => entries,
+        Err(e) => {
+            return CheckResult::fail(
+                CATEGORY,
+                "Configuration valid",
+                format!("Could not read {}: {}", CNI_CONF_DIR, e),
+                "Create the CNI configuration: see config/cni/flatnet.conflist",
+            )
+        }
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "conflist"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+
+        match cni_config::flatnet_plugin_config(&content) {
+            Ok(Some(config)) => return validate_config(&path, &config),
+            Ok(None) => continue,
+            // Only report parse errors for files that mention the plugin
+            Err(e) if content.contains(&format!("\"{}\"", PLUGIN_TYPE)) => {
+                return CheckResult::fail(
+                    CATEGORY,
+                    "Configuration valid",
+                    format!("Invalid configuration {}: {}", path.display(), e),
+                    "Compare with config/cni/flatnet.conflist",
+                )
+            }
+            Err(_) => continue,
+        }
+    }
+
+    CheckResult::fail(
+        CATEGORY,
+        "Configuration valid",
+        format!("No conflist with a flatnet plugin in {}", CNI_CONF_DIR),
+        "Install it: sudo cp config/cni/flatnet.conflist /etc/cni/net.d/",
+    )
+}
+
+/// Validate the flatnet plugin config of a conflist with the plugin itself
+fn validate_config(path: &Path, config: &serde_json::Value) -> CheckResult {
+    let Some(binary) = find_plugin_binary() else {
+        return CheckResult::warning(
+            CATEGORY,
+            "Configuration valid",
+            format!("Cannot validate {} without the plugin", path.display()),
+            "Install the plugin: sudo flatnet upgrade --component cni",
+        );
+    };
+
+    let error = match CniPlugin::new(&binary).check_config(config) {
+        Ok(()) => {
+            let network = config.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+            return CheckResult::pass(
+                CATEGORY,
+                "Configuration valid",
+                format!(
+                    "Configuration valid (network '{}', {})",
+                    network,
+                    path.display()
+                ),
+            );
+        }
+        Err(e) => e,
+    };
+
+    match error.downcast_ref::<PluginError>() {
+        Some(e) if e.code == ERR_INVALID_ENVIRONMENT => CheckResult::warning(
+            CATEGORY,
+            "Configuration valid",
+            format!(
+                "Cannot validate {}: the plugin predates --check-config",
+                path.display()
+            ),
+            "Upgrade the plugin: sudo flatnet upgrade --component cni",
+        ),
+        Some(e) => CheckResult::fail(
+            CATEGORY,
+            "Configuration valid",
+            format!("Invalid configuration {}: {}", path.display(), e),
+            "Compare with config/cni/flatnet.conflist",
+        ),
+        None => CheckResult::warning(
+            CATEGORY,
+            "Configuration valid",
+            format!("Cannot validate {}: {:#}", path.display(), error),
+            format!("Check that the plugin runs: {} --version", binary.display()),
+        ),
+    }
+}
+
+/// Installed conflists, in the order the runtime loads them
+fn conflist_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<_> = fs::read_dir(CNI_CONF_DIR)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "conflist"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Host ID configured in the installed flatnet conflist, if any
+pub fn conflist_host_id() -> Option<u8> {
+    conflist_paths().iter().find_map(|path| {
+        let content = fs::read_to_string(path).ok()?;
+        cni_config::host_id(&cni_config::flatnet_plugin_config(&content).ok()??)
+    })
+}
+
+/// Network config the runtime passes to the installed flatnet plugin
+pub fn flatnet_network_config() -> Option<serde_json::Value> {
+    conflist_paths().iter().find_map(|path| {
+        let content = fs::read_to_string(path).ok()?;
+        cni_config::flatnet_plugin_config(&content).ok()?
+    })
+}
+
+/// IPAM directory of the installed plugin (`ipam.dataDir`, else the default)
+pub fn ipam_dir() -> PathBuf {
+    flatnet_network_config()
+        .as_ref()
+        .and_then(cni_config::data_dir)
+        .map_or_else(|| PathBuf::from(IPAM_DIR), PathBuf::from)
+}
+
+/// Check that the IPAM directory is writable by the plugin (root)
+fn check_ipam_writable() -> CheckResult {
+    let store = IpamStore::new();
+    let dir = store.dir();
+
+    let metadata = match fs::metadata(dir) {
+        Ok(m) if m.is_dir() => m,
         Ok(_) => {
-            // Bridge doesn't exist - this is OK if no containers are running
-            CheckResult::warning(
+            return CheckResult::fail(
                 CATEGORY,
-                "Bridge exists",
-                "Bridge not found (flatnet-br0)",
-                "Bridge will be created when first container starts",
+                "IPAM writable",
+                format!("{} is not a directory", dir.display()),
+                format!("Remove it: sudo rm {}", dir.display()),
             )
         }
-        Err(e) => CheckResult::warning(
+        Err(_) => {
+            return CheckResult::warning(
+                CATEGORY,
+                "IPAM writable",
+                format!("{} does not exist", dir.display()),
+                format!(
+                    "It is created when the first container starts; if that fails, run: sudo mkdir -p {}",
+                    dir.display()
+                ),
+            )
+        }
+    };
+
+    if is_root() {
+        // Catch read-only mounts as well as permission bits
+        let probe = dir.join(".doctor-write-test");
+        let writable = fs::write(&probe, b"").is_ok();
+        let _ = fs::remove_file(&probe);
+
+        if writable {
+            CheckResult::pass(CATEGORY, "IPAM writable", format!("{} is writable", dir.display()))
+        } else {
+            CheckResult::fail(
+                CATEGORY,
+                "IPAM writable",
+                format!("{} is not writable", dir.display()),
+                "Check the filesystem is mounted read-write",
+            )
+        }
+    } else if metadata.permissions().mode() & 0o200 != 0 {
+        CheckResult::pass(
+            CATEGORY,
+            "IPAM writable",
+            format!("{} is owner-writable", dir.display()),
+        )
+    } else {
+        CheckResult::fail(
+            CATEGORY,
+            "IPAM writable",
+            format!("{} is not writable by its owner", dir.display()),
+            format!("Run: sudo chmod u+w {}", dir.display()),
+        )
+    }
+}
+
+/// Check the bridge, its address, and its ports via netlink
+async fn check_bridge() -> Vec<CheckResult> {
+    let client = match NetlinkClient::new() {
+        Ok(c) => c,
+        Err(e) => {
+            return vec![CheckResult::warning(
+                CATEGORY,
+                "Bridge exists",
+                format!("Could not check bridge: {:#}", e),
+                "Check that netlink is available in this environment",
+            )]
+        }
+    };
+
+    let (bridge, links) = tokio::join!(client.bridge(BRIDGE_NAME), client.links());
+    let bridge = match bridge {
+        Ok(b) => b,
+        Err(e) => {
+            return vec![CheckResult::warning(
+                CATEGORY,
+                "Bridge exists",
+                format!("Could not check bridge: {:#}", e),
+                "Check that netlink is available in this environment",
+            )]
+        }
+    };
+
+    let ipam_state = IpamStore::new().load().ok();
+    let mut results = Vec::new();
+
+    match bridge {
+        Some(ref bridge) => {
+            results.push(check_bridge_state(bridge));
+            results.push(check_bridge_address(bridge, ipam_state.as_ref()));
+            if let Some(ref state) = ipam_state {
+                results.push(check_bridge_ports(bridge, state));
+            }
+        }
+        // Bridge doesn't exist - this is OK if no containers are running
+        None => results.push(CheckResult::warning(
             CATEGORY,
             "Bridge exists",
-            format!("Could not check bridge: {}", e),
-            "Ensure 'ip' command is available (iproute2 package)",
+            "Bridge not found (flatnet-br0)",
+            "Bridge will be created when first container starts",
+        )),
+    }
+
+    if let Ok(links) = links {
+        let container_ids: HashSet<String> = ipam_state
+            .map(|s| s.allocations.into_keys().collect())
+            .unwrap_or_default();
+        results.push(check_leftover_veths(&links, &container_ids));
+    }
+
+    results
+}
+
+/// Check that the bridge is up
+fn check_bridge_state(bridge: &BridgeInfo) -> CheckResult {
+    if !bridge.link.admin_up {
+        return CheckResult::warning(
+            CATEGORY,
+            "Bridge exists",
+            "Bridge exists but is DOWN (flatnet-br0)",
+            "Run: sudo ip link set flatnet-br0 up",
+        )
+        .with_remediation(Remediation::BridgeUp {
+            bridge: BRIDGE_NAME.to_string(),
+        });
+    }
+
+    // A bridge without active ports has no carrier; that is not a fault
+    let active = bridge.ports.iter().filter(|p| p.is_oper_up()).count();
+    CheckResult::pass(
+        CATEGORY,
+        "Bridge exists",
+        format!(
+            "Bridge exists (flatnet-br0, {} ports, {} up)",
+            bridge.ports.len(),
+            active
+        ),
+    )
+}
+
+/// Check that the bridge carries its gateway address
+fn check_bridge_address(bridge: &BridgeInfo, ipam_state: Option<&IpamState>) -> CheckResult {
+    if let Some(address) = bridge.addresses.first() {
+        return CheckResult::pass(
+            CATEGORY,
+            "Bridge address",
+            format!("Bridge address {} ({})", address, BRIDGE_NAME),
+        );
+    }
+
+    // The IPAM state records the gateway address the CNI plugin assigned
+    let expected = ipam_state.and_then(|s| s.gateway_cidr());
+    let message = format!("Bridge has no IPv4 address ({})", BRIDGE_NAME);
+
+    match expected {
+        Some(address) => CheckResult::warning(
+            CATEGORY,
+            "Bridge address",
+            message,
+            format!("Run: sudo ip addr add {} dev {}", address, BRIDGE_NAME),
+        )
+        .with_remediation(Remediation::BridgeAddress {
+            bridge: BRIDGE_NAME.to_string(),
+            address,
+        }),
+        None => CheckResult::warning(
+            CATEGORY,
+            "Bridge address",
+            message,
+            "Restart a container on the flatnet network to recreate the address",
         ),
     }
 }
 
+/// Check that every allocated container has a port on the bridge
+fn check_bridge_ports(bridge: &BridgeInfo, state: &IpamState) -> CheckResult {
+    let port_names: HashSet<&str> = bridge.ports.iter().map(|p| p.name.as_str()).collect();
+    let missing = find_missing_ports(&state.allocations, &port_names);
+
+    if missing.is_empty() {
+        let down: Vec<&str> = bridge
+            .ports
+            .iter()
+            .filter(|p| p.name.starts_with(HOST_VETH_PREFIX) && !p.is_oper_up())
+            .map(|p| p.name.as_str())
+            .collect();
+        if down.is_empty() {
+            CheckResult::pass(
+                CATEGORY,
+                "Bridge ports",
+                format!("All {} allocated containers attached", state.allocations.len()),
+            )
+        } else {
+            CheckResult::warning(
+                CATEGORY,
+                "Bridge ports",
+                format!("{} bridge port(s) down: {}", down.len(), down.join(", ")),
+                "Restart the affected containers: podman restart <container>",
+            )
+        }
+    } else {
+        CheckResult::warning(
+            CATEGORY,
+            "Bridge ports",
+            format!(
+                "{} allocated container(s) have no bridge port: {}",
+                missing.len(),
+                missing.join(", ")
+            ),
+            "Restart the containers, or release stale allocations: sudo flatnet doctor --fix",
+        )
+    }
+}
+
+/// Find allocated containers whose host veth is not attached to the bridge
+///
+/// Returns short container IDs.
+fn find_missing_ports(
+    allocations: &HashMap<String, String>,
+    port_names: &HashSet<&str>,
+) -> Vec<String> {
+    let mut missing: Vec<String> = allocations
+        .keys()
+        .filter(|id| !port_names.contains(host_ifname(id).as_str()))
+        .map(|id| id.chars().take(12).collect())
+        .collect();
+    missing.sort();
+    missing
+}
+
+/// Check for host-side veths with no matching IPAM allocation
+fn check_leftover_veths(links: &[Link], container_ids: &HashSet<String>) -> CheckResult {
+    let leftover = find_leftover_veths(links, container_ids);
+
+    if leftover.is_empty() {
+        CheckResult::pass(CATEGORY, "No leftover veths", "No leftover veths")
+    } else {
+        CheckResult::warning(
+            CATEGORY,
+            "No leftover veths",
+            format!(
+                "{} veth(s) without an IPAM allocation: {}",
+                leftover.len(),
+                leftover.join(", ")
+            ),
+            format!("Run: sudo ip link delete {}", leftover[0]),
+        )
+    }
+}
+
+/// Find fn-* links that match no allocated container
+fn find_leftover_veths(links: &[Link], container_ids: &HashSet<String>) -> Vec<String> {
+    let expected: HashSet<String> = container_ids.iter().map(|id| host_ifname(id)).collect();
+
+    let mut leftover: Vec<String> = links
+        .iter()
+        .filter(|l| l.name.starts_with(HOST_VETH_PREFIX) && !expected.contains(&l.name))
+        .map(|l| l.name.clone())
+        .collect();
+
+    leftover.sort();
+    leftover
+}
+
+/// Host-side veth name for a container (same scheme as flatnet-cni)
+fn host_ifname(container_id: &str) -> String {
+    let id_part: String = container_id
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .take(MAX_IFNAME_LEN - HOST_VETH_PREFIX.len())
+        .collect();
+
+    format!("{}{}", HOST_VETH_PREFIX, id_part)
+}
+
+/// Check local IPAM allocations against Podman and the Gateway registry
+///
+/// Returns no results on hosts without a local IPAM state.
+async fn check_allocations(config: &Config) -> Vec<CheckResult> {
+    let store = IpamStore::new();
+    if !store.exists() {
+        return Vec::new();
+    }
+
+    let state = match store.load() {
+        Ok(s) => s,
+        Err(e) => {
+            return vec![CheckResult::warning(
+                CATEGORY,
+                "IPAM allocations",
+                format!("Could not read IPAM state: {}", e),
+                "Check permissions on /var/lib/flatnet/ipam",
+            )]
+        }
+    };
+
+    // Flatnet containers are rootful; a rootless podman would miss them all
+    if !is_root() {
+        return vec![CheckResult::pass(
+            CATEGORY,
+            "IPAM allocations",
+            format!(
+                "{} IPs allocated locally (run as root to check for orphans)",
+                state.allocations.len()
+            ),
+        )];
+    }
+
+    let containers = match PodmanClient::new().list_containers(true) {
+        Ok(c) => c,
+        Err(e) => {
+            return vec![CheckResult::warning(
+                CATEGORY,
+                "IPAM allocations",
+                format!("Could not list containers: {}", e),
+                "Ensure podman is installed and working",
+            )]
+        }
+    };
+
+    // Registry comparison is skipped when the Gateway is down
+    let registry = match GatewayClient::new(config.gateway_url(), config.gateway_timeout()) {
+        Ok(client) => client.containers().await.ok(),
+        Err(_) => None,
+    };
+
+    let issues = find_allocation_issues(&state, &containers, registry.as_deref());
+    let mut results = Vec::new();
+
+    if issues.orphaned.is_empty() {
+        results.push(CheckResult::pass(
+            CATEGORY,
+            "IPAM allocations",
+            format!("No orphaned allocations ({} allocated)", state.allocations.len()),
+        ));
+    } else {
+        results.push(
+            CheckResult::warning(
+                CATEGORY,
+                "IPAM allocations",
+                format!(
+                    "{} IPAM allocation(s) without a container",
+                    issues.orphaned.len()
+                ),
+                "Release them with: sudo flatnet doctor --fix",
+            )
+            .with_remediation(Remediation::ReleaseAllocations {
+                container_ids: issues.orphaned,
+            }),
+        );
+    }
+
+    if registry.is_some() {
+        if issues.unregistered.is_empty() {
+            results.push(CheckResult::pass(
+                CATEGORY,
+                "Registry complete",
+                "Local containers registered with Gateway",
+            ));
+        } else {
+            results.push(
+                CheckResult::warning(
+                    CATEGORY,
+                    "Registry complete",
+                    format!(
+                        "{} running container(s) missing from the Gateway registry",
+                        issues.unregistered.len()
+                    ),
+                    "Re-register them with: sudo flatnet doctor --fix",
+                )
+                .with_remediation(Remediation::RegisterContainers {
+                    containers: issues.unregistered,
+                }),
+            );
+        }
+    }
+
+    results
+}
+
+/// Allocation problems found by comparing IPAM, Podman, and the registry
+#[derive(Debug, Default)]
+struct AllocationIssues {
+    /// Container IDs with an allocation but no container
+    orphaned: Vec<String>,
+    /// Running containers with an allocation but no registry entry
+    unregistered: Vec<ContainerRegistration>,
+}
+
+/// Compare IPAM allocations with Podman containers and registry entries
+fn find_allocation_issues(
+    state: &IpamState,
+    containers: &[PodmanContainer],
+    registry: Option<&[ContainerInfo]>,
+) -> AllocationIssues {
+    let mut issues = AllocationIssues::default();
+
+    for (container_id, ip) in &state.allocations {
+        let container = containers.iter().find(|c| &c.id == container_id);
+
+        match container {
+            None => issues.orphaned.push(container_id.clone()),
+            Some(c) if c.is_running() => {
+                let registered = registry
+                    .map(|entries| entries.iter().any(|e| &e.ip == ip))
+                    .unwrap_or(true);
+                if !registered {
+                    issues.unregistered.push(ContainerRegistration {
+                        id: container_id.clone(),
+                        ip: ip.clone(),
+                        hostname: Some(c.name().to_string()).filter(|n| !n.is_empty()),
+                        host_id: state.host_id,
+                    });
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    issues.orphaned.sort();
+    issues.unregistered.sort_by(|a, b| a.ip.cmp(&b.ip));
+    issues
+}
+
+/// Check if the CLI is running as root
+fn is_root() -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find(|line| line.starts_with("Uid:"))
+                .and_then(|line| line.split_whitespace().nth(1).map(|uid| uid == "0"))
+        })
+        .unwrap_or(false)
+}
+
 /// Check IPAM state by querying the Gateway API
 async fn check_ipam_state(config: &Config) -> CheckResult {
     match GatewayClient::new(config.gateway_url(), config.gateway_timeout()) {
@@ -106,4 +708,99 @@ mod tests {
     fn test_category_name() {
         assert_eq!(CATEGORY, "CNI Plugin");
     }
+
+    fn link(name: &str) -> Link {
+        Link {
+            index: 0,
+            name: name.to_string(),
+            admin_up: true,
+            oper_state: "up".to_string(),
+            controller: None,
+            kind: Some("veth".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_find_leftover_veths() {
+        let links = vec![
+            link("lo"),
+            link("flatnet-br0"),
+            link("fn-0123456789ab"),
+            link("fn-deadbeef0000"),
+        ];
+        let ids: HashSet<String> = ["0123456789abcdef0123".to_string()].into_iter().collect();
+        assert_eq!(
+            find_leftover_veths(&links, &ids),
+            vec!["fn-deadbeef0000".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_find_missing_ports() {
+        let mut allocations = HashMap::new();
+        allocations.insert("0123456789abcdef0123".to_string(), "10.100.1.10".to_string());
+        allocations.insert("fedcba9876543210fedc".to_string(), "10.100.1.11".to_string());
+        let ports: HashSet<&str> = ["fn-0123456789ab"].into_iter().collect();
+
+        assert_eq!(
+            find_missing_ports(&allocations, &ports),
+            vec!["fedcba987654".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_find_allocation_issues() {
+        let state: IpamState = serde_json::from_str(
+            r#"{
+                "subnet": "10.100.1.0/24",
+                "gateway": "10.100.1.1",
+                "range_start": "10.100.1.10",
+                "range_end": "10.100.1.254",
+                "host_id": 1,
+                "allocations": {
+                    "running1": "10.100.1.10",
+                    "running2": "10.100.1.11",
+                    "stopped1": "10.100.1.12",
+                    "gone1": "10.100.1.13"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let container = |id: &str, state: &str| PodmanContainer {
+            id: id.to_string(),
+            names: vec![format!("{}-name", id)],
+            image: "nginx".to_string(),
+            state: state.to_string(),
+            status: String::new(),
+            created: None,
+            networks: None,
+            labels: None,
+        };
+        let containers = vec![
+            container("running1", "running"),
+            container("running2", "running"),
+            container("stopped1", "exited"),
+        ];
+        let registry = vec![ContainerInfo {
+            id: "running1".to_string(),
+            ip: "10.100.1.10".to_string(),
+            host_id: 1,
+            name: None,
+            registered_at: None,
+        }];
+
+        let issues = find_allocation_issues(&state, &containers, Some(&registry));
+        assert_eq!(issues.orphaned, vec!["gone1".to_string()]);
+        assert_eq!(issues.unregistered.len(), 1);
+        assert_eq!(issues.unregistered[0].ip, "10.100.1.11");
+        assert_eq!(
+            issues.unregistered[0].hostname,
+            Some("running2-name".to_string())
+        );
+
+        // Without a registry, only orphans are reported
+        let issues = find_allocation_issues(&state, &containers, None);
+        assert!(issues.unregistered.is_empty());
+    }
 }
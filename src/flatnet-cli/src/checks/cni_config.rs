@@ -0,0 +1,115 @@
+//! CNI network configuration lookup
+//!
+//! Finds the flatnet plugin entry in a conflist as the runtime passes it to
+//! the plugin. Validation is left to the plugin itself (`flatnet
+//! --check-config`, see `CniPlugin::check_config`), so the CLI does not keep
+//! its own copy of the plugin's config types.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// CNI plugin type handled by flatnet-cni
+pub const PLUGIN_TYPE: &str = "flatnet";
+
+/// Network configuration list (.conflist)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfList {
+    cni_version: String,
+    name: String,
+    #[serde(default)]
+    plugins: Vec<Value>,
+}
+
+/// Extract the flatnet plugin entry from a conflist, as the runtime passes it
+///
+/// The runtime injects `cniVersion` and `name` from the list into each plugin
+/// entry, so this does the same. Returns Ok(None) if the list has no flatnet
+/// plugin.
+pub fn flatnet_plugin_config(content: &str) -> Result<Option<Value>, String> {
+    let list: ConfList = serde_json::from_str(content).map_err(|e| e.to_string())?;
+
+    let Some(mut plugin) = list
+        .plugins
+        .into_iter()
+        .find(|p| p.get("type").and_then(|t| t.as_str()) == Some(PLUGIN_TYPE))
+    else {
+        return Ok(None);
+    };
+
+    if let Some(obj) = plugin.as_object_mut() {
+        obj.insert("cniVersion".to_string(), list.cni_version.into());
+        obj.insert("name".to_string(), list.name.into());
+    }
+
+    Ok(Some(plugin))
+}
+
+/// Host ID of a plugin config (`hostId`, else `ipam.hostId`), if set
+pub fn host_id(config: &Value) -> Option<u8> {
+    config
+        .get("hostId")
+        .or_else(|| config.get("ipam")?.get("hostId"))
+        .and_then(Value::as_u64)
+        .and_then(|id| u8::try_from(id).ok())
+}
+
+/// IPAM data directory of a plugin config (`ipam.dataDir`), if set
+pub fn data_dir(config: &Value) -> Option<&str> {
+    config.get("ipam")?.get("dataDir")?.as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatnet_plugin_config() {
+        let content = r#"{
+            "cniVersion": "1.0.0",
+            "name": "flatnet",
+            "plugins": [
+                {"type": "flatnet", "bridge": "flatnet-br0"},
+                {"type": "portmap", "capabilities": {"portMappings": true}}
+            ]
+        }"#;
+
+        let config = flatnet_plugin_config(content).unwrap().unwrap();
+        assert_eq!(
+            config,
+            serde_json::json!({
+                "cniVersion": "1.0.0",
+                "name": "flatnet",
+                "type": "flatnet",
+                "bridge": "flatnet-br0"
+            })
+        );
+
+        // Not a conflist
+        assert!(flatnet_plugin_config(r#"{"plugins": "none"}"#).is_err());
+
+        // No flatnet plugin
+        let other = r#"{"cniVersion": "1.0.0", "name": "podman", "plugins": [{"type": "bridge"}]}"#;
+        assert!(flatnet_plugin_config(other).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_host_id() {
+        assert_eq!(host_id(&serde_json::json!({"hostId": 2})), Some(2));
+        assert_eq!(host_id(&serde_json::json!({"ipam": {"hostId": 3}})), Some(3));
+        assert_eq!(
+            host_id(&serde_json::json!({"hostId": 2, "ipam": {"hostId": 3}})),
+            Some(2)
+        );
+        assert_eq!(host_id(&serde_json::json!({"hostId": 300})), None);
+        assert_eq!(host_id(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn test_data_dir() {
+        let config = serde_json::json!({"ipam": {"type": "flatnet-ipam", "dataDir": "/srv/ipam"}});
+        assert_eq!(data_dir(&config), Some("/srv/ipam"));
+        assert_eq!(data_dir(&serde_json::json!({"ipam": {}})), None);
+        assert_eq!(data_dir(&serde_json::json!({})), None);
+    }
+}
@@ -4,7 +4,7 @@
 
 use super::CheckResult;
 use crate::config::Config;
-use std::process::Stdio;
+use nix::sys::statvfs::statvfs;
 
 const CATEGORY: &str = "Disk";
 
@@ -16,85 +16,74 @@ const DISK_CRITICAL_THRESHOLD: u8 = 95;
 
 /// Run all disk checks
 pub async fn run_checks(_config: &Config) -> Vec<CheckResult> {
-    let disk_check = check_disk_usage().await;
+    let disk_check = check_disk_usage();
     vec![disk_check]
 }
 
 /// Check disk usage on the root filesystem
-async fn check_disk_usage() -> CheckResult {
-    // Use df command to check disk usage
-    let output = tokio::process::Command::new("df")
-        .args(["--output=pcent", "/"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output()
-        .await;
-
-    match output {
-        Ok(output) if output.status.success() => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
+fn check_disk_usage() -> CheckResult {
+    let stat = match statvfs("/") {
+        Ok(stat) => stat,
+        Err(e) => {
+            return CheckResult::warning(
+                CATEGORY,
+                "Disk usage",
+                format!("Could not check disk usage: {}", e),
+                "Run 'df -h' manually to check disk space",
+            )
+        }
+    };
 
-            // Parse the output - format is "Use%\n XX%"
-            if let Some(usage) = parse_disk_usage(&stdout) {
-                if usage >= DISK_CRITICAL_THRESHOLD {
-                    CheckResult::fail(
-                        CATEGORY,
-                        "Disk usage",
-                        format!("Disk usage at {}% (critical)", usage),
-                        "Free up disk space immediately: podman system prune -a",
-                    )
-                } else if usage >= DISK_WARNING_THRESHOLD {
-                    CheckResult::warning(
-                        CATEGORY,
-                        "Disk usage",
-                        format!("Disk usage at {}%", usage),
-                        "Consider cleaning up: podman system prune",
-                    )
-                } else {
-                    CheckResult::pass(
-                        CATEGORY,
-                        "Disk usage",
-                        format!("Disk usage at {}%", usage),
-                    )
-                }
-            } else {
-                CheckResult::warning(
-                    CATEGORY,
-                    "Disk usage",
-                    "Could not parse disk usage",
-                    "Run 'df -h' manually to check disk space",
-                )
-            }
+    let usage = match usage_percent(
+        stat.blocks() as u64,
+        stat.blocks_free() as u64,
+        stat.blocks_available() as u64,
+    ) {
+        Some(usage) => usage,
+        None => {
+            return CheckResult::warning(
+                CATEGORY,
+                "Disk usage",
+                "Root filesystem reports no blocks",
+                "Run 'df -h' manually to check disk space",
+            )
         }
-        Ok(_) => CheckResult::warning(
+    };
+
+    if usage >= DISK_CRITICAL_THRESHOLD {
+        CheckResult::fail(
+            CATEGORY,
+            "Disk usage",
+            format!("Disk usage at {}% (critical)", usage),
+            "Free up disk space immediately: podman system prune -a",
+        )
+    } else if usage >= DISK_WARNING_THRESHOLD {
+        CheckResult::warning(
             CATEGORY,
             "Disk usage",
-            "df command failed",
-            "Run 'df -h' manually to check disk space",
-        ),
-        Err(e) => CheckResult::warning(
+            format!("Disk usage at {}%", usage),
+            "Consider cleaning up: podman system prune",
+        )
+    } else {
+        CheckResult::pass(
             CATEGORY,
             "Disk usage",
-            format!("Could not check disk usage: {}", e),
-            "Ensure 'df' command is available",
-        ),
+            format!("Disk usage at {}%", usage),
+        )
     }
 }
 
-/// Parse disk usage percentage from df output
-fn parse_disk_usage(output: &str) -> Option<u8> {
-    // Output format is typically:
-    // Use%
-    //  XX%
-    for line in output.lines().skip(1) {
-        let trimmed = line.trim();
-        if let Some(percent_str) = trimmed.strip_suffix('%') {
-            if let Ok(percent) = percent_str.trim().parse::<u8>() {
-                return Some(percent);
-            }
-        }
+/// Compute usage percentage the way df does
+///
+/// Blocks reserved for root are excluded from the total, and the result is
+/// rounded up. Returns None for an empty filesystem.
+fn usage_percent(blocks: u64, blocks_free: u64, blocks_available: u64) -> Option<u8> {
+    let used = blocks.saturating_sub(blocks_free);
+    let total = used + blocks_available;
+    if total == 0 {
+        return None;
     }
-    None
+    Some((used * 100).div_ceil(total).min(100) as u8)
 }
 
 #[cfg(test)]
@@ -102,18 +91,12 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_parse_disk_usage() {
-        let output = "Use%\n 45%\n";
-        assert_eq!(parse_disk_usage(output), Some(45));
-
-        let output = "Use%\n  5%\n";
-        assert_eq!(parse_disk_usage(output), Some(5));
-
-        let output = "Use%\n 100%\n";
-        assert_eq!(parse_disk_usage(output), Some(100));
-
-        let output = "invalid";
-        assert_eq!(parse_disk_usage(output), None);
+    fn test_usage_percent() {
+        // 5% reserved: 1000 blocks, 500 free, 450 available to users
+        assert_eq!(usage_percent(1000, 500, 450), Some(53));
+        assert_eq!(usage_percent(1000, 1000, 950), Some(0));
+        assert_eq!(usage_percent(1000, 50, 0), Some(100));
+        assert_eq!(usage_percent(0, 0, 0), None);
     }
 
     #[test]
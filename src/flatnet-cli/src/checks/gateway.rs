@@ -2,7 +2,7 @@
 //!
 //! Checks for Gateway HTTP, API, and metrics endpoints.
 
-use super::CheckResult;
+use super::{CheckResult, Remediation};
 use crate::clients::gateway::GatewayClient;
 use crate::config::Config;
 use reqwest::Client;
@@ -19,7 +19,7 @@ pub async fn run_checks(config: &Config) -> Vec<CheckResult> {
     let (http_check, api_check, metrics_check) = tokio::join!(
         check_http(gateway_url),
         check_api(gateway_url, config.gateway_timeout()),
-        check_metrics(gateway_url),
+        check_metrics(gateway_url, config.gateway.metrics_url.as_deref()),
     );
 
     vec![http_check, api_check, metrics_check]
@@ -65,12 +65,17 @@ async fn check_http(gateway_url: &str) -> CheckResult {
             } else {
                 "Check network connectivity and Gateway configuration"
             };
-            CheckResult::fail(
+            let result = CheckResult::fail(
                 CATEGORY,
                 "HTTP responding",
                 format!("Connection failed ({})", address),
                 suggestion,
-            )
+            );
+            if e.is_timeout() || e.is_connect() {
+                result.with_remediation(Remediation::RestartGateway)
+            } else {
+                result
+            }
         }
     }
 }
@@ -114,7 +119,7 @@ async fn check_api(gateway_url: &str, timeout: Duration) -> CheckResult {
 }
 
 /// Check if the Gateway metrics endpoint is responding
-async fn check_metrics(gateway_url: &str) -> CheckResult {
+async fn check_metrics(gateway_url: &str, configured_url: Option<&str>) -> CheckResult {
     let client = match Client::builder().timeout(CHECK_TIMEOUT).build() {
         Ok(c) => c,
         Err(e) => {
@@ -129,7 +134,9 @@ async fn check_metrics(gateway_url: &str) -> CheckResult {
 
     // Metrics are typically on port 9145
     // Parse the gateway URL to construct the metrics URL
-    let metrics_url = if let Some(host) = extract_host(gateway_url) {
+    let metrics_url = if let Some(url) = configured_url {
+        url.to_string()
+    } else if let Some(host) = extract_host(gateway_url) {
         format!("http://{}:9145/metrics", host)
     } else {
         // Fallback: simple replacement
@@ -139,22 +146,27 @@ async fn check_metrics(gateway_url: &str) -> CheckResult {
         format!("{}/metrics", base.trim_end_matches('/'))
     };
 
+    let address = match configured_url {
+        Some(url) => extract_address(url),
+        None => ":9145".to_string(),
+    };
+
     match client.get(&metrics_url).send().await {
         Ok(resp) if resp.status().is_success() => CheckResult::pass(
             CATEGORY,
             "Metrics endpoint",
-            "Metrics endpoint responding (:9145)",
+            format!("Metrics endpoint responding ({})", address),
         ),
         Ok(resp) => CheckResult::warning(
             CATEGORY,
             "Metrics endpoint",
-            format!("Unexpected status {} (:9145)", resp.status()),
+            format!("Unexpected status {} ({})", resp.status(), address),
             "Check if metrics server is enabled in nginx.conf",
         ),
         Err(_) => CheckResult::warning(
             CATEGORY,
             "Metrics endpoint",
-            "Metrics endpoint not responding (:9145)",
+            format!("Metrics endpoint not responding ({})", address),
             "Check if metrics server is enabled in nginx.conf",
         ),
     }
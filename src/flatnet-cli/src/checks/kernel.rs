@@ -0,0 +1,180 @@
+//! Kernel networking checks
+//!
+//! Checks for the sysctls and routing state flatnet-cni relies on.
+
+use super::CheckResult;
+use crate::clients::ipam::IpamStore;
+use crate::clients::netlink::{Ipv4Network, NetlinkClient, Route};
+use crate::config::Config;
+use std::fs;
+use std::path::Path;
+
+const CATEGORY: &str = "Kernel";
+const BRIDGE_NAME: &str = "flatnet-br0";
+
+const IP_FORWARD_PATH: &str = "/proc/sys/net/ipv4/ip_forward";
+const BRIDGE_NF_CALL_IPTABLES_PATH: &str = "/proc/sys/net/bridge/bridge-nf-call-iptables";
+const BR_NETFILTER_MODULE_PATH: &str = "/sys/module/br_netfilter";
+
+/// Run all kernel checks
+pub async fn run_checks(_config: &Config) -> Vec<CheckResult> {
+    let mut results = vec![check_ip_forward(), check_br_netfilter()];
+    results.extend(check_route_conflicts().await);
+    results
+}
+
+/// Check that IPv4 forwarding is enabled
+fn check_ip_forward() -> CheckResult {
+    match read_sysctl(IP_FORWARD_PATH) {
+        Some(value) if value == "1" => {
+            CheckResult::pass(CATEGORY, "IP forwarding", "net.ipv4.ip_forward = 1")
+        }
+        Some(value) => CheckResult::fail(
+            CATEGORY,
+            "IP forwarding",
+            format!("net.ipv4.ip_forward = {} (containers cannot leave the host)", value),
+            "Run: sudo sysctl -w net.ipv4.ip_forward=1",
+        ),
+        None => CheckResult::warning(
+            CATEGORY,
+            "IP forwarding",
+            "Could not read net.ipv4.ip_forward",
+            "Check that /proc/sys is mounted",
+        ),
+    }
+}
+
+/// Check br_netfilter and bridge-nf-call-iptables
+fn check_br_netfilter() -> CheckResult {
+    if !Path::new(BR_NETFILTER_MODULE_PATH).exists() {
+        return CheckResult::warning(
+            CATEGORY,
+            "Bridge netfilter",
+            "br_netfilter module not loaded",
+            "Run: sudo modprobe br_netfilter",
+        );
+    }
+
+    match read_sysctl(BRIDGE_NF_CALL_IPTABLES_PATH) {
+        Some(value) if value == "1" => CheckResult::pass(
+            CATEGORY,
+            "Bridge netfilter",
+            "br_netfilter loaded, net.bridge.bridge-nf-call-iptables = 1",
+        ),
+        Some(value) => CheckResult::warning(
+            CATEGORY,
+            "Bridge netfilter",
+            format!(
+                "net.bridge.bridge-nf-call-iptables = {} (bridged traffic bypasses iptables)",
+                value
+            ),
+            "Run: sudo sysctl -w net.bridge.bridge-nf-call-iptables=1",
+        ),
+        None => CheckResult::warning(
+            CATEGORY,
+            "Bridge netfilter",
+            "Could not read net.bridge.bridge-nf-call-iptables",
+            "Run: sudo modprobe br_netfilter",
+        ),
+    }
+}
+
+/// Check for routes that overlap the local flatnet subnet
+///
+/// Returns no results when the local subnet is unknown (no IPAM state yet).
+async fn check_route_conflicts() -> Option<CheckResult> {
+    let subnet: Ipv4Network = IpamStore::new().load().ok()?.subnet.parse().ok()?;
+
+    let routes = match NetlinkClient::new() {
+        Ok(client) => client.ipv4_routes().await,
+        Err(e) => Err(e),
+    };
+    let routes = match routes {
+        Ok(routes) => routes,
+        Err(e) => {
+            return Some(CheckResult::warning(
+                CATEGORY,
+                "Route conflicts",
+                format!("Could not list routes: {:#}", e),
+                "Check that netlink is available in this environment",
+            ))
+        }
+    };
+
+    let conflicts = find_route_conflicts(&routes, &subnet);
+
+    Some(if conflicts.is_empty() {
+        CheckResult::pass(
+            CATEGORY,
+            "Route conflicts",
+            format!("No routes conflict with {}", subnet),
+        )
+    } else {
+        let described: Vec<String> = conflicts
+            .iter()
+            .map(|r| format!("{} dev {}", r.destination, r.dev.as_deref().unwrap_or("?")))
+            .collect();
+        CheckResult::fail(
+            CATEGORY,
+            "Route conflicts",
+            format!(
+                "{} route(s) overlap {}: {}",
+                conflicts.len(),
+                subnet,
+                described.join("; ")
+            ),
+            "Remove the conflicting routes or choose another flatnet subnet",
+        )
+    })
+}
+
+/// Find unicast routes at least as specific as the flatnet subnet that do not use the bridge
+///
+/// Less specific routes (e.g., the multihost 10.100.0.0/16 over Nebula) are
+/// shadowed by the bridge route and are not conflicts.
+fn find_route_conflicts<'a>(routes: &'a [Route], subnet: &Ipv4Network) -> Vec<&'a Route> {
+    routes
+        .iter()
+        .filter(|r| r.unicast)
+        .filter(|r| r.dev.as_deref() != Some(BRIDGE_NAME))
+        .filter(|r| r.destination.is_within(subnet))
+        .collect()
+}
+
+/// Read a sysctl value from /proc
+fn read_sysctl(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|v| v.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str, dev: &str, unicast: bool) -> Route {
+        Route {
+            destination: destination.parse().unwrap(),
+            gateway: None,
+            dev: Some(dev.to_string()),
+            table: 254,
+            unicast,
+            protocol: 2,
+        }
+    }
+
+    #[test]
+    fn test_find_route_conflicts() {
+        let routes = vec![
+            route("0.0.0.0/0", "eth0", true),
+            route("10.100.0.0/16", "nebula1", true),
+            route("10.100.1.0/24", "flatnet-br0", true),
+            route("10.100.1.128/25", "docker0", true),
+            route("10.100.1.1/32", "lo", false),
+            route("172.20.0.0/20", "eth0", true),
+        ];
+        let subnet: Ipv4Network = "10.100.1.0/24".parse().unwrap();
+
+        let conflicts = find_route_conflicts(&routes, &subnet);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].dev.as_deref(), Some("docker0"));
+    }
+}
@@ -2,14 +2,21 @@
 //!
 //! This module contains health checks for various Flatnet components.
 
+pub mod alerts;
 pub mod cni;
+pub mod cni_config;
 pub mod disk;
 pub mod gateway;
+pub mod kernel;
 pub mod monitoring;
+pub mod nebula;
 pub mod network;
+pub mod remediation;
 
 use serde::Serialize;
 
+pub use remediation::Remediation;
+
 /// Result of a single health check
 #[derive(Debug, Clone, Serialize)]
 pub struct CheckResult {
@@ -24,6 +31,9 @@ pub struct CheckResult {
     /// Optional suggestion for fixing issues
     #[serde(skip_serializing_if = "Option::is_none")]
     pub suggestion: Option<String>,
+    /// Optional automated fix (applied by `doctor --fix`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<Remediation>,
 }
 
 /// Status of a health check
@@ -47,6 +57,7 @@ impl CheckResult {
             status: CheckStatus::Pass,
             message: message.into(),
             suggestion: None,
+            remediation: None,
         }
     }
 
@@ -63,6 +74,7 @@ impl CheckResult {
             status: CheckStatus::Warning,
             message: message.into(),
             suggestion: Some(suggestion.into()),
+            remediation: None,
         }
     }
 
@@ -79,33 +91,74 @@ impl CheckResult {
             status: CheckStatus::Fail,
             message: message.into(),
             suggestion: Some(suggestion.into()),
+            remediation: None,
         }
     }
+
+    /// Attach an automated fix to this result
+    pub fn with_remediation(mut self, remediation: Remediation) -> Self {
+        self.remediation = Some(remediation);
+        self
+    }
+
+    /// Check if this result has a fix that `doctor --fix` can apply
+    pub fn is_fixable(&self) -> bool {
+        self.status != CheckStatus::Pass && self.remediation.is_some()
+    }
 }
 
 /// Run all health checks
 pub async fn run_all_checks(config: &crate::config::Config) -> Vec<CheckResult> {
     // Run all check categories in parallel
-    let (gateway_results, cni_results, network_results, monitoring_results, disk_results) =
-        tokio::join!(
-            gateway::run_checks(config),
-            cni::run_checks(config),
-            network::run_checks(config),
-            monitoring::run_checks(config),
-            disk::run_checks(config),
-        );
+    let (
+        gateway_results,
+        cni_results,
+        kernel_results,
+        network_results,
+        nebula_results,
+        monitoring_results,
+        alert_results,
+        disk_results,
+    ) = tokio::join!(
+        gateway::run_checks(config),
+        cni::run_checks(config),
+        kernel::run_checks(config),
+        network::run_checks(config),
+        nebula::run_checks(config),
+        monitoring::run_checks(config),
+        alerts::run_checks(config),
+        disk::run_checks(config),
+    );
 
     // Combine all results
     let mut results = Vec::new();
     results.extend(gateway_results);
     results.extend(cni_results);
+    results.extend(kernel_results);
     results.extend(network_results);
+    results.extend(nebula_results);
     results.extend(monitoring_results);
+    results.extend(alert_results);
     results.extend(disk_results);
 
     results
 }
 
+/// Run the checks for a single category (e.g., to re-check after fixes)
+pub async fn run_category(config: &crate::config::Config, category: &str) -> Vec<CheckResult> {
+    match category {
+        "Gateway" => gateway::run_checks(config).await,
+        "CNI Plugin" => cni::run_checks(config).await,
+        "Kernel" => kernel::run_checks(config).await,
+        "Network" => network::run_checks(config).await,
+        "Nebula" => nebula::run_checks(config).await,
+        "Monitoring" => monitoring::run_checks(config).await,
+        "Alerts" => alerts::run_checks(config).await,
+        "Disk" => disk::run_checks(config).await,
+        _ => Vec::new(),
+    }
+}
+
 /// Calculate summary statistics from check results
 pub fn calculate_summary(results: &[CheckResult]) -> (usize, usize, usize) {
     let passed = results
@@ -170,6 +223,21 @@ mod tests {
         assert!(result.suggestion.is_some());
     }
 
+    #[test]
+    fn test_check_result_fixable() {
+        let result = CheckResult::warning("CNI Plugin", "Bridge", "Bridge is DOWN", "Bring it up");
+        assert!(!result.is_fixable());
+
+        let result = result.with_remediation(Remediation::BridgeUp {
+            bridge: "flatnet-br0".to_string(),
+        });
+        assert!(result.is_fixable());
+
+        let result = CheckResult::pass("CNI Plugin", "Bridge", "OK")
+            .with_remediation(Remediation::RestartGateway);
+        assert!(!result.is_fixable());
+    }
+
     #[test]
     fn test_determine_exit_code() {
         // All pass
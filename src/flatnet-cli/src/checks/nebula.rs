@@ -0,0 +1,271 @@
+//! Nebula overlay checks
+//!
+//! Checks the Nebula host config and certificates deployed on the Windows
+//! host, lighthouse reachability, and that the overlay IP matches the host
+//! ID used by the CNI plugin.
+
+use super::{cni, CheckResult};
+use crate::clients::nebula::{self, NebulaCert, NebulaConfig};
+use crate::config::Config;
+use std::path::Path;
+
+const CATEGORY: &str = "Nebula";
+const PING_TIMEOUT_SECS: u64 = 2;
+
+/// Run all Nebula checks
+pub async fn run_checks(config: &Config) -> Vec<CheckResult> {
+    let path = Path::new(&config.nebula.config);
+    if !path.exists() {
+        // Single-host setups run without Nebula
+        return vec![CheckResult::pass(
+            CATEGORY,
+            "Config",
+            format!("Not configured ({} not found)", path.display()),
+        )];
+    }
+
+    let nebula = match NebulaConfig::load(path) {
+        Ok(nebula) => nebula,
+        Err(e) => {
+            return vec![CheckResult::fail(
+                CATEGORY,
+                "Config",
+                format!("{:#}", e),
+                "Compare with config/nebula/host.yaml.template",
+            )]
+        }
+    };
+
+    let dir = path.parent().unwrap_or(Path::new("/"));
+    let now = chrono::Utc::now().timestamp();
+    let warning_days = config.nebula.cert_warning_days;
+    let mut results = Vec::new();
+
+    match NebulaCert::load(&nebula::wsl_path(&nebula.pki.cert, dir)) {
+        Ok(cert) => {
+            results.push(cert_result("Host certificate", &cert, now, warning_days));
+            results.extend(host_id_result(&cert, cni::conflist_host_id()));
+        }
+        Err(e) => results.push(CheckResult::fail(
+            CATEGORY,
+            "Host certificate",
+            format!("{:#}", e),
+            "Generate it on Windows: .\\gen-host-cert.ps1 -Name <host> -Ip 10.100.<id>.1/16",
+        )),
+    }
+
+    match NebulaCert::load(&nebula::wsl_path(&nebula.pki.ca, dir)) {
+        Ok(ca) => results.push(cert_result("CA certificate", &ca, now, warning_days)),
+        Err(e) => results.push(CheckResult::fail(
+            CATEGORY,
+            "CA certificate",
+            format!("{:#}", e),
+            "Copy ca.crt from the lighthouse to the pki.ca path",
+        )),
+    }
+
+    results.push(check_lighthouse(&nebula).await);
+    results
+}
+
+/// Check a certificate's validity period
+fn cert_result(name: &str, cert: &NebulaCert, now: i64, warning_days: i64) -> CheckResult {
+    let renew = if cert.is_ca {
+        "Create a new CA (.\\gen-ca.ps1) and re-sign all host certificates".to_string()
+    } else {
+        let ip = cert.ips.first().map(|n| n.to_string()).unwrap_or_default();
+        format!(
+            "Renew on Windows: .\\gen-host-cert.ps1 -Name {} -Ip {} -Groups \"{}\" -Force",
+            cert.name,
+            ip,
+            cert.groups.join(",")
+        )
+    };
+
+    let days = cert.days_until_expiry(now);
+    if cert.not_after <= now {
+        return CheckResult::fail(
+            CATEGORY,
+            name,
+            format!("{} expired {} day(s) ago", cert.name, -days),
+            renew,
+        );
+    }
+    if cert.not_before > now {
+        return CheckResult::fail(
+            CATEGORY,
+            name,
+            format!("{} is not valid yet", cert.name),
+            "Check the clock on this host and on the CA host",
+        );
+    }
+
+    let mut details = Vec::new();
+    if !cert.ips.is_empty() {
+        let ips: Vec<String> = cert.ips.iter().map(|n| n.to_string()).collect();
+        details.push(ips.join(", "));
+    }
+    if !cert.groups.is_empty() {
+        details.push(format!("groups: {}", cert.groups.join(", ")));
+    }
+    let subject = if details.is_empty() {
+        cert.name.clone()
+    } else {
+        format!("{} ({})", cert.name, details.join("; "))
+    };
+
+    if days < warning_days {
+        CheckResult::warning(
+            CATEGORY,
+            name,
+            format!("{} expires in {} day(s)", subject, days),
+            renew,
+        )
+    } else {
+        CheckResult::pass(
+            CATEGORY,
+            name,
+            format!("{}, expires in {} days", subject, days),
+        )
+    }
+}
+
+/// Compare the overlay IP with the conflist host ID (`10.100.<id>.x`)
+///
+/// Returns no result when the conflist has no host ID (single-host setup).
+fn host_id_result(cert: &NebulaCert, host_id: Option<u8>) -> Option<CheckResult> {
+    let host_id = host_id?;
+    let Some(ip) = cert.overlay_ip() else {
+        return Some(CheckResult::fail(
+            CATEGORY,
+            "Host ID",
+            format!("{} has no overlay IP", cert.name),
+            format!("Re-sign it with -Ip 10.100.{}.1/16", host_id),
+        ));
+    };
+
+    let overlay_id = ip.octets()[2];
+    if overlay_id == host_id {
+        Some(CheckResult::pass(
+            CATEGORY,
+            "Host ID",
+            format!("Overlay IP {} matches host ID {}", ip, host_id),
+        ))
+    } else {
+        Some(CheckResult::fail(
+            CATEGORY,
+            "Host ID",
+            format!(
+                "Overlay IP {} belongs to host {}, but the conflist uses host ID {}",
+                ip, overlay_id, host_id
+            ),
+            format!(
+                "Set hostId to {} in /etc/cni/net.d, or re-sign the certificate with -Ip 10.100.{}.1/16",
+                overlay_id, host_id
+            ),
+        ))
+    }
+}
+
+/// Check that a lighthouse answers over the overlay
+async fn check_lighthouse(nebula: &NebulaConfig) -> CheckResult {
+    if nebula.lighthouse.am_lighthouse {
+        return CheckResult::pass(CATEGORY, "Lighthouse", "This host is the lighthouse");
+    }
+    if nebula.lighthouse.hosts.is_empty() {
+        return CheckResult::warning(
+            CATEGORY,
+            "Lighthouse",
+            "No lighthouse configured",
+            "Add lighthouse.hosts and static_host_map (see config/nebula/host.yaml.template)",
+        );
+    }
+
+    for host in &nebula.lighthouse.hosts {
+        if nebula::ping(host, PING_TIMEOUT_SECS).await {
+            return CheckResult::pass(
+                CATEGORY,
+                "Lighthouse",
+                format!("Lighthouse {} reachable over the overlay", host),
+            );
+        }
+    }
+
+    // Tell a stopped tunnel apart from an unreachable lighthouse host
+    let host = &nebula.lighthouse.hosts[0];
+    for underlay in nebula.underlay_hosts(host) {
+        if nebula::ping(&underlay, PING_TIMEOUT_SECS).await {
+            return CheckResult::fail(
+                CATEGORY,
+                "Lighthouse",
+                format!(
+                    "Lighthouse {} unreachable over the overlay, but its host {} answers",
+                    host, underlay
+                ),
+                "Check the Nebula service on Windows: Get-Service Nebula",
+            );
+        }
+    }
+
+    CheckResult::fail(
+        CATEGORY,
+        "Lighthouse",
+        format!("Lighthouse {} unreachable", host),
+        "Check that the lighthouse is running and UDP 4242 is allowed through its firewall",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::CheckStatus;
+
+    fn cert(not_after: i64) -> NebulaCert {
+        NebulaCert {
+            name: "host-a".to_string(),
+            ips: vec!["10.100.1.1/16".parse().unwrap()],
+            groups: vec!["flatnet".to_string(), "gateway".to_string()],
+            not_before: 0,
+            not_after,
+            is_ca: false,
+        }
+    }
+
+    #[test]
+    fn test_cert_result() {
+        let now = 1_800_000_000;
+        let day = 86400;
+
+        let result = cert_result("Host certificate", &cert(now + 100 * day), now, 30);
+        assert_eq!(result.status, CheckStatus::Pass);
+        assert_eq!(
+            result.message,
+            "host-a (10.100.1.1/16; groups: flatnet, gateway), expires in 100 days"
+        );
+
+        let result = cert_result("Host certificate", &cert(now + 10 * day), now, 30);
+        assert_eq!(result.status, CheckStatus::Warning);
+        assert!(result
+            .suggestion
+            .unwrap()
+            .contains("-Name host-a -Ip 10.100.1.1/16 -Groups \"flatnet,gateway\""));
+
+        let result = cert_result("Host certificate", &cert(now - 2 * day), now, 30);
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert_eq!(result.message, "host-a expired 2 day(s) ago");
+    }
+
+    #[test]
+    fn test_host_id_result() {
+        let cert = cert(0);
+        assert!(host_id_result(&cert, None).is_none());
+        assert_eq!(
+            host_id_result(&cert, Some(1)).unwrap().status,
+            CheckStatus::Pass
+        );
+
+        let result = host_id_result(&cert, Some(2)).unwrap();
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.message.contains("belongs to host 1"));
+    }
+}
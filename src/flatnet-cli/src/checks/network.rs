@@ -1,9 +1,11 @@
 //! Network connectivity checks
 //!
-//! Checks for network connectivity between WSL2 and Windows host.
+//! Checks for network connectivity between WSL2 and Windows host, and for
+//! the routes to the other hosts' container subnets.
 
-use super::CheckResult;
+use super::{CheckResult, Remediation};
 use crate::config::Config;
+use crate::routes::{RouteManager, RoutePlan};
 use reqwest::Client;
 use std::time::Duration;
 
@@ -16,7 +18,61 @@ pub async fn run_checks(config: &Config) -> Vec<CheckResult> {
 
     let wsl_windows_check = check_wsl_to_windows(gateway_url).await;
 
-    vec![wsl_windows_check]
+    let mut results = vec![wsl_windows_check];
+    results.extend(check_peer_routes(config).await);
+    results
+}
+
+/// Check that every peer host's subnet is routed via the next hop
+///
+/// Returns no result when the host ID or next hop is unknown (single-host
+/// setups without IPAM state or a Windows host).
+async fn check_peer_routes(config: &Config) -> Option<CheckResult> {
+    let manager = RouteManager::from_config(config).ok()?;
+
+    match manager.plan().await {
+        Ok(plan) => Some(peer_routes_result(&plan, &manager.next_hop().to_string())),
+        Err(e) => Some(CheckResult::warning(
+            CATEGORY,
+            "Peer routes",
+            format!("Could not check peer routes: {:#}", e),
+            "Check that the Gateway is running",
+        )),
+    }
+}
+
+/// Build the peer routes result from a route plan
+fn peer_routes_result(plan: &RoutePlan, next_hop: &str) -> CheckResult {
+    let missing: Vec<String> = plan
+        .missing()
+        .map(|p| format!("{} (host {})", p.destination, p.host_id))
+        .collect();
+
+    if missing.is_empty() {
+        let peers = plan.keep.len();
+        return CheckResult::pass(
+            CATEGORY,
+            "Peer routes",
+            if peers == 0 {
+                "No peer hosts registered".to_string()
+            } else {
+                format!("Routes to {} peer host(s) via {}", peers, next_hop)
+            },
+        );
+    }
+
+    CheckResult::warning(
+        CATEGORY,
+        "Peer routes",
+        format!(
+            "{} peer subnet(s) not routed via {}: {}",
+            missing.len(),
+            next_hop,
+            missing.join(", ")
+        ),
+        "Run: sudo flatnet routes sync",
+    )
+    .with_remediation(Remediation::SyncRoutes)
 }
 
 /// Check connectivity from WSL2 to Windows host
@@ -75,4 +131,29 @@ mod tests {
     fn test_category_name() {
         assert_eq!(CATEGORY, "Network");
     }
+
+    #[test]
+    fn test_peer_routes_result() {
+        use crate::checks::CheckStatus;
+        use crate::routes::{host_subnet, PlannedRoute};
+
+        let planned = |host_id| PlannedRoute {
+            host_id,
+            destination: host_subnet(host_id),
+            current: None,
+        };
+
+        let result = peer_routes_result(&RoutePlan::default(), "172.20.0.1");
+        assert_eq!(result.status, CheckStatus::Pass);
+
+        let plan = RoutePlan {
+            add: vec![planned(3)],
+            keep: vec![planned(2)],
+            ..Default::default()
+        };
+        let result = peer_routes_result(&plan, "172.20.0.1");
+        assert_eq!(result.status, CheckStatus::Warning);
+        assert!(result.message.contains("10.100.3.0/24 (host 3)"));
+        assert!(result.is_fixable());
+    }
 }
@@ -0,0 +1,199 @@
+//! Automated remediations for failed checks
+//!
+//! Checks attach a `Remediation` when they know how to fix the problem.
+//! `flatnet doctor --fix` applies them after confirmation.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::process::Stdio;
+use std::time::Duration;
+
+use super::cni::{find_plugin_binary, flatnet_network_config};
+use crate::clients::cni_plugin::CniPlugin;
+use crate::clients::gateway::{ContainerRegistration, GatewayClient};
+use crate::config::Config;
+use crate::routes::RouteManager;
+
+/// How long to wait for the Gateway to come back after a restart
+const GATEWAY_RESTART_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A fix that can be applied for a check result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Remediation {
+    /// Restart the Gateway (OpenResty) service
+    RestartGateway,
+    /// Bring a bridge interface up
+    BridgeUp { bridge: String },
+    /// Re-add the gateway address to a bridge
+    BridgeAddress { bridge: String, address: String },
+    /// Release IPAM allocations whose containers no longer exist
+    ReleaseAllocations { container_ids: Vec<String> },
+    /// Register containers missing from the Gateway registry
+    RegisterContainers {
+        containers: Vec<ContainerRegistration>,
+    },
+    /// Program the routes to peer hosts (same as `flatnet routes sync`)
+    SyncRoutes,
+}
+
+impl Remediation {
+    /// Human-readable description of what the fix does
+    pub fn description(&self) -> String {
+        match self {
+            Remediation::RestartGateway => "Restart the Gateway (OpenResty)".to_string(),
+            Remediation::BridgeUp { bridge } => format!("Bring up bridge {}", bridge),
+            Remediation::BridgeAddress { bridge, address } => {
+                format!("Add address {} to bridge {}", address, bridge)
+            }
+            Remediation::ReleaseAllocations { container_ids } => format!(
+                "Release {} orphaned IPAM allocation(s)",
+                container_ids.len()
+            ),
+            Remediation::RegisterContainers { containers } => format!(
+                "Register {} container(s) with the Gateway",
+                containers.len()
+            ),
+            Remediation::SyncRoutes => "Sync routes to peer hosts".to_string(),
+        }
+    }
+
+    /// Order in which fixes are applied (lower first)
+    ///
+    /// The Gateway must be up before containers can be re-registered.
+    pub fn priority(&self) -> u8 {
+        match self {
+            Remediation::RestartGateway => 0,
+            Remediation::BridgeUp { .. } => 1,
+            Remediation::BridgeAddress { .. } => 2,
+            Remediation::ReleaseAllocations { .. } => 3,
+            Remediation::RegisterContainers { .. } => 4,
+            Remediation::SyncRoutes => 5,
+        }
+    }
+
+    /// Apply the fix, returning a short summary of what was done
+    pub async fn apply(&self, config: &Config) -> Result<String> {
+        match self {
+            Remediation::RestartGateway => {
+                restart_gateway(config).await?;
+                Ok("Gateway is responding".to_string())
+            }
+            Remediation::BridgeUp { bridge } => {
+                run_command("ip", &["link", "set", bridge, "up"]).await?;
+                Ok(format!("Bridge {} is up", bridge))
+            }
+            Remediation::BridgeAddress { bridge, address } => {
+                run_command("ip", &["addr", "add", address, "dev", bridge]).await?;
+                Ok(format!("Added {} to {}", address, bridge))
+            }
+            Remediation::ReleaseAllocations { container_ids } => {
+                // DEL through the plugin also removes port mappings, policy
+                // membership and the registry entry, and journals the release
+                let plugin = find_plugin_binary()
+                    .map(CniPlugin::new)
+                    .context("flatnet CNI plugin is not installed")?;
+                let network = flatnet_network_config()
+                    .context("No conflist with a flatnet plugin is installed")?;
+                for container_id in container_ids {
+                    plugin
+                        .del(&network, container_id)
+                        .with_context(|| format!("Failed to release {}", container_id))?;
+                }
+                Ok(format!("Released {} allocation(s)", container_ids.len()))
+            }
+            Remediation::RegisterContainers { containers } => {
+                let client = GatewayClient::new(config.gateway_url(), config.gateway_timeout())?;
+                for container in containers {
+                    client
+                        .register_container(container)
+                        .await
+                        .with_context(|| format!("Failed to register {}", container.ip))?;
+                }
+                Ok(format!("Registered {} container(s)", containers.len()))
+            }
+            Remediation::SyncRoutes => {
+                let manager = RouteManager::from_config(config)?;
+                let plan = manager.plan().await?;
+                manager.apply(&plan).await?;
+                Ok(format!(
+                    "Added {}, replaced {}, removed {} route(s)",
+                    plan.add.len(),
+                    plan.replace.len(),
+                    plan.remove.len()
+                ))
+            }
+        }
+    }
+}
+
+/// Stop and start OpenResty, then wait for the API to respond
+async fn restart_gateway(config: &Config) -> Result<()> {
+    let nginx = &config.gateway.nginx_bin;
+    let conf = &config.gateway.nginx_conf;
+
+    // Stop may fail if the Gateway is already down; that is fine
+    let _ = run_command(nginx, &["-c", conf, "-s", "stop"]).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    // nginx.exe stays in the foreground, so start it detached
+    tokio::process::Command::new(nginx)
+        .args(["-c", conf])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start {}", nginx))?;
+
+    let client = GatewayClient::new(config.gateway_url(), config.gateway_timeout())?;
+    let deadline = tokio::time::Instant::now() + GATEWAY_RESTART_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        if let Ok(true) = client.health().await {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    bail!(
+        "Gateway did not respond within {}s after restart",
+        GATEWAY_RESTART_TIMEOUT.as_secs()
+    )
+}
+
+/// Run a command, returning stderr as the error on failure
+async fn run_command(program: &str, args: &[&str]) -> Result<()> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("{} {} failed: {}", program, args.join(" "), stderr.trim());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remediation_serialize() {
+        let fix = Remediation::BridgeUp {
+            bridge: "flatnet-br0".to_string(),
+        };
+        let json = serde_json::to_string(&fix).unwrap();
+        assert_eq!(json, r#"{"kind":"bridge_up","bridge":"flatnet-br0"}"#);
+    }
+
+    #[test]
+    fn test_remediation_priority() {
+        let register = Remediation::RegisterContainers { containers: vec![] };
+        assert!(Remediation::RestartGateway.priority() < register.priority());
+    }
+}
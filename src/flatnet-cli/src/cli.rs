@@ -2,7 +2,7 @@
 //!
 //! Defines the command-line interface structure for the Flatnet CLI tool.
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 /// Flatnet CLI - System management tool for Flatnet
 #[derive(Parser, Debug)]
@@ -38,6 +38,30 @@ pub enum Commands {
     /// Upgrade CLI to latest version
     #[command(about = "Upgrade flatnet CLI to latest version")]
     Upgrade(UpgradeArgs),
+
+    /// Manage Gateway sync peers
+    #[command(about = "List and manage Gateway sync peers")]
+    Peers(PeersArgs),
+
+    /// Show key metrics from Prometheus
+    #[command(about = "Show Gateway, escalation and IPAM metrics from Prometheus")]
+    Metrics(MetricsArgs),
+
+    /// List and silence alerts
+    #[command(about = "List and silence Flatnet alerts in Alertmanager")]
+    Alerts(AlertsArgs),
+
+    /// Manage routes to other hosts
+    #[command(about = "Program routes to other hosts' container subnets")]
+    Routes(RoutesArgs),
+
+    /// Serve container names over DNS
+    #[command(about = "Resolve container names on the flatnet network")]
+    Dns(DnsArgs),
+
+    /// Manage network policy
+    #[command(about = "Allow and deny traffic between flatnet containers and hosts")]
+    Policy(PolicyArgs),
 }
 
 /// Arguments for the status command
@@ -70,6 +94,14 @@ pub struct DoctorArgs {
     /// Verbose mode - show detailed information
     #[arg(long, short, help = "Show detailed diagnostic information")]
     pub verbose: bool,
+
+    /// Apply available fixes for detected issues
+    #[arg(long, help = "Apply available fixes for detected issues")]
+    pub fix: bool,
+
+    /// Apply fixes without prompting (for automation)
+    #[arg(long, short = 'y', requires = "fix", help = "Apply fixes without prompting")]
+    pub yes: bool,
 }
 
 /// Arguments for the ps command
@@ -80,9 +112,13 @@ pub struct PsArgs {
     pub all: bool,
 
     /// Filter containers (e.g., name=foo, id=abc, image=nginx)
-    #[arg(long, short, help = "Filter containers by name, id, or image")]
+    #[arg(long, short, help = "Filter containers by name, id, or image (with --all-hosts: host, route, gateway, health, ip)")]
     pub filter: Option<String>,
 
+    /// List every container in the Gateway registry, including remote hosts
+    #[arg(long, help = "List containers from the Gateway registry across all hosts")]
+    pub all_hosts: bool,
+
     /// Output in JSON format
     #[arg(long, help = "Output in JSON format")]
     pub json: bool,
@@ -95,9 +131,29 @@ pub struct PsArgs {
 /// Arguments for the logs command
 #[derive(Parser, Debug)]
 pub struct LogsArgs {
-    /// Component or container name
-    #[arg(help = "Component (gateway, cni, prometheus, grafana, loki) or container name")]
-    pub target: String,
+    /// Component or container names
+    #[arg(
+        required_unless_present_any = ["selector", "op"],
+        help = "Components (gateway, cni, prometheus, grafana, loki) or container names"
+    )]
+    pub targets: Vec<String>,
+
+    /// Raw Loki label selectors
+    #[arg(
+        long,
+        value_name = "SELECTOR",
+        help = "Loki label selector to include, e.g. '{host_id=\"2\"}' (repeatable)"
+    )]
+    pub selector: Vec<String>,
+
+    /// CNI operation ID to trace through the CNI plugin and Gateway logs
+    #[arg(
+        long,
+        value_name = "ID",
+        conflicts_with_all = ["targets", "selector"],
+        help = "Show every CNI plugin and Gateway line for one CNI operation (op_id)"
+    )]
+    pub op: Option<String>,
 
     /// Number of lines to show from the end
     #[arg(long, short = 'n', help = "Number of lines to show from the end")]
@@ -121,7 +177,10 @@ pub struct LogsArgs {
 }
 
 /// Arguments for the upgrade command
+///
+/// `--version` selects a release here, so the propagated version flag is off.
 #[derive(Parser, Debug)]
+#[command(disable_version_flag = true)]
 pub struct UpgradeArgs {
     /// Check for updates without installing
     #[arg(long, help = "Check for updates without installing")]
@@ -130,4 +189,293 @@ pub struct UpgradeArgs {
     /// Upgrade to a specific version
     #[arg(long, value_name = "VERSION", help = "Upgrade to a specific version (e.g., 0.2.0)")]
     pub version: Option<String>,
+
+    /// Upgrade from a release bundle, bundle directory, or mirror URL
+    #[arg(
+        long,
+        value_name = "PATH|URL",
+        help = "Upgrade from a release bundle (.tar.gz), unpacked bundle directory, or HTTP mirror instead of GitHub"
+    )]
+    pub from: Option<String>,
+
+    /// Restore the version replaced by the last upgrade
+    #[arg(
+        long,
+        conflicts_with_all = ["check", "version", "from"],
+        help = "Restore the previous version from the upgrade backup"
+    )]
+    pub rollback: bool,
+
+    /// Component to upgrade
+    #[arg(
+        long,
+        value_enum,
+        default_value = "cli",
+        help = "Component to upgrade or roll back"
+    )]
+    pub component: ComponentArg,
+}
+
+/// Components managed by the upgrade command
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComponentArg {
+    /// The flatnet CLI
+    Cli,
+    /// The flatnet-cni plugin binary
+    Cni,
+    /// The OpenResty Lua bundle
+    GatewayConfig,
+    /// All components
+    All,
+}
+
+/// Arguments for the peers command
+#[derive(Parser, Debug)]
+pub struct PeersArgs {
+    #[command(subcommand)]
+    pub command: PeersCommand,
+}
+
+/// Peers subcommands
+#[derive(Subcommand, Debug)]
+pub enum PeersCommand {
+    /// List sync peers
+    #[command(about = "List sync peers with their last sync result")]
+    List(PeersListArgs),
+
+    /// Add a sync peer
+    #[command(about = "Add a peer Gateway to sync with")]
+    Add(PeersAddArgs),
+
+    /// Remove a sync peer
+    #[command(about = "Remove a peer Gateway")]
+    Remove(PeersRemoveArgs),
+
+    /// Force a sync
+    #[command(about = "Pull registries from peers now")]
+    Pull(PeersPullArgs),
+}
+
+/// Arguments for the peers list command
+#[derive(Parser, Debug)]
+pub struct PeersListArgs {
+    /// Output in JSON format
+    #[arg(long, help = "Output in JSON format")]
+    pub json: bool,
+}
+
+/// Arguments for the peers add command
+#[derive(Parser, Debug)]
+pub struct PeersAddArgs {
+    /// Peer Gateway URL
+    #[arg(value_name = "URL", help = "Peer Gateway URL (e.g., http://10.100.2.1:8080)")]
+    pub endpoint: String,
+}
+
+/// Arguments for the peers remove command
+#[derive(Parser, Debug)]
+pub struct PeersRemoveArgs {
+    /// Peer Gateway URL
+    #[arg(value_name = "URL", help = "Peer Gateway URL to remove")]
+    pub endpoint: String,
+}
+
+/// Arguments for the peers pull command
+#[derive(Parser, Debug)]
+pub struct PeersPullArgs {
+    /// Only pull from this peer
+    #[arg(value_name = "URL", help = "Only pull from this peer (default: all peers)")]
+    pub endpoint: Option<String>,
+}
+
+/// Arguments for the metrics command
+#[derive(Parser, Debug)]
+pub struct MetricsArgs {
+    /// Time window to show
+    #[arg(long, short, default_value = "1h", value_name = "DURATION", help = "Time window to show (e.g., 15m, 1h, 1d)")]
+    pub window: String,
+
+    /// Resolution of the series
+    #[arg(long, value_name = "DURATION", help = "Query resolution (default: window split into 60 steps)")]
+    pub step: Option<String>,
+
+    /// Output in JSON format
+    #[arg(long, help = "Output in JSON format")]
+    pub json: bool,
+}
+
+/// Arguments for the alerts command
+#[derive(Parser, Debug)]
+pub struct AlertsArgs {
+    #[command(subcommand)]
+    pub command: AlertsCommand,
+}
+
+/// Alerts subcommands
+#[derive(Subcommand, Debug)]
+pub enum AlertsCommand {
+    /// List alerts
+    #[command(about = "List firing and muted Flatnet alerts")]
+    List(AlertsListArgs),
+
+    /// Silence an alert
+    #[command(about = "Silence an alert for a maintenance window")]
+    Silence(AlertsSilenceArgs),
+}
+
+/// Arguments for the alerts list command
+#[derive(Parser, Debug)]
+pub struct AlertsListArgs {
+    /// Output in JSON format
+    #[arg(long, help = "Output in JSON format")]
+    pub json: bool,
+}
+
+/// Arguments for the alerts silence command
+#[derive(Parser, Debug)]
+pub struct AlertsSilenceArgs {
+    /// Alert name
+    #[arg(value_name = "NAME", help = "Alert rule name (e.g., GatewayDown)")]
+    pub name: String,
+
+    /// Silence duration
+    #[arg(long = "for", default_value = "1h", value_name = "DURATION", help = "How long to silence (e.g., 30m, 2h)")]
+    pub duration: String,
+
+    /// Silence comment
+    #[arg(long, short, value_name = "TEXT", help = "Reason shown in Alertmanager")]
+    pub comment: Option<String>,
+}
+
+/// Arguments for the routes command
+#[derive(Parser, Debug)]
+pub struct RoutesArgs {
+    #[command(subcommand)]
+    pub command: RoutesCommand,
+}
+
+/// Routes subcommands
+#[derive(Subcommand, Debug)]
+pub enum RoutesCommand {
+    /// Sync peer routes with the Gateway registry
+    #[command(about = "Add and remove routes to peer host subnets from the Gateway registry")]
+    Sync(RoutesSyncArgs),
+}
+
+/// Arguments for the routes sync command
+#[derive(Parser, Debug)]
+pub struct RoutesSyncArgs {
+    /// Keep syncing periodically
+    #[arg(long, short, help = "Keep syncing until interrupted")]
+    pub watch: bool,
+
+    /// Watch interval in seconds
+    #[arg(long, value_name = "SECS", help = "Interval between syncs in watch mode (default: routes.interval_secs)")]
+    pub interval: Option<u64>,
+
+    /// Show the changes without applying them
+    #[arg(long, help = "Show the route changes without applying them")]
+    pub dry_run: bool,
+}
+
+/// Arguments for the dns command
+#[derive(Parser, Debug)]
+pub struct DnsArgs {
+    #[command(subcommand)]
+    pub command: DnsCommand,
+}
+
+/// DNS subcommands
+#[derive(Subcommand, Debug)]
+pub enum DnsCommand {
+    /// Run the DNS server
+    #[command(about = "Answer <container>.flatnet queries and forward the rest upstream")]
+    Serve(DnsServeArgs),
+}
+
+/// Arguments for the dns serve command
+#[derive(Parser, Debug)]
+pub struct DnsServeArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "IP", help = "Address to listen on (default: dns.listen, else the bridge gateway IP)")]
+    pub listen: Option<String>,
+
+    /// UDP port to listen on
+    #[arg(long, value_name = "PORT", help = "UDP port to listen on (default: dns.port)")]
+    pub port: Option<u16>,
+
+    /// Upstream servers
+    #[arg(
+        long,
+        value_name = "IP[:PORT]",
+        help = "Upstream server for other names (repeatable; default: dns.upstreams, else /etc/resolv.conf)"
+    )]
+    pub upstream: Vec<String>,
+}
+
+/// Arguments for the policy command
+#[derive(Parser, Debug)]
+pub struct PolicyArgs {
+    #[command(subcommand)]
+    pub command: PolicyCommand,
+}
+
+/// Policy subcommands
+#[derive(Subcommand, Debug)]
+pub enum PolicyCommand {
+    /// Load the policy into nftables
+    #[command(about = "Compile the policy file and load it into nftables")]
+    Apply(PolicyApplyArgs),
+
+    /// Show the policy
+    #[command(about = "Show the policy rules and the containers they select")]
+    Show(PolicyShowArgs),
+
+    /// Check whether traffic is allowed
+    #[command(about = "Check whether the policy allows traffic from SRC to DST")]
+    Test(PolicyTestArgs),
+}
+
+/// Arguments for the policy apply command
+#[derive(Parser, Debug)]
+pub struct PolicyApplyArgs {
+    /// Keep re-applying periodically
+    #[arg(long, short, help = "Re-apply until interrupted, picking up new containers and policy changes")]
+    pub watch: bool,
+
+    /// Watch interval in seconds
+    #[arg(long, value_name = "SECS", help = "Interval between applies in watch mode (default: policy.interval_secs)")]
+    pub interval: Option<u64>,
+
+    /// Print the nftables script without loading it
+    #[arg(long, help = "Print the nftables script without loading it")]
+    pub dry_run: bool,
+}
+
+/// Arguments for the policy show command
+#[derive(Parser, Debug)]
+pub struct PolicyShowArgs {
+    /// Print the compiled nftables script
+    #[arg(long, help = "Print the compiled nftables script instead")]
+    pub nft: bool,
+}
+
+/// Arguments for the policy test command
+#[derive(Parser, Debug)]
+pub struct PolicyTestArgs {
+    /// Source container or address
+    #[arg(value_name = "SRC", help = "Source container name or IP address")]
+    pub src: String,
+
+    /// Destination container or address
+    #[arg(value_name = "DST", help = "Destination container name or IP address")]
+    pub dst: String,
+
+    /// Destination port
+    #[arg(value_name = "PORT[/PROTO]", help = "Destination port, optionally with protocol (default: tcp)")]
+    pub port: String,
+
+    /// Network to evaluate
+    #[arg(long, value_name = "NAME", help = "CNI network to evaluate (default: the only or first network)")]
+    pub network: Option<String>,
 }
@@ -0,0 +1,350 @@
+//! Alertmanager client
+//!
+//! HTTP client for listing Flatnet alerts and creating silences through the
+//! Alertmanager v2 API.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Components used as the `component` label by the Flatnet alert rules
+pub const FLATNET_COMPONENTS: &[&str] = &["gateway", "cni-plugin", "system"];
+
+/// Errors that can occur when communicating with Alertmanager
+#[derive(Error, Debug)]
+pub enum AlertmanagerError {
+    #[error("Alertmanager connection failed: {0}")]
+    ConnectionFailed(String),
+
+    #[error("Alertmanager request failed: {0}")]
+    RequestFailed(String),
+
+    #[error("Alertmanager returned unexpected response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Alertmanager timeout")]
+    Timeout,
+}
+
+impl AlertmanagerError {
+    /// Convert a reqwest error to an AlertmanagerError
+    fn from_reqwest(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AlertmanagerError::Timeout
+        } else if e.is_connect() {
+            AlertmanagerError::ConnectionFailed(e.to_string())
+        } else {
+            AlertmanagerError::RequestFailed(e.to_string())
+        }
+    }
+}
+
+/// An alert known to Alertmanager
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
+    /// RFC 3339 time the alert started firing
+    pub starts_at: String,
+    pub status: AlertStatus,
+}
+
+/// Alertmanager's view of an alert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertStatus {
+    /// "active", "suppressed" or "unprocessed"
+    pub state: String,
+    #[serde(default)]
+    pub silenced_by: Vec<String>,
+    #[serde(default)]
+    pub inhibited_by: Vec<String>,
+}
+
+impl Alert {
+    /// Alert rule name
+    pub fn name(&self) -> &str {
+        self.labels
+            .get("alertname")
+            .map(String::as_str)
+            .unwrap_or("unknown")
+    }
+
+    /// Severity label ("critical", "warning", ...)
+    pub fn severity(&self) -> &str {
+        self.labels
+            .get("severity")
+            .map(String::as_str)
+            .unwrap_or("none")
+    }
+
+    /// Summary annotation, falling back to the alert name
+    pub fn summary(&self) -> &str {
+        self.annotations
+            .get("summary")
+            .map(String::as_str)
+            .unwrap_or_else(|| self.name())
+    }
+
+    /// Whether a silence currently mutes the alert
+    pub fn is_silenced(&self) -> bool {
+        !self.status.silenced_by.is_empty()
+    }
+
+    /// Whether the alert is firing and neither silenced nor inhibited
+    pub fn is_active(&self) -> bool {
+        self.status.state == "active"
+    }
+
+    /// Seconds since the alert started firing
+    pub fn age_secs(&self, now: DateTime<Utc>) -> Option<i64> {
+        DateTime::parse_from_rfc3339(&self.starts_at)
+            .ok()
+            .map(|t| (now - t.with_timezone(&Utc)).num_seconds().max(0))
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SilenceRequest {
+    matchers: Vec<Matcher>,
+    starts_at: String,
+    ends_at: String,
+    created_by: String,
+    comment: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Matcher {
+    name: String,
+    value: String,
+    is_regex: bool,
+    is_equal: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SilenceResponse {
+    #[serde(rename = "silenceID")]
+    silence_id: String,
+}
+
+/// Alertmanager API client
+#[derive(Debug, Clone)]
+pub struct AlertmanagerClient {
+    client: Client,
+    base_url: String,
+}
+
+impl AlertmanagerClient {
+    /// Create a new Alertmanager client
+    pub fn new(base_url: &str, timeout: Duration) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// List Flatnet alerts, including silenced and inhibited ones
+    pub async fn alerts(&self) -> Result<Vec<Alert>, AlertmanagerError> {
+        let url = format!("{}/api/v2/alerts", self.base_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .query(&[
+                ("active", "true"),
+                ("silenced", "true"),
+                ("inhibited", "true"),
+                ("filter", &component_filter()),
+            ])
+            .send()
+            .await
+            .map_err(AlertmanagerError::from_reqwest)?;
+
+        if !response.status().is_success() {
+            return Err(AlertmanagerError::RequestFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        let mut alerts: Vec<Alert> = response
+            .json()
+            .await
+            .map_err(|e| AlertmanagerError::InvalidResponse(e.to_string()))?;
+        sort_alerts(&mut alerts);
+        Ok(alerts)
+    }
+
+    /// Silence a Flatnet alert by name, returning the silence ID
+    pub async fn silence(
+        &self,
+        alert_name: &str,
+        duration: Duration,
+        created_by: &str,
+        comment: &str,
+    ) -> Result<String, AlertmanagerError> {
+        let url = format!("{}/api/v2/silences", self.base_url);
+        let request = silence_request(alert_name, Utc::now(), duration, created_by, comment);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(AlertmanagerError::from_reqwest)?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AlertmanagerError::RequestFailed(format!(
+                "HTTP {}: {}",
+                status,
+                body.trim()
+            )));
+        }
+
+        let silence: SilenceResponse = response
+            .json()
+            .await
+            .map_err(|e| AlertmanagerError::InvalidResponse(e.to_string()))?;
+        Ok(silence.silence_id)
+    }
+}
+
+/// Matcher limiting queries and silences to Flatnet alert rules
+fn component_filter() -> String {
+    format!("component=~\"{}\"", FLATNET_COMPONENTS.join("|"))
+}
+
+fn silence_request(
+    alert_name: &str,
+    now: DateTime<Utc>,
+    duration: Duration,
+    created_by: &str,
+    comment: &str,
+) -> SilenceRequest {
+    let ends_at = now + chrono::Duration::seconds(duration.as_secs() as i64);
+    SilenceRequest {
+        matchers: vec![
+            Matcher {
+                name: "alertname".to_string(),
+                value: alert_name.to_string(),
+                is_regex: false,
+                is_equal: true,
+            },
+            Matcher {
+                name: "component".to_string(),
+                value: FLATNET_COMPONENTS.join("|"),
+                is_regex: true,
+                is_equal: true,
+            },
+        ],
+        starts_at: now.to_rfc3339(),
+        ends_at: ends_at.to_rfc3339(),
+        created_by: created_by.to_string(),
+        comment: comment.to_string(),
+    }
+}
+
+/// Order alerts: unsilenced first, then by severity and name
+fn sort_alerts(alerts: &mut [Alert]) {
+    alerts.sort_by(|a, b| {
+        let rank = |alert: &Alert| match alert.severity() {
+            "critical" => 0,
+            "warning" => 1,
+            _ => 2,
+        };
+        (!a.is_active(), rank(a), a.name()).cmp(&(!b.is_active(), rank(b), b.name()))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(name: &str, severity: &str, silenced: bool) -> Alert {
+        let json = format!(
+            r#"{{"labels":{{"alertname":"{}","severity":"{}","component":"gateway"}},
+                "annotations":{{"summary":"{} summary"}},
+                "startsAt":"2026-10-18T10:00:00Z",
+                "status":{{"state":"{}","silencedBy":{},"inhibitedBy":[]}}}}"#,
+            name,
+            severity,
+            name,
+            if silenced { "suppressed" } else { "active" },
+            if silenced { r#"["abc"]"# } else { "[]" }
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_alert_fields() {
+        let a = alert("GatewayDown", "critical", false);
+        assert_eq!(a.name(), "GatewayDown");
+        assert_eq!(a.severity(), "critical");
+        assert_eq!(a.summary(), "GatewayDown summary");
+        assert!(a.is_active());
+        assert!(!a.is_silenced());
+
+        let now = DateTime::parse_from_rfc3339("2026-10-18T10:05:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(a.age_secs(now), Some(300));
+
+        let a = alert("HighLatency", "warning", true);
+        assert!(!a.is_active());
+        assert!(a.is_silenced());
+    }
+
+    #[test]
+    fn test_sort_alerts() {
+        let mut alerts = vec![
+            alert("HighLatency", "warning", false),
+            alert("GatewayDown", "critical", true),
+            alert("DiskFull", "critical", false),
+        ];
+        sort_alerts(&mut alerts);
+        let names: Vec<&str> = alerts.iter().map(|a| a.name()).collect();
+        assert_eq!(names, vec!["DiskFull", "HighLatency", "GatewayDown"]);
+    }
+
+    #[test]
+    fn test_silence_request() {
+        let now = DateTime::parse_from_rfc3339("2026-10-18T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let request = silence_request(
+            "GatewayDown",
+            now,
+            Duration::from_secs(3600),
+            "alice",
+            "maintenance",
+        );
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["matchers"][0]["name"], "alertname");
+        assert_eq!(json["matchers"][0]["value"], "GatewayDown");
+        assert_eq!(json["matchers"][1]["isRegex"], true);
+        assert_eq!(json["endsAt"], "2026-10-18T11:00:00+00:00");
+        assert_eq!(json["createdBy"], "alice");
+        assert_eq!(
+            component_filter(),
+            r#"component=~"gateway|cni-plugin|system""#
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! flatnet-cni plugin client
+//!
+//! Runs the installed plugin binary the way a container runtime does. State
+//! the plugin owns (IPAM, port mappings, policy sets, registry entries) is
+//! only ever changed through it, so the CLI never bypasses the plugin's
+//! locking and journal.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Interface name passed on DEL (the plugin only needs the container ID)
+const IFNAME: &str = "eth0";
+
+/// CNI error code for invalid environment variables, which is what a plugin
+/// without `--check-config` reports (it looks for CNI_COMMAND instead)
+pub const ERR_INVALID_ENVIRONMENT: u32 = 4;
+
+/// Error result the plugin prints on failure (CNI spec)
+#[derive(Debug, Deserialize)]
+pub struct PluginError {
+    pub code: u32,
+    pub msg: String,
+    #[serde(default)]
+    pub details: Option<String>,
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.details.as_deref().filter(|d| !d.is_empty()) {
+            Some(details) => write!(f, "{} (CNI error {}): {}", self.msg, self.code, details),
+            None => write!(f, "{} (CNI error {})", self.msg, self.code),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+/// Client for the installed flatnet-cni binary
+#[derive(Debug, Clone)]
+pub struct CniPlugin {
+    path: PathBuf,
+}
+
+impl CniPlugin {
+    /// Create a client for the plugin binary at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Remove a container from the network (CNI DEL)
+    ///
+    /// Does what the runtime's DEL does: deregisters the container from the
+    /// Gateway, deletes its veth, removes its port mappings and policy-set
+    /// membership, and releases its IP (kept for its name until the sticky
+    /// cooldown passes). Succeeds if the container is already gone.
+    pub fn del(&self, config: &serde_json::Value, container_id: &str) -> Result<()> {
+        let bin_dir = self.path.parent().unwrap_or_else(|| Path::new("/"));
+        let mut cmd = Command::new(&self.path);
+        cmd.env("CNI_COMMAND", "DEL")
+            .env("CNI_CONTAINERID", container_id)
+            .env("CNI_NETNS", "")
+            .env("CNI_IFNAME", IFNAME)
+            .env("CNI_PATH", bin_dir);
+        self.run(cmd, "DEL", config)?;
+        Ok(())
+    }
+
+    /// Validate a network config with the plugin's own parser
+    ///
+    /// Fails with a `PluginError` if the plugin rejects the config. Plugins
+    /// older than `--check-config` fail with `ERR_INVALID_ENVIRONMENT`.
+    pub fn check_config(&self, config: &serde_json::Value) -> Result<()> {
+        let mut cmd = Command::new(&self.path);
+        cmd.arg("--check-config").env_remove("CNI_COMMAND");
+        self.run(cmd, "--check-config", config)?;
+        Ok(())
+    }
+
+    /// Run the plugin with the network config on stdin, returning stdout
+    fn run(&self, mut cmd: Command, what: &str, config: &serde_json::Value) -> Result<String> {
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.path.display()))?;
+
+        // The plugin reads all of stdin before it writes anything
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(config.to_string().as_bytes())
+                .context("Failed to pass the network config to the plugin")?;
+        }
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("Failed to run {}", self.path.display()))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        if output.status.success() {
+            return Ok(stdout);
+        }
+
+        match serde_json::from_str::<PluginError>(stdout.trim()) {
+            Ok(error) => Err(error.into()),
+            Err(_) => bail!(
+                "{} {} failed: {}",
+                self.path.display(),
+                what,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Write an executable script standing in for the plugin
+    fn fake_plugin(dir: &Path, body: &str) -> CniPlugin {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("flatnet");
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        CniPlugin::new(path)
+    }
+
+    #[test]
+    fn test_del() {
+        let dir = std::env::temp_dir().join(format!("flatnet-cni-plugin-{}", std::process::id()));
+        let plugin = fake_plugin(
+            &dir,
+            "echo \"$CNI_COMMAND $CNI_CONTAINERID $CNI_IFNAME\" > \"$(dirname \"$0\")/env\"\n\
+             cat > \"$(dirname \"$0\")/stdin\"",
+        );
+        let config = serde_json::json!({"cniVersion": "1.0.0", "name": "flatnet", "type": "flatnet"});
+
+        plugin.del(&config, "abc123").unwrap();
+        assert_eq!(fs::read_to_string(dir.join("env")).unwrap(), "DEL abc123 eth0\n");
+        let stdin: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("stdin")).unwrap()).unwrap();
+        assert_eq!(stdin, config);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_del_error() {
+        let dir = std::env::temp_dir().join(format!("flatnet-cni-plugin-err-{}", std::process::id()));
+        let plugin = fake_plugin(
+            &dir,
+            "cat > /dev/null\n\
+             echo '{\"cniVersion\":\"1.0.0\",\"code\":11,\"msg\":\"timed out waiting for the IPAM lock\",\"details\":\"held for 10000ms\"}'\n\
+             exit 1",
+        );
+
+        let err = plugin.del(&serde_json::json!({}), "abc123").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "timed out waiting for the IPAM lock (CNI error 11): held for 10000ms"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_config() {
+        let dir = std::env::temp_dir().join(format!("flatnet-cni-plugin-check-{}", std::process::id()));
+        let plugin = fake_plugin(
+            &dir,
+            "[ \"$1\" = --check-config ] || exit 2\n\
+             grep -q '\"hostId\":\"one\"' || exit 0\n\
+             echo '{\"cniVersion\":\"1.0.0\",\"code\":6,\"msg\":\"failed to parse network config\",\"details\":null}'\n\
+             exit 1",
+        );
+
+        plugin.check_config(&serde_json::json!({"hostId": 1})).unwrap();
+
+        let err = plugin.check_config(&serde_json::json!({"hostId": "one"})).unwrap_err();
+        let error = err.downcast_ref::<PluginError>().unwrap();
+        assert_eq!(error.code, 6);
+        assert_eq!(err.to_string(), "failed to parse network config (CNI error 6)");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -5,9 +5,16 @@
 use anyhow::{Context, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 use thiserror::Error;
 
+/// Number of sync intervals without a successful pull before a peer is stale
+const PEER_STALE_INTERVALS: i64 = 3;
+
+/// Sync interval assumed when the Gateway does not report one (seconds)
+const DEFAULT_SYNC_INTERVAL_SECS: u64 = 30;
+
 /// Errors that can occur when communicating with the Gateway
 #[derive(Error, Debug)]
 pub enum GatewayError {
@@ -130,6 +137,186 @@ impl GatewayClient {
         // Empty or invalid response
         Ok(Vec::new())
     }
+
+    /// Register a container with the Gateway registry
+    pub async fn register_container(
+        &self,
+        container: &ContainerRegistration,
+    ) -> Result<(), GatewayError> {
+        let url = format!("{}/api/containers", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(container)
+            .send()
+            .await
+            .map_err(GatewayError::from_reqwest)?;
+
+        if !response.status().is_success() {
+            return Err(GatewayError::RequestFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Get the route chosen for every registered container, keyed by IP
+    pub async fn routes(&self) -> Result<HashMap<String, RouteInfo>, GatewayError> {
+        let url = format!("{}/api/routing/routes", self.base_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(GatewayError::from_reqwest)?;
+
+        if !response.status().is_success() {
+            return Err(GatewayError::RequestFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json::<HashMap<String, RouteInfo>>()
+            .await
+            .map_err(|e| GatewayError::InvalidResponse(e.to_string()))
+    }
+
+    /// Get the P2P healthcheck status
+    pub async fn healthcheck_status(&self) -> Result<HealthcheckStatus, GatewayError> {
+        let url = format!("{}/api/healthcheck/status", self.base_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(GatewayError::from_reqwest)?;
+
+        if !response.status().is_success() {
+            return Err(GatewayError::RequestFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json::<HealthcheckStatus>()
+            .await
+            .map_err(|e| GatewayError::InvalidResponse(e.to_string()))
+    }
+
+    /// Get the sync peers with their per-peer sync state
+    pub async fn peers(&self) -> Result<Vec<PeerInfo>, GatewayError> {
+        let url = format!("{}/api/sync/peers", self.base_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(GatewayError::from_reqwest)?;
+
+        if !response.status().is_success() {
+            return Err(GatewayError::RequestFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json::<Vec<PeerInfo>>()
+            .await
+            .map_err(|e| GatewayError::InvalidResponse(e.to_string()))
+    }
+
+    /// Add a sync peer
+    ///
+    /// Returns false if the peer was already configured.
+    pub async fn add_peer(&self, endpoint: &str) -> Result<bool, GatewayError> {
+        let url = format!("{}/api/sync/peers", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "endpoint": endpoint }))
+            .send()
+            .await
+            .map_err(GatewayError::from_reqwest)?;
+
+        if !response.status().is_success() {
+            return Err(GatewayError::RequestFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        Ok(response.status().as_u16() == 201)
+    }
+
+    /// Remove a sync peer
+    ///
+    /// Returns false if the peer was not configured.
+    pub async fn remove_peer(&self, endpoint: &str) -> Result<bool, GatewayError> {
+        let url = format!("{}/api/sync/peers", self.base_url);
+
+        let response = self
+            .client
+            .delete(&url)
+            .query(&[("endpoint", endpoint)])
+            .send()
+            .await
+            .map_err(GatewayError::from_reqwest)?;
+
+        if response.status().as_u16() == 404 {
+            return Ok(false);
+        }
+
+        if !response.status().is_success() {
+            return Err(GatewayError::RequestFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        Ok(true)
+    }
+
+    /// Force a registry pull from all peers, or from a single peer
+    pub async fn sync_pull(&self, peer: Option<&str>) -> Result<SyncPullResult, GatewayError> {
+        let url = format!("{}/api/sync/pull", self.base_url);
+
+        let mut request = self.client.post(&url);
+        if let Some(peer) = peer {
+            request = request.query(&[("peer", peer)]);
+        }
+
+        let response = request.send().await.map_err(GatewayError::from_reqwest)?;
+
+        if response.status().as_u16() == 404 {
+            return Err(GatewayError::RequestFailed(format!(
+                "peer not found: {}",
+                peer.unwrap_or("-")
+            )));
+        }
+
+        if !response.status().is_success() {
+            return Err(GatewayError::RequestFailed(format!(
+                "HTTP {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json::<SyncPullResult>()
+            .await
+            .map_err(|e| GatewayError::InvalidResponse(e.to_string()))
+    }
 }
 
 /// Gateway status response
@@ -170,8 +357,8 @@ pub struct GatewayStatus {
 /// Registry statistics
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct RegistryStats {
-    /// Total container count
-    #[serde(default)]
+    /// Total container count (`count` in the Gateway's registry stats)
+    #[serde(default, alias = "count")]
     pub total_count: u32,
 
     /// Local container count
@@ -197,6 +384,68 @@ pub struct SyncStatus {
     /// Peer count
     #[serde(default)]
     pub peer_count: u32,
+
+    /// Sync interval in seconds
+    #[serde(default)]
+    pub sync_interval: Option<u64>,
+}
+
+impl SyncStatus {
+    /// Get the sync interval, falling back to the Gateway default
+    pub fn interval_secs(&self) -> u64 {
+        self.sync_interval.unwrap_or(DEFAULT_SYNC_INTERVAL_SECS)
+    }
+}
+
+/// Per-peer sync state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerInfo {
+    /// Peer Gateway endpoint URL
+    pub endpoint: String,
+
+    /// Last pull attempt (Unix timestamp)
+    #[serde(default)]
+    pub last_attempt: Option<i64>,
+
+    /// Last successful pull (Unix timestamp)
+    #[serde(default)]
+    pub last_success: Option<i64>,
+
+    /// Error from the last failed pull
+    #[serde(default)]
+    pub last_error: Option<String>,
+
+    /// Containers registered from the last successful pull
+    #[serde(default)]
+    pub container_count: u32,
+
+    /// Failed pulls since the last success
+    #[serde(default)]
+    pub consecutive_failures: u32,
+}
+
+impl PeerInfo {
+    /// Check if the peer has not synced successfully for several sync intervals
+    pub fn is_stale(&self, now: i64, sync_interval_secs: u64) -> bool {
+        let threshold = sync_interval_secs as i64 * PEER_STALE_INTERVALS;
+        match self.last_success {
+            Some(ts) => now - ts > threshold,
+            // Never synced: only stale once a pull has been attempted
+            None => self.last_attempt.is_some(),
+        }
+    }
+}
+
+/// Result of a forced sync pull
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SyncPullResult {
+    /// Peers pulled successfully
+    #[serde(default)]
+    pub success_count: u32,
+
+    /// Peers that failed
+    #[serde(default)]
+    pub failure_count: u32,
 }
 
 /// Escalation statistics
@@ -241,6 +490,30 @@ pub struct HealthcheckStatus {
     /// Unhealthy targets count
     #[serde(default)]
     pub unhealthy_count: u32,
+
+    /// Consecutive failure threshold before fallback
+    #[serde(default)]
+    pub failure_threshold: Option<u32>,
+
+    /// Consecutive healthcheck failures by IP
+    #[serde(default)]
+    pub failure_counts: HashMap<String, u32>,
+}
+
+/// Route chosen by the Gateway for a container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteInfo {
+    /// Route type ("local", "p2p", "gateway" or "unknown")
+    #[serde(rename = "type")]
+    pub route_type: String,
+
+    /// Route target (container IP or gateway IP)
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// Escalation state (e.g., "P2P_ACTIVE", "GATEWAY_FALLBACK")
+    #[serde(default)]
+    pub state: Option<String>,
 }
 
 /// Container information
@@ -253,18 +526,36 @@ pub struct ContainerInfo {
     pub ip: String,
 
     /// Host ID
-    #[serde(default)]
+    #[serde(default, alias = "hostId")]
     pub host_id: u8,
 
     /// Container name (optional)
-    #[serde(default)]
+    #[serde(default, alias = "hostname")]
     pub name: Option<String>,
 
     /// Registration timestamp
-    #[serde(default)]
+    #[serde(default, alias = "createdAt")]
     pub registered_at: Option<String>,
 }
 
+/// Container registration request (same shape the CNI plugin sends)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerRegistration {
+    /// Container ID
+    pub id: String,
+
+    /// Container IP address
+    pub ip: String,
+
+    /// Container hostname
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+
+    /// Host ID
+    pub host_id: u8,
+}
+
 /// Containers response wrapper
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ContainersResponse {
@@ -308,4 +599,80 @@ mod tests {
         assert_eq!(container.ip, "10.100.1.10");
         assert_eq!(container.host_id, 1);
     }
+
+    #[test]
+    fn test_parse_registry_container_info() {
+        // Registry entries as returned by the Gateway's /api/containers
+        let json = r#"{
+            "id": "def456",
+            "ip": "10.100.2.11",
+            "hostname": "api",
+            "hostId": 2,
+            "createdAt": "1700000000",
+            "ttl": 300
+        }"#;
+
+        let container: ContainerInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(container.host_id, 2);
+        assert_eq!(container.name.as_deref(), Some("api"));
+        assert_eq!(container.registered_at.as_deref(), Some("1700000000"));
+    }
+
+    #[test]
+    fn test_parse_routes() {
+        let json = r#"{
+            "10.100.1.10": {"type": "local", "target": "10.100.1.10", "state": "LOCAL"},
+            "10.100.2.11": {"type": "gateway", "target": "10.100.2.1", "state": "GATEWAY_ONLY"}
+        }"#;
+
+        let routes: HashMap<String, RouteInfo> = serde_json::from_str(json).unwrap();
+        assert_eq!(routes["10.100.1.10"].route_type, "local");
+        assert_eq!(routes["10.100.2.11"].target.as_deref(), Some("10.100.2.1"));
+    }
+
+    #[test]
+    fn test_parse_peer_info() {
+        let json = r#"[
+            {
+                "endpoint": "http://10.100.2.1:8080",
+                "last_attempt": 1700000100,
+                "last_success": 1700000100,
+                "container_count": 4,
+                "consecutive_failures": 0
+            },
+            {"endpoint": "http://10.100.3.1:8080"}
+        ]"#;
+
+        let peers: Vec<PeerInfo> = serde_json::from_str(json).unwrap();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].container_count, 4);
+        assert_eq!(peers[0].last_success, Some(1700000100));
+        assert!(peers[1].last_success.is_none());
+    }
+
+    #[test]
+    fn test_peer_is_stale() {
+        let mut peer = PeerInfo {
+            endpoint: "http://10.100.2.1:8080".to_string(),
+            last_attempt: None,
+            last_success: None,
+            last_error: None,
+            container_count: 0,
+            consecutive_failures: 0,
+        };
+
+        // Not attempted yet
+        assert!(!peer.is_stale(1000, 30));
+
+        // Attempted but never succeeded
+        peer.last_attempt = Some(990);
+        assert!(peer.is_stale(1000, 30));
+
+        // Recent success
+        peer.last_success = Some(950);
+        assert!(!peer.is_stale(1000, 30));
+
+        // Older than three intervals
+        assert!(peer.is_stale(1100, 30));
+    }
 }
@@ -0,0 +1,147 @@
+//! GitHub releases client
+//!
+//! Fetches release metadata and assets for `flatnet upgrade`. The API base is
+//! configurable so upgrades can be tested against a local mock server.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// GitHub repository for releases
+pub const GITHUB_REPO: &str = "khayashi4337/flatnet";
+
+/// Tag prefix for CLI releases
+const CLI_TAG_PREFIX: &str = "cli-v";
+
+/// GitHub release information
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubRelease {
+    pub tag_name: String,
+    pub assets: Vec<GitHubAsset>,
+}
+
+impl GitHubRelease {
+    /// Version from the tag name (supports both "cli-v1.0.0" and "v1.0.0")
+    pub fn version(&self) -> String {
+        self.tag_name
+            .trim_start_matches("cli-")
+            .trim_start_matches('v')
+            .to_string()
+    }
+
+    /// Find an asset by name
+    pub fn asset(&self, name: &str) -> Option<&GitHubAsset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+/// GitHub release asset
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// Client for the GitHub releases API
+pub struct GitHubClient {
+    client: reqwest::Client,
+    api_base: String,
+}
+
+impl GitHubClient {
+    /// Create a new client for the given API base URL
+    pub fn new(api_base: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(300))
+            .user_agent("flatnet-cli")
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            api_base: api_base.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Get the latest release
+    pub async fn latest_release(&self) -> Result<GitHubRelease> {
+        let url = format!("{}/repos/{}/releases/latest", self.api_base, GITHUB_REPO);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .context("Failed to fetch latest release from GitHub")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            if status.as_u16() == 404 {
+                bail!("No releases found. Check https://github.com/{}/releases", GITHUB_REPO);
+            }
+            bail!("GitHub API returned error: {}", status);
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse GitHub release response")
+    }
+
+    /// Get the CLI release for a version
+    pub async fn release(&self, version: &str) -> Result<GitHubRelease> {
+        let url = format!(
+            "{}/repos/{}/releases/tags/{}{}",
+            self.api_base, GITHUB_REPO, CLI_TAG_PREFIX, version
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .context("Failed to fetch release info from GitHub")?;
+
+        if !response.status().is_success() {
+            bail!("Release v{} not found", version);
+        }
+
+        response.json().await.context("Failed to parse release info")
+    }
+
+    /// Start downloading an asset (for streaming large files)
+    pub async fn download(&self, asset: &GitHubAsset) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .get(&asset.browser_download_url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {}", asset.name))?;
+
+        if !response.status().is_success() {
+            bail!("Download of {} failed: {}", asset.name, response.status());
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_version() {
+        let release = GitHubRelease {
+            tag_name: "cli-v0.2.0".to_string(),
+            assets: vec![],
+        };
+        assert_eq!(release.version(), "0.2.0");
+
+        let release = GitHubRelease {
+            tag_name: "v1.0.0".to_string(),
+            assets: vec![],
+        };
+        assert_eq!(release.version(), "1.0.0");
+    }
+}
@@ -0,0 +1,147 @@
+//! IPAM state client
+//!
+//! Reads the flatnet-cni IPAM allocations file on this host. Only the plugin
+//! writes it; the CLI releases allocations through the plugin's DEL (see
+//! `CniPlugin::del`) so every change goes through its lock and journal.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default IPAM data directory (shared with flatnet-cni)
+pub const IPAM_DIR: &str = "/var/lib/flatnet/ipam";
+
+/// IPAM allocations file
+const ALLOCATIONS_FILE: &str = "allocations.json";
+
+/// IPAM state as written by flatnet-cni
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpamState {
+    /// Subnet in CIDR notation
+    pub subnet: String,
+
+    /// Gateway IP (bridge address)
+    pub gateway: String,
+
+    /// Start of allocation range
+    pub range_start: String,
+
+    /// End of allocation range
+    pub range_end: String,
+
+    /// Host ID for multihost deployments
+    #[serde(default = "default_host_id")]
+    pub host_id: u8,
+
+    /// Multihost mode enabled
+    #[serde(default)]
+    pub multihost: bool,
+
+    /// Map of container ID to allocated IP
+    #[serde(default)]
+    pub allocations: HashMap<String, String>,
+
+    /// Map of container ID to container name (if the runtime passed one)
+    #[serde(default)]
+    pub names: HashMap<String, String>,
+}
+
+fn default_host_id() -> u8 {
+    1
+}
+
+impl IpamState {
+    /// Get the bridge address in CIDR notation (e.g., "10.100.1.1/24")
+    pub fn gateway_cidr(&self) -> Option<String> {
+        let prefix = self.subnet.split_once('/')?.1;
+        prefix.parse::<u8>().ok()?;
+        Some(format!("{}/{}", self.gateway, prefix))
+    }
+}
+
+/// Client for the local IPAM state
+#[derive(Debug, Clone)]
+pub struct IpamStore {
+    dir: PathBuf,
+}
+
+impl Default for IpamStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpamStore {
+    /// Create a client for the IPAM directory the installed plugin uses
+    ///
+    /// That is `ipam.dataDir` of the installed flatnet conflist, or
+    /// /var/lib/flatnet/ipam if it sets none.
+    pub fn new() -> Self {
+        Self::with_dir(crate::checks::cni::ipam_dir())
+    }
+
+    /// Create a client for a specific IPAM directory
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// IPAM data directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Check if the allocations file exists
+    pub fn exists(&self) -> bool {
+        self.allocations_path().exists()
+    }
+
+    /// Load the IPAM state
+    pub fn load(&self) -> Result<IpamState> {
+        let path = self.allocations_path();
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn allocations_path(&self) -> PathBuf {
+        Path::new(&self.dir).join(ALLOCATIONS_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATE_JSON: &str = r#"{
+        "subnet": "10.100.1.0/24",
+        "gateway": "10.100.1.1",
+        "range_start": "10.100.1.10",
+        "range_end": "10.100.1.254",
+        "host_id": 1,
+        "multihost": true,
+        "allocations": {
+            "abc123": "10.100.1.10",
+            "def456": "10.100.1.11"
+        },
+        "names": {
+            "abc123": "web"
+        },
+        "released": {
+            "db": {"ip": "10.100.1.12", "released_at": 1700000000}
+        },
+        "last_allocated": "10.100.1.12",
+        "port_mappings": {
+            "def456": [{"hostPort": 8080, "containerPort": 80, "protocol": "tcp"}]
+        }
+    }"#;
+
+    #[test]
+    fn test_gateway_cidr() {
+        let state: IpamState = serde_json::from_str(STATE_JSON).unwrap();
+        assert_eq!(state.gateway_cidr(), Some("10.100.1.1/24".to_string()));
+    }
+}
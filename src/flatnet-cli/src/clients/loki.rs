@@ -1,12 +1,24 @@
 //! Loki client
 //!
-//! HTTP client for querying logs from Loki.
+//! HTTP client for querying logs from Loki, and websocket client for
+//! following them live.
 
 use anyhow::{Context, Result};
-use reqwest::Client;
+use futures::StreamExt;
+use reqwest::{Client, Url};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use thiserror::Error;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+
+/// First delay before reconnecting a dropped tail
+const TAIL_RETRY_MIN: Duration = Duration::from_secs(1);
+
+/// Longest delay between tail reconnection attempts
+const TAIL_RETRY_MAX: Duration = Duration::from_secs(30);
+
+/// Entries Loki may replay when a tail (re)connects (Loki's default maximum)
+const TAIL_BACKFILL_LIMIT: u32 = 5000;
 
 /// Errors that can occur when communicating with Loki
 #[derive(Error, Debug)]
@@ -50,11 +62,23 @@ pub struct LogEntry {
     pub labels: std::collections::HashMap<String, String>,
 }
 
+/// Event delivered while tailing logs
+#[derive(Debug, Clone)]
+pub enum TailEvent {
+    /// A new log entry
+    Entry(LogEntry),
+    /// Loki dropped entries because the client fell behind
+    Dropped(usize),
+    /// The connection was lost and will be retried after `delay`
+    Reconnecting { error: String, delay: Duration },
+}
+
 /// Loki API client
 #[derive(Debug, Clone)]
 pub struct LokiClient {
     client: Client,
     base_url: String,
+    timeout: Duration,
 }
 
 impl LokiClient {
@@ -68,6 +92,7 @@ impl LokiClient {
         Ok(Self {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
+            timeout,
         })
     }
 
@@ -135,17 +160,6 @@ impl LokiClient {
         Ok(parse_loki_response(body))
     }
 
-    /// Query logs for a specific job
-    pub async fn query_job_logs(
-        &self,
-        job: &str,
-        limit: u32,
-        since: Option<&str>,
-    ) -> Result<Vec<LogEntry>, LokiError> {
-        let query = format!("{{job=\"{}\"}}", job);
-        self.query_logs(&query, limit, since).await
-    }
-
     /// Query logs for a specific container
     pub async fn query_container_logs(
         &self,
@@ -157,16 +171,103 @@ impl LokiClient {
         self.query_logs(&query, limit, since).await
     }
 
-    /// Query logs with a grep filter
-    pub async fn query_logs_with_filter(
+    /// Follow logs matching a LogQL query via the tail websocket
+    ///
+    /// Delivers entries from `start_ns` on. After a disconnect the websocket
+    /// is reopened with backoff, resuming after the last delivered entry, so
+    /// this only returns if Loki rejects the query.
+    pub async fn tail<F>(&self, query: &str, start_ns: i64, mut on_event: F) -> Result<(), LokiError>
+    where
+        F: FnMut(TailEvent),
+    {
+        let mut start_ns = start_ns;
+        let mut delay = TAIL_RETRY_MIN;
+
+        loop {
+            let error = match self
+                .tail_once(query, &mut start_ns, &mut delay, &mut on_event)
+                .await
+            {
+                Ok(()) => "connection closed".to_string(),
+                Err(e @ LokiError::RequestFailed(_)) => return Err(e),
+                Err(e) => e.to_string(),
+            };
+
+            on_event(TailEvent::Reconnecting { error, delay });
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(TAIL_RETRY_MAX);
+        }
+    }
+
+    /// Run one tail connection until it closes
+    ///
+    /// Advances `start_ns` past each delivered entry and resets `delay`
+    /// once connected.
+    async fn tail_once<F>(
         &self,
         query: &str,
-        filter: &str,
-        limit: u32,
-        since: Option<&str>,
-    ) -> Result<Vec<LogEntry>, LokiError> {
-        let filtered_query = format!("{} |~ \"{}\"", query, filter);
-        self.query_logs(&filtered_query, limit, since).await
+        start_ns: &mut i64,
+        delay: &mut Duration,
+        on_event: &mut F,
+    ) -> Result<(), LokiError>
+    where
+        F: FnMut(TailEvent),
+    {
+        let url = self.tail_url(query, *start_ns)?;
+
+        let (mut socket, _) =
+            tokio::time::timeout(self.timeout, tokio_tungstenite::connect_async(url.as_str()))
+                .await
+                .map_err(|_| LokiError::Timeout)?
+                .map_err(|e| match e {
+                    // A rejected query would be rejected again on every retry
+                    WsError::Http(response) if response.status().is_client_error() => {
+                        LokiError::RequestFailed(format!("HTTP {}", response.status()))
+                    }
+                    e => LokiError::ConnectionFailed(e.to_string()),
+                })?;
+        *delay = TAIL_RETRY_MIN;
+
+        while let Some(message) = socket.next().await {
+            let text = match message.map_err(|e| LokiError::ConnectionFailed(e.to_string()))? {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+
+            let response: LokiTailResponse = serde_json::from_str(&text)
+                .map_err(|e| LokiError::InvalidResponse(e.to_string()))?;
+            let (entries, dropped) = parse_tail_response(response);
+
+            if dropped > 0 {
+                on_event(TailEvent::Dropped(dropped));
+            }
+            for entry in entries {
+                *start_ns = (*start_ns).max(entry.timestamp + 1);
+                on_event(TailEvent::Entry(entry));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Websocket URL for tailing a query from `start_ns`
+    fn tail_url(&self, query: &str, start_ns: i64) -> Result<Url, LokiError> {
+        let mut url = Url::parse_with_params(
+            &format!("{}/loki/api/v1/tail", self.base_url),
+            &[
+                ("query", query),
+                ("start", &start_ns.to_string()),
+                ("limit", &TAIL_BACKFILL_LIMIT.to_string()),
+            ],
+        )
+        .map_err(|e| LokiError::RequestFailed(format!("Invalid Loki URL: {}", e)))?;
+
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme)
+            .map_err(|_| LokiError::RequestFailed(format!("Invalid Loki URL: {}", self.base_url)))?;
+
+        Ok(url)
     }
 }
 
@@ -190,11 +291,32 @@ struct LokiStream {
     values: Vec<(String, String)>, // (timestamp, line)
 }
 
+/// Loki tail message structure
+#[derive(Debug, Deserialize)]
+struct LokiTailResponse {
+    #[serde(default)]
+    streams: Vec<LokiStream>,
+    #[serde(default)]
+    dropped_entries: Option<Vec<serde_json::Value>>,
+}
+
+/// Parse a tail message into new entries and the number dropped
+fn parse_tail_response(response: LokiTailResponse) -> (Vec<LogEntry>, usize) {
+    let dropped = response.dropped_entries.map_or(0, |d| d.len());
+    let entries = parse_streams(response.streams);
+    (entries, dropped)
+}
+
 /// Parse Loki response into LogEntry vec
 fn parse_loki_response(response: LokiQueryResponse) -> Vec<LogEntry> {
+    parse_streams(response.data.result)
+}
+
+/// Flatten streams into entries, oldest first
+fn parse_streams(streams: Vec<LokiStream>) -> Vec<LogEntry> {
     let mut entries = Vec::new();
 
-    for stream in response.data.result {
+    for stream in streams {
         for (timestamp_str, line) in stream.values {
             let timestamp = timestamp_str.parse::<i64>().unwrap_or(0);
             entries.push(LogEntry {
@@ -213,7 +335,7 @@ fn parse_loki_response(response: LokiQueryResponse) -> Vec<LogEntry> {
 /// Parse duration string to seconds
 ///
 /// Supports: "30s", "5m", "2h", "1d"
-fn parse_duration(s: &str) -> Option<u64> {
+pub fn parse_duration(s: &str) -> Option<u64> {
     let s = s.trim();
     if s.is_empty() {
         return None;
@@ -233,11 +355,38 @@ fn parse_duration(s: &str) -> Option<u64> {
     Some(num * multiplier)
 }
 
+/// Build the LogQL query for a job, with an optional `--grep` line filter
+pub fn job_query(job: &str, grep: Option<&str>) -> String {
+    selector_query(&format!("{{job=\"{}\"}}", job), grep)
+}
+
+/// Build the LogQL query for one operation's lines in a job
+///
+/// flatnet-cni logs the operation ID on every line of an invocation, and
+/// the Gateway logs it for each registry request the invocation makes.
+pub fn operation_query(job: &str, op_id: &str, grep: Option<&str>) -> String {
+    let selector = format!("{{job=\"{}\"}} |= {}", job, logql_string(op_id));
+    selector_query(&selector, grep)
+}
+
+/// Add an optional `--grep` line filter to a label selector
+pub fn selector_query(selector: &str, grep: Option<&str>) -> String {
+    match grep {
+        Some(pattern) => format!("{} |~ {}", selector, logql_string(pattern)),
+        None => selector.to_string(),
+    }
+}
+
+/// Quote a string literal for LogQL
+fn logql_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 /// Map component names to Loki job names
 pub fn component_to_job(component: &str) -> Option<&'static str> {
     match component.to_lowercase().as_str() {
         "gateway" => Some("gateway"),
-        "cni" => Some("flatnet-cni"),
+        "cni" => Some("cni-plugin"),
         "prometheus" => Some("prometheus"),
         "grafana" => Some("grafana"),
         "loki" => Some("loki"),
@@ -268,7 +417,7 @@ mod tests {
     fn test_component_to_job() {
         assert_eq!(component_to_job("gateway"), Some("gateway"));
         assert_eq!(component_to_job("Gateway"), Some("gateway"));
-        assert_eq!(component_to_job("cni"), Some("flatnet-cni"));
+        assert_eq!(component_to_job("cni"), Some("cni-plugin"));
         assert_eq!(component_to_job("unknown"), None);
     }
 
@@ -279,6 +428,49 @@ mod tests {
         assert!(!is_known_component("my-container"));
     }
 
+    #[test]
+    fn test_job_query() {
+        assert_eq!(job_query("gateway", None), r#"{job="gateway"}"#);
+        assert_eq!(
+            selector_query(r#"{host_id="2"}"#, Some("502")),
+            r#"{host_id="2"} |~ "502""#
+        );
+        assert_eq!(
+            job_query("gateway", Some(r#"say "hi"\d"#)),
+            r#"{job="gateway"} |~ "say \"hi\"\\d""#
+        );
+        assert_eq!(
+            operation_query("cni-plugin", "3f9a2c1d5e7b8a40", Some("ADD")),
+            r#"{job="cni-plugin"} |= "3f9a2c1d5e7b8a40" |~ "ADD""#
+        );
+    }
+
+    #[test]
+    fn test_tail_url() {
+        let client = LokiClient::new("https://loki.local:3100/", Duration::from_secs(5)).unwrap();
+        let url = client.tail_url(r#"{job="gateway"}"#, 42).unwrap();
+        assert_eq!(url.scheme(), "wss");
+        assert_eq!(url.path(), "/loki/api/v1/tail");
+        assert!(url.query_pairs().any(|(k, v)| k == "start" && v == "42"));
+    }
+
+    #[test]
+    fn test_parse_tail_response() {
+        let json = r#"{
+            "streams": [
+                {"stream": {"job": "cni-plugin"}, "values": [["1700000001000000000", "ADD ok"]]}
+            ],
+            "dropped_entries": [{"labels": {"job": "cni-plugin"}, "timestamp": "1700000000000000000"}]
+        }"#;
+
+        let response: LokiTailResponse = serde_json::from_str(json).unwrap();
+        let (entries, dropped) = parse_tail_response(response);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].line, "ADD ok");
+        assert_eq!(dropped, 1);
+    }
+
     #[test]
     fn test_parse_loki_response() {
         let json = r#"{
@@ -2,9 +2,16 @@
 //!
 //! This module provides HTTP clients for communicating with various Flatnet services.
 
+pub mod alertmanager;
+pub mod cni_plugin;
 pub mod gateway;
+pub mod github;
+pub mod ipam;
 pub mod loki;
+pub mod nebula;
+pub mod netlink;
 pub mod podman;
+pub mod prometheus;
 
 pub use gateway::GatewayClient;
 pub use loki::LokiClient;
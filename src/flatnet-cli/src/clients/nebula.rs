@@ -0,0 +1,400 @@
+//! Nebula overlay client
+//!
+//! Reads the Nebula host configuration and certificates deployed on the
+//! Windows host (see `config/nebula/host.yaml.template`), and probes the
+//! lighthouse. Certificates are decoded directly (PEM + protobuf), so
+//! `nebula-cert` is not needed in WSL2.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use crate::clients::netlink::Ipv4Network;
+
+/// PEM banner of Nebula v1 certificates
+const CERT_BANNER: &str = "NEBULA CERTIFICATE";
+
+/// Nebula host configuration (only the fields Flatnet relies on)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NebulaConfig {
+    #[serde(default)]
+    pub pki: PkiConfig,
+    /// Overlay IP -> underlay "host:port" addresses
+    #[serde(default)]
+    pub static_host_map: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub lighthouse: LighthouseConfig,
+}
+
+/// Certificate and key paths, as written on the Windows host
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PkiConfig {
+    #[serde(default)]
+    pub ca: String,
+    #[serde(default)]
+    pub cert: String,
+}
+
+/// Lighthouse settings
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LighthouseConfig {
+    #[serde(default)]
+    pub am_lighthouse: bool,
+    /// Overlay IPs of the lighthouses
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+impl NebulaConfig {
+    /// Load a Nebula config file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Parse Nebula config YAML
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    /// Underlay hosts (without port) registered for an overlay IP
+    pub fn underlay_hosts(&self, overlay_ip: &str) -> Vec<String> {
+        self.static_host_map
+            .get(overlay_ip)
+            .map(|addrs| {
+                addrs
+                    .iter()
+                    .map(|a| match a.rsplit_once(':') {
+                        Some((host, _)) => host.to_string(),
+                        None => a.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Map a path from the Nebula config to a WSL2 path
+///
+/// `F:/flatnet/...` and `F:\flatnet\...` become `/mnt/f/flatnet/...`;
+/// relative paths are resolved against the config file's directory.
+pub fn wsl_path(path: &str, config_dir: &Path) -> PathBuf {
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        let drive = (bytes[0] as char).to_ascii_lowercase();
+        let rest = path[2..].replace('\\', "/");
+        return PathBuf::from(format!("/mnt/{}/{}", drive, rest.trim_start_matches('/')));
+    }
+
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        config_dir.join(path)
+    }
+}
+
+/// Details of a Nebula certificate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NebulaCert {
+    pub name: String,
+    pub ips: Vec<Ipv4Network>,
+    pub groups: Vec<String>,
+    /// Unix timestamps
+    pub not_before: i64,
+    pub not_after: i64,
+    pub is_ca: bool,
+}
+
+impl NebulaCert {
+    /// Load a PEM certificate file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_pem(&content).with_context(|| format!("Invalid certificate {}", path.display()))
+    }
+
+    /// Decode the first certificate of a PEM bundle
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        if pem.contains("NEBULA CERTIFICATE V2") {
+            bail!("Nebula v2 certificates are not supported");
+        }
+
+        let begin = format!("-----BEGIN {}-----", CERT_BANNER);
+        let end = format!("-----END {}-----", CERT_BANNER);
+        let Some(start) = pem.find(&begin) else {
+            bail!("No {} PEM block", CERT_BANNER);
+        };
+        let body = &pem[start + begin.len()..];
+        let Some(stop) = body.find(&end) else {
+            bail!("Unterminated {} PEM block", CERT_BANNER);
+        };
+
+        let encoded: String = body[..stop].split_whitespace().collect();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .context("Invalid base64 in PEM block")?;
+        Self::from_protobuf(&der)
+    }
+
+    /// Decode a `RawNebulaCertificate` protobuf message
+    fn from_protobuf(bytes: &[u8]) -> Result<Self> {
+        let mut details = None;
+        let mut reader = ProtoReader::new(bytes);
+        while let Some((field, value)) = reader.next_field()? {
+            if let (1, ProtoValue::Bytes(b)) = (field, value) {
+                details = Some(b);
+            }
+        }
+        let Some(details) = details else {
+            bail!("Certificate has no details");
+        };
+
+        let mut cert = NebulaCert::default();
+        let mut ips = Vec::new();
+        let mut reader = ProtoReader::new(details);
+        while let Some((field, value)) = reader.next_field()? {
+            match (field, value) {
+                (1, ProtoValue::Bytes(b)) => cert.name = String::from_utf8_lossy(b).into_owned(),
+                // Packed (proto3 default) or unpacked repeated uint32
+                (2, ProtoValue::Bytes(b)) => {
+                    let mut packed = ProtoReader::new(b);
+                    while !packed.is_empty() {
+                        ips.push(packed.varint()? as u32);
+                    }
+                }
+                (2, ProtoValue::Varint(v)) => ips.push(v as u32),
+                (4, ProtoValue::Bytes(b)) => {
+                    cert.groups.push(String::from_utf8_lossy(b).into_owned())
+                }
+                (5, ProtoValue::Varint(v)) => cert.not_before = v as i64,
+                (6, ProtoValue::Varint(v)) => cert.not_after = v as i64,
+                (8, ProtoValue::Varint(v)) => cert.is_ca = v != 0,
+                _ => {}
+            }
+        }
+
+        // IPs are stored as (address, mask) pairs
+        cert.ips = ips
+            .chunks_exact(2)
+            .map(|pair| Ipv4Network {
+                address: Ipv4Addr::from(pair[0]),
+                prefix_len: pair[1].count_ones() as u8,
+            })
+            .collect();
+
+        Ok(cert)
+    }
+
+    /// First overlay IP of the certificate
+    pub fn overlay_ip(&self) -> Option<Ipv4Addr> {
+        self.ips.first().map(|n| n.address)
+    }
+
+    /// Whole days until the certificate expires (negative once expired)
+    pub fn days_until_expiry(&self, now: i64) -> i64 {
+        (self.not_after - now).div_euclid(86400)
+    }
+}
+
+/// Probe an address with a single ICMP echo
+pub async fn ping(address: &str, timeout_secs: u64) -> bool {
+    tokio::process::Command::new("ping")
+        .args(["-c", "1", "-W", &timeout_secs.to_string(), address])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Protobuf field value (only the wire types used by Nebula certificates)
+enum ProtoValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Minimal protobuf wire-format reader
+struct ProtoReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ProtoReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let Some(&byte) = self.bytes.get(self.pos) else {
+                bail!("Truncated varint");
+            };
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("Varint too long")
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&e| e <= self.bytes.len());
+        let Some(end) = end else {
+            bail!("Truncated field");
+        };
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn next_field(&mut self) -> Result<Option<(u64, ProtoValue<'a>)>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => ProtoValue::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                ProtoValue::Fixed
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                ProtoValue::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                ProtoValue::Fixed
+            }
+            wire => bail!("Unsupported wire type {}", wire),
+        };
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut v: u64, out: &mut Vec<u8>) {
+        while v >= 0x80 {
+            out.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn field_bytes(field: u64, bytes: &[u8], out: &mut Vec<u8>) {
+        varint(field << 3 | 2, out);
+        varint(bytes.len() as u64, out);
+        out.extend_from_slice(bytes);
+    }
+
+    fn field_varint(field: u64, v: u64, out: &mut Vec<u8>) {
+        varint(field << 3, out);
+        varint(v, out);
+    }
+
+    /// Encode a certificate the way nebula-cert does
+    fn encode_pem(name: &str, ip: [u8; 4], groups: &[&str], not_after: i64) -> String {
+        let mut ips = Vec::new();
+        varint(u32::from(Ipv4Addr::from(ip)) as u64, &mut ips);
+        varint(0xffff_0000, &mut ips);
+
+        let mut details = Vec::new();
+        field_bytes(1, name.as_bytes(), &mut details);
+        field_bytes(2, &ips, &mut details);
+        for group in groups {
+            field_bytes(4, group.as_bytes(), &mut details);
+        }
+        field_varint(5, 1_700_000_000, &mut details);
+        field_varint(6, not_after as u64, &mut details);
+        field_bytes(7, &[0u8; 32], &mut details);
+
+        let mut cert = Vec::new();
+        field_bytes(1, &details, &mut cert);
+        field_bytes(2, &[1u8; 64], &mut cert);
+
+        format!(
+            "-----BEGIN NEBULA CERTIFICATE-----\n{}\n-----END NEBULA CERTIFICATE-----\n",
+            base64::engine::general_purpose::STANDARD.encode(cert)
+        )
+    }
+
+    #[test]
+    fn test_parse_cert() {
+        let pem = encode_pem(
+            "host-a",
+            [10, 100, 1, 1],
+            &["flatnet", "gateway"],
+            1_800_000_000,
+        );
+        let cert = NebulaCert::from_pem(&pem).unwrap();
+
+        assert_eq!(cert.name, "host-a");
+        assert_eq!(cert.ips[0].to_string(), "10.100.1.1/16");
+        assert_eq!(cert.overlay_ip(), Some(Ipv4Addr::new(10, 100, 1, 1)));
+        assert_eq!(cert.groups, vec!["flatnet", "gateway"]);
+        assert_eq!(cert.not_before, 1_700_000_000);
+        assert!(!cert.is_ca);
+        assert_eq!(cert.days_until_expiry(1_800_000_000 - 86400 * 10), 10);
+        assert_eq!(cert.days_until_expiry(1_800_000_000 + 1), -1);
+
+        assert!(NebulaCert::from_pem("-----BEGIN NEBULA CERTIFICATE-----\n!!").is_err());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = NebulaConfig::parse(
+            r#"
+pki:
+  ca: F:/flatnet/config/nebula/ca.crt
+  cert: F:/flatnet/config/nebula/host-a.crt
+  key: F:/flatnet/config/nebula/host-a.key
+static_host_map:
+  "10.100.0.1": ["192.168.1.10:4242"]
+lighthouse:
+  am_lighthouse: false
+  hosts:
+    - "10.100.0.1"
+tun:
+  dev: nebula1
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.lighthouse.hosts, vec!["10.100.0.1"]);
+        assert_eq!(config.underlay_hosts("10.100.0.1"), vec!["192.168.1.10"]);
+        assert!(config.underlay_hosts("10.100.9.9").is_empty());
+
+        let dir = Path::new("/mnt/f/flatnet/config/nebula");
+        assert_eq!(
+            wsl_path(&config.pki.cert, dir),
+            PathBuf::from("/mnt/f/flatnet/config/nebula/host-a.crt")
+        );
+        assert_eq!(
+            wsl_path(r"F:\flatnet\config\nebula\ca.crt", dir),
+            PathBuf::from("/mnt/f/flatnet/config/nebula/ca.crt")
+        );
+        assert_eq!(
+            wsl_path("ca.crt", dir),
+            PathBuf::from("/mnt/f/flatnet/config/nebula/ca.crt")
+        );
+    }
+}
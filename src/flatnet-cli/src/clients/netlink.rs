@@ -0,0 +1,371 @@
+//! Netlink client
+//!
+//! Queries links, addresses, and routes via rtnetlink (as flatnet-cni does)
+//! instead of parsing `ip` output, which varies across iproute2 versions.
+
+use anyhow::{Context, Result};
+use futures::TryStreamExt;
+use netlink_packet_route::address::AddressAttribute;
+use netlink_packet_route::link::{InfoKind, LinkAttribute, LinkFlag, LinkInfo, LinkMessage, State};
+use netlink_packet_route::route::{
+    RouteAddress, RouteAttribute, RouteMessage, RouteProtocol, RouteType,
+};
+use rtnetlink::{new_connection, Handle, IpVersion};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Main routing table (RT_TABLE_MAIN)
+pub const MAIN_TABLE: u32 = 254;
+
+/// A network interface
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Link {
+    /// Interface index
+    pub index: u32,
+    /// Interface name
+    pub name: String,
+    /// Administratively up (IFF_UP)
+    pub admin_up: bool,
+    /// Operational state ("up", "down", or "unknown")
+    pub oper_state: String,
+    /// Index of the bridge this link is attached to
+    pub controller: Option<u32>,
+    /// Link kind (e.g., "bridge", "veth")
+    pub kind: Option<String>,
+}
+
+impl Link {
+    /// Check if the link is operationally up
+    pub fn is_oper_up(&self) -> bool {
+        self.oper_state == "up"
+    }
+}
+
+/// An IPv4 address with prefix length
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Ipv4Network {
+    pub address: Ipv4Addr,
+    pub prefix_len: u8,
+}
+
+impl Ipv4Network {
+    /// Network address with host bits cleared
+    pub fn network(&self) -> u32 {
+        mask(u32::from(self.address), self.prefix_len)
+    }
+
+    /// Check if this network lies within another (is at least as specific)
+    pub fn is_within(&self, other: &Ipv4Network) -> bool {
+        self.prefix_len >= other.prefix_len
+            && mask(u32::from(self.address), other.prefix_len) == other.network()
+    }
+}
+
+impl fmt::Display for Ipv4Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+impl std::str::FromStr for Ipv4Network {
+    type Err = String;
+
+    /// Parse "a.b.c.d/n" (or a bare address as /32)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, len.parse::<u8>().map_err(|e| e.to_string())?),
+            None => (s, 32),
+        };
+        if prefix_len > 32 {
+            return Err(format!("invalid prefix length: {}", prefix_len));
+        }
+        let address = addr.parse().map_err(|e: std::net::AddrParseError| e.to_string())?;
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+}
+
+/// An IPv4 route
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Route {
+    /// Destination network (0.0.0.0/0 for the default route)
+    pub destination: Ipv4Network,
+    /// Next hop
+    pub gateway: Option<Ipv4Addr>,
+    /// Output interface name
+    pub dev: Option<String>,
+    /// Routing table ID
+    pub table: u32,
+    /// Unicast route (excludes local, broadcast, and similar kernel routes)
+    pub unicast: bool,
+    /// Route protocol (`proto` in `ip route`), which tells who installed it
+    pub protocol: u8,
+}
+
+/// Bridge state with its addresses and attached ports
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeInfo {
+    pub link: Link,
+    pub addresses: Vec<Ipv4Network>,
+    pub ports: Vec<Link>,
+}
+
+/// Netlink client for the host network namespace
+pub struct NetlinkClient {
+    handle: Handle,
+}
+
+impl NetlinkClient {
+    /// Open a netlink connection
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn new() -> Result<Self> {
+        let (connection, handle, _) =
+            new_connection().context("Failed to create netlink connection")?;
+        tokio::spawn(connection);
+        Ok(Self { handle })
+    }
+
+    /// List all links
+    pub async fn links(&self) -> Result<Vec<Link>> {
+        let mut stream = self.handle.link().get().execute();
+        let mut links = Vec::new();
+
+        while let Some(msg) = stream.try_next().await.context("Failed to list links")? {
+            links.push(link_from_message(&msg));
+        }
+
+        Ok(links)
+    }
+
+    /// List IPv4 addresses on a link
+    pub async fn ipv4_addresses(&self, index: u32) -> Result<Vec<Ipv4Network>> {
+        let mut stream = self
+            .handle
+            .address()
+            .get()
+            .set_link_index_filter(index)
+            .execute();
+        let mut addresses = Vec::new();
+
+        while let Some(msg) = stream.try_next().await.context("Failed to list addresses")? {
+            for attr in &msg.attributes {
+                if let AddressAttribute::Address(IpAddr::V4(address)) = attr {
+                    addresses.push(Ipv4Network {
+                        address: *address,
+                        prefix_len: msg.header.prefix_len,
+                    });
+                }
+            }
+        }
+
+        addresses.dedup();
+        Ok(addresses)
+    }
+
+    /// List IPv4 routes from all tables
+    pub async fn ipv4_routes(&self) -> Result<Vec<Route>> {
+        let names: HashMap<u32, String> = self
+            .links()
+            .await?
+            .into_iter()
+            .map(|l| (l.index, l.name))
+            .collect();
+
+        let mut stream = self.handle.route().get(IpVersion::V4).execute();
+        let mut routes = Vec::new();
+
+        while let Some(msg) = stream.try_next().await.context("Failed to list routes")? {
+            routes.push(route_from_message(&msg, &names));
+        }
+
+        Ok(routes)
+    }
+
+    /// Add an IPv4 route via a next hop to the main table
+    pub async fn add_ipv4_route(
+        &self,
+        destination: Ipv4Network,
+        gateway: Ipv4Addr,
+        protocol: u8,
+    ) -> Result<()> {
+        self.handle
+            .route()
+            .add()
+            .v4()
+            .destination_prefix(destination.address, destination.prefix_len)
+            .gateway(gateway)
+            .protocol(RouteProtocol::from(protocol))
+            .execute()
+            .await
+            .with_context(|| format!("Failed to add route {} via {}", destination, gateway))
+    }
+
+    /// Delete the kernel routes matching a listed route
+    pub async fn delete_ipv4_route(&self, route: &Route) -> Result<()> {
+        let names = HashMap::new();
+        let mut stream = self.handle.route().get(IpVersion::V4).execute();
+        let mut matching = Vec::new();
+
+        while let Some(msg) = stream.try_next().await.context("Failed to list routes")? {
+            let candidate = route_from_message(&msg, &names);
+            if candidate.destination == route.destination
+                && candidate.gateway == route.gateway
+                && candidate.table == route.table
+            {
+                matching.push(msg);
+            }
+        }
+
+        for msg in matching {
+            self.handle
+                .route()
+                .del(msg)
+                .execute()
+                .await
+                .with_context(|| format!("Failed to delete route {}", route.destination))?;
+        }
+
+        Ok(())
+    }
+
+    /// Get a bridge with its addresses and ports
+    pub async fn bridge(&self, name: &str) -> Result<Option<BridgeInfo>> {
+        let links = self.links().await?;
+        let Some(link) = links.iter().find(|l| l.name == name).cloned() else {
+            return Ok(None);
+        };
+
+        let addresses = self.ipv4_addresses(link.index).await?;
+        let mut ports: Vec<Link> = links
+            .into_iter()
+            .filter(|l| l.controller == Some(link.index))
+            .collect();
+        ports.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Some(BridgeInfo {
+            link,
+            addresses,
+            ports,
+        }))
+    }
+}
+
+/// Convert a netlink link message
+fn link_from_message(msg: &LinkMessage) -> Link {
+    let mut link = Link {
+        index: msg.header.index,
+        name: String::new(),
+        admin_up: msg.header.flags.contains(&LinkFlag::Up),
+        oper_state: "unknown".to_string(),
+        controller: None,
+        kind: None,
+    };
+
+    for attr in &msg.attributes {
+        match attr {
+            LinkAttribute::IfName(name) => link.name = name.clone(),
+            LinkAttribute::OperState(state) => {
+                link.oper_state = match state {
+                    State::Up => "up",
+                    State::Down | State::LowerLayerDown => "down",
+                    _ => "unknown",
+                }
+                .to_string()
+            }
+            LinkAttribute::Controller(index) => link.controller = Some(*index),
+            LinkAttribute::LinkInfo(infos) => {
+                for info in infos {
+                    if let LinkInfo::Kind(kind) = info {
+                        link.kind = Some(
+                            match kind {
+                                InfoKind::Bridge => "bridge",
+                                InfoKind::Veth => "veth",
+                                _ => "other",
+                            }
+                            .to_string(),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    link
+}
+
+/// Convert a netlink route message
+fn route_from_message(msg: &RouteMessage, names: &HashMap<u32, String>) -> Route {
+    let mut route = Route {
+        destination: Ipv4Network {
+            address: Ipv4Addr::UNSPECIFIED,
+            prefix_len: msg.header.destination_prefix_length,
+        },
+        gateway: None,
+        dev: None,
+        table: u32::from(msg.header.table),
+        unicast: msg.header.kind == RouteType::Unicast,
+        protocol: u8::from(msg.header.protocol),
+    };
+
+    for attr in &msg.attributes {
+        match attr {
+            RouteAttribute::Destination(RouteAddress::Inet(address)) => {
+                route.destination.address = *address
+            }
+            RouteAttribute::Gateway(RouteAddress::Inet(address)) => route.gateway = Some(*address),
+            RouteAttribute::Oif(index) => route.dev = names.get(index).cloned(),
+            // Tables above 255 are only carried in the attribute
+            RouteAttribute::Table(table) => route.table = *table,
+            _ => {}
+        }
+    }
+
+    route
+}
+
+/// Apply a prefix mask to an address
+fn mask(addr: u32, prefix_len: u8) -> u32 {
+    if prefix_len == 0 {
+        0
+    } else {
+        addr & (u32::MAX << (32 - prefix_len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ipv4_network() {
+        let net: Ipv4Network = "10.100.1.0/24".parse().unwrap();
+        assert_eq!(net.address, Ipv4Addr::new(10, 100, 1, 0));
+        assert_eq!(net.prefix_len, 24);
+        assert_eq!(net.to_string(), "10.100.1.0/24");
+
+        let host: Ipv4Network = "10.100.1.5".parse().unwrap();
+        assert_eq!(host.prefix_len, 32);
+
+        assert!("10.100.1.0/33".parse::<Ipv4Network>().is_err());
+        assert!("not-an-ip".parse::<Ipv4Network>().is_err());
+    }
+
+    #[test]
+    fn test_ipv4_network_is_within() {
+        let subnet: Ipv4Network = "10.100.1.0/24".parse().unwrap();
+        let more_specific: Ipv4Network = "10.100.1.128/25".parse().unwrap();
+        let broader: Ipv4Network = "10.100.0.0/16".parse().unwrap();
+        let other: Ipv4Network = "10.100.2.0/24".parse().unwrap();
+
+        assert!(more_specific.is_within(&subnet));
+        assert!(subnet.is_within(&subnet));
+        assert!(!broader.is_within(&subnet));
+        assert!(!other.is_within(&subnet));
+    }
+}
@@ -4,6 +4,7 @@
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::process::Command;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command as TokioCommand;
@@ -39,6 +40,10 @@ pub struct PodmanContainer {
     /// Network settings
     #[serde(rename = "Networks", default)]
     pub networks: Option<Vec<String>>,
+
+    /// Container labels
+    #[serde(rename = "Labels", default)]
+    pub labels: Option<HashMap<String, String>>,
 }
 
 impl PodmanContainer {
@@ -107,6 +112,41 @@ impl PodmanClient {
 
     /// Get logs from a container (synchronous, limited lines)
     pub fn get_logs(&self, container: &str, tail: Option<u32>, since: Option<&str>) -> Result<String> {
+        self.run_logs(container, tail, since, false)
+    }
+
+    /// Get logs with their timestamps (for merging with other sources)
+    ///
+    /// Lines without a parseable timestamp inherit the previous line's.
+    pub fn get_timestamped_logs(
+        &self,
+        container: &str,
+        tail: Option<u32>,
+        since: Option<&str>,
+    ) -> Result<Vec<(i64, String)>> {
+        let logs = self.run_logs(container, tail, since, true)?;
+
+        let mut last = 0;
+        Ok(logs
+            .lines()
+            .map(|line| match parse_timestamped_line(line) {
+                Some((timestamp, rest)) => {
+                    last = timestamp;
+                    (timestamp, rest.to_string())
+                }
+                None => (last, line.to_string()),
+            })
+            .collect())
+    }
+
+    /// Run `podman logs` and return stdout and stderr combined
+    fn run_logs(
+        &self,
+        container: &str,
+        tail: Option<u32>,
+        since: Option<&str>,
+        timestamps: bool,
+    ) -> Result<String> {
         let mut cmd = Command::new("podman");
         cmd.arg("logs");
 
@@ -118,6 +158,10 @@ impl PodmanClient {
             cmd.arg("--since").arg(s);
         }
 
+        if timestamps {
+            cmd.arg("--timestamps");
+        }
+
         cmd.arg(container);
 
         let output = cmd
@@ -146,12 +190,20 @@ impl PodmanClient {
     }
 
     /// Follow container logs (streaming)
-    pub async fn follow_logs<F>(&self, container: &str, mut callback: F) -> Result<()>
+    ///
+    /// Starts with the last `tail` lines (all lines if None).
+    pub async fn follow_logs<F>(&self, container: &str, tail: Option<u32>, mut callback: F) -> Result<()>
     where
         F: FnMut(&str),
     {
-        let mut child = TokioCommand::new("podman")
-            .args(["logs", "--follow", container])
+        let mut cmd = TokioCommand::new("podman");
+        cmd.args(["logs", "--follow"]);
+        if let Some(n) = tail {
+            cmd.arg("--tail").arg(n.to_string());
+        }
+
+        let mut child = cmd
+            .arg(container)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
@@ -200,6 +252,13 @@ impl PodmanClient {
     }
 }
 
+/// Split a `podman logs --timestamps` line into nanoseconds and the log line
+fn parse_timestamped_line(line: &str) -> Option<(i64, &str)> {
+    let (timestamp, rest) = line.split_once(' ')?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    Some((timestamp.timestamp_nanos_opt()?, rest))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,6 +304,19 @@ mod tests {
         assert!(!containers[1].is_running());
     }
 
+    #[test]
+    fn test_parse_timestamped_line() {
+        assert_eq!(
+            parse_timestamped_line("2023-11-14T22:13:20.000000001Z GET /"),
+            Some((1_700_000_000_000_000_001, "GET /"))
+        );
+        assert_eq!(
+            parse_timestamped_line("2023-11-14T23:13:20+01:00 GET /"),
+            Some((1_700_000_000_000_000_000, "GET /"))
+        );
+        assert_eq!(parse_timestamped_line("GET /"), None);
+    }
+
     #[test]
     fn test_short_id() {
         let container = PodmanContainer {
@@ -255,6 +327,7 @@ mod tests {
             status: "Up".to_string(),
             created: None,
             networks: None,
+            labels: None,
         };
 
         assert_eq!(container.short_id(), "abc123def456");
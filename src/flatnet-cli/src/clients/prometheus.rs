@@ -0,0 +1,214 @@
+//! Prometheus client
+//!
+//! HTTP client for running PromQL range queries against Prometheus.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors that can occur when communicating with Prometheus
+#[derive(Error, Debug)]
+pub enum PrometheusError {
+    #[error("Prometheus connection failed: {0}")]
+    ConnectionFailed(String),
+
+    #[error("Prometheus request failed: {0}")]
+    RequestFailed(String),
+
+    #[error("Prometheus rejected query: {0}")]
+    QueryFailed(String),
+
+    #[error("Prometheus returned unexpected response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Prometheus timeout")]
+    Timeout,
+}
+
+impl PrometheusError {
+    /// Convert a reqwest error to a PrometheusError
+    fn from_reqwest(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            PrometheusError::Timeout
+        } else if e.is_connect() {
+            PrometheusError::ConnectionFailed(e.to_string())
+        } else {
+            PrometheusError::RequestFailed(e.to_string())
+        }
+    }
+}
+
+/// One series of a range query result
+#[derive(Debug, Clone)]
+pub struct Series {
+    /// Labels of the series
+    pub labels: HashMap<String, String>,
+
+    /// Samples as (unix seconds, value), oldest first
+    pub points: Vec<(i64, f64)>,
+}
+
+/// Prometheus API client
+#[derive(Debug, Clone)]
+pub struct PrometheusClient {
+    client: Client,
+    base_url: String,
+}
+
+impl PrometheusClient {
+    /// Create a new Prometheus client
+    pub fn new(base_url: &str, timeout: Duration) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Evaluate a PromQL expression over a time range
+    pub async fn query_range(
+        &self,
+        query: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        step: Duration,
+    ) -> Result<Vec<Series>, PrometheusError> {
+        let url = format!("{}/api/v1/query_range", self.base_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .query(&[
+                ("query", query),
+                ("start", &start.timestamp().to_string()),
+                ("end", &end.timestamp().to_string()),
+                ("step", &step.as_secs().max(1).to_string()),
+            ])
+            .send()
+            .await
+            .map_err(PrometheusError::from_reqwest)?;
+
+        // Prometheus answers bad queries with 400 and an error body
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| PrometheusError::InvalidResponse(e.to_string()))?;
+
+        if !status.is_success() && !status.is_client_error() {
+            return Err(PrometheusError::RequestFailed(format!(
+                "HTTP {}: {}",
+                status.as_u16(),
+                status.canonical_reason().unwrap_or("Unknown")
+            )));
+        }
+
+        parse_matrix(&body)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    status: String,
+    data: Option<ApiData>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiData {
+    result_type: String,
+    result: Vec<MatrixResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatrixResult {
+    metric: HashMap<String, String>,
+    values: Vec<(f64, String)>,
+}
+
+/// Parse a `/api/v1/query_range` response body
+fn parse_matrix(body: &str) -> Result<Vec<Series>, PrometheusError> {
+    let response: ApiResponse =
+        serde_json::from_str(body).map_err(|e| PrometheusError::InvalidResponse(e.to_string()))?;
+
+    if response.status != "success" {
+        return Err(PrometheusError::QueryFailed(
+            response.error.unwrap_or_else(|| response.status.clone()),
+        ));
+    }
+
+    let data = response
+        .data
+        .ok_or_else(|| PrometheusError::InvalidResponse("missing data".to_string()))?;
+    if data.result_type != "matrix" {
+        return Err(PrometheusError::InvalidResponse(format!(
+            "expected matrix, got {}",
+            data.result_type
+        )));
+    }
+
+    data.result
+        .into_iter()
+        .map(|r| {
+            let points = r
+                .values
+                .into_iter()
+                .map(|(ts, value)| {
+                    // Values are strings so that NaN and +Inf survive JSON
+                    let value = value.parse::<f64>().map_err(|_| {
+                        PrometheusError::InvalidResponse(format!("invalid sample value: {}", value))
+                    })?;
+                    Ok((ts as i64, value))
+                })
+                .collect::<Result<Vec<_>, PrometheusError>>()?;
+            Ok(Series {
+                labels: r.metric,
+                points,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_matrix() {
+        let body = r#"{"status":"success","data":{"resultType":"matrix","result":[
+            {"metric":{"network":"flatnet"},"values":[[1700000000,"1.5"],[1700000060.5,"NaN"],[1700000120,"+Inf"]]}
+        ]}}"#;
+
+        let series = parse_matrix(body).unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].labels["network"], "flatnet");
+        assert_eq!(series[0].points[0], (1700000000, 1.5));
+        assert_eq!(series[0].points[1].0, 1700000060);
+        assert!(series[0].points[1].1.is_nan());
+        assert!(series[0].points[2].1.is_infinite());
+    }
+
+    #[test]
+    fn test_parse_matrix_errors() {
+        let body = r#"{"status":"error","errorType":"bad_data","error":"parse error at char 5"}"#;
+        assert!(matches!(
+            parse_matrix(body),
+            Err(PrometheusError::QueryFailed(msg)) if msg.contains("parse error")
+        ));
+
+        let body = r#"{"status":"success","data":{"resultType":"vector","result":[]}}"#;
+        assert!(matches!(
+            parse_matrix(body),
+            Err(PrometheusError::InvalidResponse(_))
+        ));
+    }
+}
@@ -0,0 +1,200 @@
+//! Alerts command implementation
+//!
+//! Lists Flatnet alerts from Alertmanager and creates silences for
+//! maintenance windows.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use colored::Colorize;
+use std::time::Duration;
+use tabled::{settings::Style, Table, Tabled};
+
+use crate::cli::{AlertsArgs, AlertsCommand, AlertsSilenceArgs};
+use crate::clients::alertmanager::{Alert, AlertmanagerClient};
+use crate::clients::loki::parse_duration;
+use crate::config::Config;
+
+/// Alert display information
+#[derive(Debug, Clone, Tabled)]
+struct AlertDisplay {
+    #[tabled(rename = "ALERT")]
+    name: String,
+
+    #[tabled(rename = "SEVERITY")]
+    severity: String,
+
+    #[tabled(rename = "STATE")]
+    state: String,
+
+    #[tabled(rename = "SINCE")]
+    since: String,
+
+    #[tabled(rename = "SUMMARY")]
+    summary: String,
+}
+
+/// Run the alerts command
+pub async fn run(args: AlertsArgs) -> Result<()> {
+    let config = Config::load()?;
+    let url = &config.monitoring.alertmanager_url;
+    let client = AlertmanagerClient::new(url, config.gateway_timeout())?;
+    let use_color = config.color_enabled();
+
+    match args.command {
+        AlertsCommand::List(list_args) => {
+            let alerts = client
+                .alerts()
+                .await
+                .with_context(|| format!("Failed to list alerts from {}", url))?;
+
+            if list_args.json {
+                println!("{}", serde_json::to_string_pretty(&alerts)?);
+            } else {
+                print_alerts(&alerts, use_color);
+            }
+            Ok(())
+        }
+        AlertsCommand::Silence(silence_args) => silence(&client, &silence_args, use_color).await,
+    }
+}
+
+/// Silence an alert by name
+async fn silence(
+    client: &AlertmanagerClient,
+    args: &AlertsSilenceArgs,
+    use_color: bool,
+) -> Result<()> {
+    let secs = match parse_duration(&args.duration) {
+        Some(secs) if secs > 0 => secs,
+        _ => bail!(
+            "Invalid duration '{}' (expected e.g. 30m, 1h, 1d)",
+            args.duration
+        ),
+    };
+
+    let created_by = std::env::var("USER").unwrap_or_else(|_| "flatnet-cli".to_string());
+    let comment = args
+        .comment
+        .clone()
+        .unwrap_or_else(|| "Maintenance window (flatnet alerts silence)".to_string());
+
+    let id = client
+        .silence(&args.name, Duration::from_secs(secs), &created_by, &comment)
+        .await
+        .context("Failed to create silence")?;
+
+    let message = format!(
+        "Silenced {} for {} (silence {})",
+        args.name, args.duration, id
+    );
+    if use_color {
+        println!("{}", message.green());
+    } else {
+        println!("{}", message);
+    }
+
+    // A silence for a rule that is not firing is valid, but often a typo
+    if let Ok(alerts) = client.alerts().await {
+        if !alerts.iter().any(|a| a.name() == args.name) {
+            println!(
+                "Note: {} is not firing now; the silence applies if it fires before it expires.",
+                args.name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Print alerts as a table
+fn print_alerts(alerts: &[Alert], use_color: bool) {
+    if alerts.is_empty() {
+        if use_color {
+            println!("{}", "No active alerts.".dimmed());
+        } else {
+            println!("No active alerts.");
+        }
+        return;
+    }
+
+    let now = Utc::now();
+    let display: Vec<AlertDisplay> = alerts.iter().map(|a| build_alert_display(a, now)).collect();
+    let table = Table::new(&display).with(Style::blank()).to_string();
+    println!("{}", table);
+
+    let firing = alerts.iter().filter(|a| a.is_active()).count();
+    println!();
+    let summary = format!(
+        "Total: {} alerts, {} firing, {} muted",
+        alerts.len(),
+        firing,
+        alerts.len() - firing
+    );
+    if use_color && firing > 0 {
+        println!("{}", summary.yellow());
+    } else if use_color {
+        println!("{}", summary.dimmed());
+    } else {
+        println!("{}", summary);
+    }
+}
+
+/// Build the table row for an alert
+fn build_alert_display(alert: &Alert, now: chrono::DateTime<Utc>) -> AlertDisplay {
+    let state = if alert.is_silenced() {
+        "silenced"
+    } else if alert.is_active() {
+        "firing"
+    } else {
+        "inhibited"
+    };
+
+    AlertDisplay {
+        name: alert.name().to_string(),
+        severity: alert.severity().to_string(),
+        state: state.to_string(),
+        since: alert
+            .age_secs(now)
+            .map(format_age)
+            .unwrap_or_else(|| "-".to_string()),
+        summary: alert.summary().to_string(),
+    }
+}
+
+/// Format an age in seconds as a short relative time
+fn format_age(secs: i64) -> String {
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h ago", secs / 3600)
+    } else {
+        format!("{}d ago", secs / 86400)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_alert_display() {
+        let alert: Alert = serde_json::from_value(serde_json::json!({
+            "labels": {"alertname": "HighLatency", "severity": "warning"},
+            "annotations": {"summary": "High latency detected"},
+            "startsAt": "2026-10-18T08:00:00Z",
+            "status": {"state": "suppressed", "silencedBy": ["id"], "inhibitedBy": []}
+        }))
+        .unwrap();
+        let now = chrono::DateTime::parse_from_rfc3339("2026-10-18T10:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let display = build_alert_display(&alert, now);
+        assert_eq!(display.name, "HighLatency");
+        assert_eq!(display.state, "silenced");
+        assert_eq!(display.since, "2h ago");
+        assert_eq!(display.summary, "High latency detected");
+    }
+}
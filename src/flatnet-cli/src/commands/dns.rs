@@ -0,0 +1,276 @@
+//! DNS command implementation
+//!
+//! Serves the container names of the flatnet network, normally on the
+//! bridge gateway IP that flatnet-cni hands out as the containers'
+//! nameserver. The name table is reloaded from the local IPAM state and the
+//! Gateway registry every `dns.refresh_secs`; other queries are forwarded.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use tokio::net::UdpSocket;
+use tokio::time::{sleep, timeout, Duration};
+
+use crate::cli::{DnsArgs, DnsCommand, DnsServeArgs};
+use crate::clients::gateway::{ContainerInfo, GatewayClient};
+use crate::clients::ipam::IpamStore;
+use crate::config::Config;
+use crate::dns::{self, Question, Records};
+
+/// How long to wait for each upstream server
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Largest UDP message accepted
+const MAX_MESSAGE_LEN: usize = 65535;
+
+/// Run the dns command
+pub async fn run(args: DnsArgs) -> Result<()> {
+    let config = Config::load()?;
+    let use_color = config.color_enabled();
+
+    match args.command {
+        DnsCommand::Serve(serve_args) => serve(&config, serve_args, use_color).await,
+    }
+}
+
+/// Answer queries until interrupted
+async fn serve(config: &Config, args: DnsServeArgs, use_color: bool) -> Result<()> {
+    let store = config.dns.ipam_dir.as_ref().map_or_else(IpamStore::new, IpamStore::with_dir);
+
+    let listen: IpAddr = match args.listen.or_else(|| config.dns.listen.clone()) {
+        Some(listen) => listen
+            .parse()
+            .with_context(|| format!("Invalid listen address: {}", listen))?,
+        None => {
+            let state = store.load().context(
+                "Cannot determine the bridge gateway IP; set dns.listen or use --listen",
+            )?;
+            state.gateway.parse().with_context(|| {
+                format!("Invalid gateway IP in the IPAM state: {}", state.gateway)
+            })?
+        }
+    };
+    let listen = SocketAddr::new(listen, args.port.unwrap_or(config.dns.port));
+
+    let upstreams = upstreams(config, &args.upstream, listen)?;
+    if upstreams.is_empty() {
+        print_error(
+            "No upstream DNS servers; only container names will resolve",
+            use_color,
+        );
+    }
+
+    let socket = UdpSocket::bind(listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+    let socket = Arc::new(socket);
+
+    let mut source = RecordSource {
+        store,
+        gateway: GatewayClient::new(config.gateway_url(), config.gateway_timeout())?,
+        domain: config.dns.domain.clone(),
+        registry: Vec::new(),
+        ipam_failed: false,
+        registry_failed: false,
+        use_color,
+    };
+    let records = Arc::new(RwLock::new(source.load().await));
+
+    let forwarding = if upstreams.is_empty() {
+        "nowhere".to_string()
+    } else {
+        upstreams
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    println!(
+        "Serving *.{} on {} ({} names), forwarding other queries to {}. Press Ctrl+C to exit.",
+        config.dns.domain,
+        listen,
+        records.read().unwrap().len(),
+        forwarding
+    );
+
+    let refresh = Duration::from_secs(config.dns.refresh_secs.max(1));
+    let shared = Arc::clone(&records);
+    tokio::spawn(async move {
+        loop {
+            sleep(refresh).await;
+            let loaded = source.load().await;
+            *shared.write().unwrap() = loaded;
+        }
+    });
+
+    let upstreams = Arc::new(upstreams);
+    let ttl = config.dns.ttl_secs;
+    let mut buf = vec![0u8; MAX_MESSAGE_LEN];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                print_error(&format!("Failed to receive a query: {}", e), use_color);
+                continue;
+            }
+        };
+        let packet = buf[..len].to_vec();
+        // Responses and truncated packets are dropped, never forwarded
+        if !dns::is_query(&packet) {
+            continue;
+        }
+
+        let question = dns::parse_query(&packet);
+        if let Some(question) = &question {
+            let lookup = records.read().unwrap().lookup(question);
+            if let Some(reply) = dns::answer(&packet, question, &lookup, ttl) {
+                let _ = socket.send_to(&reply, peer).await;
+                continue;
+            }
+        }
+
+        tokio::spawn(forward(
+            Arc::clone(&socket),
+            packet,
+            question,
+            peer,
+            Arc::clone(&upstreams),
+        ));
+    }
+}
+
+/// Upstream servers from the arguments, config or /etc/resolv.conf
+///
+/// The listen address itself is skipped so queries never loop back.
+fn upstreams(config: &Config, args: &[String], listen: SocketAddr) -> Result<Vec<SocketAddr>> {
+    let configured = if !args.is_empty() {
+        args.to_vec()
+    } else if !config.dns.upstreams.is_empty() {
+        config.dns.upstreams.clone()
+    } else {
+        let resolv = std::fs::read_to_string("/etc/resolv.conf").unwrap_or_default();
+        dns::resolv_conf_nameservers(&resolv)
+    };
+
+    let mut upstreams = Vec::new();
+    for upstream in &configured {
+        let addr = dns::parse_upstream(upstream)?;
+        if addr != listen {
+            upstreams.push(addr);
+        }
+    }
+    Ok(upstreams)
+}
+
+/// Relay a query to the first upstream server that answers
+async fn forward(
+    socket: Arc<UdpSocket>,
+    packet: Vec<u8>,
+    question: Option<Question>,
+    peer: SocketAddr,
+    upstreams: Arc<Vec<SocketAddr>>,
+) {
+    for upstream in upstreams.iter() {
+        if let Ok(reply) = exchange(&packet, *upstream).await {
+            let _ = socket.send_to(&reply, peer).await;
+            return;
+        }
+    }
+    if let Some(question) = question {
+        let _ = socket
+            .send_to(&dns::servfail(&packet, &question), peer)
+            .await;
+    }
+}
+
+/// Send a query to one server and wait for the reply with the same ID
+async fn exchange(packet: &[u8], upstream: SocketAddr) -> io::Result<Vec<u8>> {
+    let local: SocketAddr = if upstream.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(upstream).await?;
+    socket.send(packet).await?;
+
+    let mut buf = vec![0u8; MAX_MESSAGE_LEN];
+    let receive = async {
+        loop {
+            let len = socket.recv(&mut buf).await?;
+            if len >= 2 && buf[..2] == packet[..2] {
+                return Ok(buf[..len].to_vec());
+            }
+        }
+    };
+    timeout(UPSTREAM_TIMEOUT, receive)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "upstream timed out"))?
+}
+
+/// Loads the name table, keeping the last registry copy while the Gateway is down
+struct RecordSource {
+    store: IpamStore,
+    gateway: GatewayClient,
+    domain: String,
+    registry: Vec<ContainerInfo>,
+    ipam_failed: bool,
+    registry_failed: bool,
+    use_color: bool,
+}
+
+impl RecordSource {
+    /// Build the table, reporting each source's failures and recoveries once
+    async fn load(&mut self) -> Records {
+        // No state file yet just means no local containers
+        let local = match self.store.exists().then(|| self.store.load()) {
+            Some(Err(e)) => {
+                if !self.ipam_failed {
+                    print_error(&format!("{:#}", e), self.use_color);
+                }
+                self.ipam_failed = true;
+                None
+            }
+            Some(Ok(state)) => {
+                self.ipam_failed = false;
+                Some(state)
+            }
+            None => None,
+        };
+
+        match self.gateway.containers().await {
+            Ok(containers) => {
+                if self.registry_failed {
+                    println!("Gateway registry is reachable again");
+                }
+                self.registry_failed = false;
+                self.registry = containers;
+            }
+            Err(e) => {
+                if !self.registry_failed {
+                    print_error(
+                        &format!(
+                            "Failed to list containers from the Gateway registry (serving {} cached): {}",
+                            self.registry.len(),
+                            e
+                        ),
+                        self.use_color,
+                    );
+                }
+                self.registry_failed = true;
+            }
+        }
+
+        Records::build(&self.domain, local.as_ref(), &self.registry)
+    }
+}
+
+fn print_error(message: &str, use_color: bool) {
+    if use_color {
+        eprintln!("{} {}", "Error:".red(), message);
+    } else {
+        eprintln!("Error: {}", message);
+    }
+}
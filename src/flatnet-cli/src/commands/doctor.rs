@@ -1,11 +1,13 @@
 //! Doctor command implementation
 //!
-//! Runs system diagnostics and reports issues with suggestions.
+//! Runs system diagnostics, reports issues with suggestions, and applies
+//! available fixes with `--fix`.
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use colored::Colorize;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
 use std::process::ExitCode;
 
 use crate::checks::{self, calculate_summary, determine_exit_code, CheckResult, CheckStatus};
@@ -24,6 +26,8 @@ const SUMMARY_LINE: &str = "\u{2501}"; // ━
 #[derive(Debug, Clone, Serialize)]
 struct DoctorOutput {
     checks: Vec<CheckResult>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fixes: Vec<FixOutcome>,
     summary: SummaryOutput,
 }
 
@@ -35,9 +39,36 @@ struct SummaryOutput {
     exit_code: i32,
 }
 
+/// Result of applying a single fix
+#[derive(Debug, Clone, Serialize)]
+struct FixOutcome {
+    category: String,
+    name: String,
+    action: String,
+    status: FixStatus,
+    message: String,
+}
+
+/// Status of an applied fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum FixStatus {
+    /// Fix was applied
+    Applied,
+    /// User declined the fix
+    Skipped,
+    /// Fix failed to apply
+    Failed,
+}
+
 /// Run the doctor command
 pub async fn run(args: DoctorArgs) -> Result<ExitCode> {
     let config = Config::load()?;
+    let use_color = config.color_enabled();
+
+    if args.fix && args.json && !args.yes {
+        bail!("--fix with --json cannot prompt; add --yes to apply fixes");
+    }
 
     if !args.quiet && !args.json {
         println!();
@@ -46,7 +77,52 @@ pub async fn run(args: DoctorArgs) -> Result<ExitCode> {
     }
 
     // Run all checks
-    let results = checks::run_all_checks(&config).await;
+    let mut results = checks::run_all_checks(&config).await;
+
+    if !args.json {
+        if args.quiet {
+            print_quiet_output(&results, use_color);
+        } else {
+            print_formatted_output(&results, args.verbose, use_color);
+        }
+    }
+
+    // Apply fixes and re-run the affected checks
+    let mut fixes = Vec::new();
+    if args.fix {
+        fixes = apply_fixes(&config, &results, &args, use_color).await;
+
+        let mut affected: Vec<String> = fixes
+            .iter()
+            .filter(|f| f.status == FixStatus::Applied)
+            .map(|f| f.category.clone())
+            .collect();
+        affected.sort();
+        affected.dedup();
+
+        if !affected.is_empty() {
+            if !args.json && !args.quiet {
+                println!("{}", "Re-running affected checks...".bold());
+                println!();
+            }
+
+            let mut rechecked = Vec::new();
+            for category in &affected {
+                rechecked.extend(checks::run_category(&config, category).await);
+            }
+
+            if !args.json {
+                if args.quiet {
+                    print_quiet_output(&rechecked, use_color);
+                } else {
+                    print_formatted_output(&rechecked, args.verbose, use_color);
+                }
+            }
+
+            results.retain(|r| !affected.contains(&r.category));
+            results.extend(rechecked);
+        }
+    }
 
     // Calculate summary
     let (passed, warnings, failed) = calculate_summary(&results);
@@ -54,12 +130,23 @@ pub async fn run(args: DoctorArgs) -> Result<ExitCode> {
 
     // Output results
     if args.json {
-        print_json_output(&results, passed, warnings, failed, exit_code)?;
-    } else if args.quiet {
-        print_quiet_output(&results, config.color_enabled());
-    } else {
-        print_formatted_output(&results, args.verbose, config.color_enabled());
-        print_summary(passed, warnings, failed, config.color_enabled());
+        print_json_output(&results, fixes, passed, warnings, failed, exit_code)?;
+    } else if !args.quiet {
+        print_summary(passed, warnings, failed, use_color);
+        if !args.fix {
+            let fixable = results.iter().filter(|r| r.is_fixable()).count();
+            if fixable > 0 {
+                let hint = format!(
+                    "{} issue(s) can be fixed automatically: flatnet doctor --fix",
+                    fixable
+                );
+                if use_color {
+                    println!("{}", hint.dimmed());
+                } else {
+                    println!("{}", hint);
+                }
+            }
+        }
     }
 
     // Return appropriate exit code
@@ -70,9 +157,120 @@ pub async fn run(args: DoctorArgs) -> Result<ExitCode> {
     })
 }
 
+/// Apply the remediations attached to failed checks
+async fn apply_fixes(
+    config: &Config,
+    results: &[CheckResult],
+    args: &DoctorArgs,
+    use_color: bool,
+) -> Vec<FixOutcome> {
+    let mut fixable: Vec<&CheckResult> = results.iter().filter(|r| r.is_fixable()).collect();
+    fixable.sort_by_key(|r| r.remediation.as_ref().map(|f| f.priority()));
+
+    let interactive = !args.yes;
+    let show = !args.json;
+
+    if fixable.is_empty() {
+        if show {
+            println!("No automatic fixes available.");
+            println!();
+        }
+        return Vec::new();
+    }
+
+    if show {
+        println!("{}", "Applying fixes...".bold());
+        println!();
+    }
+
+    let mut outcomes = Vec::new();
+    for result in fixable {
+        let Some(ref remediation) = result.remediation else {
+            continue;
+        };
+        let action = remediation.description();
+
+        let (status, message) = if interactive && !confirm(&action) {
+            (FixStatus::Skipped, "Skipped".to_string())
+        } else {
+            match remediation.apply(config).await {
+                Ok(message) => (FixStatus::Applied, message),
+                Err(e) => (FixStatus::Failed, format!("{:#}", e)),
+            }
+        };
+
+        if show {
+            print_fix_outcome(&action, status, &message, use_color);
+        }
+
+        outcomes.push(FixOutcome {
+            category: result.category.clone(),
+            name: result.name.clone(),
+            action,
+            status,
+            message,
+        });
+    }
+
+    if show {
+        println!();
+    }
+
+    outcomes
+}
+
+/// Ask the user to confirm a fix (defaults to no)
+fn confirm(action: &str) -> bool {
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+
+    print!("  {}? [y/N] ", action);
+    if std::io::stdout().flush().is_err() {
+        return false;
+    }
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Print the outcome of a single fix
+fn print_fix_outcome(action: &str, status: FixStatus, message: &str, use_color: bool) {
+    let (symbol, label) = match status {
+        FixStatus::Applied => (CHECK_PASS, "fixed"),
+        FixStatus::Skipped => (CHECK_WARN, "skipped"),
+        FixStatus::Failed => (CHECK_FAIL, "failed"),
+    };
+
+    let line = format!("  [{}] {}: {}", symbol, action, label);
+    if use_color {
+        let colored_line = match status {
+            FixStatus::Applied => line.green(),
+            FixStatus::Skipped => line.yellow(),
+            FixStatus::Failed => line.red(),
+        };
+        println!("{}", colored_line);
+    } else {
+        println!("{}", line);
+    }
+
+    if status != FixStatus::Skipped {
+        if use_color {
+            println!("      {} {}", "\u{2192}".dimmed(), message.dimmed());
+        } else {
+            println!("      -> {}", message);
+        }
+    }
+}
+
 /// Print results in JSON format
 fn print_json_output(
     results: &[CheckResult],
+    fixes: Vec<FixOutcome>,
     passed: usize,
     warnings: usize,
     failed: usize,
@@ -80,6 +278,7 @@ fn print_json_output(
 ) -> Result<()> {
     let output = DoctorOutput {
         checks: results.to_vec(),
+        fixes,
         summary: SummaryOutput {
             passed,
             warnings,
@@ -139,7 +338,16 @@ fn print_formatted_output(results: &[CheckResult], verbose: bool, use_color: boo
     }
 
     // Define category order
-    let category_order = ["Gateway", "CNI Plugin", "Network", "Monitoring", "Disk"];
+    let category_order = [
+        "Gateway",
+        "CNI Plugin",
+        "Kernel",
+        "Network",
+        "Nebula",
+        "Monitoring",
+        "Alerts",
+        "Disk",
+    ];
 
     for category in category_order.iter() {
         if let Some(checks) = categories.get(*category) {
@@ -287,4 +495,19 @@ mod tests {
         assert!(json.contains("\"failed\":1"));
         assert!(json.contains("\"exit_code\":2"));
     }
+
+    #[test]
+    fn test_fix_outcome_serialize() {
+        let outcome = FixOutcome {
+            category: "CNI Plugin".to_string(),
+            name: "Bridge exists".to_string(),
+            action: "Bring up bridge flatnet-br0".to_string(),
+            status: FixStatus::Applied,
+            message: "Bridge flatnet-br0 is up".to_string(),
+        };
+
+        let json = serde_json::to_string(&outcome).unwrap();
+        assert!(json.contains("\"status\":\"applied\""));
+        assert!(json.contains("\"category\":\"CNI Plugin\""));
+    }
 }
@@ -1,14 +1,20 @@
 //! Logs command implementation
 //!
-//! Displays logs from Flatnet components and containers.
+//! Displays logs from Flatnet components and containers. Components and
+//! label selectors are queried (and followed) through Loki; containers
+//! through Podman. Several targets are merged by timestamp, as are the
+//! CNI plugin and Gateway lines of one CNI operation (`--op`).
 
 use anyhow::{bail, Result};
-use colored::Colorize;
+use colored::{Color, Colorize};
 use serde::Serialize;
 use std::time::Duration;
 
 use crate::cli::LogsArgs;
-use crate::clients::loki::{component_to_job, is_known_component, LokiClient, LogEntry};
+use crate::clients::loki::{
+    component_to_job, is_known_component, job_query, operation_query, selector_query, LogEntry,
+    LokiClient, TailEvent,
+};
 use crate::clients::podman::PodmanClient;
 use crate::config::Config;
 
@@ -26,18 +32,76 @@ struct LogEntryOutput {
     line: String,
 }
 
+/// JSON output structure for merged logs
+#[derive(Debug, Clone, Serialize)]
+struct MergedLogsOutput {
+    targets: Vec<String>,
+    entries: Vec<MergedEntryOutput>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MergedEntryOutput {
+    source: String,
+    timestamp: String,
+    line: String,
+}
+
+/// Where a target's logs come from in merged output
+#[derive(Debug, Clone, PartialEq)]
+enum MergedSource {
+    /// A LogQL query (component job or `--selector`)
+    Loki(String),
+    /// A container's Podman logs
+    Podman(String),
+}
+
+/// A target in merged output
+#[derive(Debug, Clone)]
+struct MergedTarget {
+    /// Prefix for its lines (target name or selector)
+    label: String,
+    source: MergedSource,
+}
+
+/// A line in merged output
+#[derive(Debug, Clone)]
+struct MergedEntry {
+    /// Index of the target it came from
+    target: usize,
+    timestamp: i64,
+    line: String,
+}
+
+/// Colors cycled through for line prefixes
+const PREFIX_COLORS: [Color; 5] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+];
+
 /// Run the logs command
 pub async fn run(args: LogsArgs) -> Result<()> {
     let config = Config::load()?;
 
+    // Several targets, a selector, or an operation: merge all sources by timestamp
+    if args.targets.len() != 1 || !args.selector.is_empty() {
+        return run_merged(&config, &args).await;
+    }
+
     // Determine the target (component or container name)
-    let target = &args.target;
+    let target = &args.targets[0];
 
     // Try Loki first if it's a known component
     if is_known_component(target) {
         if let Some(job) = component_to_job(target) {
             match fetch_from_loki(&config, job, &args).await {
-                Ok(entries) => {
+                Ok((client, entries)) => {
+                    if args.follow {
+                        return follow_from_loki(&client, job, &args, &entries, config.color_enabled())
+                            .await;
+                    }
                     output_logs(&args, target, "loki", &entries, config.color_enabled())?;
                     return Ok(());
                 }
@@ -57,18 +121,20 @@ pub async fn run(args: LogsArgs) -> Result<()> {
 
     // Fallback to Podman logs
     if args.follow {
-        follow_podman_logs(target, config.color_enabled()).await
+        follow_podman_logs(target, &args, config.color_enabled()).await
     } else {
         fetch_from_podman(target, &args, config.color_enabled())
     }
 }
 
 /// Fetch logs from Loki
+///
+/// Returns the client too, so `--follow` can continue from these entries.
 async fn fetch_from_loki(
     config: &Config,
     job: &str,
     args: &LogsArgs,
-) -> Result<Vec<LogEntry>> {
+) -> Result<(LokiClient, Vec<LogEntry>)> {
     let timeout = Duration::from_secs(config.gateway.timeout_secs);
     let client = LokiClient::new(&config.monitoring.loki_url, timeout)?;
 
@@ -80,16 +146,317 @@ async fn fetch_from_loki(
     let limit = args.tail.unwrap_or(100);
     let since = args.since.as_deref();
 
-    let entries = if let Some(ref grep) = args.grep {
-        let query = format!("{{job=\"{}\"}}", job);
-        client
-            .query_logs_with_filter(&query, grep, limit, since)
+    let query = job_query(job, args.grep.as_deref());
+    let entries = client.query_logs(&query, limit, since).await?;
+
+    Ok((client, entries))
+}
+
+/// Print recent entries, then stream new ones from Loki until interrupted
+///
+/// With `--json`, each entry is printed as one JSON object per line.
+async fn follow_from_loki(
+    client: &LokiClient,
+    job: &str,
+    args: &LogsArgs,
+    history: &[LogEntry],
+    use_color: bool,
+) -> Result<()> {
+    for entry in history {
+        print_entry(entry, args.json, use_color);
+    }
+
+    // Continue right after the last entry shown
+    let start_ns = history
+        .last()
+        .map(|e| e.timestamp + 1)
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0));
+
+    let query = job_query(job, args.grep.as_deref());
+    client
+        .tail(&query, start_ns, |event| match event {
+            TailEvent::Entry(entry) => print_entry(&entry, args.json, use_color),
+            other => report_tail_event(other),
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Report dropped entries and reconnects on stderr
+fn report_tail_event(event: TailEvent) {
+    match event {
+        TailEvent::Entry(_) => {}
+        TailEvent::Dropped(count) => {
+            eprintln!(
+                "{}: Loki dropped {} entries (output too slow)",
+                "Note".yellow(),
+                count
+            );
+        }
+        TailEvent::Reconnecting { error, delay } => {
+            eprintln!(
+                "{}: Lost connection to Loki ({}), reconnecting in {}s...",
+                "Note".yellow(),
+                error,
+                delay.as_secs()
+            );
+        }
+    }
+}
+
+/// Show logs from several targets and selectors merged by timestamp
+async fn run_merged(config: &Config, args: &LogsArgs) -> Result<()> {
+    let use_color = config.color_enabled();
+    let timeout = Duration::from_secs(config.gateway.timeout_secs);
+    let client = LokiClient::new(&config.monitoring.loki_url, timeout)?;
+    let loki_ready = client.is_ready().await;
+
+    let targets = resolve_targets(args, loki_ready)?;
+    if !loki_ready && !args.json && args.targets.iter().any(|t| is_known_component(t)) {
+        eprintln!(
+            "{}: Loki unavailable, falling back to Podman logs for components...",
+            "Note".yellow()
+        );
+    }
+
+    // Fetch every target; following continues after the last entry of each
+    let started_ns = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+    let mut entries = Vec::new();
+    let mut follow_from = Vec::new();
+    for (index, target) in targets.iter().enumerate() {
+        let fetched = fetch_target(&client, target, index, args).await?;
+        follow_from.push(fetched.iter().map(|e| e.timestamp + 1).max().unwrap_or(started_ns));
+        entries.extend(fetched);
+    }
+    let entries = merge_entries(entries, args.tail);
+
+    let labels: Vec<String> = targets.iter().map(|t| t.label.clone()).collect();
+
+    if args.json && !args.follow {
+        let output = MergedLogsOutput {
+            entries: entries
+                .iter()
+                .map(|e| MergedEntryOutput {
+                    source: labels[e.target].clone(),
+                    timestamp: format_timestamp(e.timestamp),
+                    line: e.line.clone(),
+                })
+                .collect(),
+            targets: labels,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let printer = LinePrinter::new(labels, args.json, use_color);
+    for entry in &entries {
+        printer.print(entry.target, entry.timestamp, &entry.line);
+    }
+
+    if !args.follow {
+        if entries.is_empty() {
+            if use_color {
+                println!("{}", "No logs found.".dimmed());
+            } else {
+                println!("No logs found.");
+            }
+        }
+        return Ok(());
+    }
+
+    let follows = targets.iter().enumerate().map(|(index, target)| {
+        follow_target(&client, target, index, follow_from[index], args, &printer)
+    });
+    futures::future::try_join_all(follows).await?;
+
+    Ok(())
+}
+
+/// Map targets, selectors, and `--op` to log sources
+///
+/// Components fall back to Podman when Loki is unavailable; selectors and
+/// operations need Loki.
+fn resolve_targets(args: &LogsArgs, loki_ready: bool) -> Result<Vec<MergedTarget>> {
+    let grep = args.grep.as_deref();
+    let mut targets = Vec::new();
+
+    if let Some(op_id) = &args.op {
+        if op_id.is_empty() || !op_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            bail!("Invalid operation ID '{}': expected the op_id of a CNI log line", op_id);
+        }
+        if !loki_ready {
+            bail!("Loki is not ready; --op needs Loki");
+        }
+        for component in ["cni", "gateway"] {
+            let job = component_to_job(component).unwrap_or(component);
+            targets.push(MergedTarget {
+                label: component.to_string(),
+                source: MergedSource::Loki(operation_query(job, op_id, grep)),
+            });
+        }
+    }
+
+    for name in &args.targets {
+        let source = match component_to_job(name) {
+            Some(job) if loki_ready => MergedSource::Loki(job_query(job, grep)),
+            _ => MergedSource::Podman(name.clone()),
+        };
+        targets.push(MergedTarget {
+            label: name.clone(),
+            source,
+        });
+    }
+
+    for selector in &args.selector {
+        if !(selector.starts_with('{') && selector.ends_with('}')) {
+            bail!(
+                "Invalid selector '{}': expected a LogQL label selector like '{{host_id=\"2\"}}'",
+                selector
+            );
+        }
+        if !loki_ready {
+            bail!("Loki is not ready; --selector needs Loki");
+        }
+        targets.push(MergedTarget {
+            label: selector.clone(),
+            source: MergedSource::Loki(selector_query(selector, grep)),
+        });
+    }
+
+    Ok(targets)
+}
+
+/// Fetch one target's entries for merged output
+async fn fetch_target(
+    client: &LokiClient,
+    target: &MergedTarget,
+    index: usize,
+    args: &LogsArgs,
+) -> Result<Vec<MergedEntry>> {
+    let since = args.since.as_deref();
+
+    let lines: Vec<(i64, String)> = match &target.source {
+        MergedSource::Loki(query) => client
+            .query_logs(query, args.tail.unwrap_or(100), since)
             .await?
-    } else {
-        client.query_job_logs(job, limit, since).await?
+            .into_iter()
+            .map(|e| (e.timestamp, e.line))
+            .collect(),
+        MergedSource::Podman(container) => {
+            let podman = PodmanClient::new();
+            if !podman.container_exists(container) {
+                bail!("Target not found: {}", container);
+            }
+            podman
+                .get_timestamped_logs(container, args.tail, since)?
+                .into_iter()
+                .filter(|(_, line)| {
+                    args.grep
+                        .as_deref()
+                        .is_none_or(|pattern| line.contains(pattern))
+                })
+                .collect()
+        }
     };
 
-    Ok(entries)
+    Ok(lines
+        .into_iter()
+        .map(|(timestamp, line)| MergedEntry {
+            target: index,
+            timestamp,
+            line,
+        })
+        .collect())
+}
+
+/// Follow one target for merged output until interrupted
+async fn follow_target(
+    client: &LokiClient,
+    target: &MergedTarget,
+    index: usize,
+    start_ns: i64,
+    args: &LogsArgs,
+    printer: &LinePrinter,
+) -> Result<()> {
+    match &target.source {
+        MergedSource::Loki(query) => {
+            client
+                .tail(query, start_ns, |event| match event {
+                    TailEvent::Entry(entry) => printer.print(index, entry.timestamp, &entry.line),
+                    other => report_tail_event(other),
+                })
+                .await?;
+        }
+        MergedSource::Podman(container) => {
+            // History was already shown; lines are stamped on arrival
+            PodmanClient::new()
+                .follow_logs(container, Some(0), |line| {
+                    if args.grep.as_deref().is_none_or(|pattern| line.contains(pattern)) {
+                        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+                        printer.print(index, now, line);
+                    }
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Order entries by timestamp and keep the last `tail`
+///
+/// The sort is stable, so each source keeps its own order on ties.
+fn merge_entries(mut entries: Vec<MergedEntry>, tail: Option<u32>) -> Vec<MergedEntry> {
+    entries.sort_by_key(|e| e.timestamp);
+    if let Some(tail) = tail {
+        let excess = entries.len().saturating_sub(tail as usize);
+        entries.drain(..excess);
+    }
+    entries
+}
+
+/// Prints merged lines prefixed with the label of their target
+struct LinePrinter {
+    labels: Vec<String>,
+    width: usize,
+    json: bool,
+    use_color: bool,
+}
+
+impl LinePrinter {
+    fn new(labels: Vec<String>, json: bool, use_color: bool) -> Self {
+        let width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+        Self {
+            labels,
+            width,
+            json,
+            use_color,
+        }
+    }
+
+    /// Print a line (as a JSON line with `json`)
+    fn print(&self, target: usize, timestamp: i64, line: &str) {
+        let label = &self.labels[target];
+        let timestamp = format_timestamp(timestamp);
+
+        if self.json {
+            let output = MergedEntryOutput {
+                source: label.clone(),
+                timestamp,
+                line: line.to_string(),
+            };
+            if let Ok(json) = serde_json::to_string(&output) {
+                println!("{}", json);
+            }
+        } else if self.use_color {
+            let prefix = format!("{:<width$} |", label, width = self.width)
+                .color(PREFIX_COLORS[target % PREFIX_COLORS.len()]);
+            println!("{} {} {}", prefix, timestamp.dimmed(), line);
+        } else {
+            println!("{:<width$} | {} {}", label, timestamp, line, width = self.width);
+        }
+    }
 }
 
 /// Fetch logs from Podman
@@ -148,7 +515,7 @@ fn fetch_from_podman(target: &str, args: &LogsArgs, use_color: bool) -> Result<(
 }
 
 /// Follow Podman logs in real-time
-async fn follow_podman_logs(target: &str, use_color: bool) -> Result<()> {
+async fn follow_podman_logs(target: &str, args: &LogsArgs, use_color: bool) -> Result<()> {
     let podman = PodmanClient::new();
 
     // Check if container exists
@@ -167,8 +534,10 @@ async fn follow_podman_logs(target: &str, use_color: bool) -> Result<()> {
 
     // Follow logs
     podman
-        .follow_logs(target, |line| {
-            println!("{}", line);
+        .follow_logs(target, args.tail, |line| {
+            if args.grep.as_deref().is_none_or(|pattern| line.contains(pattern)) {
+                println!("{}", line);
+            }
         })
         .await
 }
@@ -205,18 +574,32 @@ fn output_logs(
         }
 
         for entry in entries {
-            let timestamp = format_timestamp(entry.timestamp);
-            if use_color {
-                println!("{} {}", timestamp.dimmed(), entry.line);
-            } else {
-                println!("{} {}", timestamp, entry.line);
-            }
+            print_entry(entry, false, use_color);
         }
     }
 
     Ok(())
 }
 
+/// Print a single entry (as a JSON line with `json`)
+fn print_entry(entry: &LogEntry, json: bool, use_color: bool) {
+    let timestamp = format_timestamp(entry.timestamp);
+
+    if json {
+        let output = LogEntryOutput {
+            timestamp: Some(timestamp),
+            line: entry.line.clone(),
+        };
+        if let Ok(line) = serde_json::to_string(&output) {
+            println!("{}", line);
+        }
+    } else if use_color {
+        println!("{} {}", timestamp.dimmed(), entry.line);
+    } else {
+        println!("{} {}", timestamp, entry.line);
+    }
+}
+
 /// Format nanosecond timestamp to human-readable format
 fn format_timestamp(timestamp_ns: i64) -> String {
     use chrono::{DateTime, Utc};
@@ -242,6 +625,78 @@ mod tests {
         assert!(formatted.starts_with("2023-11-14"));
     }
 
+    fn logs_args(targets: &[&str], selector: &[&str]) -> LogsArgs {
+        LogsArgs {
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            selector: selector.iter().map(|s| s.to_string()).collect(),
+            op: None,
+            tail: None,
+            follow: false,
+            since: None,
+            grep: Some("502".to_string()),
+            json: false,
+        }
+    }
+
+    #[test]
+    fn test_resolve_targets() {
+        let args = logs_args(&["gateway", "web"], &[r#"{host_id="2"}"#]);
+
+        let targets = resolve_targets(&args, true).unwrap();
+        assert_eq!(
+            targets[0].source,
+            MergedSource::Loki(r#"{job="gateway"} |~ "502""#.to_string())
+        );
+        assert_eq!(targets[1].source, MergedSource::Podman("web".to_string()));
+        assert_eq!(targets[2].label, r#"{host_id="2"}"#);
+
+        // Components fall back to Podman, selectors cannot
+        assert!(resolve_targets(&args, false).is_err());
+        let args = logs_args(&["gateway"], &[]);
+        let targets = resolve_targets(&args, false).unwrap();
+        assert_eq!(targets[0].source, MergedSource::Podman("gateway".to_string()));
+
+        let args = logs_args(&[], &["host_id=2"]);
+        assert!(resolve_targets(&args, true).is_err());
+    }
+
+    #[test]
+    fn test_resolve_targets_operation() {
+        let mut args = logs_args(&[], &[]);
+        args.grep = None;
+        args.op = Some("3f9a2c1d5e7b8a40".to_string());
+
+        let targets = resolve_targets(&args, true).unwrap();
+        let labels: Vec<&str> = targets.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["cni", "gateway"]);
+        assert_eq!(
+            targets[0].source,
+            MergedSource::Loki(r#"{job="cni-plugin"} |= "3f9a2c1d5e7b8a40""#.to_string())
+        );
+
+        assert!(resolve_targets(&args, false).is_err());
+        args.op = Some("\"} or {job=~\".+".to_string());
+        assert!(resolve_targets(&args, true).is_err());
+    }
+
+    #[test]
+    fn test_merge_entries() {
+        let entry = |target, timestamp| MergedEntry {
+            target,
+            timestamp,
+            line: String::new(),
+        };
+        let entries = vec![entry(0, 30), entry(0, 10), entry(1, 20), entry(1, 10)];
+
+        let merged = merge_entries(entries.clone(), None);
+        let order: Vec<(usize, i64)> = merged.iter().map(|e| (e.target, e.timestamp)).collect();
+        assert_eq!(order, vec![(0, 10), (1, 10), (1, 20), (0, 30)]);
+
+        let merged = merge_entries(entries, Some(2));
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].timestamp, 20);
+    }
+
     #[test]
     fn test_format_timestamp_zero() {
         let formatted = format_timestamp(0);
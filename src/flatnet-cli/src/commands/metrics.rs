@@ -0,0 +1,404 @@
+//! Metrics command implementation
+//!
+//! Queries Prometheus for the key Flatnet health metrics over a time window
+//! and prints them as sparklines, so Grafana is not needed from a terminal.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use colored::Colorize;
+use futures::future::try_join_all;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::cli::MetricsArgs;
+use crate::clients::loki::parse_duration;
+use crate::clients::prometheus::{PrometheusClient, Series};
+use crate::config::Config;
+
+/// Number of points (and sparkline characters) for the default step
+const SPARKLINE_WIDTH: usize = 60;
+
+/// Smallest step; matches the Prometheus scrape interval
+const MIN_STEP_SECS: u64 = 15;
+
+/// Shortest range for `rate()`, so it always covers several scrapes
+const MIN_RATE_RANGE_SECS: u64 = 60;
+
+/// Sparkline characters from lowest to highest
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How a metric value is displayed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Unit {
+    RequestsPerSecond,
+    Ratio,
+    Seconds,
+    Count,
+}
+
+/// A metric shown by the command
+struct MetricDef {
+    name: &'static str,
+    title: &'static str,
+    unit: Unit,
+    /// PromQL with `$range` standing for the rate range
+    query: &'static str,
+    /// Value from which the metric is highlighted (same as the alert rules)
+    warn_above: Option<f64>,
+    warn_below: Option<f64>,
+}
+
+// The Gateway exports an unlabeled request total next to the per-status
+// series of the same name, so totals select `status=""`.
+const METRICS: &[MetricDef] = &[
+    MetricDef {
+        name: "request_rate",
+        title: "Request rate",
+        unit: Unit::RequestsPerSecond,
+        query: r#"sum(rate(flatnet_http_requests_total{status=""}[$range]))"#,
+        warn_above: None,
+        warn_below: None,
+    },
+    MetricDef {
+        name: "error_ratio",
+        title: "Error ratio (5xx)",
+        unit: Unit::Ratio,
+        query: r#"sum(rate(flatnet_http_requests_total{status=~"5.."}[$range])) / sum(rate(flatnet_http_requests_total{status=""}[$range]))"#,
+        warn_above: Some(0.05),
+        warn_below: None,
+    },
+    MetricDef {
+        name: "latency_p95",
+        title: "Latency p95",
+        unit: Unit::Seconds,
+        query: "histogram_quantile(0.95, sum(rate(flatnet_http_request_duration_seconds_bucket[$range])) by (le))",
+        warn_above: Some(1.0),
+        warn_below: None,
+    },
+    MetricDef {
+        name: "active_connections",
+        title: "Active connections",
+        unit: Unit::Count,
+        query: "sum(flatnet_active_connections)",
+        warn_above: Some(1000.0),
+        warn_below: None,
+    },
+    MetricDef {
+        name: "escalation_success",
+        title: "Escalation success",
+        unit: Unit::Ratio,
+        query: r#"sum(increase(flatnet_escalation_results_total{result="success"}[$range])) / sum(increase(flatnet_escalation_results_total[$range]))"#,
+        warn_above: None,
+        warn_below: Some(0.9),
+    },
+    MetricDef {
+        name: "ipam_usage",
+        title: "IPAM usage",
+        unit: Unit::Ratio,
+        query: "sum by (network) (flatnet_cni_ip_allocated) / sum by (network) (flatnet_cni_ip_pool_size)",
+        warn_above: Some(0.9),
+        warn_below: None,
+    },
+];
+
+/// JSON output structure
+#[derive(Debug, Serialize)]
+struct MetricsOutput {
+    prometheus_url: String,
+    window: String,
+    step_secs: u64,
+    start: i64,
+    end: i64,
+    metrics: Vec<MetricOutput>,
+}
+
+/// One series of a metric
+#[derive(Debug, Serialize)]
+struct MetricOutput {
+    name: &'static str,
+    title: &'static str,
+    unit: Unit,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    labels: HashMap<String, String>,
+    current: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+    /// Samples as (unix seconds, value); gaps are omitted
+    points: Vec<(i64, f64)>,
+    #[serde(skip)]
+    warning: bool,
+}
+
+/// Run the metrics command
+pub async fn run(args: MetricsArgs) -> Result<()> {
+    let config = Config::load()?;
+    let use_color = config.color_enabled();
+
+    let window_secs = match parse_duration(&args.window) {
+        Some(secs) if secs > 0 => secs,
+        _ => bail!(
+            "Invalid window '{}' (expected e.g. 15m, 1h, 1d)",
+            args.window
+        ),
+    };
+    let step_secs = match args.step.as_deref() {
+        Some(step) => match parse_duration(step) {
+            Some(secs) if secs > 0 => secs,
+            _ => bail!("Invalid step '{}' (expected e.g. 30s, 1m)", step),
+        },
+        None => default_step(window_secs),
+    };
+
+    let client =
+        PrometheusClient::new(&config.monitoring.prometheus_url, config.gateway_timeout())?;
+    let end = Utc::now();
+    let start = end - chrono::Duration::seconds(window_secs as i64);
+    let step = Duration::from_secs(step_secs);
+    let range = format!("{}s", step_secs.max(MIN_RATE_RANGE_SECS));
+
+    let results = try_join_all(METRICS.iter().map(|def| {
+        let query = def.query.replace("$range", &range);
+        let client = &client;
+        async move { client.query_range(&query, start, end, step).await }
+    }))
+    .await
+    .with_context(|| {
+        format!(
+            "Failed to query Prometheus at {}",
+            config.monitoring.prometheus_url
+        )
+    })?;
+
+    let metrics: Vec<MetricOutput> = METRICS
+        .iter()
+        .zip(results)
+        .flat_map(|(def, series)| build_outputs(def, series))
+        .collect();
+
+    if args.json {
+        let output = MetricsOutput {
+            prometheus_url: config.monitoring.prometheus_url.clone(),
+            window: args.window,
+            step_secs,
+            start: start.timestamp(),
+            end: end.timestamp(),
+            metrics,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let header = format!(
+        "Flatnet metrics (last {}, step {}s)",
+        args.window, step_secs
+    );
+    if use_color {
+        println!("{}", header.bold());
+    } else {
+        println!("{}", header);
+    }
+    println!();
+
+    for metric in &metrics {
+        print_metric(metric, use_color);
+    }
+
+    Ok(())
+}
+
+/// Step that splits the window into `SPARKLINE_WIDTH` points
+fn default_step(window_secs: u64) -> u64 {
+    (window_secs / SPARKLINE_WIDTH as u64).max(MIN_STEP_SECS)
+}
+
+/// Turn a query result into output rows (one row when no data came back)
+fn build_outputs(def: &MetricDef, series: Vec<Series>) -> Vec<MetricOutput> {
+    if series.is_empty() {
+        return vec![build_output(def, HashMap::new(), Vec::new())];
+    }
+
+    series
+        .into_iter()
+        .map(|s| {
+            // 0/0 ratios come back as NaN; treat them as gaps
+            let points = s
+                .points
+                .into_iter()
+                .filter(|(_, v)| v.is_finite())
+                .collect();
+            build_output(def, s.labels, points)
+        })
+        .collect()
+}
+
+fn build_output(
+    def: &MetricDef,
+    labels: HashMap<String, String>,
+    points: Vec<(i64, f64)>,
+) -> MetricOutput {
+    let values = points.iter().map(|(_, v)| *v);
+    let min = values.clone().reduce(f64::min);
+    let max = values.reduce(f64::max);
+    let current = points.last().map(|(_, v)| *v);
+    let warning = current.is_some_and(|v| {
+        def.warn_above.is_some_and(|limit| v > limit)
+            || def.warn_below.is_some_and(|limit| v < limit)
+    });
+
+    MetricOutput {
+        name: def.name,
+        title: def.title,
+        unit: def.unit,
+        labels,
+        current,
+        min,
+        max,
+        points,
+        warning,
+    }
+}
+
+/// Print one metric row
+fn print_metric(metric: &MetricOutput, use_color: bool) {
+    let mut title = metric.title.to_string();
+    if !metric.labels.is_empty() {
+        let mut values: Vec<&str> = metric.labels.values().map(|v| v.as_str()).collect();
+        values.sort();
+        title = format!("{} ({})", title, values.join(", "));
+    }
+
+    let Some(current) = metric.current else {
+        let line = format!("  {:<28} no data", title);
+        if use_color {
+            println!("{}", line.dimmed());
+        } else {
+            println!("{}", line);
+        }
+        return;
+    };
+
+    // Pad before coloring; escape codes would throw off the widths
+    let values: Vec<f64> = metric.points.iter().map(|(_, v)| *v).collect();
+    let spark = format!(
+        "{:<width$}",
+        sparkline(&values, SPARKLINE_WIDTH),
+        width = SPARKLINE_WIDTH
+    );
+    let value = format!("{:>12}", format_value(current, metric.unit));
+    let range = format!(
+        "min {}  max {}",
+        format_value(metric.min.unwrap_or(current), metric.unit),
+        format_value(metric.max.unwrap_or(current), metric.unit)
+    );
+
+    if use_color {
+        let value = if metric.warning {
+            value.yellow().bold()
+        } else {
+            value.green()
+        };
+        println!(
+            "  {:<28} {}  {}  {}",
+            title,
+            spark.cyan(),
+            value,
+            range.dimmed()
+        );
+    } else {
+        println!("  {:<28} {}  {}  {}", title, spark, value, range);
+    }
+}
+
+/// Render values as a sparkline of at most `width` characters
+///
+/// Longer series are averaged down to `width` buckets.
+fn sparkline(values: &[f64], width: usize) -> String {
+    if values.is_empty() || width == 0 {
+        return String::new();
+    }
+
+    let chunk = values.len().div_ceil(width);
+    let buckets: Vec<f64> = values
+        .chunks(chunk)
+        .map(|c| c.iter().sum::<f64>() / c.len() as f64)
+        .collect();
+
+    let min = buckets.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = buckets.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let span = max - min;
+
+    buckets
+        .iter()
+        .map(|v| {
+            if span <= f64::EPSILON {
+                BARS[0]
+            } else {
+                let idx = ((v - min) / span * (BARS.len() - 1) as f64).round() as usize;
+                BARS[idx.min(BARS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+/// Format a value for display
+fn format_value(value: f64, unit: Unit) -> String {
+    match unit {
+        Unit::RequestsPerSecond => format!("{:.2} req/s", value),
+        Unit::Ratio => format!("{:.1}%", value * 100.0),
+        Unit::Seconds if value < 1.0 => format!("{:.0} ms", value * 1000.0),
+        Unit::Seconds => format!("{:.2} s", value),
+        Unit::Count => format!("{:.0}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[], 10), "");
+        assert_eq!(sparkline(&[0.0, 7.0], 10), "▁█");
+        assert_eq!(sparkline(&[3.0, 3.0, 3.0], 10), "▁▁▁");
+        // Averaged down to the width
+        assert_eq!(sparkline(&[0.0, 0.0, 1.0, 1.0], 2), "▁█");
+        assert_eq!(sparkline(&[1.0; 120], 60).chars().count(), 60);
+    }
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_value(12.345, Unit::RequestsPerSecond), "12.35 req/s");
+        assert_eq!(format_value(0.0125, Unit::Ratio), "1.2%");
+        assert_eq!(format_value(0.25, Unit::Seconds), "250 ms");
+        assert_eq!(format_value(2.5, Unit::Seconds), "2.50 s");
+        assert_eq!(format_value(42.0, Unit::Count), "42");
+    }
+
+    #[test]
+    fn test_build_outputs() {
+        let def = &METRICS[1];
+        let series = vec![Series {
+            labels: HashMap::new(),
+            points: vec![(1, 0.01), (2, f64::NAN), (3, 0.08)],
+        }];
+
+        let outputs = build_outputs(def, series);
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].points, vec![(1, 0.01), (3, 0.08)]);
+        assert_eq!(outputs[0].current, Some(0.08));
+        assert_eq!(outputs[0].min, Some(0.01));
+        assert!(outputs[0].warning);
+
+        let empty = build_outputs(def, Vec::new());
+        assert_eq!(empty.len(), 1);
+        assert_eq!(empty[0].current, None);
+    }
+
+    #[test]
+    fn test_default_step() {
+        assert_eq!(default_step(3600), 60);
+        assert_eq!(default_step(300), MIN_STEP_SECS);
+    }
+}
@@ -2,8 +2,14 @@
 //!
 //! This module contains the implementation of all CLI commands.
 
+pub mod alerts;
+pub mod dns;
 pub mod doctor;
 pub mod logs;
+pub mod metrics;
+pub mod peers;
+pub mod policy;
 pub mod ps;
+pub mod routes;
 pub mod status;
 pub mod upgrade;
@@ -0,0 +1,248 @@
+//! Peers command implementation
+//!
+//! Lists and manages the Gateway sync peers used for multihost registry sync.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+use tabled::{settings::Style, Table, Tabled};
+
+use crate::cli::{PeersArgs, PeersCommand};
+use crate::clients::gateway::{GatewayClient, PeerInfo};
+use crate::config::Config;
+
+/// Peer display information
+#[derive(Debug, Clone, Serialize, Tabled)]
+struct PeerDisplay {
+    #[tabled(rename = "PEER")]
+    endpoint: String,
+
+    #[tabled(rename = "STATE")]
+    state: String,
+
+    #[tabled(rename = "LAST SUCCESS")]
+    last_success: String,
+
+    #[tabled(rename = "CONTAINERS")]
+    containers: u32,
+
+    #[tabled(rename = "ERROR")]
+    error: String,
+}
+
+/// JSON output structure
+#[derive(Debug, Clone, Serialize)]
+struct PeersOutput {
+    peers: Vec<PeerOutput>,
+    sync_interval: u64,
+    stale_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PeerOutput {
+    #[serde(flatten)]
+    peer: PeerInfo,
+    stale: bool,
+}
+
+/// Run the peers command
+pub async fn run(args: PeersArgs) -> Result<()> {
+    let config = Config::load()?;
+    let client = GatewayClient::new(config.gateway_url(), config.gateway_timeout())?;
+    let use_color = config.color_enabled();
+
+    match args.command {
+        PeersCommand::List(list_args) => list_peers(&client, list_args.json, use_color).await,
+        PeersCommand::Add(add_args) => {
+            let endpoint = add_args.endpoint.trim_end_matches('/');
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                bail!("Peer URL must start with http:// or https://");
+            }
+
+            if client.add_peer(endpoint).await? {
+                print_success(&format!("Added peer {}", endpoint), use_color);
+            } else {
+                println!("Peer {} is already configured", endpoint);
+            }
+            Ok(())
+        }
+        PeersCommand::Remove(remove_args) => {
+            let endpoint = remove_args.endpoint.trim_end_matches('/');
+            if !client.remove_peer(endpoint).await? {
+                bail!("Peer not found: {}", endpoint);
+            }
+            print_success(&format!("Removed peer {}", endpoint), use_color);
+            Ok(())
+        }
+        PeersCommand::Pull(pull_args) => {
+            let endpoint = pull_args.endpoint.as_deref().map(|e| e.trim_end_matches('/'));
+            let result = client.sync_pull(endpoint).await?;
+
+            let summary = format!(
+                "Sync complete: {} peers succeeded, {} failed",
+                result.success_count, result.failure_count
+            );
+            if result.failure_count > 0 {
+                if use_color {
+                    println!("{}", summary.yellow());
+                } else {
+                    println!("{}", summary);
+                }
+                println!("Run 'flatnet peers list' for per-peer errors.");
+            } else {
+                print_success(&summary, use_color);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// List peers with their sync state
+async fn list_peers(client: &GatewayClient, json: bool, use_color: bool) -> Result<()> {
+    let peers = client.peers().await?;
+
+    // Sync interval drives the staleness threshold; fall back to the default
+    let sync_interval = match client.status().await {
+        Ok(status) => status.sync.unwrap_or_default().interval_secs(),
+        Err(_) => crate::clients::gateway::SyncStatus::default().interval_secs(),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+
+    if json {
+        let outputs: Vec<PeerOutput> = peers
+            .into_iter()
+            .map(|peer| PeerOutput {
+                stale: peer.is_stale(now, sync_interval),
+                peer,
+            })
+            .collect();
+        let output = PeersOutput {
+            stale_count: outputs.iter().filter(|p| p.stale).count(),
+            sync_interval,
+            peers: outputs,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if peers.is_empty() {
+        if use_color {
+            println!("{}", "No sync peers configured.".dimmed());
+        } else {
+            println!("No sync peers configured.");
+        }
+        return Ok(());
+    }
+
+    let display: Vec<PeerDisplay> = peers
+        .iter()
+        .map(|p| build_peer_display(p, now, sync_interval))
+        .collect();
+    let stale_count = display.iter().filter(|p| p.state == "stale").count();
+
+    let table = Table::new(&display).with(Style::blank()).to_string();
+    println!("{}", table);
+
+    println!();
+    let summary = format!(
+        "Total: {} peers, {} stale (sync interval {}s)",
+        display.len(),
+        stale_count,
+        sync_interval
+    );
+    if use_color && stale_count > 0 {
+        println!("{}", summary.yellow());
+    } else if use_color {
+        println!("{}", summary.dimmed());
+    } else {
+        println!("{}", summary);
+    }
+
+    Ok(())
+}
+
+/// Build the table row for a peer
+fn build_peer_display(peer: &PeerInfo, now: i64, sync_interval: u64) -> PeerDisplay {
+    let state = if peer.is_stale(now, sync_interval) {
+        "stale"
+    } else if peer.last_attempt.is_none() {
+        "pending"
+    } else if peer.consecutive_failures > 0 {
+        "failing"
+    } else {
+        "ok"
+    };
+
+    PeerDisplay {
+        endpoint: peer.endpoint.clone(),
+        state: state.to_string(),
+        last_success: peer
+            .last_success
+            .map(|ts| format_age(now - ts))
+            .unwrap_or_else(|| "never".to_string()),
+        containers: peer.container_count,
+        error: peer.last_error.clone().unwrap_or_else(|| "-".to_string()),
+    }
+}
+
+/// Format an age in seconds as a short relative time
+fn format_age(secs: i64) -> String {
+    let secs = secs.max(0);
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h ago", secs / 3600)
+    } else {
+        format!("{}d ago", secs / 86400)
+    }
+}
+
+/// Print a success message
+fn print_success(message: &str, use_color: bool) {
+    if use_color {
+        println!("{}", message.green());
+    } else {
+        println!("{}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5), "5s ago");
+        assert_eq!(format_age(120), "2m ago");
+        assert_eq!(format_age(7200), "2h ago");
+        assert_eq!(format_age(172800), "2d ago");
+        assert_eq!(format_age(-3), "0s ago");
+    }
+
+    #[test]
+    fn test_build_peer_display_state() {
+        let peer = PeerInfo {
+            endpoint: "http://10.100.2.1:8080".to_string(),
+            last_attempt: Some(1000),
+            last_success: Some(1000),
+            last_error: None,
+            container_count: 3,
+            consecutive_failures: 0,
+        };
+        assert_eq!(build_peer_display(&peer, 1010, 30).state, "ok");
+        assert_eq!(build_peer_display(&peer, 2000, 30).state, "stale");
+
+        let failing = PeerInfo {
+            last_attempt: Some(1020),
+            last_error: Some("request failed: timeout".to_string()),
+            consecutive_failures: 1,
+            ..peer
+        };
+        let display = build_peer_display(&failing, 1030, 30);
+        assert_eq!(display.state, "failing");
+        assert_eq!(display.error, "request failed: timeout");
+    }
+}
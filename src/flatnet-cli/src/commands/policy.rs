@@ -0,0 +1,410 @@
+//! Policy command implementation
+//!
+//! Compiles the policy file against the containers currently known (local
+//! IPAM state with Podman labels, plus the Gateway registry) and loads it
+//! into nftables, shows it, or evaluates a single connection.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::process::ExitCode;
+use tokio::time::{sleep, Duration};
+
+use crate::cli::{PolicyApplyArgs, PolicyArgs, PolicyCommand, PolicyShowArgs, PolicyTestArgs};
+use crate::clients::gateway::{ContainerInfo, GatewayClient};
+use crate::clients::ipam::IpamStore;
+use crate::clients::podman::PodmanClient;
+use crate::config::Config;
+use crate::policy::{self, Action, Direction, Inventory, Policy, Protocol};
+
+/// Run the policy command
+pub async fn run(args: PolicyArgs) -> Result<ExitCode> {
+    let config = Config::load()?;
+    let use_color = config.color_enabled();
+    let mut source = InventorySource::new(&config, use_color)?;
+
+    match args.command {
+        PolicyCommand::Apply(apply_args) => {
+            if apply_args.watch {
+                let interval = apply_args.interval.unwrap_or(config.policy.interval_secs);
+                run_watch(&config, &mut source, &apply_args, interval, use_color).await?;
+            } else {
+                apply_once(&config, &mut source, &apply_args, use_color).await?;
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        PolicyCommand::Show(show_args) => {
+            show(&config, &mut source, &show_args, use_color).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        PolicyCommand::Test(test_args) => test(&config, &mut source, &test_args, use_color).await,
+    }
+}
+
+/// Compile and load the policy once
+async fn apply_once(
+    config: &Config,
+    source: &mut InventorySource,
+    args: &PolicyApplyArgs,
+    use_color: bool,
+) -> Result<()> {
+    let policy = Policy::load(Path::new(&config.policy.file))?;
+    let inventory = source.load().await;
+    let compiled = policy::compile(&policy, &inventory);
+
+    if args.dry_run {
+        print!("{}", compiled.script);
+        return Ok(());
+    }
+
+    policy::load_ruleset(&compiled.script)?;
+    policy::save_state(&compiled.state, Path::new(&config.policy.state_file))?;
+    print_success(
+        &format!(
+            "Policy applied: {} rules on {} networks, {} containers known",
+            policy.rules.len(),
+            policy.networks().len(),
+            inventory.endpoints.len()
+        ),
+        use_color,
+    );
+    Ok(())
+}
+
+/// Re-apply periodically, reloading only when the compiled ruleset changes
+async fn run_watch(
+    config: &Config,
+    source: &mut InventorySource,
+    args: &PolicyApplyArgs,
+    interval: u64,
+    use_color: bool,
+) -> Result<()> {
+    let mode = if args.dry_run { " (dry run)" } else { "" };
+    println!(
+        "Applying {} every {}s{}. Press Ctrl+C to exit.",
+        config.policy.file, interval, mode
+    );
+
+    let mut last_script = String::new();
+
+    loop {
+        // Errors are reported and retried; the policy file may be mid-edit
+        let compiled = match Policy::load(Path::new(&config.policy.file)) {
+            Ok(policy) => Some((policy::compile(&policy, &source.load().await), policy)),
+            Err(e) => {
+                print_error(&format!("{:#}", e), use_color);
+                None
+            }
+        };
+
+        if let Some((compiled, policy)) = compiled {
+            if compiled.script != last_script {
+                println!();
+                println!("[{}]", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+                let result = if args.dry_run {
+                    print!("{}", compiled.script);
+                    Ok(())
+                } else {
+                    policy::load_ruleset(&compiled.script).and_then(|_| {
+                        policy::save_state(&compiled.state, Path::new(&config.policy.state_file))
+                    })
+                };
+                match result {
+                    Ok(()) => {
+                        if !args.dry_run {
+                            print_success(
+                                &format!("Policy applied: {} rules", policy.rules.len()),
+                                use_color,
+                            );
+                        }
+                        last_script = compiled.script;
+                    }
+                    Err(e) => print_error(&format!("{:#}", e), use_color),
+                }
+            }
+        }
+
+        sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+/// Print the networks and rules with the containers they select
+async fn show(
+    config: &Config,
+    source: &mut InventorySource,
+    args: &PolicyShowArgs,
+    use_color: bool,
+) -> Result<()> {
+    let policy = Policy::load(Path::new(&config.policy.file))?;
+    let inventory = source.load().await;
+
+    if args.nft {
+        print!("{}", policy::compile(&policy, &inventory).script);
+        return Ok(());
+    }
+
+    let header = format!("Network policy ({})", config.policy.file);
+    if use_color {
+        println!("{}", header.bold());
+    } else {
+        println!("{}", header);
+    }
+    println!();
+
+    println!("Networks:");
+    for (name, network) in policy.networks() {
+        println!(
+            "  {:<12} bridge {:<14} default {}",
+            name,
+            network.bridge,
+            format_action(network.default, use_color)
+        );
+    }
+    println!();
+
+    if policy.rules.is_empty() {
+        println!("No rules.");
+        return Ok(());
+    }
+
+    println!("Rules (first match wins):");
+    for (index, rule) in policy.rules.iter().enumerate() {
+        let containers = rule.containers.describe();
+        let peers = rule.peers.describe();
+        let flow = match rule.direction {
+            Direction::Ingress => format!("{} -> {}", peers, containers),
+            Direction::Egress => format!("{} -> {}", containers, peers),
+            Direction::Both => format!("{} <-> {}", containers, peers),
+        };
+        let ports = if rule.ports.is_empty() {
+            "all ports".to_string()
+        } else {
+            rule.ports
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        println!(
+            "  {:>2}. {:<5} {}  {}  ({})",
+            index + 1,
+            format_action(rule.action, use_color),
+            rule.label(index),
+            flow,
+            ports
+        );
+
+        for (role, selector) in [("containers", &rule.containers), ("peers", &rule.peers)] {
+            if selector.names.is_empty() && selector.labels.is_empty() {
+                continue;
+            }
+            let members: Vec<String> = inventory
+                .members(selector)
+                .iter()
+                .map(|e| e.describe())
+                .collect();
+            let members = if members.is_empty() {
+                "none".to_string()
+            } else {
+                members.join(", ")
+            };
+            println!("        {}: {}", role, members);
+        }
+    }
+
+    Ok(())
+}
+
+/// Evaluate one connection; exits 0 if allowed and 1 if denied
+async fn test(
+    config: &Config,
+    source: &mut InventorySource,
+    args: &PolicyTestArgs,
+    use_color: bool,
+) -> Result<ExitCode> {
+    let policy = Policy::load(Path::new(&config.policy.file))?;
+    let (protocol, port) = parse_port(&args.port)?;
+    let inventory = source.load().await;
+
+    let find = |name: &str| {
+        inventory
+            .find(name)
+            .with_context(|| format!("Unknown container: {}", name))
+    };
+    let src = find(&args.src)?;
+    let dst = find(&args.dst)?;
+
+    let network = match &args.network {
+        Some(network) => network.clone(),
+        None => policy.networks()[0].0.clone(),
+    };
+    let verdict = policy.evaluate(&network, &inventory, &src, &dst, protocol, port)?;
+
+    println!(
+        "{}: {} -> {} {}/{} ({})",
+        format_action(verdict.action, use_color),
+        src.describe(),
+        dst.describe(),
+        port,
+        protocol.as_str(),
+        verdict.reason
+    );
+
+    Ok(match verdict.action {
+        Action::Allow => ExitCode::SUCCESS,
+        Action::Deny => ExitCode::from(1),
+    })
+}
+
+/// Parse "PORT[/PROTO]"
+fn parse_port(value: &str) -> Result<(Protocol, u16)> {
+    let (port, protocol) = match value.split_once('/') {
+        Some((port, protocol)) => (port, protocol.parse().map_err(anyhow::Error::msg)?),
+        None => (value, Protocol::Tcp),
+    };
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok((protocol, port)),
+        _ => bail!("Invalid port: {}", value),
+    }
+}
+
+/// Gathers the inventory, keeping the last registry copy while the Gateway is down
+struct InventorySource {
+    store: IpamStore,
+    gateway: GatewayClient,
+    registry: Vec<ContainerInfo>,
+    ipam_failed: bool,
+    podman_failed: bool,
+    registry_failed: bool,
+    use_color: bool,
+}
+
+impl InventorySource {
+    fn new(config: &Config, use_color: bool) -> Result<Self> {
+        Ok(Self {
+            store: config
+                .policy
+                .ipam_dir
+                .as_ref()
+                .map_or_else(IpamStore::new, IpamStore::with_dir),
+            gateway: GatewayClient::new(config.gateway_url(), config.gateway_timeout())?,
+            registry: Vec::new(),
+            ipam_failed: false,
+            podman_failed: false,
+            registry_failed: false,
+            use_color,
+        })
+    }
+
+    /// Build the inventory, reporting each source's failures once
+    async fn load(&mut self) -> Inventory {
+        // No state file yet just means no local containers
+        let local = match self.store.exists().then(|| self.store.load()) {
+            Some(Err(e)) => {
+                if !self.ipam_failed {
+                    print_warning(&format!("{:#}", e), self.use_color);
+                }
+                self.ipam_failed = true;
+                None
+            }
+            Some(Ok(state)) => {
+                self.ipam_failed = false;
+                Some(state)
+            }
+            None => None,
+        };
+
+        // Labels are only needed (and Podman only asked) when there are local containers
+        let mut labels: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+        if local.as_ref().is_some_and(|s| !s.allocations.is_empty()) {
+            match PodmanClient::new().list_containers(true) {
+                Ok(containers) => {
+                    self.podman_failed = false;
+                    for container in containers {
+                        let container_labels = container.labels.unwrap_or_default();
+                        labels.insert(container.id, container_labels.into_iter().collect());
+                    }
+                }
+                Err(e) => {
+                    if !self.podman_failed {
+                        print_warning(
+                            &format!("Container labels are unavailable: {:#}", e),
+                            self.use_color,
+                        );
+                    }
+                    self.podman_failed = true;
+                }
+            }
+        }
+
+        match self.gateway.containers().await {
+            Ok(containers) => {
+                self.registry_failed = false;
+                self.registry = containers;
+            }
+            Err(e) => {
+                if !self.registry_failed {
+                    print_warning(
+                        &format!(
+                            "Failed to list containers from the Gateway registry (using {} cached): {}",
+                            self.registry.len(),
+                            e
+                        ),
+                        self.use_color,
+                    );
+                }
+                self.registry_failed = true;
+            }
+        }
+
+        Inventory::build(local.as_ref(), &labels, &self.registry)
+    }
+}
+
+fn format_action(action: Action, use_color: bool) -> String {
+    let text = action.to_string();
+    match (use_color, action) {
+        (false, _) => text,
+        (true, Action::Allow) => text.green().to_string(),
+        (true, Action::Deny) => text.red().to_string(),
+    }
+}
+
+fn print_success(message: &str, use_color: bool) {
+    if use_color {
+        println!("{}", message.green());
+    } else {
+        println!("{}", message);
+    }
+}
+
+fn print_warning(message: &str, use_color: bool) {
+    if use_color {
+        eprintln!("{} {}", "Warning:".yellow(), message);
+    } else {
+        eprintln!("Warning: {}", message);
+    }
+}
+
+fn print_error(message: &str, use_color: bool) {
+    if use_color {
+        eprintln!("{} {}", "Error:".red(), message);
+    } else {
+        eprintln!("Error: {}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_port() {
+        assert_eq!(parse_port("80").unwrap(), (Protocol::Tcp, 80));
+        assert_eq!(parse_port("53/udp").unwrap(), (Protocol::Udp, 53));
+        assert!(parse_port("0").is_err());
+        assert!(parse_port("80/icmp").is_err());
+        assert!(parse_port("80-90").is_err());
+    }
+}
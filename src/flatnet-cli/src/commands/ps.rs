@@ -5,11 +5,12 @@
 use anyhow::Result;
 use colored::Colorize;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv4Addr;
 use tabled::{settings::Style, Table, Tabled};
 
 use crate::cli::PsArgs;
-use crate::clients::gateway::GatewayClient;
+use crate::clients::gateway::{ContainerInfo, GatewayClient, HealthcheckStatus, RouteInfo};
 use crate::clients::podman::{PodmanClient, PodmanContainer};
 use crate::config::Config;
 
@@ -40,10 +41,47 @@ struct PsOutput {
     flatnet_ips_allocated: usize,
 }
 
+/// Registry container display information (--all-hosts)
+#[derive(Debug, Clone, Serialize, Tabled)]
+struct RegistryContainerDisplay {
+    #[tabled(rename = "CONTAINER ID")]
+    id: String,
+
+    #[tabled(rename = "NAME")]
+    name: String,
+
+    #[tabled(rename = "FLATNET IP")]
+    flatnet_ip: String,
+
+    #[tabled(rename = "HOST")]
+    host_id: u8,
+
+    #[tabled(rename = "GATEWAY")]
+    gateway: String,
+
+    #[tabled(rename = "ROUTE")]
+    route: String,
+
+    #[tabled(rename = "HEALTH")]
+    health: String,
+}
+
+/// JSON output structure (--all-hosts)
+#[derive(Debug, Clone, Serialize)]
+struct AllHostsOutput {
+    containers: Vec<RegistryContainerDisplay>,
+    total_containers: usize,
+    hosts: usize,
+}
+
 /// Run the ps command
 pub async fn run(args: PsArgs) -> Result<()> {
     let config = Config::load()?;
 
+    if args.all_hosts {
+        return run_all_hosts(&config, &args).await;
+    }
+
     // Get containers from Podman
     let podman = PodmanClient::new();
     let podman_containers = match podman.list_containers(args.all) {
@@ -87,6 +125,189 @@ pub async fn run(args: PsArgs) -> Result<()> {
     Ok(())
 }
 
+/// List every container known to the Gateway registry, including remote hosts
+async fn run_all_hosts(config: &Config, args: &PsArgs) -> Result<()> {
+    let client = GatewayClient::new(config.gateway_url(), config.gateway_timeout())?;
+
+    let containers = match client.containers().await {
+        Ok(c) => c,
+        Err(e) => {
+            if !args.quiet && !args.json {
+                eprintln!("{}: {}", "Error".red(), e);
+                eprintln!(
+                    "{}",
+                    "The Gateway registry is required for --all-hosts.".dimmed()
+                );
+            }
+            return Err(e.into());
+        }
+    };
+
+    // Route and health information is best-effort
+    let (routes, healthcheck) = tokio::join!(client.routes(), client.healthcheck_status());
+    let routes = routes.unwrap_or_default();
+    let healthcheck = healthcheck.unwrap_or_default();
+
+    let display = build_registry_display_list(&containers, &routes, &healthcheck);
+    let display = apply_registry_filters(display, &args.filter);
+
+    if args.json {
+        let output = AllHostsOutput {
+            total_containers: display.len(),
+            hosts: count_hosts(&display),
+            containers: display,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if args.quiet {
+        for c in &display {
+            println!("{}", c.id);
+        }
+    } else {
+        print_registry_table(&display, config.color_enabled());
+    }
+
+    Ok(())
+}
+
+/// Build display list from Gateway registry entries
+fn build_registry_display_list(
+    containers: &[ContainerInfo],
+    routes: &HashMap<String, RouteInfo>,
+    healthcheck: &HealthcheckStatus,
+) -> Vec<RegistryContainerDisplay> {
+    let mut display: Vec<RegistryContainerDisplay> = containers
+        .iter()
+        .map(|c| {
+            let route = routes.get(&c.ip);
+            let failures = healthcheck.failure_counts.get(&c.ip).copied().unwrap_or(0);
+
+            RegistryContainerDisplay {
+                id: c.id.chars().take(12).collect(),
+                name: c.name.clone().unwrap_or_else(|| "-".to_string()),
+                flatnet_ip: c.ip.clone(),
+                host_id: c.host_id,
+                gateway: owning_gateway(c, route),
+                route: route
+                    .map(|r| r.route_type.clone())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                health: derive_health(route, failures, healthcheck.failure_threshold),
+            }
+        })
+        .collect();
+
+    // Sort by host, then by IP
+    display.sort_by_key(|c| {
+        (
+            c.host_id,
+            c.flatnet_ip.parse::<Ipv4Addr>().map(u32::from).unwrap_or(u32::MAX),
+        )
+    });
+    display
+}
+
+/// Determine the Gateway that owns a container
+///
+/// Gateway routes report their target; otherwise the multihost convention
+/// places the Gateway at 10.100.<host-id>.1.
+fn owning_gateway(container: &ContainerInfo, route: Option<&RouteInfo>) -> String {
+    match route {
+        Some(r) if r.route_type == "gateway" && r.target.is_some() => {
+            r.target.clone().unwrap_or_default()
+        }
+        _ => format!("10.100.{}.1", container.host_id),
+    }
+}
+
+/// Derive a health label from the route state and healthcheck failures
+fn derive_health(route: Option<&RouteInfo>, failures: u32, threshold: Option<u32>) -> String {
+    if failures > 0 {
+        return match threshold {
+            Some(t) => format!("failing ({}/{})", failures, t),
+            None => format!("failing ({})", failures),
+        };
+    }
+
+    match route.and_then(|r| r.state.as_deref()) {
+        Some("P2P_ACTIVE") => "healthy".to_string(),
+        Some("P2P_ATTEMPTING") => "probing".to_string(),
+        Some("GATEWAY_FALLBACK") => "degraded".to_string(),
+        // Local and gateway-only routes are not health-checked
+        _ => "-".to_string(),
+    }
+}
+
+/// Apply filters to registry containers
+///
+/// Multiple filters can be combined with commas (e.g., "host=2,route=p2p").
+fn apply_registry_filters(
+    containers: Vec<RegistryContainerDisplay>,
+    filter: &Option<String>,
+) -> Vec<RegistryContainerDisplay> {
+    let Some(ref f) = filter else {
+        return containers;
+    };
+
+    containers
+        .into_iter()
+        .filter(|c| {
+            f.split(',').map(str::trim).filter(|t| !t.is_empty()).all(|term| {
+                if let Some((key, value)) = term.split_once('=') {
+                    match key.to_lowercase().as_str() {
+                        "host" | "host_id" => c.host_id.to_string() == value,
+                        "route" => c.route.eq_ignore_ascii_case(value),
+                        "gateway" => c.gateway.contains(value),
+                        "health" => c.health.starts_with(&value.to_lowercase()),
+                        "name" => c.name.contains(value),
+                        "id" => c.id.contains(value),
+                        "ip" => c.flatnet_ip.contains(value),
+                        _ => true,
+                    }
+                } else {
+                    // If no key, search in name, id, and IP
+                    c.name.contains(term) || c.id.contains(term) || c.flatnet_ip.contains(term)
+                }
+            })
+        })
+        .collect()
+}
+
+/// Count distinct hosts in the display list
+fn count_hosts(containers: &[RegistryContainerDisplay]) -> usize {
+    containers
+        .iter()
+        .map(|c| c.host_id)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Print registry containers as a table
+fn print_registry_table(containers: &[RegistryContainerDisplay], use_color: bool) {
+    if containers.is_empty() {
+        if use_color {
+            println!("{}", "No containers found in the Gateway registry.".dimmed());
+        } else {
+            println!("No containers found in the Gateway registry.");
+        }
+        return;
+    }
+
+    let table = Table::new(containers).with(Style::blank()).to_string();
+    println!("{}", table);
+
+    println!();
+    let summary = format!(
+        "Total: {} containers on {} hosts",
+        containers.len(),
+        count_hosts(containers)
+    );
+
+    if use_color {
+        println!("{}", summary.dimmed());
+    } else {
+        println!("{}", summary);
+    }
+}
+
 /// Get Flatnet IPs from Gateway registry
 async fn get_flatnet_ips(config: &Config) -> HashMap<String, String> {
     let mut ip_map = HashMap::new();
@@ -271,6 +492,7 @@ mod tests {
                 status: "Up 1 hour".to_string(),
                 created: None,
                 networks: None,
+                labels: None,
             },
             PodmanContainer {
                 id: "def456".to_string(),
@@ -280,6 +502,7 @@ mod tests {
                 status: "Exited (0)".to_string(),
                 created: None,
                 networks: None,
+                labels: None,
             },
         ];
 
@@ -299,4 +522,86 @@ mod tests {
         let filtered = apply_filters(&containers, &None);
         assert_eq!(filtered.len(), 2);
     }
+
+    fn registry_container(id: &str, ip: &str, host_id: u8) -> ContainerInfo {
+        ContainerInfo {
+            id: id.to_string(),
+            ip: ip.to_string(),
+            host_id,
+            name: Some(format!("svc-{}", id)),
+            registered_at: None,
+        }
+    }
+
+    fn route(route_type: &str, target: &str, state: &str) -> RouteInfo {
+        RouteInfo {
+            route_type: route_type.to_string(),
+            target: Some(target.to_string()),
+            state: Some(state.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_build_registry_display_list() {
+        let containers = vec![
+            registry_container("c3", "10.100.2.10", 2),
+            registry_container("c1", "10.100.1.10", 1),
+            registry_container("c2", "10.100.3.10", 3),
+        ];
+        let mut routes = HashMap::new();
+        routes.insert("10.100.1.10".to_string(), route("local", "10.100.1.10", "LOCAL"));
+        routes.insert("10.100.2.10".to_string(), route("p2p", "10.100.2.10", "P2P_ACTIVE"));
+        routes.insert("10.100.3.10".to_string(), route("gateway", "10.100.3.1", "GATEWAY_FALLBACK"));
+
+        let mut healthcheck = HealthcheckStatus {
+            failure_threshold: Some(3),
+            ..Default::default()
+        };
+
+        let display = build_registry_display_list(&containers, &routes, &healthcheck);
+        assert_eq!(display.len(), 3);
+
+        // Sorted by host ID
+        assert_eq!(display[0].host_id, 1);
+        assert_eq!(display[0].route, "local");
+        assert_eq!(display[0].gateway, "10.100.1.1");
+        assert_eq!(display[1].health, "healthy");
+        assert_eq!(display[2].gateway, "10.100.3.1");
+        assert_eq!(display[2].health, "degraded");
+
+        healthcheck
+            .failure_counts
+            .insert("10.100.2.10".to_string(), 2);
+        let display = build_registry_display_list(&containers, &routes, &healthcheck);
+        assert_eq!(display[1].health, "failing (2/3)");
+    }
+
+    #[test]
+    fn test_apply_registry_filters() {
+        let containers = vec![
+            registry_container("c1", "10.100.1.10", 1),
+            registry_container("c2", "10.100.2.10", 2),
+            registry_container("c3", "10.100.2.11", 2),
+        ];
+        let mut routes = HashMap::new();
+        routes.insert("10.100.2.10".to_string(), route("p2p", "10.100.2.10", "P2P_ACTIVE"));
+
+        let display =
+            build_registry_display_list(&containers, &routes, &HealthcheckStatus::default());
+
+        let filtered = apply_registry_filters(display.clone(), &Some("host=2".to_string()));
+        assert_eq!(filtered.len(), 2);
+
+        let filtered = apply_registry_filters(display.clone(), &Some("route=p2p".to_string()));
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].flatnet_ip, "10.100.2.10");
+
+        let filtered =
+            apply_registry_filters(display.clone(), &Some("host=2, route=unknown".to_string()));
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].flatnet_ip, "10.100.2.11");
+
+        let filtered = apply_registry_filters(display, &None);
+        assert_eq!(filtered.len(), 3);
+    }
 }
@@ -0,0 +1,261 @@
+//! Routes command implementation
+//!
+//! Programs the routes to the other hosts' container subnets from the
+//! Gateway registry, once or continuously.
+
+use anyhow::Result;
+use colored::Colorize;
+use std::net::Ipv4Addr;
+use tokio::time::{sleep, Duration};
+
+use crate::cli::{RoutesArgs, RoutesCommand, RoutesSyncArgs};
+use crate::config::Config;
+use crate::routes::{PlannedRoute, RouteManager, RoutePlan};
+
+/// Run the routes command
+pub async fn run(args: RoutesArgs) -> Result<()> {
+    let config = Config::load()?;
+    let use_color = config.color_enabled();
+
+    match args.command {
+        RoutesCommand::Sync(sync_args) => {
+            let manager = RouteManager::from_config(&config)?;
+            if sync_args.watch {
+                let interval = sync_args.interval.unwrap_or(config.routes.interval_secs);
+                run_watch(&manager, &sync_args, interval, use_color).await
+            } else {
+                sync_once(&manager, &sync_args, use_color).await
+            }
+        }
+    }
+}
+
+/// Sync once and print the full route table for the peer hosts
+async fn sync_once(manager: &RouteManager, args: &RoutesSyncArgs, use_color: bool) -> Result<()> {
+    let plan = manager.plan().await?;
+    let next_hop = manager.next_hop();
+
+    let header = format!(
+        "Peer routes for host {} (next hop {})",
+        manager.host_id(),
+        next_hop
+    );
+    if use_color {
+        println!("{}", header.bold());
+    } else {
+        println!("{}", header);
+    }
+    println!();
+    if let Some(e) = &plan.peers_error {
+        print_warning(&peers_warning(e), use_color);
+        println!();
+    }
+
+    if plan.is_empty() && plan.keep.is_empty() {
+        println!("No peer hosts registered.");
+        return Ok(());
+    }
+
+    for planned in &plan.keep {
+        println!("  {}", format_change(' ', planned, next_hop));
+    }
+    print_changes(&change_lines(&plan, next_hop), use_color);
+    println!();
+
+    let summary = format!(
+        "{} to add, {} to replace, {} to remove",
+        plan.add.len(),
+        plan.replace.len(),
+        plan.remove.len()
+    );
+    if plan.is_empty() {
+        print_success(
+            &format!("Routes are up to date ({} peer hosts)", plan.keep.len()),
+            use_color,
+        );
+    } else if args.dry_run {
+        println!("Dry run: {}", summary);
+    } else {
+        manager.apply(&plan).await?;
+        print_success(&format!("Routes updated: {}", summary), use_color);
+    }
+
+    Ok(())
+}
+
+/// Sync periodically, printing only changes
+async fn run_watch(
+    manager: &RouteManager,
+    args: &RoutesSyncArgs,
+    interval: u64,
+    use_color: bool,
+) -> Result<()> {
+    let mode = if args.dry_run { " (dry run)" } else { "" };
+    println!(
+        "Syncing peer routes for host {} via {} every {}s{}. Press Ctrl+C to exit.",
+        manager.host_id(),
+        manager.next_hop(),
+        interval,
+        mode
+    );
+
+    // In dry-run mode the same changes come back every time; print them once
+    let mut last_changes = Vec::new();
+    let mut last_peers_error = None;
+
+    loop {
+        // Errors are reported and retried; the Gateway may be restarting
+        match manager.plan().await {
+            Ok(plan) => {
+                if plan.peers_error != last_peers_error {
+                    if let Some(e) = &plan.peers_error {
+                        print_warning(&peers_warning(e), use_color);
+                    }
+                    last_peers_error = plan.peers_error.clone();
+                }
+
+                let changes = change_lines(&plan, manager.next_hop());
+                if !changes.is_empty() && changes != last_changes {
+                    println!();
+                    println!("[{}]", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+                    print_changes(&changes, use_color);
+                }
+                last_changes = if args.dry_run { changes } else { Vec::new() };
+
+                if !args.dry_run && !plan.is_empty() {
+                    if let Err(e) = manager.apply(&plan).await {
+                        print_error(&format!("{:#}", e), use_color);
+                    }
+                }
+            }
+            Err(e) => print_error(&format!("{:#}", e), use_color),
+        }
+
+        sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+/// Diff lines for the additions, replacements and removals of a plan
+fn change_lines(plan: &RoutePlan, next_hop: Ipv4Addr) -> Vec<String> {
+    [('+', &plan.add), ('~', &plan.replace), ('-', &plan.remove)]
+        .into_iter()
+        .flat_map(|(sign, planned)| {
+            planned
+                .iter()
+                .map(move |p| format_change(sign, p, next_hop))
+        })
+        .collect()
+}
+
+/// Print diff lines, colored by their sign
+fn print_changes(lines: &[String], use_color: bool) {
+    for line in lines {
+        let line = format!("  {}", line);
+        if !use_color {
+            println!("{}", line);
+        } else if line.starts_with("  +") {
+            println!("{}", line.green());
+        } else if line.starts_with("  ~") {
+            println!("{}", line.yellow());
+        } else {
+            println!("{}", line.red());
+        }
+    }
+}
+
+/// Format one line of the route diff
+fn format_change(sign: char, planned: &PlannedRoute, next_hop: Ipv4Addr) -> String {
+    let current = planned.current.as_ref();
+    let via = match (sign, current) {
+        // Kept device routes (e.g., a Nebula interface) have no gateway
+        (' ', Some(route)) if route.gateway.is_none() => {
+            format!("dev {}", route.dev.as_deref().unwrap_or("?"))
+        }
+        _ => format!("via {}", next_hop),
+    };
+
+    let note = match (sign, current.and_then(|r| r.gateway)) {
+        ('~', Some(old)) => format!(", was via {}", old),
+        ('-', _) => ", not in registry".to_string(),
+        _ => String::new(),
+    };
+
+    format!(
+        "{} {} {} (host {}{})",
+        sign, planned.destination, via, planned.host_id, note
+    )
+}
+
+/// Warning shown while the sync peers cannot be read
+fn peers_warning(error: &str) -> String {
+    format!(
+        "Could not read the sync peers ({}); no routes are removed until they can be",
+        error
+    )
+}
+
+fn print_warning(message: &str, use_color: bool) {
+    if use_color {
+        println!("{}", message.yellow());
+    } else {
+        println!("{}", message);
+    }
+}
+
+fn print_success(message: &str, use_color: bool) {
+    if use_color {
+        println!("{}", message.green());
+    } else {
+        println!("{}", message);
+    }
+}
+
+fn print_error(message: &str, use_color: bool) {
+    if use_color {
+        eprintln!("{} {}", "Error:".red(), message);
+    } else {
+        eprintln!("Error: {}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::netlink::{Route, MAIN_TABLE};
+    use crate::routes::{host_subnet, RTPROT_FLATNET};
+
+    #[test]
+    fn test_format_change() {
+        let next_hop: Ipv4Addr = "172.20.0.1".parse().unwrap();
+        let route = |gateway: Option<&str>| Route {
+            destination: host_subnet(3),
+            gateway: gateway.map(|g| g.parse().unwrap()),
+            dev: Some("nebula1".to_string()),
+            table: MAIN_TABLE,
+            unicast: true,
+            protocol: RTPROT_FLATNET,
+        };
+        let planned = |current: Option<Route>| PlannedRoute {
+            host_id: 3,
+            destination: host_subnet(3),
+            current,
+        };
+
+        assert_eq!(
+            format_change('+', &planned(None), next_hop),
+            "+ 10.100.3.0/24 via 172.20.0.1 (host 3)"
+        );
+        assert_eq!(
+            format_change('~', &planned(Some(route(Some("172.20.0.9")))), next_hop),
+            "~ 10.100.3.0/24 via 172.20.0.1 (host 3, was via 172.20.0.9)"
+        );
+        assert_eq!(
+            format_change('-', &planned(Some(route(Some("172.20.0.1")))), next_hop),
+            "- 10.100.3.0/24 via 172.20.0.1 (host 3, not in registry)"
+        );
+        assert_eq!(
+            format_change(' ', &planned(Some(route(None))), next_hop),
+            "  10.100.3.0/24 dev nebula1 (host 3)"
+        );
+    }
+}
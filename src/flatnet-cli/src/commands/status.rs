@@ -9,7 +9,10 @@ use std::io::{self, Write};
 use tokio::time::{sleep, Duration};
 
 use crate::cli::StatusArgs;
-use crate::clients::gateway::{GatewayClient, GatewayError};
+use crate::clients::alertmanager::{Alert, AlertmanagerClient};
+use crate::clients::gateway::{GatewayClient, GatewayError, SyncStatus};
+use crate::clients::nebula::{self, NebulaCert, NebulaConfig};
+use crate::clients::netlink::{BridgeInfo, NetlinkClient};
 use crate::config::Config;
 
 /// Box drawing characters for nice formatting
@@ -29,6 +32,9 @@ const STATUS_OK: &str = "\u{25CF}"; // ● (green for running/ready)
 const STATUS_STOPPED: &str = "\u{25CB}"; // ○ (red for stopped/error)
 const STATUS_WARN: &str = "\u{25CF}"; // ● (yellow for warning/disabled)
 
+/// Bridge created by flatnet-cni
+const BRIDGE_NAME: &str = "flatnet-br0";
+
 /// Box width for the status display
 const BOX_WIDTH: usize = 55;
 
@@ -47,6 +53,16 @@ struct SystemStatus {
     containers: u32,
     uptime: Option<String>,
     gateway_url: String,
+    alerts: AlertsStatus,
+}
+
+/// Flatnet alerts from Alertmanager
+#[derive(Debug, Clone, Serialize)]
+struct AlertsStatus {
+    /// Set when Alertmanager could not be queried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    alerts: Vec<Alert>,
 }
 
 /// Run the status command
@@ -150,6 +166,13 @@ async fn collect_status(config: &Config) -> SystemStatus {
                         details: hc_details,
                     });
                 }
+
+                // Sync peers (stale peers are listed individually)
+                if let Some(ref sync) = status.sync {
+                    if sync.enabled {
+                        components.extend(collect_sync_status(&client, sync).await);
+                    }
+                }
             }
             Err(e) => {
                 components.push(gateway_error_status(&e, &gateway_url));
@@ -174,12 +197,18 @@ async fn collect_status(config: &Config) -> SystemStatus {
         }
     }
 
-    // Monitoring services status (check via HTTP in parallel)
-    let (prometheus, grafana, loki) = tokio::join!(
+    // Local bridge and its container ports
+    components.extend(collect_bridge_status().await);
+
+    // Nebula overlay and monitoring services (checked in parallel)
+    let (nebula, prometheus, grafana, loki, alerts) = tokio::join!(
+        collect_nebula_status(config),
         check_monitoring_service("Prometheus", &config.monitoring.prometheus_url, ":9090"),
         check_monitoring_service("Grafana", &config.monitoring.grafana_url, ":3000"),
         check_monitoring_service("Loki", &config.monitoring.loki_url, ":3100"),
+        collect_alerts(&config.monitoring.alertmanager_url),
     );
+    components.extend(nebula);
     components.push(prometheus);
     components.push(grafana);
     components.push(loki);
@@ -189,9 +218,182 @@ async fn collect_status(config: &Config) -> SystemStatus {
         containers: container_count,
         uptime: None, // Would need uptime tracking in Gateway
         gateway_url,
+        alerts,
     }
 }
 
+/// Collect Nebula overlay status (none on single-host setups)
+async fn collect_nebula_status(config: &Config) -> Option<ComponentStatus> {
+    let path = std::path::Path::new(&config.nebula.config);
+    if !path.exists() {
+        return None;
+    }
+
+    let error = |details: &str| ComponentStatus {
+        name: "Nebula".to_string(),
+        status: "Error".to_string(),
+        details: details.to_string(),
+    };
+    let Ok(nebula) = NebulaConfig::load(path) else {
+        return Some(error("invalid config"));
+    };
+    let dir = path.parent().unwrap_or(std::path::Path::new("/"));
+    let Ok(cert) = NebulaCert::load(&nebula::wsl_path(&nebula.pki.cert, dir)) else {
+        return Some(error("cert unreadable"));
+    };
+
+    let mut lighthouse_reachable = nebula.lighthouse.am_lighthouse;
+    for host in &nebula.lighthouse.hosts {
+        if lighthouse_reachable {
+            break;
+        }
+        lighthouse_reachable = nebula::ping(host, 1).await;
+    }
+
+    Some(nebula_component(
+        &cert,
+        lighthouse_reachable,
+        chrono::Utc::now().timestamp(),
+        config.nebula.cert_warning_days,
+    ))
+}
+
+/// Status row for the Nebula overlay
+fn nebula_component(
+    cert: &NebulaCert,
+    lighthouse_reachable: bool,
+    now: i64,
+    warning_days: i64,
+) -> ComponentStatus {
+    let days = cert.days_until_expiry(now);
+    let ip = cert
+        .overlay_ip()
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "no IP".to_string());
+
+    let (status, details) = if cert.not_after <= now {
+        ("Error", "cert expired".to_string())
+    } else if !lighthouse_reachable {
+        ("Warning", "lighthouse unreachable".to_string())
+    } else if days < warning_days {
+        ("Warning", format!("{}, cert {}d", ip, days))
+    } else {
+        ("Ready", format!("{}, cert {}d", ip, days))
+    };
+
+    ComponentStatus {
+        name: "Nebula".to_string(),
+        status: status.to_string(),
+        details,
+    }
+}
+
+/// Collect Flatnet alerts from Alertmanager
+async fn collect_alerts(url: &str) -> AlertsStatus {
+    let result = match AlertmanagerClient::new(url, Duration::from_secs(2)) {
+        Ok(client) => client.alerts().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    match result {
+        Ok(alerts) => AlertsStatus {
+            error: None,
+            alerts,
+        },
+        Err(error) => AlertsStatus {
+            error: Some(error),
+            alerts: Vec::new(),
+        },
+    }
+}
+
+/// Collect sync peer status, adding an entry for every stale peer
+async fn collect_sync_status(client: &GatewayClient, sync: &SyncStatus) -> Vec<ComponentStatus> {
+    let peers = match client.peers().await {
+        Ok(peers) => peers,
+        Err(_) => {
+            return vec![ComponentStatus {
+                name: "Sync".to_string(),
+                status: "Unknown".to_string(),
+                details: format!("{} peers", sync.peer_count),
+            }];
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let stale: Vec<_> = peers
+        .iter()
+        .filter(|p| p.is_stale(now, sync.interval_secs()))
+        .collect();
+
+    let mut components = vec![ComponentStatus {
+        name: "Sync".to_string(),
+        status: if stale.is_empty() { "Running" } else { "Warning" }.to_string(),
+        details: format!("{} peers, {} stale", peers.len(), stale.len()),
+    }];
+
+    for peer in stale {
+        let details = match peer.last_error {
+            Some(ref err) => format!("{} ({})", extract_gateway_address(&peer.endpoint), err),
+            None => extract_gateway_address(&peer.endpoint),
+        };
+        components.push(ComponentStatus {
+            name: "Peer".to_string(),
+            status: "Stale".to_string(),
+            details,
+        });
+    }
+
+    components
+}
+
+/// Collect bridge status, adding an entry for every container port
+async fn collect_bridge_status() -> Vec<ComponentStatus> {
+    let bridge = match NetlinkClient::new() {
+        Ok(client) => client.bridge(BRIDGE_NAME).await,
+        Err(e) => Err(e),
+    };
+
+    match bridge {
+        Ok(Some(bridge)) => bridge_components(&bridge),
+        Ok(None) => vec![ComponentStatus {
+            name: "Bridge".to_string(),
+            status: "Missing".to_string(),
+            details: BRIDGE_NAME.to_string(),
+        }],
+        Err(_) => vec![ComponentStatus {
+            name: "Bridge".to_string(),
+            status: "Unknown".to_string(),
+            details: BRIDGE_NAME.to_string(),
+        }],
+    }
+}
+
+/// Build the Bridge entry and one Port entry per attached link
+fn bridge_components(bridge: &BridgeInfo) -> Vec<ComponentStatus> {
+    let address = bridge
+        .addresses
+        .first()
+        .map(|a| a.to_string())
+        .unwrap_or_else(|| "no address".to_string());
+
+    let mut components = vec![ComponentStatus {
+        name: "Bridge".to_string(),
+        status: if bridge.link.admin_up { "Ready" } else { "Down" }.to_string(),
+        details: format!("{}, {} ports", address, bridge.ports.len()),
+    }];
+
+    for port in &bridge.ports {
+        components.push(ComponentStatus {
+            name: "Port".to_string(),
+            status: if port.is_oper_up() { "Running" } else { "Down" }.to_string(),
+            details: port.name.clone(),
+        });
+    }
+
+    components
+}
+
 /// Check a monitoring service's health
 async fn check_monitoring_service(name: &str, url: &str, port: &str) -> ComponentStatus {
     let client = match reqwest::Client::builder()
@@ -284,6 +486,16 @@ fn print_formatted_status(status: &SystemStatus, use_color: bool) {
         print_component_status(component, use_color);
     }
 
+    // Alerts section
+    print_box_separator(use_color);
+    print_box_title("Alerts", use_color);
+    print_box_separator(use_color);
+    print_component_status(&alertmanager_component(&status.alerts), use_color);
+    let now = chrono::Utc::now();
+    for alert in &status.alerts.alerts {
+        print_alert(alert, now, use_color);
+    }
+
     // Bottom border
     print_box_bottom(use_color);
 
@@ -299,6 +511,98 @@ fn print_formatted_status(status: &SystemStatus, use_color: bool) {
     }
 }
 
+/// Summary row for the alerts section
+fn alertmanager_component(alerts: &AlertsStatus) -> ComponentStatus {
+    if alerts.error.is_some() {
+        return ComponentStatus {
+            name: "Alertmanager".to_string(),
+            status: "Stopped".to_string(),
+            details: ":9093".to_string(),
+        };
+    }
+
+    let firing = alerts.alerts.iter().filter(|a| a.is_active()).count();
+    let details = if alerts.alerts.is_empty() {
+        "no active alerts".to_string()
+    } else {
+        format!(
+            "{} firing, {} muted",
+            firing,
+            alerts.alerts.len() - firing
+        )
+    };
+
+    ComponentStatus {
+        name: "Alertmanager".to_string(),
+        status: if firing > 0 { "Warning" } else { "Running" }.to_string(),
+        details,
+    }
+}
+
+/// Format the severity and age columns of an alert row
+fn alert_columns(alert: &Alert, now: chrono::DateTime<chrono::Utc>) -> (String, String) {
+    let severity = if alert.is_silenced() {
+        "silenced".to_string()
+    } else if !alert.is_active() {
+        "inhibited".to_string()
+    } else {
+        alert.severity().to_string()
+    };
+
+    let age = match alert.age_secs(now) {
+        Some(secs) if secs < 60 => format!("for {}s", secs),
+        Some(secs) if secs < 3600 => format!("for {}m", secs / 60),
+        Some(secs) if secs < 86400 => format!("for {}h", secs / 3600),
+        Some(secs) => format!("for {}d", secs / 86400),
+        None => String::new(),
+    };
+
+    (severity, age)
+}
+
+/// Print an alert row
+fn print_alert(alert: &Alert, now: chrono::DateTime<chrono::Utc>, use_color: bool) {
+    let (severity, age) = alert_columns(alert, now);
+
+    // Format: "│ ● AlertName                critical  for 5m        │"
+    let name_width = 24;
+    let severity_width = 9;
+    let age_width = BOX_WIDTH - 8 - name_width - severity_width;
+    let name: String = alert.name().chars().take(name_width).collect();
+
+    if use_color {
+        let (indicator, severity_text) = match severity.as_str() {
+            "critical" => (STATUS_STOPPED.red(), severity.red()),
+            "silenced" | "inhibited" => (STATUS_WARN.bright_black(), severity.bright_black()),
+            _ => (STATUS_WARN.yellow(), severity.yellow()),
+        };
+        println!(
+            "{} {} {:<name_width$} {:<severity_width$} {:<age_width$} {}",
+            box_chars::VERTICAL.bright_black(),
+            indicator,
+            name,
+            severity_text,
+            age.dimmed(),
+            box_chars::VERTICAL.bright_black(),
+        );
+    } else {
+        let indicator = if severity == "critical" {
+            STATUS_STOPPED
+        } else {
+            STATUS_WARN
+        };
+        println!(
+            "{} {} {:<name_width$} {:<severity_width$} {:<age_width$} {}",
+            box_chars::VERTICAL,
+            indicator,
+            name,
+            severity,
+            age,
+            box_chars::VERTICAL,
+        );
+    }
+}
+
 /// Print box top border
 fn print_box_top(use_color: bool) {
     let line = format!(
@@ -374,11 +678,11 @@ fn print_component_status(component: &ComponentStatus, use_color: bool) {
                 STATUS_OK.green().to_string(),
                 component.status.green().to_string(),
             ),
-            "Warning" | "Disabled" => (
+            "Warning" | "Disabled" | "Stale" => (
                 STATUS_WARN.yellow().to_string(),
                 component.status.yellow().to_string(),
             ),
-            "Stopped" | "Error" | "Unknown" | "Timeout" => (
+            "Stopped" | "Error" | "Unknown" | "Timeout" | "Down" | "Missing" => (
                 STATUS_STOPPED.red().to_string(),
                 component.status.red().to_string(),
             ),
@@ -390,7 +694,7 @@ fn print_component_status(component: &ComponentStatus, use_color: bool) {
     } else {
         let indicator = match component.status.as_str() {
             "Running" | "Ready" => STATUS_OK,
-            "Warning" | "Disabled" => STATUS_WARN,
+            "Warning" | "Disabled" | "Stale" => STATUS_WARN,
             _ => STATUS_STOPPED,
         };
         (indicator.to_string(), component.status.clone())
@@ -475,4 +779,83 @@ mod tests {
         assert!(json.contains("Gateway"));
         assert!(json.contains("Running"));
     }
+
+    #[test]
+    fn test_alert_rows() {
+        let alert: Alert = serde_json::from_value(serde_json::json!({
+            "labels": {"alertname": "GatewayDown", "severity": "critical"},
+            "startsAt": "2026-10-18T10:00:00Z",
+            "status": {"state": "active", "silencedBy": [], "inhibitedBy": []}
+        }))
+        .unwrap();
+        let now = chrono::DateTime::parse_from_rfc3339("2026-10-18T10:05:30Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        assert_eq!(
+            alert_columns(&alert, now),
+            ("critical".to_string(), "for 5m".to_string())
+        );
+
+        let status = AlertsStatus {
+            error: None,
+            alerts: vec![alert],
+        };
+        let component = alertmanager_component(&status);
+        assert_eq!(component.status, "Warning");
+        assert_eq!(component.details, "1 firing, 0 muted");
+
+        let status = AlertsStatus {
+            error: Some("timeout".to_string()),
+            alerts: Vec::new(),
+        };
+        assert_eq!(alertmanager_component(&status).status, "Stopped");
+    }
+
+    #[test]
+    fn test_bridge_components() {
+        use crate::clients::netlink::Link;
+
+        let link = |name: &str, oper_state: &str| Link {
+            index: 0,
+            name: name.to_string(),
+            admin_up: true,
+            oper_state: oper_state.to_string(),
+            controller: Some(5),
+            kind: Some("veth".to_string()),
+        };
+        let bridge = BridgeInfo {
+            link: link("flatnet-br0", "up"),
+            addresses: vec!["10.100.1.1/24".parse().unwrap()],
+            ports: vec![link("fn-0123456789ab", "up"), link("fn-deadbeef0000", "down")],
+        };
+
+        let components = bridge_components(&bridge);
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0].status, "Ready");
+        assert_eq!(components[0].details, "10.100.1.1/24, 2 ports");
+        assert_eq!(components[2].status, "Down");
+    }
+
+    #[test]
+    fn test_nebula_component() {
+        let now = 1_800_000_000;
+        let cert = NebulaCert {
+            name: "host-a".to_string(),
+            ips: vec!["10.100.1.1/16".parse().unwrap()],
+            not_after: now + 200 * 86400,
+            ..Default::default()
+        };
+
+        let component = nebula_component(&cert, true, now, 30);
+        assert_eq!(component.status, "Ready");
+        assert_eq!(component.details, "10.100.1.1, cert 200d");
+
+        let component = nebula_component(&cert, false, now, 30);
+        assert_eq!(component.status, "Warning");
+        assert_eq!(component.details, "lighthouse unreachable");
+
+        let component = nebula_component(&cert, true, now + 201 * 86400, 30);
+        assert_eq!(component.status, "Error");
+    }
 }
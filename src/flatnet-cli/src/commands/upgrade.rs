@@ -1,68 +1,60 @@
 //! Upgrade command implementation
 //!
-//! Handles self-upgrading the CLI to newer versions.
+//! Upgrades the CLI, the CNI plugin, and the Gateway Lua bundle from a
+//! GitHub release or a local bundle. Everything is checked against the release's signed manifest and
+//! compatibility matrix before anything is replaced.
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use colored::Colorize;
-use serde::Deserialize;
-use std::env;
-use std::fs;
+use std::collections::HashMap;
 use std::io::{self, Write};
-use std::os::unix::fs::PermissionsExt;
-use std::path::Path;
-use std::time::Duration;
+use std::process::Stdio;
 
-use crate::cli::UpgradeArgs;
+use crate::cli::{ComponentArg, UpgradeArgs};
+use crate::clients::github::GitHubClient;
 use crate::config::Config;
-
-/// GitHub repository for releases
-const GITHUB_REPO: &str = "khayashi4337/flatnet";
-
-/// GitHub API base URL
-const GITHUB_API: &str = "https://api.github.com";
-
-/// GitHub release information
-#[derive(Debug, Deserialize)]
-struct GitHubRelease {
-    tag_name: String,
-    assets: Vec<GitHubAsset>,
-}
-
-/// GitHub release asset
-#[derive(Debug, Deserialize)]
-struct GitHubAsset {
-    name: String,
-    browser_download_url: String,
-}
+use crate::upgrade::compat::CompatibilityMatrix;
+use crate::upgrade::source::{AssetData, Release, ReleaseSource};
+use crate::upgrade::{install, Component, Installed};
 
 /// Run the upgrade command
 pub async fn run(args: UpgradeArgs) -> Result<()> {
     let config = Config::load()?;
     let use_color = config.color_enabled();
+    let components = selected_components(args.component);
 
-    // Get current version
-    let current_version = env!("CARGO_PKG_VERSION");
+    if args.rollback {
+        return run_rollback(&config, &components, use_color).await;
+    }
 
-    // Determine target version
-    let target_version = if let Some(ref version) = args.version {
-        version.trim_start_matches('v').to_string()
-    } else {
-        get_latest_version().await?
-    };
+    let release = open_release(&config, &args).await?;
+    let release_version = release.version();
+    let matrix = &release.matrix;
 
-    // Display version information
-    if use_color {
-        println!();
-        println!("Current version: {}", current_version.cyan());
-        println!("Latest version:  {}", target_version.cyan());
-    } else {
-        println!();
-        println!("Current version: {}", current_version);
-        println!("Latest version:  {}", target_version);
+    // Discover every component; the matrix may constrain unselected ones
+    let mut installed = HashMap::new();
+    for component in Component::ALL {
+        installed.insert(component, component.installed(&config).await);
+    }
+
+    println!();
+    print_versions(&components, &installed, matrix, use_color);
+
+    let mut pending = Vec::new();
+    for component in &components {
+        let available = matrix.version(*component).ok_or_else(|| {
+            anyhow!(
+                "Release v{} does not include {}",
+                release_version,
+                component
+            )
+        })?;
+        if !installed[component].is(available) {
+            pending.push(*component);
+        }
     }
 
-    // Compare versions
-    if current_version == target_version {
+    if pending.is_empty() {
         println!();
         if use_color {
             println!("{}", "Already up to date!".green());
@@ -72,172 +64,311 @@ pub async fn run(args: UpgradeArgs) -> Result<()> {
         return Ok(());
     }
 
+    let problems = matrix.check(&pending, &installed);
+    if !problems.is_empty() {
+        bail!(
+            "Upgrade would leave incompatible components:\n  {}\n\nUpgrade them together: flatnet upgrade --component all",
+            problems.join("\n  ")
+        );
+    }
+
+    let names: Vec<&str> = pending.iter().map(|c| c.name()).collect();
+
     // Check-only mode
     if args.check {
         println!();
         if use_color {
             println!(
                 "{}",
-                format!("Update available: v{} -> v{}", current_version, target_version).yellow()
+                format!("Update available: {} (release v{})", names.join(", "), release_version)
+                    .yellow()
             );
             println!();
-            println!("Run '{}' to upgrade.", "flatnet upgrade".bold());
+            println!("Run '{}' to upgrade.", upgrade_command(args.component, args.from.as_deref()).bold());
         } else {
             println!(
-                "Update available: v{} -> v{}",
-                current_version, target_version
+                "Update available: {} (release v{})",
+                names.join(", "),
+                release_version
             );
             println!();
-            println!("Run 'flatnet upgrade' to upgrade.");
+            println!("Run '{}' to upgrade.", upgrade_command(args.component, args.from.as_deref()));
         }
         return Ok(());
     }
 
-    // Perform upgrade
+    let platform = detect_platform()?;
+    let mut done: Vec<Component> = Vec::new();
+
+    for component in pending {
+        let result = upgrade_component(&release, &config, component, &platform, use_color).await;
+
+        if let Err(e) = result {
+            if done.is_empty() {
+                return Err(e);
+            }
+            let names: Vec<&str> = done.iter().map(|c| c.name()).collect();
+            return Err(e.context(format!(
+                "Upgrade stopped part way; already upgraded: {} (undo with 'flatnet upgrade --rollback --component <name>')",
+                names.join(", ")
+            )));
+        }
+        done.push(component);
+    }
+
     println!();
     if use_color {
         println!(
             "{}",
-            format!("Downloading flatnet v{}...", target_version).bold()
+            format!("Upgraded successfully to v{}!", release_version).green()
+        );
+        println!();
+        println!(
+            "Previous versions saved. Run '{}' to restore them.",
+            rollback_command(args.component).bold()
         );
     } else {
-        println!("Downloading flatnet v{}...", target_version);
+        println!("Upgraded successfully to v{}!", release_version);
+        println!();
+        println!(
+            "Previous versions saved. Run '{}' to restore them.",
+            rollback_command(args.component)
+        );
     }
 
-    let binary = download_binary(&target_version, use_color).await?;
+    Ok(())
+}
+
+/// Expand the --component argument
+fn selected_components(arg: ComponentArg) -> Vec<Component> {
+    match arg {
+        ComponentArg::Cli => vec![Component::Cli],
+        ComponentArg::Cni => vec![Component::Cni],
+        ComponentArg::GatewayConfig => vec![Component::GatewayConfig],
+        ComponentArg::All => Component::ALL.to_vec(),
+    }
+}
+
+/// Open the release to upgrade from
+async fn open_release(config: &Config, args: &UpgradeArgs) -> Result<Release> {
+    let version = args.version.as_deref().map(|v| v.trim_start_matches('v'));
+
+    let source = match &args.from {
+        Some(from) => ReleaseSource::open(from).await?,
+        None => {
+            let client = GitHubClient::new(&config.upgrade.github_api)?;
+            let release = match version {
+                Some(version) => client.release(version).await?,
+                None => client.latest_release().await?,
+            };
+            ReleaseSource::GitHub { client, release }
+        }
+    };
+
+    Release::verify(source, version).await
+}
+
+/// Download a component and check it against the manifest
+async fn download(
+    release: &Release,
+    component: Component,
+    platform: &str,
+    use_color: bool,
+) -> Result<Vec<u8>> {
+    let asset_name = component.asset_name(platform);
+    let data = match release.source.fetch(&asset_name).await? {
+        Some(AssetData::Remote(response)) => download_with_progress(response, use_color).await?,
+        Some(AssetData::Local(data)) => data,
+        None => bail!(
+            "No {} asset for platform '{}' in {}",
+            component,
+            platform,
+            release.source.describe()
+        ),
+    };
+    release.manifest.check(&asset_name, &data)?;
+
+    if use_color {
+        println!("{}", "Signature and checksum verified.".green());
+    } else {
+        println!("Signature and checksum verified.");
+    }
 
-    // Get current executable path
-    let current_exe = env::current_exe().context("Failed to get current executable path")?;
+    Ok(data)
+}
 
-    // Replace binary atomically
-    replace_binary(&current_exe, &binary)?;
+/// Download, verify, and install one component
+async fn upgrade_component(
+    release: &Release,
+    config: &Config,
+    component: Component,
+    platform: &str,
+    use_color: bool,
+) -> Result<()> {
+    let version = release
+        .matrix
+        .version(component)
+        .context("Component missing from the compatibility matrix")?;
 
     println!();
+    if use_color {
+        println!("{}", format!("Downloading {} v{}...", component, version).bold());
+    } else {
+        println!("Downloading {} v{}...", component, version);
+    }
+
+    let data = download(release, component, platform, use_color).await?;
+
+    let path = component.location(config)?;
+    match component {
+        Component::Cli | Component::Cni => install::install_binary(&path, &data, version).await?,
+        Component::GatewayConfig => install::install_bundle(&path, &data, version)?,
+    }
+
     if use_color {
         println!(
             "{}",
-            format!("Upgraded successfully to v{}!", target_version).green()
-        );
-        println!();
-        println!(
-            "Run '{}' to confirm.",
-            "flatnet --version".bold()
+            format!("Installed {} v{} ({})", component, version, path.display()).green()
         );
     } else {
-        println!("Upgraded successfully to v{}!", target_version);
-        println!();
-        println!("Run 'flatnet --version' to confirm.");
+        println!("Installed {} v{} ({})", component, version, path.display());
+    }
+
+    if component == Component::GatewayConfig {
+        reload_gateway(config, use_color).await;
     }
 
     Ok(())
 }
 
-/// Get the latest version from GitHub releases
-async fn get_latest_version() -> Result<String> {
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .user_agent("flatnet-cli")
-        .build()
-        .context("Failed to create HTTP client")?;
-
-    let url = format!("{}/repos/{}/releases/latest", GITHUB_API, GITHUB_REPO);
-
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .context("Failed to fetch latest release from GitHub")?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        if status.as_u16() == 404 {
-            bail!("No releases found. Check https://github.com/{}/releases", GITHUB_REPO);
+/// Restore the previous version of each component
+async fn run_rollback(config: &Config, components: &[Component], use_color: bool) -> Result<()> {
+    let single = components.len() == 1;
+    println!();
+
+    for component in components {
+        let path = component.location(config)?;
+        let backup = install::backup_path(&path)?;
+
+        if !backup.exists() {
+            if single {
+                bail!(
+                    "No previous version of {} to roll back to ({} not found)",
+                    component,
+                    backup.display()
+                );
+            }
+            println!("{}: no backup, skipped", component);
+            continue;
+        }
+
+        let version = match component {
+            Component::Cli | Component::Cni => {
+                let output = install::smoke_test(&backup)
+                    .await
+                    .with_context(|| format!("{} backup failed the --version smoke test", component))?;
+                install::reported_version(&output).unwrap_or("unknown").to_string()
+            }
+            Component::GatewayConfig => {
+                install::read_bundle_version(&backup).unwrap_or_else(|| "unknown".to_string())
+            }
+        };
+
+        install::rollback(&path)?;
+
+        if use_color {
+            println!("{}", format!("Rolled back {} to v{}.", component, version).green());
+        } else {
+            println!("Rolled back {} to v{}.", component, version);
+        }
+
+        if *component == Component::GatewayConfig {
+            reload_gateway(config, use_color).await;
         }
-        bail!("GitHub API returned error: {}", status);
     }
 
-    let release: GitHubRelease = response
-        .json()
-        .await
-        .context("Failed to parse GitHub release response")?;
+    println!();
+    println!("Run the same command again to undo.");
 
-    // Extract version from tag name (supports both "cli-v1.0.0" and "v1.0.0" formats)
-    let version = release
-        .tag_name
-        .trim_start_matches("cli-")
-        .trim_start_matches('v')
-        .to_string();
+    Ok(())
+}
 
-    Ok(version)
+/// Reload OpenResty so it picks up a new Lua bundle
+async fn reload_gateway(config: &Config, use_color: bool) {
+    let nginx = &config.gateway.nginx_bin;
+    let conf = &config.gateway.nginx_conf;
+
+    let status = tokio::process::Command::new(nginx)
+        .args(["-c", conf, "-s", "reload"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+
+    if !matches!(status, Ok(s) if s.success()) {
+        let message = format!(
+            "Could not reload OpenResty; run: {} -c {} -s reload",
+            nginx, conf
+        );
+        if use_color {
+            println!("{}", message.yellow());
+        } else {
+            println!("{}", message);
+        }
+    }
 }
 
-/// Download binary for the current platform
-async fn download_binary(version: &str, use_color: bool) -> Result<Vec<u8>> {
-    let platform = detect_platform()?;
-    let asset_name = format!("flatnet-{}", platform);
-
-    // Get release info
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(120))
-        .user_agent("flatnet-cli")
-        .build()
-        .context("Failed to create HTTP client")?;
-
-    let url = format!(
-        "{}/repos/{}/releases/tags/cli-v{}",
-        GITHUB_API, GITHUB_REPO, version
-    );
-
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .context("Failed to fetch release info from GitHub")?;
-
-    if !response.status().is_success() {
-        bail!("Release v{} not found", version);
+/// Print installed and available versions
+fn print_versions(
+    components: &[Component],
+    installed: &HashMap<Component, Installed>,
+    matrix: &CompatibilityMatrix,
+    use_color: bool,
+) {
+    println!("{:<16} {:<14} AVAILABLE", "COMPONENT", "INSTALLED");
+    for component in components {
+        let current = installed[component].to_string();
+        let available = matrix.version(*component).unwrap_or("-");
+        let line = format!("{:<16} {:<14} {}", component.name(), current, available);
+        if use_color && !installed[component].is(available) {
+            println!("{}", line.cyan());
+        } else {
+            println!("{}", line);
+        }
     }
+}
 
-    let release: GitHubRelease = response
-        .json()
-        .await
-        .context("Failed to parse release info")?;
-
-    // Find matching asset
-    let asset = release
-        .assets
-        .iter()
-        .find(|a| a.name == asset_name)
-        .ok_or_else(|| {
-            anyhow::anyhow!(
-                "No binary found for platform '{}' in release v{}",
-                platform,
-                version
-            )
-        })?;
+/// Command line that upgrades the same components from the same source
+fn upgrade_command(arg: ComponentArg, from: Option<&str>) -> String {
+    let mut command = match arg {
+        ComponentArg::Cli => "flatnet upgrade".to_string(),
+        _ => format!("flatnet upgrade --component {}", component_flag(arg)),
+    };
+    if let Some(from) = from {
+        command.push_str(&format!(" --from {}", from));
+    }
+    command
+}
 
-    // Download binary with progress
-    download_with_progress(&asset.browser_download_url, use_color).await
+/// Command line that rolls back the same components
+fn rollback_command(arg: ComponentArg) -> String {
+    match arg {
+        ComponentArg::Cli => "flatnet upgrade --rollback".to_string(),
+        _ => format!("flatnet upgrade --rollback --component {}", component_flag(arg)),
+    }
 }
 
-/// Download file with progress indicator
-async fn download_with_progress(url: &str, use_color: bool) -> Result<Vec<u8>> {
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(300))
-        .user_agent("flatnet-cli")
-        .build()
-        .context("Failed to create HTTP client")?;
-
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .context("Failed to start download")?;
-
-    if !response.status().is_success() {
-        bail!("Download failed: {}", response.status());
+fn component_flag(arg: ComponentArg) -> &'static str {
+    match arg {
+        ComponentArg::Cli => "cli",
+        ComponentArg::Cni => "cni",
+        ComponentArg::GatewayConfig => "gateway-config",
+        ComponentArg::All => "all",
     }
+}
 
+/// Download file with progress indicator
+async fn download_with_progress(response: reqwest::Response, use_color: bool) -> Result<Vec<u8>> {
     let total_size = response.content_length();
     let mut downloaded: u64 = 0;
     let mut data = Vec::new();
@@ -307,33 +438,6 @@ fn detect_platform() -> Result<String> {
     Ok(format!("{}-{}", os, arch))
 }
 
-/// Replace the current binary atomically
-fn replace_binary(path: &Path, new_binary: &[u8]) -> Result<()> {
-    // Create temporary file in the same directory (for atomic rename)
-    let parent = path.parent().context("Invalid executable path")?;
-    let tmp_path = parent.join(".flatnet.tmp");
-
-    // Write new binary to temp file
-    fs::write(&tmp_path, new_binary)
-        .with_context(|| format!("Failed to write temporary file: {}", tmp_path.display()))?;
-
-    // Set executable permissions
-    let mut perms = fs::metadata(&tmp_path)
-        .context("Failed to get temp file metadata")?
-        .permissions();
-    perms.set_mode(0o755);
-    fs::set_permissions(&tmp_path, perms).context("Failed to set executable permissions")?;
-
-    // Atomic rename
-    fs::rename(&tmp_path, path).with_context(|| {
-        // Clean up temp file on error
-        let _ = fs::remove_file(&tmp_path);
-        format!("Failed to replace binary: {}", path.display())
-    })?;
-
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,4 +456,18 @@ mod tests {
         let version = env!("CARGO_PKG_VERSION");
         assert!(!version.is_empty());
     }
+
+    #[test]
+    fn test_selected_components() {
+        assert_eq!(selected_components(ComponentArg::Cli), vec![Component::Cli]);
+        assert_eq!(selected_components(ComponentArg::All), Component::ALL.to_vec());
+        assert_eq!(
+            rollback_command(ComponentArg::GatewayConfig),
+            "flatnet upgrade --rollback --component gateway-config"
+        );
+        assert_eq!(
+            upgrade_command(ComponentArg::All, Some("./flatnet-bundle-0.3.0.tar.gz")),
+            "flatnet upgrade --component all --from ./flatnet-bundle-0.3.0.tar.gz"
+        );
+    }
 }
@@ -25,6 +25,26 @@ pub struct Config {
     /// Display settings
     #[serde(default)]
     pub display: DisplayConfig,
+
+    /// Upgrade settings
+    #[serde(default)]
+    pub upgrade: UpgradeConfig,
+
+    /// Cross-host route settings
+    #[serde(default)]
+    pub routes: RoutesConfig,
+
+    /// Nebula overlay settings
+    #[serde(default)]
+    pub nebula: NebulaConfig,
+
+    /// Embedded DNS server settings
+    #[serde(default)]
+    pub dns: DnsConfig,
+
+    /// Network policy settings
+    #[serde(default)]
+    pub policy: PolicyConfig,
 }
 
 impl Default for Config {
@@ -33,6 +53,11 @@ impl Default for Config {
             gateway: GatewayConfig::default(),
             monitoring: MonitoringConfig::default(),
             display: DisplayConfig::default(),
+            upgrade: UpgradeConfig::default(),
+            routes: RoutesConfig::default(),
+            nebula: NebulaConfig::default(),
+            dns: DnsConfig::default(),
+            policy: PolicyConfig::default(),
         }
     }
 }
@@ -46,17 +71,49 @@ pub struct GatewayConfig {
     /// Request timeout in seconds
     #[serde(default = "default_timeout")]
     pub timeout_secs: u64,
+
+    /// Gateway metrics URL (default: port 9145 on the Gateway host)
+    #[serde(default)]
+    pub metrics_url: Option<String>,
+
+    /// OpenResty binary, as seen from WSL2 (used by `doctor --fix`)
+    #[serde(default = "default_nginx_bin")]
+    pub nginx_bin: String,
+
+    /// OpenResty configuration file, as a Windows path
+    #[serde(default = "default_nginx_conf")]
+    pub nginx_conf: String,
+
+    /// OpenResty configuration directory, as seen from WSL2 (used by `upgrade`)
+    #[serde(default = "default_config_dir")]
+    pub config_dir: String,
 }
 
 fn default_timeout() -> u64 {
     5
 }
 
+fn default_nginx_bin() -> String {
+    "/mnt/f/flatnet/openresty/nginx.exe".to_string()
+}
+
+fn default_nginx_conf() -> String {
+    "F:/flatnet/config/nginx.conf".to_string()
+}
+
+fn default_config_dir() -> String {
+    "/mnt/f/flatnet/config".to_string()
+}
+
 impl Default for GatewayConfig {
     fn default() -> Self {
         Self {
             url: None,
             timeout_secs: default_timeout(),
+            metrics_url: None,
+            nginx_bin: default_nginx_bin(),
+            nginx_conf: default_nginx_conf(),
+            config_dir: default_config_dir(),
         }
     }
 }
@@ -75,6 +132,10 @@ pub struct MonitoringConfig {
     /// Loki URL
     #[serde(default = "default_loki_url")]
     pub loki_url: String,
+
+    /// Alertmanager URL
+    #[serde(default = "default_alertmanager_url")]
+    pub alertmanager_url: String,
 }
 
 fn default_prometheus_url() -> String {
@@ -89,12 +150,17 @@ fn default_loki_url() -> String {
     "http://localhost:3100".to_string()
 }
 
+fn default_alertmanager_url() -> String {
+    "http://localhost:9093".to_string()
+}
+
 impl Default for MonitoringConfig {
     fn default() -> Self {
         Self {
             prometheus_url: default_prometheus_url(),
             grafana_url: default_grafana_url(),
             loki_url: default_loki_url(),
+            alertmanager_url: default_alertmanager_url(),
         }
     }
 }
@@ -119,6 +185,187 @@ impl Default for DisplayConfig {
     }
 }
 
+/// Upgrade configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeConfig {
+    /// GitHub API base URL (point at a mock server for testing)
+    #[serde(default = "default_github_api")]
+    pub github_api: String,
+}
+
+fn default_github_api() -> String {
+    "https://api.github.com".to_string()
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        Self {
+            github_api: default_github_api(),
+        }
+    }
+}
+
+/// Cross-host route configuration (`flatnet routes sync`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutesConfig {
+    /// Next hop for peer host subnets (default: Windows host IP from /etc/resolv.conf)
+    pub next_hop: Option<String>,
+
+    /// This host's ID (default: `host_id` from the IPAM state)
+    pub host_id: Option<u8>,
+
+    /// Interval between syncs in watch mode
+    #[serde(default = "default_routes_interval")]
+    pub interval_secs: u64,
+}
+
+fn default_routes_interval() -> u64 {
+    30
+}
+
+impl Default for RoutesConfig {
+    fn default() -> Self {
+        Self {
+            next_hop: None,
+            host_id: None,
+            interval_secs: default_routes_interval(),
+        }
+    }
+}
+
+/// Nebula overlay configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NebulaConfig {
+    /// Nebula host config, as seen from WSL2
+    #[serde(default = "default_nebula_config")]
+    pub config: String,
+
+    /// Warn when a certificate expires within this many days
+    #[serde(default = "default_cert_warning_days")]
+    pub cert_warning_days: i64,
+}
+
+fn default_nebula_config() -> String {
+    "/mnt/f/flatnet/config/nebula/config.yaml".to_string()
+}
+
+fn default_cert_warning_days() -> i64 {
+    30
+}
+
+impl Default for NebulaConfig {
+    fn default() -> Self {
+        Self {
+            config: default_nebula_config(),
+            cert_warning_days: default_cert_warning_days(),
+        }
+    }
+}
+
+/// Embedded DNS server configuration (`flatnet dns serve`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsConfig {
+    /// Address to listen on (default: bridge gateway IP from the IPAM state)
+    pub listen: Option<String>,
+
+    /// UDP port to listen on
+    #[serde(default = "default_dns_port")]
+    pub port: u16,
+
+    /// Domain the container names are served under
+    #[serde(default = "default_dns_domain")]
+    pub domain: String,
+
+    /// Upstream servers for other names (default: nameservers in /etc/resolv.conf)
+    #[serde(default)]
+    pub upstreams: Vec<String>,
+
+    /// TTL of container records
+    #[serde(default = "default_dns_ttl")]
+    pub ttl_secs: u32,
+
+    /// Interval between reloads of the IPAM state and Gateway registry
+    #[serde(default = "default_dns_refresh")]
+    pub refresh_secs: u64,
+
+    /// IPAM data directory of the local flatnet-cni
+    /// (default: `ipam.dataDir` of the installed conflist, else /var/lib/flatnet/ipam)
+    pub ipam_dir: Option<String>,
+}
+
+fn default_dns_port() -> u16 {
+    53
+}
+
+fn default_dns_domain() -> String {
+    "flatnet".to_string()
+}
+
+fn default_dns_ttl() -> u32 {
+    5
+}
+
+fn default_dns_refresh() -> u64 {
+    5
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            listen: None,
+            port: default_dns_port(),
+            domain: default_dns_domain(),
+            upstreams: Vec::new(),
+            ttl_secs: default_dns_ttl(),
+            refresh_secs: default_dns_refresh(),
+            ipam_dir: None,
+        }
+    }
+}
+
+/// Network policy configuration (`flatnet policy`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyConfig {
+    /// Policy file
+    #[serde(default = "default_policy_file")]
+    pub file: String,
+
+    /// Compiled container sets read by flatnet-cni
+    #[serde(default = "default_policy_state_file")]
+    pub state_file: String,
+
+    /// IPAM data directory of the local flatnet-cni
+    /// (default: `ipam.dataDir` of the installed conflist, else /var/lib/flatnet/ipam)
+    pub ipam_dir: Option<String>,
+
+    /// Interval between re-applies in watch mode
+    #[serde(default = "default_policy_interval")]
+    pub interval_secs: u64,
+}
+
+fn default_policy_file() -> String {
+    "/etc/flatnet/policy.toml".to_string()
+}
+
+fn default_policy_state_file() -> String {
+    "/var/lib/flatnet/policy.json".to_string()
+}
+
+fn default_policy_interval() -> u64 {
+    30
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            file: default_policy_file(),
+            state_file: default_policy_state_file(),
+            ipam_dir: None,
+            interval_secs: default_policy_interval(),
+        }
+    }
+}
+
 impl Config {
     /// Load configuration from file and environment variables
     ///
@@ -169,6 +416,10 @@ impl Config {
             }
         }
 
+        if let Ok(url) = env::var("FLATNET_GATEWAY_METRICS_URL") {
+            self.gateway.metrics_url = Some(url);
+        }
+
         if let Ok(url) = env::var("FLATNET_PROMETHEUS_URL") {
             self.monitoring.prometheus_url = url;
         }
@@ -181,6 +432,22 @@ impl Config {
             self.monitoring.loki_url = url;
         }
 
+        if let Ok(url) = env::var("FLATNET_ALERTMANAGER_URL") {
+            self.monitoring.alertmanager_url = url;
+        }
+
+        if let Ok(next_hop) = env::var("FLATNET_ROUTES_NEXT_HOP") {
+            self.routes.next_hop = Some(next_hop);
+        }
+
+        if let Ok(path) = env::var("FLATNET_NEBULA_CONFIG") {
+            self.nebula.config = path;
+        }
+
+        if let Ok(url) = env::var("FLATNET_GITHUB_API") {
+            self.upgrade.github_api = url;
+        }
+
         // NO_COLOR standard takes precedence (https://no-color.org/)
         if env::var("NO_COLOR").is_ok() {
             self.display.color = false;
@@ -220,6 +487,11 @@ impl Config {
         std::time::Duration::from_secs(self.gateway.timeout_secs)
     }
 
+    /// Get the next hop for peer host routes
+    pub fn routes_next_hop(&self) -> Option<String> {
+        self.routes.next_hop.clone().or_else(get_windows_ip)
+    }
+
     /// Check if color output is enabled
     pub fn color_enabled(&self) -> bool {
         self.display.color
@@ -282,5 +554,10 @@ color = false
         );
         assert_eq!(config.gateway.timeout_secs, 10);
         assert!(!config.display.color);
+        assert_eq!(config.monitoring.alertmanager_url, "http://localhost:9093");
+        assert_eq!(config.nebula.cert_warning_days, 30);
+        assert_eq!(config.dns.port, 53);
+        assert_eq!(config.dns.domain, "flatnet");
+        assert_eq!(config.policy.file, "/etc/flatnet/policy.toml");
     }
 }
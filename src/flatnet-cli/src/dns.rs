@@ -0,0 +1,445 @@
+//! Embedded DNS for container names
+//!
+//! Answers `<container>.<domain>` A/AAAA queries and the matching PTR
+//! queries from a name table built from the local IPAM state and the Gateway
+//! registry. Everything else is left to `flatnet dns serve` to forward
+//! upstream. Only the parts of RFC 1035 needed for that are implemented.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use crate::clients::gateway::ContainerInfo;
+use crate::clients::ipam::IpamState;
+
+/// Size of the message header
+const HEADER_LEN: usize = 12;
+
+/// Port of upstream servers given without one
+const DEFAULT_PORT: u16 = 53;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+
+/// Header flags
+const FLAG_QR: u16 = 0x8000;
+const FLAG_AA: u16 = 0x0400;
+const FLAG_RD: u16 = 0x0100;
+const FLAG_RA: u16 = 0x0080;
+
+/// Response codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rcode {
+    NoError = 0,
+    ServFail = 2,
+    NxDomain = 3,
+}
+
+/// The question of a standard query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// Lowercased name without the trailing dot
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+    /// Offset of the end of the question section
+    end: usize,
+}
+
+/// How to answer a question
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup {
+    /// A record of a container
+    Address(Ipv4Addr),
+    /// PTR record of a container address (fully qualified name)
+    Pointer(String),
+    /// The name exists but has no record of the requested type
+    NoData,
+    /// No container has this name
+    NxDomain,
+    /// Not ours; ask an upstream server
+    Forward,
+}
+
+/// Check if a packet is a query with a full header
+///
+/// Anything else (responses, truncated packets) is never answered or
+/// forwarded.
+pub fn is_query(packet: &[u8]) -> bool {
+    packet.len() >= HEADER_LEN && flags(packet) & FLAG_QR == 0
+}
+
+fn flags(packet: &[u8]) -> u16 {
+    u16::from_be_bytes([packet[2], packet[3]])
+}
+
+/// Parse the question of a standard query with exactly one question
+pub fn parse_query(packet: &[u8]) -> Option<Question> {
+    if packet.len() < HEADER_LEN {
+        return None;
+    }
+    let opcode = (flags(packet) >> 11) & 0xf;
+    let qdcount = u16::from_be_bytes([packet[4], packet[5]]);
+    if !is_query(packet) || opcode != 0 || qdcount != 1 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut pos = HEADER_LEN;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        // Longer lengths are compression pointers, which queries do not use
+        if len > 63 {
+            return None;
+        }
+        let label = packet.get(pos..pos + len)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        pos += len;
+    }
+
+    let fixed = packet.get(pos..pos + 4)?;
+    Some(Question {
+        name: labels.join("."),
+        qtype: u16::from_be_bytes([fixed[0], fixed[1]]),
+        qclass: u16::from_be_bytes([fixed[2], fixed[3]]),
+        end: pos + 4,
+    })
+}
+
+/// Response to `query` for a local lookup, or `None` to forward it
+pub fn answer(query: &[u8], question: &Question, lookup: &Lookup, ttl: u32) -> Option<Vec<u8>> {
+    let (rcode, record) = match lookup {
+        Lookup::Address(ip) => (Rcode::NoError, Some((TYPE_A, ip.octets().to_vec()))),
+        Lookup::Pointer(name) => (Rcode::NoError, Some((TYPE_PTR, encode_name(name)))),
+        Lookup::NoData => (Rcode::NoError, None),
+        Lookup::NxDomain => (Rcode::NxDomain, None),
+        Lookup::Forward => return None,
+    };
+    Some(response(query, question, rcode, record, ttl))
+}
+
+/// SERVFAIL response, for queries no upstream server answered
+pub fn servfail(query: &[u8], question: &Question) -> Vec<u8> {
+    response(query, question, Rcode::ServFail, None, 0)
+}
+
+fn response(
+    query: &[u8],
+    question: &Question,
+    rcode: Rcode,
+    record: Option<(u16, Vec<u8>)>,
+    ttl: u32,
+) -> Vec<u8> {
+    let mut flags = FLAG_QR | FLAG_RA | (flags(query) & FLAG_RD) | rcode as u16;
+    if rcode != Rcode::ServFail {
+        flags |= FLAG_AA;
+    }
+
+    let mut packet = Vec::with_capacity(question.end + 32);
+    packet.extend_from_slice(&query[..2]);
+    packet.extend_from_slice(&flags.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet.extend_from_slice(&(record.is_some() as u16).to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 0]);
+    packet.extend_from_slice(&query[HEADER_LEN..question.end]);
+
+    if let Some((rtype, rdata)) = record {
+        // Owner name: pointer to the question name
+        packet.extend_from_slice(&[0xc0, HEADER_LEN as u8]);
+        packet.extend_from_slice(&rtype.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet.extend_from_slice(&ttl.to_be_bytes());
+        packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&rdata);
+    }
+    packet
+}
+
+/// Encode a dotted name in wire format
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label);
+    }
+    encoded.push(0);
+    encoded
+}
+
+/// Address of an `in-addr.arpa` name
+fn reverse_address(name: &str) -> Option<Ipv4Addr> {
+    let octets: Vec<u8> = name
+        .strip_suffix(".in-addr.arpa")?
+        .split('.')
+        .map(|o| o.parse().ok())
+        .collect::<Option<_>>()?;
+    match octets[..] {
+        [d, c, b, a] => Some(Ipv4Addr::new(a, b, c, d)),
+        _ => None,
+    }
+}
+
+/// Container names and addresses served under one domain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Records {
+    domain: String,
+    /// Name or short container ID -> address
+    names: HashMap<String, Ipv4Addr>,
+    /// Address -> name for PTR answers
+    addresses: HashMap<Ipv4Addr, String>,
+}
+
+impl Records {
+    /// Build the table from the local IPAM state and the Gateway registry
+    ///
+    /// Containers are found by name and by short (12-character) ID. Local
+    /// allocations win over registry entries with the same name.
+    pub fn build(domain: &str, local: Option<&IpamState>, registry: &[ContainerInfo]) -> Self {
+        let mut records = Self {
+            domain: domain.trim_matches('.').to_ascii_lowercase(),
+            ..Self::default()
+        };
+
+        if let Some(state) = local {
+            let mut allocations: Vec<(&String, &String)> = state.allocations.iter().collect();
+            allocations.sort();
+            for (id, ip) in allocations {
+                records.insert(state.names.get(id).map(String::as_str), id, ip);
+            }
+        }
+
+        let mut remote: Vec<&ContainerInfo> = registry.iter().collect();
+        remote.sort_by(|a, b| (a.host_id, &a.id).cmp(&(b.host_id, &b.id)));
+        for container in remote {
+            records.insert(container.name.as_deref(), &container.id, &container.ip);
+        }
+
+        records
+    }
+
+    fn insert(&mut self, name: Option<&str>, id: &str, ip: &str) {
+        let Ok(ip) = ip.parse::<Ipv4Addr>() else {
+            return;
+        };
+        let short_id: String = id.chars().take(12).collect::<String>().to_ascii_lowercase();
+        let name = name
+            .map(|n| n.trim_matches('.').to_ascii_lowercase())
+            .filter(|n| !n.is_empty());
+
+        for key in name.iter().chain([&short_id]) {
+            self.names.entry(key.clone()).or_insert(ip);
+        }
+
+        // PTR answers must resolve back to the same address
+        let canonical = name
+            .filter(|n| self.names.get(n) == Some(&ip))
+            .unwrap_or(short_id);
+        self.addresses.entry(ip).or_insert(canonical);
+    }
+
+    /// Number of names served (including short IDs)
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Answer a question from the table
+    pub fn lookup(&self, question: &Question) -> Lookup {
+        if question.qclass != CLASS_IN {
+            return Lookup::Forward;
+        }
+
+        if question.name == self.domain {
+            return Lookup::NoData;
+        }
+        let host = question
+            .name
+            .strip_suffix(self.domain.as_str())
+            .and_then(|n| n.strip_suffix('.'));
+        if let Some(host) = host {
+            return match (self.names.get(host), question.qtype) {
+                (Some(&ip), TYPE_A | TYPE_ANY) => Lookup::Address(ip),
+                // Containers only have IPv4 addresses, so AAAA is empty
+                (Some(_), _) => Lookup::NoData,
+                (None, _) => Lookup::NxDomain,
+            };
+        }
+
+        let name = reverse_address(&question.name).and_then(|ip| self.addresses.get(&ip));
+        match (name, question.qtype) {
+            (Some(name), TYPE_PTR | TYPE_ANY) => {
+                Lookup::Pointer(format!("{}.{}", name, self.domain))
+            }
+            (Some(_), _) => Lookup::NoData,
+            (None, _) => Lookup::Forward,
+        }
+    }
+}
+
+/// Parse an upstream server (`IP` or `IP:PORT`)
+pub fn parse_upstream(upstream: &str) -> Result<SocketAddr> {
+    if let Ok(ip) = upstream.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, DEFAULT_PORT));
+    }
+    upstream
+        .parse()
+        .with_context(|| format!("Invalid upstream DNS server: {}", upstream))
+}
+
+/// Nameservers listed in a resolv.conf
+pub fn resolv_conf_nameservers(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .filter(|ip| ip.parse::<IpAddr>().is_ok())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPE_AAAA: u16 = 28;
+
+    /// A recursive query for `name`
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        packet.extend(encode_name(name));
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet
+    }
+
+    fn records() -> Records {
+        let state: IpamState = serde_json::from_value(serde_json::json!({
+            "subnet": "10.100.1.0/24",
+            "gateway": "10.100.1.1",
+            "range_start": "10.100.1.10",
+            "range_end": "10.100.1.254",
+            "allocations": {
+                "aaaaaaaaaaaa1111": "10.100.1.10",
+                "bbbbbbbbbbbb2222": "10.100.1.11"
+            },
+            "names": {"aaaaaaaaaaaa1111": "Web"}
+        }))
+        .unwrap();
+        let registry: Vec<ContainerInfo> = serde_json::from_value(serde_json::json!([
+            {"id": "aaaaaaaaaaaa1111", "ip": "10.100.1.10", "hostId": 1, "hostname": "web"},
+            {"id": "cccccccccccc3333", "ip": "10.100.2.10", "hostId": 2, "hostname": "db"},
+            {"id": "dddddddddddd4444", "ip": "10.100.2.11", "hostId": 2, "hostname": "web"}
+        ]))
+        .unwrap();
+        Records::build("flatnet", Some(&state), &registry)
+    }
+
+    fn lookup(name: &str, qtype: u16) -> Lookup {
+        records().lookup(&parse_query(&query(name, qtype)).unwrap())
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let packet = query("Web.Flatnet.", TYPE_A);
+        let question = parse_query(&packet).unwrap();
+        assert_eq!(question.name, "web.flatnet");
+        assert_eq!(question.qtype, TYPE_A);
+        assert_eq!(question.end, packet.len());
+
+        assert!(parse_query(&packet[..packet.len() - 1]).is_none());
+        assert!(is_query(&packet));
+        let mut response = packet.clone();
+        response[2] |= 0x80;
+        assert!(!is_query(&response));
+        assert!(parse_query(&response).is_none());
+
+        // Shorter than a header: dropped, not forwarded
+        assert!(!is_query(&[]));
+        assert!(!is_query(&packet[..1]));
+        assert!(!is_query(&packet[..HEADER_LEN - 1]));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let web = Ipv4Addr::new(10, 100, 1, 10);
+        assert_eq!(lookup("web.flatnet", TYPE_A), Lookup::Address(web));
+        assert_eq!(lookup("aaaaaaaaaaaa.flatnet", TYPE_A), Lookup::Address(web));
+        // Remote containers, but the local "web" wins
+        assert_eq!(
+            lookup("db.flatnet", TYPE_A),
+            Lookup::Address(Ipv4Addr::new(10, 100, 2, 10))
+        );
+        assert_eq!(lookup("web.flatnet", TYPE_AAAA), Lookup::NoData);
+        assert_eq!(lookup("missing.flatnet", TYPE_A), Lookup::NxDomain);
+        assert_eq!(lookup("flatnet", TYPE_A), Lookup::NoData);
+        assert_eq!(lookup("example.com", TYPE_A), Lookup::Forward);
+
+        assert_eq!(
+            lookup("10.1.100.10.in-addr.arpa", TYPE_PTR),
+            Lookup::Pointer("web.flatnet".to_string())
+        );
+        // The shadowed remote "web" is only known by its ID
+        assert_eq!(
+            lookup("11.2.100.10.in-addr.arpa", TYPE_PTR),
+            Lookup::Pointer("dddddddddddd.flatnet".to_string())
+        );
+        assert_eq!(
+            lookup("11.1.100.10.in-addr.arpa", TYPE_PTR),
+            Lookup::Pointer("bbbbbbbbbbbb.flatnet".to_string())
+        );
+        assert_eq!(lookup("1.1.100.10.in-addr.arpa", TYPE_PTR), Lookup::Forward);
+    }
+
+    #[test]
+    fn test_answer() {
+        let packet = query("web.flatnet", TYPE_A);
+        let question = parse_query(&packet).unwrap();
+        let lookup = Lookup::Address(Ipv4Addr::new(10, 100, 1, 10));
+        let reply = answer(&packet, &question, &lookup, 5).unwrap();
+
+        assert_eq!(&reply[..2], &[0x12, 0x34]);
+        // QR, AA, RD, RA; NOERROR; one question, one answer
+        assert_eq!(&reply[2..12], &[0x85, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(&reply[12..question.end], &packet[12..]);
+        assert_eq!(
+            &reply[question.end..],
+            &[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 5, 0, 4, 10, 100, 1, 10]
+        );
+
+        let reply = answer(&packet, &question, &Lookup::NxDomain, 5).unwrap();
+        assert_eq!(&reply[2..8], &[0x85, 0x83, 0, 1, 0, 0]);
+        assert_eq!(reply.len(), packet.len());
+
+        let ptr = Lookup::Pointer("web.flatnet".to_string());
+        let reply = answer(&packet, &question, &ptr, 5).unwrap();
+        assert!(reply.ends_with(b"\x00\x0d\x03web\x07flatnet\x00"));
+
+        assert!(answer(&packet, &question, &Lookup::Forward, 5).is_none());
+        assert_eq!(servfail(&packet, &question)[3], 0x82);
+    }
+
+    #[test]
+    fn test_upstreams() {
+        assert_eq!(
+            parse_upstream("1.1.1.1").unwrap(),
+            "1.1.1.1:53".parse().unwrap()
+        );
+        assert_eq!(
+            parse_upstream("127.0.0.1:5353").unwrap(),
+            "127.0.0.1:5353".parse().unwrap()
+        );
+        assert!(parse_upstream("dns.example").is_err());
+
+        let resolv = "# generated\nnameserver 172.20.0.1\nnameserver fe80::1\nsearch lan\nnameserver bogus\n";
+        assert_eq!(
+            resolv_conf_nameservers(resolv),
+            vec!["172.20.0.1", "fe80::1"]
+        );
+    }
+}
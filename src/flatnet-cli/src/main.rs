@@ -7,6 +7,10 @@ mod cli;
 mod clients;
 mod commands;
 mod config;
+mod dns;
+mod policy;
+mod routes;
+mod upgrade;
 
 use anyhow::Result;
 use clap::Parser;
@@ -35,6 +39,27 @@ async fn main() -> Result<ExitCode> {
             commands::upgrade::run(args).await?;
             ExitCode::SUCCESS
         }
+        Commands::Peers(args) => {
+            commands::peers::run(args).await?;
+            ExitCode::SUCCESS
+        }
+        Commands::Metrics(args) => {
+            commands::metrics::run(args).await?;
+            ExitCode::SUCCESS
+        }
+        Commands::Alerts(args) => {
+            commands::alerts::run(args).await?;
+            ExitCode::SUCCESS
+        }
+        Commands::Routes(args) => {
+            commands::routes::run(args).await?;
+            ExitCode::SUCCESS
+        }
+        Commands::Dns(args) => {
+            commands::dns::run(args).await?;
+            ExitCode::SUCCESS
+        }
+        Commands::Policy(args) => commands::policy::run(args).await?,
     };
 
     Ok(exit_code)
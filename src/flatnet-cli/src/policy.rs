@@ -0,0 +1,956 @@
+//! Network policy between flatnet containers and hosts
+//!
+//! The policy file lists allow/deny rules between selectors (container name
+//! globs and labels, host IDs, CIDRs) and a default action per network.
+//! `compile` turns it into the `flatnet_policy` nftables tables, in the
+//! `inet` family for routed traffic and the `bridge` family for traffic
+//! between containers on the same bridge. Container selectors become named
+//! sets; their definitions are also written to a state file so flatnet-cni
+//! can add a new container to the matching sets during ADD.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use flatnet_policy::nft;
+pub use flatnet_policy::{ContainerSet, PolicyState};
+
+use crate::clients::gateway::ContainerInfo;
+use crate::clients::ipam::IpamState;
+use crate::clients::netlink::Ipv4Network;
+
+/// nftables table name (in both the inet and bridge families)
+pub const NFT_TABLE: &str = "flatnet_policy";
+
+/// Network the rules apply to when the policy lists none
+const DEFAULT_NETWORK: &str = "flatnet";
+
+const DEFAULT_BRIDGE: &str = "flatnet-br0";
+
+/// Network holding every host's `10.100.<id>.0/24` subnet
+const MULTIHOST_NETWORK: Ipv4Network = Ipv4Network {
+    address: Ipv4Addr::new(10, 100, 0, 0),
+    prefix_len: 16,
+};
+
+/// What happens to matching traffic
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    #[default]
+    Allow,
+    Deny,
+}
+
+impl Action {
+    fn verdict(self) -> &'static str {
+        match self {
+            Action::Allow => "accept",
+            Action::Deny => "drop",
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Allow => "allow",
+            Action::Deny => "deny",
+        })
+    }
+}
+
+/// Which way a rule applies, seen from its `containers`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// From `peers` to `containers`
+    #[default]
+    Ingress,
+    /// From `containers` to `peers`
+    Egress,
+    /// Either way
+    Both,
+}
+
+/// Transport protocol of a port range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+impl Protocol {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Sctp => "sctp",
+        }
+    }
+}
+
+impl std::str::FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            "sctp" => Ok(Protocol::Sctp),
+            _ => Err(format!("unknown protocol: {}", s)),
+        }
+    }
+}
+
+/// Destination port range: "80", "53/udp", "8000-8100/tcp"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PortRange {
+    pub protocol: Protocol,
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    fn contains(&self, protocol: Protocol, port: u16) -> bool {
+        self.protocol == protocol && (self.start..=self.end).contains(&port)
+    }
+}
+
+impl TryFrom<String> for PortRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (ports, protocol) = match value.split_once('/') {
+            Some((ports, protocol)) => (ports, protocol.parse()?),
+            None => (value.as_str(), Protocol::Tcp),
+        };
+        let parse = |port: &str| match port.trim().parse::<u16>() {
+            Ok(port) if port > 0 => Ok(port),
+            _ => Err(format!("invalid port: {}", value)),
+        };
+        let (start, end) = match ports.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(ports)?, parse(ports)?),
+        };
+        if start > end {
+            return Err(format!("invalid port range: {}", value));
+        }
+        Ok(Self {
+            protocol,
+            start,
+            end,
+        })
+    }
+}
+
+impl From<PortRange> for String {
+    fn from(range: PortRange) -> Self {
+        range.to_string()
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}/{}", self.start, self.protocol.as_str())
+        } else {
+            write!(f, "{}-{}/{}", self.start, self.end, self.protocol.as_str())
+        }
+    }
+}
+
+/// A set of containers and addresses
+///
+/// A container matches if its name matches one of `names` (when given) and
+/// it has all of `labels` (when given). An address matches if it is such a
+/// container or lies in one of `hosts` or `cidrs`. An empty selector
+/// matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Selector {
+    /// Container name globs (`*` and `?`)
+    pub names: Vec<String>,
+
+    /// Labels a container must all carry
+    pub labels: BTreeMap<String, String>,
+
+    /// Host IDs, for their `10.100.<id>.0/24` subnets
+    pub hosts: Vec<u8>,
+
+    /// Address ranges ("a.b.c.d/n" or a single address)
+    pub cidrs: Vec<String>,
+}
+
+impl Selector {
+    fn is_any(&self) -> bool {
+        self.names.is_empty()
+            && self.labels.is_empty()
+            && self.hosts.is_empty()
+            && self.cidrs.is_empty()
+    }
+
+    fn selects_containers(&self) -> bool {
+        !self.names.is_empty() || !self.labels.is_empty()
+    }
+
+    /// Host subnets and CIDRs (validated when the policy is loaded)
+    fn networks(&self) -> Vec<Ipv4Network> {
+        self.hosts
+            .iter()
+            .map(|&id| Ipv4Network {
+                address: Ipv4Addr::new(10, 100, id, 0),
+                prefix_len: 24,
+            })
+            .chain(self.cidrs.iter().filter_map(|c| c.parse().ok()))
+            .collect()
+    }
+
+    /// Whether a container's name and labels match
+    fn matches_container(&self, endpoint: &Endpoint) -> bool {
+        flatnet_policy::selects_container(
+            &self.names,
+            &self.labels,
+            endpoint.name.as_deref(),
+            &endpoint.labels,
+        )
+    }
+
+    fn matches(&self, endpoint: &Endpoint) -> bool {
+        self.is_any()
+            || self.matches_container(endpoint)
+            || self.networks().iter().any(|net| contains(net, endpoint.ip))
+    }
+
+    /// Short description for listings
+    pub fn describe(&self) -> String {
+        if self.is_any() {
+            return "any".to_string();
+        }
+        let mut parts: Vec<String> = self.names.clone();
+        parts.extend(self.labels.iter().map(|(k, v)| format!("{}={}", k, v)));
+        parts.extend(self.hosts.iter().map(|id| format!("host {}", id)));
+        parts.extend(self.cidrs.iter().cloned());
+        parts.join(", ")
+    }
+}
+
+/// One allow or deny rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Name shown by `policy show` and `policy test`
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub action: Action,
+
+    #[serde(default)]
+    pub direction: Direction,
+
+    /// Containers the rule is about
+    #[serde(default)]
+    pub containers: Selector,
+
+    /// The other end of the traffic
+    #[serde(default)]
+    pub peers: Selector,
+
+    /// Destination ports (default: all traffic)
+    #[serde(default)]
+    pub ports: Vec<PortRange>,
+}
+
+impl Rule {
+    /// (source, destination) selector pairs the rule covers
+    fn flows(&self) -> Vec<(&Selector, &Selector)> {
+        let ingress = (&self.peers, &self.containers);
+        let egress = (&self.containers, &self.peers);
+        match self.direction {
+            Direction::Ingress => vec![ingress],
+            Direction::Egress => vec![egress],
+            Direction::Both if ingress == egress => vec![ingress],
+            Direction::Both => vec![ingress, egress],
+        }
+    }
+
+    fn matches(&self, src: &Endpoint, dst: &Endpoint, protocol: Protocol, port: u16) -> bool {
+        self.flows()
+            .iter()
+            .any(|(from, to)| from.matches(src) && to.matches(dst))
+            && (self.ports.is_empty() || self.ports.iter().any(|p| p.contains(protocol, port)))
+    }
+
+    /// Name, or position in the file
+    pub fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("rule {}", index + 1))
+    }
+}
+
+/// Settings of one CNI network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkPolicy {
+    /// Bridge the network's containers are attached to
+    #[serde(default = "default_bridge")]
+    pub bridge: String,
+
+    /// Action for traffic no rule matches
+    #[serde(default)]
+    pub default: Action,
+}
+
+fn default_bridge() -> String {
+    DEFAULT_BRIDGE.to_string()
+}
+
+/// The policy file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Networks by CNI network name
+    #[serde(default)]
+    pub networks: BTreeMap<String, NetworkPolicy>,
+
+    /// Rules, first match wins
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl Policy {
+    /// Load and validate a policy file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read policy file {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid policy file {}", path.display()))
+    }
+
+    /// Parse and validate a policy
+    pub fn parse(content: &str) -> Result<Self> {
+        let policy: Policy = toml::from_str(content)?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, network) in &self.networks {
+            let bridge = &network.bridge;
+            if bridge.is_empty() || bridge.len() > 15 || bridge.contains(['"', ' ']) {
+                bail!("network {}: invalid bridge name {:?}", name, bridge);
+            }
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            let label = rule.label(index);
+            if label.contains('"') {
+                bail!("{}: rule names cannot contain quotes", label);
+            }
+            for selector in [&rule.containers, &rule.peers] {
+                if let Some(id) = selector.hosts.iter().find(|&&id| id == 0 || id == 255) {
+                    bail!("{}: invalid host ID {}", label, id);
+                }
+                for cidr in &selector.cidrs {
+                    cidr.parse::<Ipv4Network>()
+                        .map_err(|e| anyhow::anyhow!("{}: invalid CIDR {}: {}", label, cidr, e))?;
+                }
+                if selector.names.iter().any(|g| g.is_empty()) {
+                    bail!("{}: empty container name pattern", label);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Networks with their settings (the default network if none is listed)
+    pub fn networks(&self) -> Vec<(String, NetworkPolicy)> {
+        if self.networks.is_empty() {
+            let network = NetworkPolicy {
+                bridge: default_bridge(),
+                default: Action::Allow,
+            };
+            return vec![(DEFAULT_NETWORK.to_string(), network)];
+        }
+        self.networks
+            .iter()
+            .map(|(name, network)| (name.clone(), network.clone()))
+            .collect()
+    }
+
+    /// Decide traffic from `src` to `dst` on one network
+    ///
+    /// Mirrors the compiled rules: traffic with an end outside the policy
+    /// scope is allowed, otherwise the first matching rule or the network
+    /// default decides.
+    pub fn evaluate(
+        &self,
+        network: &str,
+        inventory: &Inventory,
+        src: &Endpoint,
+        dst: &Endpoint,
+        protocol: Protocol,
+        port: u16,
+    ) -> Result<Verdict> {
+        let Some((_, settings)) = self.networks().into_iter().find(|(n, _)| n == network) else {
+            bail!("Network {} is not in the policy", network);
+        };
+
+        let scope = self.scope(inventory);
+        if let Some(outside) = [src, dst]
+            .iter()
+            .find(|e| !scope.iter().any(|net| contains(net, e.ip)))
+        {
+            return Ok(Verdict {
+                action: Action::Allow,
+                reason: format!("{} is outside the policy scope", outside.ip),
+            });
+        }
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.matches(src, dst, protocol, port) {
+                return Ok(Verdict {
+                    action: rule.action,
+                    reason: rule.label(index),
+                });
+            }
+        }
+        Ok(Verdict {
+            action: settings.default,
+            reason: format!("default of network {}", network),
+        })
+    }
+
+    /// Addresses the policy governs: this host's subnet, every host's
+    /// multihost subnet and the networks named in the rules
+    fn scope(&self, inventory: &Inventory) -> Vec<Ipv4Network> {
+        let mut scope = vec![MULTIHOST_NETWORK];
+        scope.extend(inventory.subnet);
+        for rule in &self.rules {
+            scope.extend(rule.containers.networks());
+            scope.extend(rule.peers.networks());
+        }
+        scope
+    }
+}
+
+/// Result of `Policy::evaluate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    pub action: Action,
+    /// Rule or default that decided
+    pub reason: String,
+}
+
+/// A container or bare address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub ip: Ipv4Addr,
+    pub name: Option<String>,
+    pub labels: BTreeMap<String, String>,
+}
+
+impl Endpoint {
+    fn address(ip: Ipv4Addr) -> Self {
+        Self {
+            ip,
+            name: None,
+            labels: BTreeMap::new(),
+        }
+    }
+
+    /// Name, or address for bare addresses
+    pub fn describe(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} ({})", name, self.ip),
+            None => self.ip.to_string(),
+        }
+    }
+}
+
+/// Containers known on this host and in the Gateway registry
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    /// This host's container subnet
+    pub subnet: Option<Ipv4Network>,
+    pub endpoints: Vec<Endpoint>,
+}
+
+impl Inventory {
+    /// Build from the IPAM state, Podman labels (by container ID) and the registry
+    ///
+    /// Labels are only known for local containers.
+    pub fn build(
+        local: Option<&IpamState>,
+        labels: &HashMap<String, BTreeMap<String, String>>,
+        registry: &[ContainerInfo],
+    ) -> Self {
+        let mut inventory = Self {
+            subnet: local.and_then(|s| s.subnet.parse().ok()),
+            endpoints: Vec::new(),
+        };
+
+        if let Some(state) = local {
+            let mut allocations: Vec<(&String, &String)> = state.allocations.iter().collect();
+            allocations.sort();
+            for (id, ip) in allocations {
+                let Ok(ip) = ip.parse() else {
+                    continue;
+                };
+                inventory.endpoints.push(Endpoint {
+                    ip,
+                    name: state.names.get(id).cloned(),
+                    labels: labels.get(id).cloned().unwrap_or_default(),
+                });
+            }
+        }
+
+        for container in registry {
+            let Ok(ip) = container.ip.parse() else {
+                continue;
+            };
+            if inventory.endpoints.iter().any(|e| e.ip == ip) {
+                continue;
+            }
+            inventory.endpoints.push(Endpoint {
+                ip,
+                name: container.name.clone(),
+                labels: BTreeMap::new(),
+            });
+        }
+
+        inventory
+    }
+
+    /// Find a container by name or address (any address is accepted)
+    pub fn find(&self, name_or_ip: &str) -> Option<Endpoint> {
+        if let Ok(ip) = name_or_ip.parse::<Ipv4Addr>() {
+            let known = self.endpoints.iter().find(|e| e.ip == ip);
+            return Some(known.cloned().unwrap_or_else(|| Endpoint::address(ip)));
+        }
+        self.endpoints
+            .iter()
+            .find(|e| e.name.as_deref() == Some(name_or_ip))
+            .cloned()
+    }
+
+    /// Containers a selector picks by name or label
+    pub fn members(&self, selector: &Selector) -> Vec<&Endpoint> {
+        self.endpoints
+            .iter()
+            .filter(|e| selector.matches_container(e))
+            .collect()
+    }
+}
+
+/// Write the state file for flatnet-cni atomically
+pub fn save_state(state: &PolicyState, path: &Path) -> Result<()> {
+    state
+        .save(path)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Compiled policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compiled {
+    /// nftables script replacing both tables
+    pub script: String,
+    pub state: PolicyState,
+}
+
+/// Compile the policy against the current containers
+pub fn compile(policy: &Policy, inventory: &Inventory) -> Compiled {
+    // One pair of sets per distinct selector
+    let mut selectors: Vec<&Selector> = Vec::new();
+    for rule in &policy.rules {
+        for selector in [&rule.containers, &rule.peers] {
+            if !selector.is_any() && !selectors.contains(&selector) {
+                selectors.push(selector);
+            }
+        }
+    }
+
+    let mut sets = String::new();
+    let mut container_sets = Vec::new();
+    set_definition(&mut sets, "scope", true, &policy.scope(inventory), |n| {
+        network_element(n)
+    });
+    for (index, selector) in selectors.iter().enumerate() {
+        if selector.selects_containers() {
+            let name = format!("c{}", index);
+            let members: BTreeSet<Ipv4Addr> =
+                inventory.members(selector).iter().map(|e| e.ip).collect();
+            let members: Vec<Ipv4Addr> = members.into_iter().collect();
+            set_definition(&mut sets, &name, false, &members, Ipv4Addr::to_string);
+            container_sets.push(ContainerSet {
+                name,
+                names: selector.names.clone(),
+                labels: selector.labels.clone(),
+            });
+        }
+        let networks = selector.networks();
+        if !networks.is_empty() {
+            set_definition(&mut sets, &format!("a{}", index), true, &networks, |n| {
+                network_element(n)
+            });
+        }
+    }
+
+    // Address matches of a selector: its sets, or nothing for "any"
+    let matches = |selector: &Selector, field: &str| -> Vec<String> {
+        let Some(index) = selectors.iter().position(|s| *s == selector) else {
+            return vec![String::new()];
+        };
+        let mut matches = Vec::new();
+        if selector.selects_containers() {
+            matches.push(format!("ip {} @c{} ", field, index));
+        }
+        if !selector.networks().is_empty() {
+            matches.push(format!("ip {} @a{} ", field, index));
+        }
+        matches
+    };
+
+    let mut rules = Vec::new();
+    for (index, rule) in policy.rules.iter().enumerate() {
+        let mut protocols: BTreeMap<Protocol, Vec<String>> = BTreeMap::new();
+        for range in &rule.ports {
+            let ports = match range.start == range.end {
+                true => range.start.to_string(),
+                false => format!("{}-{}", range.start, range.end),
+            };
+            protocols.entry(range.protocol).or_default().push(ports);
+        }
+        let ports: Vec<String> = if protocols.is_empty() {
+            vec![String::new()]
+        } else {
+            protocols
+                .iter()
+                .map(|(protocol, ports)| {
+                    format!("{} dport {{ {} }} ", protocol.as_str(), ports.join(", "))
+                })
+                .collect()
+        };
+
+        for (from, to) in rule.flows() {
+            for src in matches(from, "saddr") {
+                for dst in matches(to, "daddr") {
+                    for port in &ports {
+                        rules.push(format!(
+                            "{}{}{}{} comment \"{}\"",
+                            src,
+                            dst,
+                            port,
+                            rule.action.verdict(),
+                            rule.label(index)
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    let networks = policy.networks();
+    let mut script = String::new();
+    for family in ["inet", "bridge"] {
+        script.push_str(&format!(
+            "table {family} {table}\ndelete table {family} {table}\n",
+            family = family,
+            table = NFT_TABLE
+        ));
+    }
+    for family in ["inet", "bridge"] {
+        script.push_str(&format!("table {} {} {{\n", family, NFT_TABLE));
+        script.push_str(&sets);
+
+        script.push_str("    chain forward {\n");
+        script.push_str("        type filter hook forward priority filter; policy accept;\n");
+        for (index, (_, network)) in networks.iter().enumerate() {
+            if family == "inet" {
+                script.push_str(&format!(
+                    "        iifname \"{bridge}\" jump net{index}\n        oifname \"{bridge}\" jump net{index}\n",
+                    bridge = network.bridge,
+                    index = index
+                ));
+            } else {
+                script.push_str(&format!(
+                    "        meta ibrname \"{}\" jump net{}\n",
+                    network.bridge, index
+                ));
+            }
+        }
+        script.push_str("    }\n");
+
+        for (index, (name, network)) in networks.iter().enumerate() {
+            script.push_str(&format!("    chain net{} {{\n", index));
+            script.push_str(match family {
+                "inet" => "        meta nfproto != ipv4 accept\n",
+                _ => "        ether type != ip accept\n",
+            });
+            script.push_str("        ct state established,related accept\n");
+            script.push_str("        ip saddr != @scope accept\n");
+            script.push_str("        ip daddr != @scope accept\n");
+            for rule in &rules {
+                script.push_str(&format!("        {}\n", rule));
+            }
+            script.push_str(&format!(
+                "        {} comment \"default of network {}\"\n",
+                network.default.verdict(),
+                name
+            ));
+            script.push_str("    }\n");
+        }
+        script.push_str("}\n");
+    }
+
+    Compiled {
+        script,
+        state: PolicyState {
+            table: NFT_TABLE.to_string(),
+            container_sets,
+        },
+    }
+}
+
+/// Load a script with `nft -f -`, as one transaction
+pub fn load_ruleset(script: &str) -> Result<()> {
+    nft::run(script).context("nft failed to load the policy")
+}
+
+/// Append an ipv4_addr set declaration
+fn set_definition<T>(
+    out: &mut String,
+    name: &str,
+    interval: bool,
+    elements: &[T],
+    element: impl Fn(&T) -> String,
+) {
+    out.push_str(&format!("    set {} {{\n        type ipv4_addr;\n", name));
+    if interval {
+        out.push_str("        flags interval;\n        auto-merge;\n");
+    }
+    if !elements.is_empty() {
+        let elements: Vec<String> = elements.iter().map(element).collect();
+        out.push_str(&format!(
+            "        elements = {{ {} }}\n",
+            elements.join(", ")
+        ));
+    }
+    out.push_str("    }\n");
+}
+
+/// Network in nftables syntax, with host bits cleared
+fn network_element(network: &Ipv4Network) -> String {
+    format!(
+        "{}/{}",
+        Ipv4Addr::from(network.network()),
+        network.prefix_len
+    )
+}
+
+fn contains(network: &Ipv4Network, ip: Ipv4Addr) -> bool {
+    Ipv4Network {
+        address: ip,
+        prefix_len: 32,
+    }
+    .is_within(network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+[networks.flatnet]
+default = "deny"
+
+[[rules]]
+name = "web-to-db"
+action = "allow"
+containers = { names = ["db"] }
+peers = { labels = { app = "web" } }
+ports = ["5432", "9187/tcp"]
+
+[[rules]]
+name = "block-host-3"
+action = "deny"
+direction = "both"
+peers = { hosts = [3] }
+
+[[rules]]
+name = "dns"
+action = "allow"
+direction = "egress"
+ports = ["53/udp", "53/tcp"]
+
+[[rules]]
+action = "allow"
+containers = { names = ["api-*"] }
+"#;
+
+    fn inventory() -> Inventory {
+        let state: IpamState = serde_json::from_value(serde_json::json!({
+            "subnet": "10.100.1.0/24",
+            "gateway": "10.100.1.1",
+            "range_start": "10.100.1.10",
+            "range_end": "10.100.1.254",
+            "allocations": {
+                "aaaa": "10.100.1.10",
+                "bbbb": "10.100.1.11",
+                "cccc": "10.100.1.12"
+            },
+            "names": {"aaaa": "web", "bbbb": "db", "cccc": "cache"}
+        }))
+        .unwrap();
+        let labels = HashMap::from([(
+            "aaaa".to_string(),
+            BTreeMap::from([("app".to_string(), "web".to_string())]),
+        )]);
+        let registry: Vec<ContainerInfo> = serde_json::from_value(serde_json::json!([
+            {"id": "aaaa", "ip": "10.100.1.10", "hostId": 1, "hostname": "web"},
+            {"id": "dddd", "ip": "10.100.2.10", "hostId": 2, "hostname": "api-1"},
+            {"id": "eeee", "ip": "10.100.3.10", "hostId": 3, "hostname": "api-2"}
+        ]))
+        .unwrap();
+        Inventory::build(Some(&state), &labels, &registry)
+    }
+
+    fn verdict(src: &str, dst: &str, protocol: Protocol, port: u16) -> Verdict {
+        let policy = Policy::parse(POLICY).unwrap();
+        let inventory = inventory();
+        let src = inventory.find(src).unwrap();
+        let dst = inventory.find(dst).unwrap();
+        policy
+            .evaluate("flatnet", &inventory, &src, &dst, protocol, port)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            PortRange::try_from("8000-8100/udp".to_string()).unwrap(),
+            PortRange {
+                protocol: Protocol::Udp,
+                start: 8000,
+                end: 8100
+            }
+        );
+        for invalid in ["0", "80/icmp", "90-80", "http"] {
+            assert!(
+                PortRange::try_from(invalid.to_string()).is_err(),
+                "{}",
+                invalid
+            );
+        }
+
+        let invalid = [
+            "[[rules]]\naction = \"reject\"",
+            "[[rules]]\naction = \"allow\"\nports = [\"80/icmp\"]",
+            "[[rules]]\naction = \"allow\"\npeers = { cidrs = [\"10.0.0.0/33\"] }",
+            "[[rules]]\naction = \"allow\"\npeers = { hosts = [0] }",
+            "[[rules]]\naction = \"allow\"\npeers = { image = \"nginx\" }",
+            "[networks.flatnet]\nbridge = \"a-very-long-bridge-name\"",
+        ];
+        for policy in invalid {
+            assert!(Policy::parse(policy).is_err(), "{}", policy);
+        }
+        assert_eq!(
+            Policy::parse("").unwrap().networks()[0].1.bridge,
+            "flatnet-br0"
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let decided = |src, dst, protocol, port| {
+            let v = verdict(src, dst, protocol, port);
+            (v.action, v.reason)
+        };
+        let allow = |reason: &str| (Action::Allow, reason.to_string());
+        let deny = |reason: &str| (Action::Deny, reason.to_string());
+        let default = deny("default of network flatnet");
+
+        assert_eq!(
+            decided("web", "db", Protocol::Tcp, 5432),
+            allow("web-to-db")
+        );
+        assert_eq!(decided("db", "web", Protocol::Tcp, 5432), default);
+        assert_eq!(decided("cache", "db", Protocol::Tcp, 5432), default);
+        assert_eq!(decided("web", "db", Protocol::Udp, 5432), default);
+        assert_eq!(
+            decided("web", "api-2", Protocol::Tcp, 80),
+            deny("block-host-3")
+        );
+        assert_eq!(
+            decided("api-2", "web", Protocol::Udp, 53),
+            deny("block-host-3")
+        );
+        assert_eq!(decided("web", "cache", Protocol::Udp, 53), allow("dns"));
+        assert_eq!(decided("web", "api-1", Protocol::Tcp, 80), allow("rule 4"));
+        assert_eq!(
+            decided("web", "192.168.1.10", Protocol::Tcp, 443),
+            allow("192.168.1.10 is outside the policy scope")
+        );
+
+        let policy = Policy::parse(POLICY).unwrap();
+        let web = inventory().find("web").unwrap();
+        assert!(policy
+            .evaluate("other", &inventory(), &web, &web, Protocol::Tcp, 80)
+            .is_err());
+    }
+
+    #[test]
+    fn test_compile() {
+        let compiled = compile(&Policy::parse(POLICY).unwrap(), &inventory());
+        let script = &compiled.script;
+
+        assert!(script.starts_with(
+            "table inet flatnet_policy\ndelete table inet flatnet_policy\n\
+             table bridge flatnet_policy\ndelete table bridge flatnet_policy\n"
+        ));
+        assert!(script.contains(
+            "    set scope {\n        type ipv4_addr;\n        flags interval;\n        auto-merge;\n        \
+             elements = { 10.100.0.0/16, 10.100.1.0/24, 10.100.3.0/24 }\n    }\n"
+        ));
+        // db is c0, app=web is c1, host 3 is a2
+        assert!(script.contains(
+            "    set c0 {\n        type ipv4_addr;\n        elements = { 10.100.1.11 }\n"
+        ));
+        assert!(script.contains(
+            "    set c1 {\n        type ipv4_addr;\n        elements = { 10.100.1.10 }\n"
+        ));
+        assert!(script.contains("    set a2 {\n        type ipv4_addr;\n        flags interval;\n"));
+        // No api-* container has a name label on this host, but remote ones match by name
+        assert!(script.contains("    set c3 {\n        type ipv4_addr;\n        elements = { 10.100.2.10, 10.100.3.10 }\n"));
+
+        assert!(script.contains(
+            "        ip saddr @c1 ip daddr @c0 tcp dport { 5432, 9187 } accept comment \"web-to-db\"\n"
+        ));
+        assert!(script.contains("        ip daddr @a2 drop comment \"block-host-3\"\n"));
+        assert!(script.contains("        ip saddr @a2 drop comment \"block-host-3\"\n"));
+        assert!(script.contains("        tcp dport { 53 } accept comment \"dns\"\n"));
+        assert!(script.contains("        udp dport { 53 } accept comment \"dns\"\n"));
+        assert!(script.contains("        ip daddr @c3 accept comment \"rule 4\"\n"));
+        assert!(script.contains("        drop comment \"default of network flatnet\"\n"));
+
+        assert!(script.contains("        iifname \"flatnet-br0\" jump net0\n"));
+        assert!(script.contains("        meta ibrname \"flatnet-br0\" jump net0\n"));
+        assert!(script.contains("        ether type != ip accept\n"));
+
+        assert_eq!(compiled.state.table, "flatnet_policy");
+        let sets: Vec<&str> = compiled
+            .state
+            .container_sets
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(sets, vec!["c0", "c1", "c3"]);
+        assert_eq!(compiled.state.container_sets[2].names, vec!["api-*"]);
+    }
+}
@@ -0,0 +1,330 @@
+//! Cross-host route manager
+//!
+//! Derives the peer host IDs from the Gateway registry and sync peers and
+//! keeps a `10.100.<id>.0/24` route to each of them via the configured next
+//! hop (the Windows host running Nebula). Replaces running
+//! `scripts/cross-host-routing.sh --peers ...` by hand.
+
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeSet;
+use std::net::Ipv4Addr;
+
+use crate::clients::gateway::{ContainerInfo, GatewayClient, PeerInfo};
+use crate::clients::ipam::IpamStore;
+use crate::clients::netlink::{Ipv4Network, NetlinkClient, Route, MAIN_TABLE};
+use crate::config::Config;
+
+/// Route protocol (`proto 70` in `ip route`) of the routes flatnet installs
+///
+/// Only routes with it are removed, so routes added by hand or by other
+/// tools are never touched. 70 is not assigned in iproute2's rt_protos.
+pub const RTPROT_FLATNET: u8 = 70;
+
+/// Network holding every host's `10.100.<id>.0/24` subnet
+const MULTIHOST_NETWORK: Ipv4Network = Ipv4Network {
+    address: Ipv4Addr::new(10, 100, 0, 0),
+    prefix_len: 16,
+};
+
+/// Subnet of a host in the multihost scheme
+pub fn host_subnet(host_id: u8) -> Ipv4Network {
+    Ipv4Network {
+        address: Ipv4Addr::new(10, 100, host_id, 0),
+        prefix_len: 24,
+    }
+}
+
+/// Host ID of a `10.100.<id>.0/24` subnet
+fn subnet_host_id(subnet: &Ipv4Network) -> Option<u8> {
+    (subnet.prefix_len == 24 && subnet.is_within(&MULTIHOST_NETWORK))
+        .then(|| subnet.address.octets()[2])
+}
+
+/// A route change for one peer host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRoute {
+    pub host_id: u8,
+    pub destination: Ipv4Network,
+    /// Existing route (for replacements, removals and kept routes)
+    pub current: Option<Route>,
+}
+
+/// Route changes needed to reach the current set of peer hosts
+#[derive(Debug, Clone, Default)]
+pub struct RoutePlan {
+    /// Peer subnets without a route
+    pub add: Vec<PlannedRoute>,
+    /// Peer subnets routed via another next hop
+    pub replace: Vec<PlannedRoute>,
+    /// Routes flatnet installed to hosts no longer known
+    pub remove: Vec<PlannedRoute>,
+    /// Routes already in place
+    pub keep: Vec<PlannedRoute>,
+    /// Why the peer list could not be read; removals are skipped then
+    pub peers_error: Option<String>,
+}
+
+impl RoutePlan {
+    /// Check if the routes are up to date
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.replace.is_empty() && self.remove.is_empty()
+    }
+
+    /// Peer subnets that cannot be reached through the next hop yet
+    pub fn missing(&self) -> impl Iterator<Item = &PlannedRoute> {
+        self.add.iter().chain(&self.replace)
+    }
+}
+
+/// Collect the IDs of the other hosts known to the Gateway
+///
+/// Container registrations carry their host ID; sync peers are identified by
+/// their endpoint address when it lies in the multihost network.
+pub fn peer_host_ids(local: u8, containers: &[ContainerInfo], peers: &[PeerInfo]) -> BTreeSet<u8> {
+    let from_peers = peers.iter().filter_map(|p| {
+        let url = reqwest::Url::parse(&p.endpoint).ok()?;
+        let address: Ipv4Addr = url.host_str()?.parse().ok()?;
+        let host = Ipv4Network {
+            address,
+            prefix_len: 32,
+        };
+        host.is_within(&MULTIHOST_NETWORK)
+            .then(|| address.octets()[2])
+    });
+
+    containers
+        .iter()
+        .map(|c| c.host_id)
+        .chain(from_peers)
+        .filter(|&id| id != 0 && id != 255 && id != local)
+        .collect()
+}
+
+/// Compare the kernel routes with the wanted peer hosts
+///
+/// Only main-table routes flatnet installed (`RTPROT_FLATNET`) are removed;
+/// routes via other gateways to wanted subnets are replaced, and device
+/// routes (e.g., a Nebula interface in WSL2) are left alone.
+pub fn plan_routes(routes: &[Route], wanted: &BTreeSet<u8>, next_hop: Ipv4Addr) -> RoutePlan {
+    let candidates: Vec<&Route> = routes
+        .iter()
+        .filter(|r| r.unicast && r.table == MAIN_TABLE)
+        .filter(|r| subnet_host_id(&r.destination).is_some())
+        .collect();
+
+    let mut plan = RoutePlan::default();
+
+    for &host_id in wanted {
+        let destination = host_subnet(host_id);
+        let existing: Vec<&&Route> = candidates
+            .iter()
+            .filter(|r| r.destination == destination)
+            .collect();
+        let planned = |current: Option<&Route>| PlannedRoute {
+            host_id,
+            destination,
+            current: current.cloned(),
+        };
+
+        if let Some(route) = existing
+            .iter()
+            .find(|r| r.gateway.is_none() || r.gateway == Some(next_hop))
+        {
+            plan.keep.push(planned(Some(route)));
+        } else if let Some(route) = existing.first() {
+            plan.replace.push(planned(Some(route)));
+        } else {
+            plan.add.push(planned(None));
+        }
+    }
+
+    for route in candidates {
+        let Some(host_id) = subnet_host_id(&route.destination) else {
+            continue;
+        };
+        if route.protocol == RTPROT_FLATNET && !wanted.contains(&host_id) {
+            plan.remove.push(PlannedRoute {
+                host_id,
+                destination: route.destination,
+                current: Some(route.clone()),
+            });
+        }
+    }
+
+    plan
+}
+
+/// Keeps peer host routes in sync with the Gateway registry
+pub struct RouteManager {
+    gateway: GatewayClient,
+    netlink: NetlinkClient,
+    host_id: u8,
+    next_hop: Ipv4Addr,
+}
+
+impl RouteManager {
+    /// Create a route manager from the CLI configuration
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let host_id = match config.routes.host_id {
+            Some(id) => id,
+            None => IpamStore::new()
+                .load()
+                .map(|state| state.host_id)
+                .context("Cannot determine this host's ID; set routes.host_id in the config")?,
+        };
+
+        let Some(next_hop) = config.routes_next_hop() else {
+            bail!("Cannot determine the next hop; set routes.next_hop or FLATNET_ROUTES_NEXT_HOP");
+        };
+        let next_hop: Ipv4Addr = next_hop
+            .parse()
+            .with_context(|| format!("Invalid next hop: {}", next_hop))?;
+
+        Ok(Self {
+            gateway: GatewayClient::new(config.gateway_url(), config.gateway_timeout())?,
+            netlink: NetlinkClient::new()?,
+            host_id,
+            next_hop,
+        })
+    }
+
+    /// This host's ID
+    pub fn host_id(&self) -> u8 {
+        self.host_id
+    }
+
+    /// Next hop for peer subnets
+    pub fn next_hop(&self) -> Ipv4Addr {
+        self.next_hop
+    }
+
+    /// Compute the route changes for the current registry
+    pub async fn plan(&self) -> Result<RoutePlan> {
+        let containers = self
+            .gateway
+            .containers()
+            .await
+            .context("Failed to read the Gateway registry")?;
+        // Sync may be disabled on single-host setups. Without the peer list
+        // a host may only look gone, so nothing is removed this time.
+        let (peers, peers_error) = match self.gateway.peers().await {
+            Ok(peers) => (peers, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        let wanted = peer_host_ids(self.host_id, &containers, &peers);
+
+        let routes = self.netlink.ipv4_routes().await?;
+        let mut plan = plan_routes(&routes, &wanted, self.next_hop);
+        if peers_error.is_some() {
+            plan.remove.clear();
+            plan.peers_error = peers_error;
+        }
+        Ok(plan)
+    }
+
+    /// Apply a plan: remove stale routes, then replace and add peer routes
+    pub async fn apply(&self, plan: &RoutePlan) -> Result<()> {
+        for planned in plan.remove.iter().chain(&plan.replace) {
+            if let Some(route) = &planned.current {
+                self.netlink.delete_ipv4_route(route).await?;
+            }
+        }
+
+        for planned in plan.replace.iter().chain(&plan.add) {
+            self.netlink
+                .add_ipv4_route(planned.destination, self.next_hop, RTPROT_FLATNET)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Boot-time route (`ip route add` without `proto`)
+    const RTPROT_BOOT: u8 = 3;
+
+    fn route(destination: &str, gateway: Option<&str>) -> Route {
+        Route {
+            destination: destination.parse().unwrap(),
+            gateway: gateway.map(|g| g.parse().unwrap()),
+            dev: Some("eth0".to_string()),
+            table: MAIN_TABLE,
+            unicast: true,
+            protocol: RTPROT_FLATNET,
+        }
+    }
+
+    fn manual_route(destination: &str, gateway: Option<&str>) -> Route {
+        Route {
+            protocol: RTPROT_BOOT,
+            ..route(destination, gateway)
+        }
+    }
+
+    fn container(host_id: u8) -> ContainerInfo {
+        serde_json::from_value(serde_json::json!({
+            "id": format!("c{}", host_id),
+            "ip": format!("10.100.{}.10", host_id),
+            "hostId": host_id
+        }))
+        .unwrap()
+    }
+
+    fn peer(endpoint: &str) -> PeerInfo {
+        serde_json::from_value(serde_json::json!({ "endpoint": endpoint })).unwrap()
+    }
+
+    #[test]
+    fn test_peer_host_ids() {
+        let containers = vec![container(1), container(2), container(2)];
+        let peers = vec![
+            peer("http://10.100.3.1:8080"),
+            peer("http://gateway.example:8080"),
+        ];
+
+        let ids = peer_host_ids(1, &containers, &peers);
+        assert_eq!(ids.into_iter().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_plan_routes() {
+        let next_hop: Ipv4Addr = "172.20.0.1".parse().unwrap();
+        let routes = vec![
+            route("10.100.2.0/24", Some("172.20.0.1")),
+            route("10.100.3.0/24", Some("172.20.0.9")),
+            route("10.100.5.0/24", Some("172.20.0.1")),
+            route("10.100.6.0/24", Some("172.20.0.9")),
+            manual_route("10.100.8.0/24", Some("172.20.0.1")),
+            manual_route("10.100.0.0/16", Some("172.20.0.1")),
+            manual_route("10.100.7.0/24", None),
+        ];
+        let wanted: BTreeSet<u8> = [2, 3, 4, 7].into_iter().collect();
+
+        let plan = plan_routes(&routes, &wanted, next_hop);
+        let ids = |planned: &[PlannedRoute]| planned.iter().map(|p| p.host_id).collect::<Vec<_>>();
+        assert_eq!(ids(&plan.keep), vec![2, 7]);
+        assert_eq!(ids(&plan.replace), vec![3]);
+        assert_eq!(ids(&plan.add), vec![4]);
+        // Only routes flatnet installed are removed, whatever their next hop
+        assert_eq!(ids(&plan.remove), vec![5, 6]);
+        assert_eq!(plan.missing().count(), 2);
+        assert!(!plan.is_empty());
+
+        let plan = plan_routes(&routes, &[2, 7].into_iter().collect(), next_hop);
+        assert_eq!(ids(&plan.remove), vec![3, 5, 6]);
+    }
+
+    #[test]
+    fn test_host_subnet() {
+        assert_eq!(host_subnet(3).to_string(), "10.100.3.0/24");
+        assert_eq!(subnet_host_id(&host_subnet(3)), Some(3));
+        assert_eq!(subnet_host_id(&"10.100.0.0/16".parse().unwrap()), None);
+        assert_eq!(subnet_host_id(&"10.200.3.0/24".parse().unwrap()), None);
+    }
+}
@@ -0,0 +1,166 @@
+//! Component compatibility matrix
+//!
+//! Each release publishes `compatibility.json` with the version of every
+//! component it ships and the versions of the other components each one
+//! needs. Upgrades that would leave the host outside the matrix are refused.
+
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{Component, Installed};
+
+/// Compatibility matrix asset name
+pub const MATRIX_ASSET: &str = "compatibility.json";
+
+/// Compatibility matrix published with a release
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompatibilityMatrix {
+    /// Component name to its entry
+    pub components: BTreeMap<String, ComponentEntry>,
+}
+
+/// A component shipped in a release
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComponentEntry {
+    /// Version shipped in this release
+    pub version: String,
+
+    /// Required versions of other components (semver requirements)
+    #[serde(default)]
+    pub requires: BTreeMap<String, String>,
+}
+
+impl CompatibilityMatrix {
+    /// Parse a matrix
+    pub fn parse(content: &str) -> Result<Self> {
+        serde_json::from_str(content).with_context(|| format!("Failed to parse {}", MATRIX_ASSET))
+    }
+
+    /// Matrix for releases published before components were bundled
+    pub fn cli_only(version: &str) -> Self {
+        let entry = ComponentEntry {
+            version: version.to_string(),
+            requires: BTreeMap::new(),
+        };
+        Self {
+            components: BTreeMap::from([(Component::Cli.name().to_string(), entry)]),
+        }
+    }
+
+    /// Version of a component in this release
+    pub fn version(&self, component: Component) -> Option<&str> {
+        self.components
+            .get(component.name())
+            .map(|e| e.version.as_str())
+    }
+
+    /// Check the versions the host would have after upgrading some components
+    ///
+    /// Returns a description of every unmet requirement.
+    pub fn check(
+        &self,
+        upgrading: &[Component],
+        installed: &HashMap<Component, Installed>,
+    ) -> Vec<String> {
+        let resulting = |component: Component| -> Installed {
+            match self.version(component) {
+                Some(version) if upgrading.contains(&component) => {
+                    Installed::Version(version.to_string())
+                }
+                _ => installed
+                    .get(&component)
+                    .cloned()
+                    .unwrap_or(Installed::Missing),
+            }
+        };
+
+        let mut problems = Vec::new();
+
+        for component in upgrading {
+            let Some(entry) = self.components.get(component.name()) else {
+                continue;
+            };
+
+            for (dependency, requirement) in &entry.requires {
+                let Some(dependency) = Component::from_name(dependency) else {
+                    continue;
+                };
+
+                let unmet = match resulting(dependency) {
+                    // Nothing to be incompatible with
+                    Installed::Missing => None,
+                    Installed::Unknown => Some("an unknown version".to_string()),
+                    Installed::Version(version) => {
+                        (!satisfies(&version, requirement)).then_some(version)
+                    }
+                };
+
+                if let Some(found) = unmet {
+                    problems.push(format!(
+                        "{} {} requires {} {}, but the host would have {}",
+                        component.name(),
+                        entry.version,
+                        dependency.name(),
+                        requirement,
+                        found
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+/// Check a version against a semver requirement (invalid input never matches)
+fn satisfies(version: &str, requirement: &str) -> bool {
+    match (Version::parse(version), VersionReq::parse(requirement)) {
+        (Ok(version), Ok(requirement)) => requirement.matches(&version),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATRIX: &str = r#"{
+        "components": {
+            "cli": {"version": "0.3.0", "requires": {"cni": ">=0.3.0"}},
+            "cni": {"version": "0.3.0", "requires": {"gateway-config": ">=0.3.0, <0.4.0"}},
+            "gateway-config": {"version": "0.3.0"}
+        }
+    }"#;
+
+    #[test]
+    fn test_check_compatibility() {
+        let matrix = CompatibilityMatrix::parse(MATRIX).unwrap();
+        let installed = HashMap::from([
+            (Component::Cli, Installed::Version("0.2.0".to_string())),
+            (Component::Cni, Installed::Version("0.2.0".to_string())),
+            (Component::GatewayConfig, Installed::Unknown),
+        ]);
+
+        // CLI alone would leave an old CNI plugin behind
+        let problems = matrix.check(&[Component::Cli], &installed);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("cli 0.3.0 requires cni >=0.3.0"));
+
+        // CLI and CNI together still need a known Gateway bundle
+        let problems = matrix.check(&[Component::Cli, Component::Cni], &installed);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("gateway-config"));
+
+        // Everything together is consistent
+        assert!(matrix.check(&Component::ALL, &installed).is_empty());
+    }
+
+    #[test]
+    fn test_satisfies() {
+        assert!(satisfies("0.3.1", ">=0.3.0, <0.4.0"));
+        assert!(!satisfies("0.4.0", ">=0.3.0, <0.4.0"));
+        assert!(!satisfies("not-a-version", ">=0.3.0"));
+    }
+}
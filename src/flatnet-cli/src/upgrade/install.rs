@@ -0,0 +1,293 @@
+//! Atomic installation
+//!
+//! New files and directories are staged next to their destination, checked,
+//! and renamed into place. The replaced version is kept as `.<name>.bak` so
+//! it can be restored with `flatnet upgrade --rollback`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Version file at the root of the Gateway config bundle
+pub const BUNDLE_VERSION_FILE: &str = "VERSION";
+
+/// How long the `--version` smoke test may take
+const SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Install an executable, smoke-testing it with `--version` before the swap
+pub async fn install_binary(path: &Path, data: &[u8], version: &str) -> Result<()> {
+    let staging = sibling(path, "tmp")?;
+
+    // Write new binary to the staging file
+    fs::write(&staging, data)
+        .with_context(|| format!("Failed to write temporary file: {}", staging.display()))?;
+
+    // Set executable permissions
+    let mut perms = fs::metadata(&staging)
+        .context("Failed to get temp file metadata")?
+        .permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&staging, perms).context("Failed to set executable permissions")?;
+
+    // Never swap in a binary that cannot report its own version
+    let reported = match smoke_test(&staging).await {
+        Ok(output) => reported_version(&output).map(str::to_string),
+        Err(e) => {
+            let _ = fs::remove_file(&staging);
+            return Err(e.context("New binary failed the --version smoke test"));
+        }
+    };
+    if reported.as_deref() != Some(version) {
+        let _ = fs::remove_file(&staging);
+        bail!(
+            "New binary reports version {}, expected {}",
+            reported.as_deref().unwrap_or("unknown"),
+            version
+        );
+    }
+
+    swap_in_file(path, &staging)
+}
+
+/// Install a directory from a gzipped tarball, checking its VERSION file
+pub fn install_bundle(path: &Path, archive: &[u8], version: &str) -> Result<()> {
+    let staging = sibling(path, "tmp")?;
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .with_context(|| format!("Failed to remove stale {}", staging.display()))?;
+    }
+
+    let unpacked = unpack(archive, &staging).and_then(|_| {
+        let reported = read_bundle_version(&staging)
+            .ok_or_else(|| anyhow!("Bundle has no {} file", BUNDLE_VERSION_FILE))?;
+        if reported != version {
+            bail!("Bundle reports version {}, expected {}", reported, version);
+        }
+        Ok(())
+    });
+    if let Err(e) = unpacked {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    swap_in_dir(path, &staging)
+}
+
+/// Exchange an installed file or directory with its backup
+///
+/// Running it twice restores the original state.
+pub fn rollback(path: &Path) -> Result<()> {
+    let backup = backup_path(path)?;
+    let staging = sibling(path, "tmp")?;
+
+    if !backup.exists() {
+        bail!(
+            "No previous version to roll back to ({} not found)",
+            backup.display()
+        );
+    }
+
+    // Three renames; the staging name is free because installs clean it up
+    fs::rename(path, &staging)
+        .with_context(|| format!("Failed to move {} aside", path.display()))?;
+    if let Err(e) = fs::rename(&backup, path) {
+        let _ = fs::rename(&staging, path);
+        return Err(e).with_context(|| format!("Failed to restore {}", backup.display()));
+    }
+    fs::rename(&staging, &backup)
+        .with_context(|| format!("Failed to save replaced version to {}", backup.display()))?;
+
+    Ok(())
+}
+
+/// Backup location for an installed file or directory
+pub fn backup_path(path: &Path) -> Result<PathBuf> {
+    sibling(path, "bak")
+}
+
+/// Version recorded in an installed bundle
+pub fn read_bundle_version(dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(dir.join(BUNDLE_VERSION_FILE)).ok()?;
+    let version = content.trim().trim_start_matches('v');
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Run `<binary> --version` and return its output
+pub async fn smoke_test(binary: &Path) -> Result<String> {
+    let output = tokio::time::timeout(
+        SMOKE_TEST_TIMEOUT,
+        tokio::process::Command::new(binary)
+            .arg("--version")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| anyhow!("{} --version timed out", binary.display()))?
+    .with_context(|| format!("Failed to run {}", binary.display()))?;
+
+    if !output.status.success() {
+        bail!(
+            "{} --version exited with {}",
+            binary.display(),
+            output.status
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Extract the version from `--version` output ("flatnet 0.2.0")
+pub fn reported_version(output: &str) -> Option<&str> {
+    output
+        .split_whitespace()
+        .nth(1)
+        .map(|v| v.trim_start_matches('v'))
+}
+
+/// Back up the current file and atomically rename the staged one over it
+fn swap_in_file(path: &Path, staging: &Path) -> Result<()> {
+    // Copy rather than rename so the path never goes missing
+    if path.exists() {
+        let backup = backup_path(path)?;
+        fs::copy(path, &backup).with_context(|| {
+            let _ = fs::remove_file(staging);
+            format!("Failed to back up {} to {}", path.display(), backup.display())
+        })?;
+    }
+
+    // Atomic rename
+    fs::rename(staging, path).with_context(|| {
+        // Clean up temp file on error
+        let _ = fs::remove_file(staging);
+        format!("Failed to replace {}", path.display())
+    })?;
+
+    Ok(())
+}
+
+/// Move the current directory to the backup and the staged one into place
+///
+/// Directories cannot be renamed over each other, so there is a brief window
+/// where the path is missing; a failure in it restores the old directory.
+fn swap_in_dir(path: &Path, staging: &Path) -> Result<()> {
+    let backup = backup_path(path)?;
+
+    if path.exists() {
+        if backup.exists() {
+            fs::remove_dir_all(&backup)
+                .with_context(|| format!("Failed to remove old backup {}", backup.display()))?;
+        }
+        fs::rename(path, &backup)
+            .with_context(|| format!("Failed to back up {}", path.display()))?;
+    } else if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    if let Err(e) = fs::rename(staging, path) {
+        if backup.exists() {
+            let _ = fs::rename(&backup, path);
+        }
+        let _ = fs::remove_dir_all(staging);
+        return Err(e).with_context(|| format!("Failed to replace {}", path.display()));
+    }
+
+    Ok(())
+}
+
+/// Unpack a gzipped tarball into a new directory
+pub fn unpack(archive: &[u8], dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+
+    // tar::Archive::unpack refuses entries that escape the destination
+    tar::Archive::new(GzDecoder::new(archive))
+        .unpack(dest)
+        .with_context(|| format!("Failed to unpack bundle into {}", dest.display()))
+}
+
+/// Hidden file next to `path` (e.g., /opt/cni/bin/.flatnet.bak)
+fn sibling(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let parent = path.parent().context("Invalid install path")?;
+    let name = path
+        .file_name()
+        .context("Invalid install path")?
+        .to_string_lossy();
+    Ok(parent.join(format!(".{}.{}", name, suffix)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("flatnet-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn bundle(version: &str) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (name, content) in [(BUNDLE_VERSION_FILE, version), ("registry.lua", "return {}")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_swap_file_and_rollback() {
+        let dir = test_dir("install-file");
+        let exe = dir.join("flatnet");
+        let staging = sibling(&exe, "tmp").unwrap();
+        fs::write(&exe, "old").unwrap();
+        fs::write(&staging, "new").unwrap();
+
+        swap_in_file(&exe, &staging).unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dir.join(".flatnet.bak")).unwrap(), "old");
+        assert!(!staging.exists());
+
+        rollback(&exe).unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "old");
+        assert_eq!(fs::read_to_string(dir.join(".flatnet.bak")).unwrap(), "new");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_bundle() {
+        let dir = test_dir("install-bundle");
+        let target = dir.join("flatnet");
+
+        install_bundle(&target, &bundle("0.2.0"), "0.2.0").unwrap();
+        assert_eq!(read_bundle_version(&target).as_deref(), Some("0.2.0"));
+
+        // Version mismatch leaves the installed bundle alone
+        assert!(install_bundle(&target, &bundle("0.3.0"), "0.4.0").is_err());
+        assert_eq!(read_bundle_version(&target).as_deref(), Some("0.2.0"));
+
+        install_bundle(&target, &bundle("0.3.0"), "0.3.0").unwrap();
+        assert_eq!(read_bundle_version(&target).as_deref(), Some("0.3.0"));
+        rollback(&target).unwrap();
+        assert_eq!(read_bundle_version(&target).as_deref(), Some("0.2.0"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reported_version() {
+        assert_eq!(reported_version("flatnet 0.2.0"), Some("0.2.0"));
+        assert_eq!(reported_version("flatnet-cni v0.2.0"), Some("0.2.0"));
+        assert_eq!(reported_version("flatnet"), None);
+    }
+}
@@ -0,0 +1,143 @@
+//! Component upgrades
+//!
+//! Version discovery, release verification, and atomic installation for the
+//! components `flatnet upgrade` manages.
+
+pub mod compat;
+pub mod install;
+pub mod source;
+pub mod verify;
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::checks::cni::{find_plugin_binary, CNI_BIN_DIRS};
+use crate::checks::cni_config::PLUGIN_TYPE;
+use crate::config::Config;
+
+/// An upgradeable component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Component {
+    /// The flatnet CLI itself
+    Cli,
+    /// The flatnet-cni plugin binary
+    Cni,
+    /// The OpenResty Lua bundle (lualib/flatnet)
+    GatewayConfig,
+}
+
+impl Component {
+    /// All components, in install order (the running CLI is replaced last)
+    pub const ALL: [Component; 3] = [Component::Cni, Component::GatewayConfig, Component::Cli];
+
+    /// Name used in release metadata and on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Component::Cli => "cli",
+            Component::Cni => "cni",
+            Component::GatewayConfig => "gateway-config",
+        }
+    }
+
+    /// Look up a component by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// Release asset holding this component
+    pub fn asset_name(&self, platform: &str) -> String {
+        match self {
+            Component::Cli => format!("flatnet-{}", platform),
+            Component::Cni => format!("flatnet-cni-{}", platform),
+            Component::GatewayConfig => "flatnet-gateway-config.tar.gz".to_string(),
+        }
+    }
+
+    /// Where this component is installed on this host
+    pub fn location(&self, config: &Config) -> Result<PathBuf> {
+        match self {
+            Component::Cli => env::current_exe().context("Failed to get current executable path"),
+            Component::Cni => Ok(find_plugin_binary()
+                .unwrap_or_else(|| Path::new(CNI_BIN_DIRS[0]).join(PLUGIN_TYPE))),
+            Component::GatewayConfig => {
+                Ok(Path::new(&config.gateway.config_dir).join("lualib/flatnet"))
+            }
+        }
+    }
+
+    /// Discover the installed version
+    pub async fn installed(&self, config: &Config) -> Installed {
+        let path = match self.location(config) {
+            Ok(path) => path,
+            Err(_) => return Installed::Unknown,
+        };
+
+        match self {
+            Component::Cli => Installed::Version(env!("CARGO_PKG_VERSION").to_string()),
+            _ if !path.exists() => Installed::Missing,
+            // Plugins built before `--version` existed answer with a CNI error
+            Component::Cni => match install::smoke_test(&path).await {
+                Ok(output) => install::reported_version(&output)
+                    .map(|v| Installed::Version(v.to_string()))
+                    .unwrap_or(Installed::Unknown),
+                Err(_) => Installed::Unknown,
+            },
+            Component::GatewayConfig => install::read_bundle_version(&path)
+                .map(Installed::Version)
+                .unwrap_or(Installed::Unknown),
+        }
+    }
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Installed state of a component
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Installed {
+    /// Not installed on this host
+    Missing,
+    /// Installed, but its version cannot be determined
+    Unknown,
+    /// Installed with a known version
+    Version(String),
+}
+
+impl Installed {
+    /// Check if this is exactly the given version
+    pub fn is(&self, version: &str) -> bool {
+        matches!(self, Installed::Version(v) if v == version)
+    }
+}
+
+impl fmt::Display for Installed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Installed::Missing => f.write_str("not installed"),
+            Installed::Unknown => f.write_str("unknown"),
+            Installed::Version(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_component_names() {
+        for component in Component::ALL {
+            assert_eq!(Component::from_name(component.name()), Some(component));
+        }
+        assert_eq!(
+            Component::Cni.asset_name("linux-x86_64"),
+            "flatnet-cni-linux-x86_64"
+        );
+        assert_eq!(Component::ALL.last(), Some(&Component::Cli));
+    }
+}
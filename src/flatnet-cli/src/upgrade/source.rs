@@ -0,0 +1,301 @@
+//! Release sources
+//!
+//! A release can come from GitHub, a bundle tarball, an unpacked bundle
+//! directory, or an internal HTTP mirror serving a bundle. Every source
+//! provides the same files (see `docs/cli/commands/upgrade.md`), so
+//! verification and platform selection do not depend on where it came from.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::compat::{CompatibilityMatrix, MATRIX_ASSET};
+use super::install;
+use super::verify::{release_public_key, VerifiedManifest, MANIFEST_ASSET, SIGNATURE_ASSET};
+use crate::clients::github::{GitHubClient, GitHubRelease};
+
+/// Where release files are read from
+pub enum ReleaseSource {
+    /// A GitHub release
+    GitHub {
+        client: GitHubClient,
+        release: GitHubRelease,
+    },
+    /// A directory holding the release files (an unpacked bundle)
+    Directory {
+        dir: PathBuf,
+        /// Set when the directory was unpacked from a tarball and must be removed
+        temporary: bool,
+    },
+    /// An HTTP mirror serving the release files under a base URL
+    Mirror {
+        client: reqwest::Client,
+        base_url: String,
+    },
+}
+
+/// Contents of a release file
+pub enum AssetData {
+    /// Streaming HTTP download (shown with a progress bar)
+    Remote(reqwest::Response),
+    /// Already in memory
+    Local(Vec<u8>),
+}
+
+impl ReleaseSource {
+    /// Open a bundle tarball, bundle directory, or mirror URL given to `--from`
+    ///
+    /// A URL ending in `.tar.gz` or `.tgz` is downloaded and unpacked;
+    /// any other URL is treated as a mirror of the unpacked files.
+    pub async fn open(from: &str) -> Result<Self> {
+        if from.starts_with("http://") || from.starts_with("https://") {
+            let client = http_client()?;
+            if is_tarball(from) {
+                let response = client
+                    .get(from)
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to download {}", from))?;
+                if !response.status().is_success() {
+                    bail!("Download of {} failed: {}", from, response.status());
+                }
+                let data = response
+                    .bytes()
+                    .await
+                    .with_context(|| format!("Failed to download {}", from))?;
+                return Self::unpack(&data);
+            }
+            return Ok(ReleaseSource::Mirror {
+                client,
+                base_url: from.trim_end_matches('/').to_string(),
+            });
+        }
+
+        let path = Path::new(from);
+        if path.is_dir() {
+            Ok(ReleaseSource::Directory {
+                dir: bundle_root(path),
+                temporary: false,
+            })
+        } else if path.is_file() {
+            let data = fs::read(path).with_context(|| format!("Failed to read {}", from))?;
+            Self::unpack(&data)
+        } else {
+            bail!("{} is not a bundle file, directory, or http(s) URL", from)
+        }
+    }
+
+    /// Unpack a bundle tarball into a temporary directory
+    fn unpack(data: &[u8]) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("flatnet-bundle-{}", std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .with_context(|| format!("Failed to remove stale {}", dir.display()))?;
+        }
+        if let Err(e) = install::unpack(data, &dir) {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+
+        Ok(ReleaseSource::Directory {
+            dir: bundle_root(&dir),
+            temporary: true,
+        })
+    }
+
+    /// Human-readable description
+    pub fn describe(&self) -> String {
+        match self {
+            ReleaseSource::GitHub { release, .. } => format!("GitHub release {}", release.tag_name),
+            ReleaseSource::Directory { dir, .. } => format!("bundle {}", dir.display()),
+            ReleaseSource::Mirror { base_url, .. } => format!("mirror {}", base_url),
+        }
+    }
+
+    /// Release version, if the source states it (only GitHub does)
+    fn stated_version(&self) -> Option<String> {
+        match self {
+            ReleaseSource::GitHub { release, .. } => Some(release.version()),
+            _ => None,
+        }
+    }
+
+    /// Read a release file, returning None if the release does not have it
+    pub async fn fetch(&self, name: &str) -> Result<Option<AssetData>> {
+        match self {
+            ReleaseSource::GitHub { client, release } => match release.asset(name) {
+                Some(asset) => Ok(Some(AssetData::Remote(client.download(asset).await?))),
+                None => Ok(None),
+            },
+            ReleaseSource::Directory { dir, .. } => {
+                let path = dir.join(name);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                let data =
+                    fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+                Ok(Some(AssetData::Local(data)))
+            }
+            ReleaseSource::Mirror { client, base_url } => {
+                let url = format!("{}/{}", base_url, name);
+                let response = client
+                    .get(&url)
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to download {}", url))?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                if !response.status().is_success() {
+                    bail!("Download of {} failed: {}", url, response.status());
+                }
+                Ok(Some(AssetData::Remote(response)))
+            }
+        }
+    }
+
+    /// Read a small release file as text
+    async fn fetch_text(&self, name: &str) -> Result<Option<String>> {
+        let data = match self.fetch(name).await? {
+            Some(AssetData::Remote(response)) => response
+                .bytes()
+                .await
+                .with_context(|| format!("Failed to read {}", name))?
+                .to_vec(),
+            Some(AssetData::Local(data)) => data,
+            None => return Ok(None),
+        };
+
+        String::from_utf8(data)
+            .map(Some)
+            .with_context(|| format!("{} is not valid UTF-8", name))
+    }
+}
+
+impl Drop for ReleaseSource {
+    fn drop(&mut self) {
+        if let ReleaseSource::Directory {
+            dir,
+            temporary: true,
+        } = self
+        {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// A release whose manifest and compatibility matrix have been verified
+pub struct Release {
+    pub source: ReleaseSource,
+    pub manifest: VerifiedManifest,
+    pub matrix: CompatibilityMatrix,
+}
+
+impl Release {
+    /// Verify a source's manifest and load its compatibility matrix
+    ///
+    /// `expected_version` is the version the user asked for, if any.
+    pub async fn verify(source: ReleaseSource, expected_version: Option<&str>) -> Result<Self> {
+        let expected = source
+            .stated_version()
+            .or_else(|| expected_version.map(str::to_string));
+
+        // Releases without a signed manifest are refused rather than installed blind
+        let (manifest, signature) = match (
+            source.fetch_text(MANIFEST_ASSET).await?,
+            source.fetch_text(SIGNATURE_ASSET).await?,
+        ) {
+            (Some(m), Some(s)) => (m, s),
+            _ => bail!(
+                "{} has no signed checksum manifest ({} and {})",
+                source.describe(),
+                MANIFEST_ASSET,
+                SIGNATURE_ASSET
+            ),
+        };
+        let manifest = VerifiedManifest::verify(
+            release_public_key()?,
+            &manifest,
+            &signature,
+            expected.as_deref(),
+        )?;
+
+        // Releases from before components were bundled only carry the CLI
+        let matrix = match source.fetch_text(MATRIX_ASSET).await? {
+            Some(content) => {
+                manifest.check(MATRIX_ASSET, content.as_bytes())?;
+                CompatibilityMatrix::parse(&content)?
+            }
+            None => CompatibilityMatrix::cli_only(manifest.version()),
+        };
+
+        Ok(Self {
+            source,
+            manifest,
+            matrix,
+        })
+    }
+
+    /// Release version (as signed)
+    pub fn version(&self) -> &str {
+        self.manifest.version()
+    }
+}
+
+/// Descend into a bundle's single top-level directory if the files are there
+fn bundle_root(dir: &Path) -> PathBuf {
+    if dir.join(MANIFEST_ASSET).exists() {
+        return dir.to_path_buf();
+    }
+
+    let subdirs: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    match subdirs.as_slice() {
+        [only] => only.clone(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+fn is_tarball(url: &str) -> bool {
+    url.ends_with(".tar.gz") || url.ends_with(".tgz")
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(300))
+        .user_agent("flatnet-cli")
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_root() {
+        let dir = std::env::temp_dir().join(format!("flatnet-bundle-root-{}", std::process::id()));
+        let nested = dir.join("flatnet-bundle-0.3.0");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join(MANIFEST_ASSET), "").unwrap();
+
+        assert_eq!(bundle_root(&dir), nested);
+        assert_eq!(bundle_root(&nested), nested);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_tarball() {
+        assert!(is_tarball("http://mirror.lan/flatnet-bundle-0.3.0.tar.gz"));
+        assert!(!is_tarball("http://mirror.lan/flatnet/0.3.0/"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Release verification
+//!
+//! Every release publishes `SHA256SUMS` covering all of its assets, signed
+//! with the release key. Nothing is installed unless its checksum is listed
+//! in a manifest whose signature verifies.
+
+use anyhow::{anyhow, bail, Result};
+use minisign_verify::{PublicKey, Signature};
+use semver::Version;
+use sha2::{Digest, Sha256};
+
+/// Checksum manifest published with each release (`sha256sum` format)
+pub const MANIFEST_ASSET: &str = "SHA256SUMS";
+
+/// Detached minisign signature of the manifest
+pub const SIGNATURE_ASSET: &str = "SHA256SUMS.minisig";
+
+/// Release signing public key (minisign)
+///
+/// Generated by a maintainer, who keeps the secret half and stores it as the
+/// MINISIGN_SECRET_KEY secret of the release-cli workflow (see
+/// docs/security/release-signing.md). Until it is set, upgrades fail closed.
+pub const RELEASE_PUBLIC_KEY: Option<&str> = None;
+
+/// The release signing key, or an error if this build has none
+pub fn release_public_key() -> Result<&'static str> {
+    RELEASE_PUBLIC_KEY.ok_or_else(|| {
+        anyhow!(
+            "This build has no release signing key, so downloads cannot be verified \
+             (see docs/security/release-signing.md)"
+        )
+    })
+}
+
+/// A manifest whose signature has been verified
+#[derive(Debug, Clone)]
+pub struct VerifiedManifest {
+    content: String,
+    version: String,
+}
+
+impl VerifiedManifest {
+    /// Verify the manifest signature and read the release version it was signed for
+    ///
+    /// The version comes from the trusted comment ("flatnet-cli v0.3.0"),
+    /// which the signature covers. When the caller knows which release it
+    /// asked for, the two must match, so an older release's manifest cannot
+    /// be replayed.
+    pub fn verify(
+        public_key: &str,
+        manifest: &str,
+        signature: &str,
+        expected_version: Option<&str>,
+    ) -> Result<Self> {
+        let public_key = PublicKey::from_base64(public_key)
+            .map_err(|e| anyhow!("Invalid release public key: {}", e))?;
+        let signature = Signature::decode(signature)
+            .map_err(|e| anyhow!("Invalid {}: {}", SIGNATURE_ASSET, e))?;
+
+        public_key
+            .verify(manifest.as_bytes(), &signature, false)
+            .map_err(|e| anyhow!("Signature verification of {} failed: {}", MANIFEST_ASSET, e))?;
+
+        let version = signed_version(signature.trusted_comment()).ok_or_else(|| {
+            anyhow!(
+                "{} signature does not name a release version ('{}')",
+                MANIFEST_ASSET,
+                signature.trusted_comment()
+            )
+        })?;
+
+        if let Some(expected) = expected_version {
+            if version != expected {
+                bail!(
+                    "{} was signed for v{}, not v{}",
+                    MANIFEST_ASSET,
+                    version,
+                    expected
+                );
+            }
+        }
+
+        Ok(Self {
+            content: manifest.to_string(),
+            version,
+        })
+    }
+
+    /// Release version the manifest was signed for
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Check downloaded data against its manifest entry
+    pub fn check(&self, name: &str, data: &[u8]) -> Result<()> {
+        let expected = find_checksum(&self.content, name)
+            .ok_or_else(|| anyhow!("{} has no entry for {}", MANIFEST_ASSET, name))?;
+
+        let actual = sha256_hex(data);
+        if !actual.eq_ignore_ascii_case(expected) {
+            bail!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                name,
+                expected,
+                actual
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Find the version in a trusted comment ("flatnet-cli v0.3.0")
+fn signed_version(comment: &str) -> Option<String> {
+    comment
+        .split_whitespace()
+        .filter_map(|word| word.trim_start_matches("cli-").strip_prefix('v'))
+        .find(|v| Version::parse(v).is_ok())
+        .map(str::to_string)
+}
+
+/// Find the checksum for a file in a `sha256sum` manifest
+fn find_checksum<'a>(manifest: &'a str, name: &str) -> Option<&'a str> {
+    manifest.lines().find_map(|line| {
+        let (hash, file) = line.trim().split_once(char::is_whitespace)?;
+        // Binary-mode entries are prefixed with '*'
+        let file = file.trim_start().trim_start_matches('*');
+        (file == name).then_some(hash)
+    })
+}
+
+/// SHA-256 of data as lowercase hex
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test-only key and a signature over TEST_MANIFEST with comment "flatnet-cli v0.2.0"
+    const TEST_PUBLIC_KEY: &str = "RWRmbGF0bmV0AOpKbGPinFIKvvVQexMuxfmVR3auvr57kkIe6mkURtIs";
+    const TEST_MANIFEST: &str =
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  flatnet-linux-x86_64\n";
+    const TEST_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RURmbGF0bmV0AGl3SlCR9YZvarJ0aSheW/LK7LJIejJ7MjBhLjLfRddxzuTD+n98X8w1FMyIBWE5+H8wsm7oHUaMnWP03ze8bwI=
+trusted comment: flatnet-cli v0.2.0
+FAW1humCs7xmv6CyT6Yi4Cox6OZ0IKCvy5BXDGjXB/QEIPPgEJsy6AEhzhP5SIanOdG1Z6qEItfIEvuz87hvCg==
+";
+
+    /// A second test-only key that did not sign anything here
+    const OTHER_TEST_PUBLIC_KEY: &str = "RWRmbGF0bmV0AT871RpJuF6vKStxztd2nMw8enrwhYHyaMqdxmN56KqK";
+
+    #[test]
+    fn test_verify_manifest() {
+        let manifest =
+            VerifiedManifest::verify(TEST_PUBLIC_KEY, TEST_MANIFEST, TEST_SIGNATURE, Some("0.2.0"))
+                .unwrap();
+        assert_eq!(manifest.version(), "0.2.0");
+        assert!(manifest.check("flatnet-linux-x86_64", b"").is_ok());
+        assert!(manifest.check("flatnet-linux-x86_64", b"tampered").is_err());
+        assert!(manifest.check("flatnet-cni-linux-x86_64", b"").is_err());
+
+        // Tampered manifest
+        let tampered = TEST_MANIFEST.replace("e3b0", "0000");
+        assert!(VerifiedManifest::verify(TEST_PUBLIC_KEY, &tampered, TEST_SIGNATURE, None).is_err());
+
+        // Valid signature, but for another release
+        assert!(
+            VerifiedManifest::verify(TEST_PUBLIC_KEY, TEST_MANIFEST, TEST_SIGNATURE, Some("0.3.0"))
+                .is_err()
+        );
+
+        // Signed with a different key
+        assert!(
+            VerifiedManifest::verify(OTHER_TEST_PUBLIC_KEY, TEST_MANIFEST, TEST_SIGNATURE, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_signed_version() {
+        assert_eq!(signed_version("flatnet-cli v0.3.0").as_deref(), Some("0.3.0"));
+        assert_eq!(signed_version("cli-v1.0.0").as_deref(), Some("1.0.0"));
+        assert_eq!(signed_version("timestamp:1700000000 file:SHA256SUMS"), None);
+    }
+
+    #[test]
+    fn test_find_checksum() {
+        let manifest = format!("{}abcd *flatnet-linux-aarch64\n", TEST_MANIFEST);
+        let expected = find_checksum(&manifest, "flatnet-linux-x86_64").unwrap();
+        assert_eq!(expected, sha256_hex(b""));
+        assert_eq!(find_checksum(&manifest, "flatnet-linux-aarch64"), Some("abcd"));
+        assert_eq!(find_checksum(&manifest, "flatnet-darwin-x86_64"), None);
+    }
+}
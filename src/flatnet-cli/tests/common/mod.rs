@@ -0,0 +1,133 @@
+//! Shared helpers for the end-to-end CLI tests
+//!
+//! Each test runs the real `flatnet` binary against a mock Gateway and a
+//! second mock standing in for Prometheus, Grafana, Loki, Alertmanager and
+//! the GitHub API, with a private config directory.
+
+#![allow(dead_code)]
+
+use assert_cmd::Command;
+use flatnet_mock_gateway::MockGateway;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Environment variables read by `Config::load`
+const CONFIG_VARS: &[&str] = &[
+    "FLATNET_GATEWAY_URL",
+    "FLATNET_GATEWAY_TIMEOUT",
+    "FLATNET_GATEWAY_METRICS_URL",
+    "FLATNET_PROMETHEUS_URL",
+    "FLATNET_GRAFANA_URL",
+    "FLATNET_LOKI_URL",
+    "FLATNET_ALERTMANAGER_URL",
+    "FLATNET_ROUTES_NEXT_HOP",
+    "FLATNET_NEBULA_CONFIG",
+    "FLATNET_GITHUB_API",
+    "FLATNET_COLOR",
+];
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A mock Gateway, a mock monitoring stack and a private config directory
+pub struct TestEnv {
+    pub gateway: MockGateway,
+    pub services: MockGateway,
+    pub home: PathBuf,
+    timeout_secs: u64,
+}
+
+impl TestEnv {
+    pub fn new() -> Self {
+        let home = std::env::temp_dir().join(format!(
+            "flatnet-cli-test-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(home.join("config/flatnet")).unwrap();
+
+        Self {
+            gateway: MockGateway::start(),
+            services: MockGateway::start(),
+            home,
+            timeout_secs: 5,
+        }
+    }
+
+    /// Set the Gateway request timeout (FLATNET_GATEWAY_TIMEOUT)
+    pub fn with_timeout(mut self, secs: u64) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+
+    /// Write `flatnet/config.toml` (for settings without an env override)
+    pub fn write_config(&self, contents: &str) {
+        std::fs::write(self.home.join("config/flatnet/config.toml"), contents).unwrap();
+    }
+
+    /// `flatnet` with the given arguments, pointed at the mocks
+    pub fn flatnet(&self, args: &[&str]) -> Command {
+        Command::from_std(self.std_command(args))
+    }
+
+    /// Same as `flatnet`, for long-running commands the test spawns itself
+    pub fn std_command(&self, args: &[&str]) -> std::process::Command {
+        let mut cmd = std::process::Command::new(assert_cmd::cargo::cargo_bin("flatnet"));
+        for var in CONFIG_VARS {
+            cmd.env_remove(var);
+        }
+        let services = self.services.url();
+        cmd.args(args)
+            .env("HOME", &self.home)
+            .env("XDG_CONFIG_HOME", self.home.join("config"))
+            .env("NO_COLOR", "1")
+            .env("FLATNET_GATEWAY_URL", self.gateway.url())
+            .env("FLATNET_GATEWAY_TIMEOUT", self.timeout_secs.to_string())
+            .env(
+                "FLATNET_GATEWAY_METRICS_URL",
+                format!("{}/metrics", self.gateway.url()),
+            )
+            .env("FLATNET_PROMETHEUS_URL", &services)
+            .env("FLATNET_GRAFANA_URL", &services)
+            .env("FLATNET_LOKI_URL", &services)
+            .env("FLATNET_ALERTMANAGER_URL", &services)
+            .env("FLATNET_GITHUB_API", &services)
+            .env("FLATNET_NEBULA_CONFIG", self.home.join("nebula/config.yml"));
+        cmd
+    }
+
+    /// Run a command that must succeed and return its stdout
+    pub fn stdout(&self, args: &[&str]) -> String {
+        let output = self.flatnet(args).assert().success().get_output().clone();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Run a command that must succeed and parse its stdout as JSON
+    pub fn json(&self, args: &[&str]) -> Value {
+        let stdout = self.stdout(args);
+        serde_json::from_str(&stdout)
+            .unwrap_or_else(|e| panic!("invalid JSON from {:?}: {}\n{}", args, e, stdout))
+    }
+
+    /// Run a command regardless of its exit status and parse stdout as JSON
+    pub fn json_any(&self, args: &[&str]) -> Value {
+        let output = self.flatnet(args).output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        serde_json::from_str(&stdout)
+            .unwrap_or_else(|e| panic!("invalid JSON from {:?}: {}\n{}", args, e, stdout))
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.home);
+    }
+}
+
+/// Find the entry of a JSON array whose `key` equals `value`
+pub fn find<'a>(items: &'a Value, key: &str, value: &str) -> &'a Value {
+    items
+        .as_array()
+        .and_then(|items| items.iter().find(|i| i[key] == value))
+        .unwrap_or_else(|| panic!("no entry with {} = {} in {}", key, value, items))
+}
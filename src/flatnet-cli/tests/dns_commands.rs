@@ -0,0 +1,196 @@
+//! End-to-end tests for `flatnet dns serve`
+//!
+//! Runs the server on a free loopback port against a scratch IPAM state, the
+//! mock Gateway registry and a stand-in upstream server.
+
+mod common;
+
+use common::TestEnv;
+use flatnet_mock_gateway::Container;
+use std::net::UdpSocket;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+
+/// Response code the stand-in upstream answers with (REFUSED)
+const UPSTREAM_RCODE: u8 = 5;
+
+/// Kills the server when the test ends
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// A recursive query for `name`
+fn query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+    let mut packet = id.to_be_bytes().to_vec();
+    packet.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet
+}
+
+/// Send a query and wait briefly for the reply
+fn ask(server: &str, name: &str, qtype: u16) -> Option<Vec<u8>> {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    let packet = query(0x4242, name, qtype);
+    socket.send_to(&packet, server).unwrap();
+    let mut buf = [0u8; 512];
+    let len = socket.recv(&mut buf).ok()?;
+    assert_eq!(&buf[..2], &packet[..2], "reply ID does not match");
+    Some(buf[..len].to_vec())
+}
+
+fn rcode(reply: &[u8]) -> u8 {
+    reply[3] & 0x0f
+}
+
+fn answers(reply: &[u8]) -> u16 {
+    u16::from_be_bytes([reply[6], reply[7]])
+}
+
+/// Address in the single A answer
+fn address(reply: &[u8]) -> [u8; 4] {
+    assert_eq!(rcode(reply), 0);
+    assert_eq!(answers(reply), 1);
+    reply[reply.len() - 4..].try_into().unwrap()
+}
+
+fn free_port() -> u16 {
+    UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Upstream server refusing everything, so forwarded replies are recognizable
+fn start_upstream() -> u16 {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = socket.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+            let mut reply = buf[..len].to_vec();
+            reply[2] |= 0x80;
+            reply[3] = 0x80 | UPSTREAM_RCODE;
+            let _ = socket.send_to(&reply, peer);
+        }
+    });
+    port
+}
+
+/// Start `flatnet dns serve` and wait until it answers
+fn start_server(env: &TestEnv) -> (Server, String) {
+    let ipam_dir = env.home.join("ipam");
+    std::fs::create_dir_all(&ipam_dir).unwrap();
+    std::fs::write(
+        ipam_dir.join("allocations.json"),
+        serde_json::json!({
+            "subnet": "10.87.1.0/24",
+            "gateway": "10.87.1.1",
+            "range_start": "10.87.1.2",
+            "range_end": "10.87.1.254",
+            "allocations": {"aaaaaaaaaaaa1111": "10.87.1.2"},
+            "names": {"aaaaaaaaaaaa1111": "web"}
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let port = free_port();
+    env.write_config(&format!(
+        "[dns]\nlisten = \"127.0.0.1\"\nport = {}\nipam_dir = \"{}\"\nupstreams = [\"127.0.0.1:{}\"]\nrefresh_secs = 1\n",
+        port,
+        ipam_dir.display(),
+        start_upstream()
+    ));
+
+    let child = env
+        .std_command(&["dns", "serve"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start flatnet dns serve");
+    let server = Server(child);
+    let addr = format!("127.0.0.1:{}", port);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while ask(&addr, "web.flatnet", TYPE_A).is_none() {
+        assert!(Instant::now() < deadline, "DNS server did not start");
+    }
+    (server, addr)
+}
+
+#[test]
+fn test_dns_serve() {
+    let env = TestEnv::new();
+    let mut db = Container::new("cccccccccccc3333", "10.100.2.10", 2);
+    db.hostname = Some("db".to_string());
+    env.gateway.add_container(db);
+    let (_server, addr) = start_server(&env);
+
+    // Local containers from the IPAM state, remote ones from the registry
+    let reply = ask(&addr, "web.flatnet", TYPE_A).unwrap();
+    assert_eq!(address(&reply), [10, 87, 1, 2]);
+    let reply = ask(&addr, "aaaaaaaaaaaa.flatnet", TYPE_A).unwrap();
+    assert_eq!(address(&reply), [10, 87, 1, 2]);
+    let reply = ask(&addr, "db.flatnet", TYPE_A).unwrap();
+    assert_eq!(address(&reply), [10, 100, 2, 10]);
+
+    let reply = ask(&addr, "web.flatnet", TYPE_AAAA).unwrap();
+    assert_eq!((rcode(&reply), answers(&reply)), (0, 0));
+    let reply = ask(&addr, "missing.flatnet", TYPE_A).unwrap();
+    assert_eq!(rcode(&reply), 3);
+
+    let reply = ask(&addr, "2.1.87.10.in-addr.arpa", TYPE_PTR).unwrap();
+    assert!(reply.ends_with(b"\x03web\x07flatnet\x00"));
+
+    // Everything else goes upstream
+    let reply = ask(&addr, "example.com", TYPE_A).unwrap();
+    assert_eq!(rcode(&reply), UPSTREAM_RCODE);
+
+    // Packets shorter than a header get no reply and are not forwarded
+    // (the upstream above would stop on them)
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    for packet in [&[][..], &[0x42][..], &[0x42, 0x42, 0x01][..]] {
+        socket.send_to(packet, &addr).unwrap();
+    }
+    assert!(socket.recv(&mut [0u8; 512]).is_err());
+    let reply = ask(&addr, "example.com", TYPE_A).unwrap();
+    assert_eq!(rcode(&reply), UPSTREAM_RCODE);
+
+    // New registrations show up after the next refresh
+    let mut cache = Container::new("dddddddddddd4444", "10.100.3.20", 3);
+    cache.hostname = Some("cache".to_string());
+    env.gateway.add_container(cache);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let reply = ask(&addr, "cache.flatnet", TYPE_A).unwrap();
+        if rcode(&reply) == 0 {
+            assert_eq!(address(&reply), [10, 100, 3, 20]);
+            break;
+        }
+        assert!(Instant::now() < deadline, "registry was not reloaded");
+        thread::sleep(Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,373 @@
+//! End-to-end tests for the commands backed by the Gateway API
+//!
+//! `status`, `ps --all-hosts`, `doctor`, `peers` and `routes sync` run
+//! against a mock Gateway, in table and JSON output.
+
+mod common;
+
+use common::{find, TestEnv};
+use flatnet_mock_gateway::{Container, Fault, Peer};
+use predicates::prelude::*;
+use std::time::Duration;
+
+const PEER_OK: &str = "http://10.100.2.1:8080";
+const PEER_DOWN: &str = "http://10.100.3.1:8080";
+
+/// Containers on three hosts: local, P2P-routed and failing healthchecks
+fn multihost_env() -> TestEnv {
+    let env = TestEnv::new();
+    env.gateway
+        .add_container(Container::new("aaaa1111bbbb2222", "10.100.1.10", 1).with_hostname("web"));
+    env.gateway
+        .add_container(Container::new("cccc3333dddd4444", "10.100.2.10", 2).with_hostname("db"));
+    env.gateway
+        .add_container(Container::new("eeee5555ffff6666", "10.100.3.10", 3));
+    env.gateway.set_escalation("10.100.2.10", "P2P_ACTIVE");
+    env.gateway.set_health_failures("10.100.3.10", 2);
+    env
+}
+
+/// One healthy peer and one that has not synced for ten minutes
+fn add_peers(env: &TestEnv) {
+    env.gateway.add_peer(Peer::synced(PEER_OK, 5, 4));
+    env.gateway.add_peer(Peer {
+        last_error: Some("connection refused".to_string()),
+        consecutive_failures: 20,
+        ..Peer::synced(PEER_DOWN, 600, 2)
+    });
+}
+
+#[test]
+fn test_status_json() {
+    let env = multihost_env();
+    add_peers(&env);
+    env.services
+        .respond_text("/-/healthy", 200, "Prometheus is Healthy.\n");
+
+    let status = env.json(&["status", "--json"]);
+    let components = &status["components"];
+
+    assert_eq!(status["gateway_url"], env.gateway.url());
+    assert_eq!(status["containers"], 3);
+    let gateway = find(components, "name", "Gateway");
+    assert_eq!(gateway["status"], "Running");
+    assert_eq!(gateway["details"], env.gateway.addr().to_string());
+    assert_eq!(
+        find(components, "name", "CNI Plugin")["details"],
+        "10.100.x.0/24 (3 IPs)"
+    );
+    assert_eq!(find(components, "name", "Healthcheck")["status"], "Running");
+
+    let sync = find(components, "name", "Sync");
+    assert_eq!(sync["status"], "Warning");
+    assert_eq!(sync["details"], "2 peers, 1 stale");
+    let peer = find(components, "name", "Peer");
+    assert_eq!(peer["status"], "Stale");
+    assert_eq!(peer["details"], "10.100.3.1:8080 (connection refused)");
+
+    // The services mock answers Prometheus, and Grafana through the
+    // Gateway's own /api/health; Loki's /ready is unknown to it
+    assert_eq!(find(components, "name", "Prometheus")["status"], "Running");
+    assert_eq!(find(components, "name", "Grafana")["status"], "Running");
+    assert_eq!(find(components, "name", "Loki")["status"], "Warning");
+    assert!(status["alerts"]["error"].is_string());
+}
+
+#[test]
+fn test_status_table() {
+    let env = multihost_env();
+    add_peers(&env);
+
+    env.flatnet(&["status"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Gateway"))
+        .stdout(predicate::str::contains(env.gateway.addr().to_string()))
+        .stdout(predicate::str::contains("10.100.x.0/24 (3 IPs)"))
+        .stdout(predicate::str::contains("2 peers, 1 stale"))
+        .stdout(predicate::str::contains("Stale"));
+}
+
+#[test]
+fn test_status_gateway_errors() {
+    let env = TestEnv::new().with_timeout(1);
+
+    env.gateway.fail("/api/status", Fault::Status(500));
+    let status = env.json(&["status", "--json"]);
+    let gateway = find(&status["components"], "name", "Gateway");
+    assert_eq!(gateway["status"], "Error");
+    assert!(gateway["details"].as_str().unwrap().contains("HTTP 500"));
+    assert_eq!(
+        find(&status["components"], "name", "CNI Plugin")["details"],
+        "Gateway unavailable"
+    );
+
+    env.gateway.reset_rules();
+    env.gateway.delay("/api/status", Duration::from_secs(3));
+    let status = env.json(&["status", "--json"]);
+    assert_eq!(
+        find(&status["components"], "name", "Gateway")["status"],
+        "Timeout"
+    );
+
+    // Nothing listens on port 1
+    let output = env
+        .flatnet(&["status", "--json"])
+        .env("FLATNET_GATEWAY_URL", "http://127.0.0.1:1")
+        .output()
+        .unwrap();
+    let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let gateway = find(&status["components"], "name", "Gateway");
+    assert_eq!(gateway["status"], "Stopped");
+    assert_eq!(gateway["details"], "127.0.0.1:1 (connection refused)");
+}
+
+#[test]
+fn test_ps_all_hosts_json() {
+    let env = multihost_env();
+
+    let output = env.json(&["ps", "--all-hosts", "--json"]);
+    assert_eq!(output["total_containers"], 3);
+    assert_eq!(output["hosts"], 3);
+
+    let containers = &output["containers"];
+    let web = find(containers, "flatnet_ip", "10.100.1.10");
+    assert_eq!(web["id"], "aaaa1111bbbb");
+    assert_eq!(web["name"], "web");
+    assert_eq!(web["route"], "local");
+    assert_eq!(web["health"], "-");
+
+    let db = find(containers, "flatnet_ip", "10.100.2.10");
+    assert_eq!(db["route"], "p2p");
+    assert_eq!(db["gateway"], "10.100.2.1");
+    assert_eq!(db["health"], "healthy");
+
+    let failing = find(containers, "flatnet_ip", "10.100.3.10");
+    assert_eq!(failing["name"], "-");
+    assert_eq!(failing["route"], "gateway");
+    assert_eq!(failing["gateway"], "10.100.3.1");
+    assert_eq!(failing["health"], "failing (2/3)");
+
+    let filtered = env.json(&["ps", "--all-hosts", "--json", "--filter", "route=p2p"]);
+    assert_eq!(filtered["total_containers"], 1);
+    assert_eq!(filtered["containers"][0]["flatnet_ip"], "10.100.2.10");
+}
+
+#[test]
+fn test_ps_all_hosts_table() {
+    let env = multihost_env();
+
+    env.flatnet(&["ps", "--all-hosts"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("FLATNET IP"))
+        .stdout(predicate::str::contains("failing (2/3)"))
+        .stdout(predicate::str::contains("Total: 3 containers on 3 hosts"));
+
+    env.flatnet(&["ps", "--all-hosts", "--quiet"])
+        .assert()
+        .success()
+        .stdout("aaaa1111bbbb\ncccc3333dddd\neeee5555ffff\n");
+
+    // Routes and healthchecks are best-effort
+    env.gateway.fail("/api/routing", Fault::Status(500));
+    env.gateway.fail("/api/healthcheck", Fault::Malformed);
+    let output = env.json(&["ps", "--all-hosts", "--json"]);
+    assert_eq!(output["containers"][0]["route"], "unknown");
+
+    // The registry is not
+    env.gateway.fail("/api/containers", Fault::Status(503));
+    env.flatnet(&["ps", "--all-hosts"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The Gateway registry is required for --all-hosts.",
+        ));
+}
+
+#[test]
+fn test_ps_all_hosts_empty_registry() {
+    let env = TestEnv::new();
+
+    env.flatnet(&["ps", "--all-hosts"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "No containers found in the Gateway registry.",
+        ));
+}
+
+#[test]
+fn test_doctor_gateway_checks() {
+    let env = TestEnv::new();
+    let address = env.gateway.addr().to_string();
+
+    // Other categories depend on the host, so only the Gateway is checked
+    let output = env.json_any(&["doctor", "--json"]);
+    let checks = output["checks"].as_array().unwrap();
+    let gateway: Vec<_> = checks
+        .iter()
+        .filter(|c| c["category"] == "Gateway")
+        .collect();
+    assert_eq!(gateway.len(), 3);
+    for check in &gateway {
+        assert_eq!(check["status"], "pass", "{}", check);
+    }
+    assert_eq!(
+        find(&output["checks"], "name", "API available")["message"],
+        format!("API endpoint responding ({})", address)
+    );
+    assert!(output["summary"]["passed"].as_u64().unwrap() >= 3);
+
+    env.flatnet(&["doctor"])
+        .assert()
+        .stdout(predicate::str::contains("Gateway\n  [✓] HTTP responding"));
+    env.flatnet(&["doctor", "--verbose"])
+        .assert()
+        .stdout(predicate::str::contains(format!(
+            "[✓] Gateway is responding ({})",
+            address
+        )))
+        .stdout(predicate::str::contains("[✓] Metrics endpoint responding"));
+}
+
+#[test]
+fn test_doctor_gateway_failures() {
+    let env = TestEnv::new();
+    env.gateway.fail("/api/status", Fault::Malformed);
+    env.gateway.fail("/metrics", Fault::Status(500));
+
+    let output = env.json_any(&["doctor", "--json"]);
+    let api = find(&output["checks"], "name", "API available");
+    assert_eq!(api["status"], "fail");
+    assert!(api["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("API request failed"));
+    let metrics = find(&output["checks"], "name", "Metrics endpoint");
+    assert_eq!(metrics["status"], "warning");
+    assert!(metrics["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Unexpected status 500"));
+    assert_eq!(output["summary"]["exit_code"], 2);
+}
+
+#[test]
+fn test_peers_list() {
+    let env = TestEnv::new();
+
+    env.flatnet(&["peers", "list"])
+        .assert()
+        .success()
+        .stdout("No sync peers configured.\n");
+
+    add_peers(&env);
+    env.gateway.set_sync_interval(60);
+
+    let output = env.json(&["peers", "list", "--json"]);
+    assert_eq!(output["sync_interval"], 60);
+    assert_eq!(output["stale_count"], 1);
+    let ok = find(&output["peers"], "endpoint", PEER_OK);
+    assert_eq!(ok["stale"], false);
+    assert_eq!(ok["container_count"], 4);
+    let down = find(&output["peers"], "endpoint", PEER_DOWN);
+    assert_eq!(down["stale"], true);
+    assert_eq!(down["last_error"], "connection refused");
+
+    env.flatnet(&["peers", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("LAST SUCCESS"))
+        .stdout(predicate::str::contains("10m ago"))
+        .stdout(predicate::str::contains(
+            "Total: 2 peers, 1 stale (sync interval 60s)",
+        ));
+}
+
+#[test]
+fn test_peers_add_remove() {
+    let env = TestEnv::new();
+
+    env.flatnet(&["peers", "add", "http://10.100.2.1:8080/"])
+        .assert()
+        .success()
+        .stdout(format!("Added peer {}\n", PEER_OK));
+    env.flatnet(&["peers", "add", PEER_OK])
+        .assert()
+        .success()
+        .stdout(format!("Peer {} is already configured\n", PEER_OK));
+    assert_eq!(env.gateway.peers().len(), 1);
+
+    env.flatnet(&["peers", "add", "10.100.2.1:8080"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must start with http://"));
+
+    env.flatnet(&["peers", "remove", PEER_OK])
+        .assert()
+        .success()
+        .stdout(format!("Removed peer {}\n", PEER_OK));
+    env.flatnet(&["peers", "remove", PEER_OK])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Peer not found"));
+    assert!(env.gateway.peers().is_empty());
+}
+
+#[test]
+fn test_peers_pull() {
+    let env = TestEnv::new();
+    env.gateway.add_peer(Peer::new(PEER_OK));
+    env.gateway.add_peer(Peer::new(PEER_DOWN));
+    env.gateway.fail_peer(PEER_DOWN, "timeout");
+
+    env.flatnet(&["peers", "pull"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Sync complete: 1 peers succeeded, 1 failed",
+        ))
+        .stdout(predicate::str::contains("flatnet peers list"));
+
+    env.flatnet(&["peers", "pull", PEER_OK])
+        .assert()
+        .success()
+        .stdout("Sync complete: 1 peers succeeded, 0 failed\n");
+    let pulls = env.gateway.requests_to("POST", "/api/sync/pull");
+    assert_eq!(pulls.len(), 2);
+    assert_eq!(pulls[1].query("peer"), Some(PEER_OK));
+
+    env.gateway.fail("/api/sync/pull", Fault::Disconnect);
+    env.flatnet(&["peers", "pull"]).assert().failure();
+}
+
+#[test]
+fn test_routes_sync_dry_run() {
+    let env = multihost_env();
+    env.write_config("[routes]\nhost_id = 1\nnext_hop = \"10.100.0.254\"\n");
+
+    // Assumes the test host has no routes of its own to 10.100.2.0/24 or
+    // 10.100.3.0/24
+    env.flatnet(&["routes", "sync", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Peer routes for host 1 (next hop 10.100.0.254)",
+        ))
+        .stdout(predicate::str::contains(
+            "+ 10.100.2.0/24 via 10.100.0.254 (host 2)",
+        ))
+        .stdout(predicate::str::contains(
+            "+ 10.100.3.0/24 via 10.100.0.254 (host 3)",
+        ))
+        .stdout(predicate::str::contains(
+            "Dry run: 2 to add, 0 to replace, 0 to remove",
+        ));
+
+    // Without a registry there is nothing to plan
+    env.gateway.fail("/api/containers", Fault::Status(500));
+    env.flatnet(&["routes", "sync", "--dry-run"])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,203 @@
+//! End-to-end tests for the commands backed by the monitoring stack
+//!
+//! `metrics`, `alerts`, `logs` and `upgrade` run against canned Prometheus,
+//! Alertmanager, Loki and GitHub API responses.
+
+mod common;
+
+use common::{find, TestEnv};
+use flatnet_mock_gateway::Fault;
+use predicates::prelude::*;
+use serde_json::json;
+
+/// A Prometheus matrix with one series of three samples
+fn matrix() -> serde_json::Value {
+    json!({
+        "status": "success",
+        "data": {
+            "resultType": "matrix",
+            "result": [{
+                "metric": {},
+                "values": [[1700000000, "1"], [1700000060, "2"], [1700000120, "3"]]
+            }]
+        }
+    })
+}
+
+fn alerts() -> serde_json::Value {
+    json!([
+        {
+            "labels": {"alertname": "GatewayDown", "severity": "critical", "component": "gateway"},
+            "annotations": {"summary": "Gateway is not responding"},
+            "startsAt": "2026-01-01T00:00:00Z",
+            "status": {"state": "active", "silencedBy": [], "inhibitedBy": []}
+        },
+        {
+            "labels": {"alertname": "IpamPoolLow", "severity": "warning", "component": "cni"},
+            "annotations": {},
+            "startsAt": "2026-01-01T00:00:00Z",
+            "status": {"state": "suppressed", "silencedBy": ["abc"], "inhibitedBy": []}
+        }
+    ])
+}
+
+fn loki_streams() -> serde_json::Value {
+    json!({
+        "status": "success",
+        "data": {
+            "resultType": "streams",
+            "result": [{
+                "stream": {"job": "gateway"},
+                "values": [
+                    ["1700000002000000000", "GET /api/status 200"],
+                    ["1700000001000000000", "GET /api/health 200"]
+                ]
+            }]
+        }
+    })
+}
+
+#[test]
+fn test_metrics_json() {
+    let env = TestEnv::new();
+    env.services.respond("/api/v1/query_range", 200, matrix());
+
+    let output = env.json(&["metrics", "--json", "--window", "1h"]);
+    assert_eq!(output["prometheus_url"], env.services.url());
+    assert_eq!(output["window"], "1h");
+    assert_eq!(output["step_secs"], 60);
+    assert_eq!(output["metrics"].as_array().unwrap().len(), 6);
+
+    let rate = find(&output["metrics"], "name", "request_rate");
+    assert_eq!(rate["current"], 3.0);
+    assert_eq!(rate["min"], 1.0);
+    assert_eq!(rate["max"], 3.0);
+    assert_eq!(rate["points"][0], json!([1700000000, 1.0]));
+
+    // Rates use at least a one-minute range
+    let queries = env.services.requests_to("GET", "/api/v1/query_range");
+    assert_eq!(queries.len(), 6);
+    assert!(queries
+        .iter()
+        .any(|q| q.query("query").unwrap().contains("[60s]")));
+    assert!(queries.iter().all(|q| q.query("step") == Some("60")));
+}
+
+#[test]
+fn test_metrics_table() {
+    let env = TestEnv::new();
+    env.services.respond("/api/v1/query_range", 200, matrix());
+
+    env.flatnet(&["metrics", "--window", "15m", "--step", "30s"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Flatnet metrics (last 15m, step 30s)",
+        ))
+        .stdout(predicate::str::contains("Request rate"))
+        .stdout(predicate::str::contains("3.00 req/s"))
+        .stdout(predicate::str::contains("min 1.00 req/s  max 3.00 req/s"));
+
+    env.services.reset_rules();
+    env.services.respond(
+        "/api/v1/query_range",
+        200,
+        json!({"status": "success", "data": {"resultType": "matrix", "result": []}}),
+    );
+    env.flatnet(&["metrics"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("no data"));
+
+    env.services.fail("/api/v1/query_range", Fault::Status(503));
+    env.flatnet(&["metrics"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to query Prometheus"));
+}
+
+#[test]
+fn test_alerts_list() {
+    let env = TestEnv::new();
+    env.services.respond("/api/v2/alerts", 200, alerts());
+
+    let output = env.json(&["alerts", "list", "--json"]);
+    assert_eq!(output.as_array().unwrap().len(), 2);
+    assert_eq!(output[0]["labels"]["alertname"], "GatewayDown");
+    assert_eq!(output[0]["status"]["state"], "active");
+
+    let requests = env.services.requests_to("GET", "/api/v2/alerts");
+    assert!(requests[0]
+        .query("filter")
+        .unwrap()
+        .starts_with("component=~"));
+    assert_eq!(requests[0].query("silenced"), Some("true"));
+
+    env.flatnet(&["alerts", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Gateway is not responding"))
+        .stdout(predicate::str::contains("silenced"))
+        .stdout(predicate::str::contains(
+            "Total: 2 alerts, 1 firing, 1 muted",
+        ));
+}
+
+#[test]
+fn test_alerts_list_empty_and_unavailable() {
+    let env = TestEnv::new();
+    env.services.respond("/api/v2/alerts", 200, json!([]));
+
+    env.flatnet(&["alerts", "list"])
+        .assert()
+        .success()
+        .stdout("No active alerts.\n");
+
+    env.services.fail("/api/v2/alerts", Fault::Disconnect);
+    env.flatnet(&["alerts", "list"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to list alerts from"));
+}
+
+#[test]
+fn test_logs_from_loki() {
+    let env = TestEnv::new();
+    env.services.respond_text("/ready", 200, "ready\n");
+    env.services
+        .respond("/loki/api/v1/query_range", 200, loki_streams());
+
+    let output = env.json(&["logs", "gateway", "--json", "--tail", "10"]);
+    assert_eq!(output["target"], "gateway");
+    assert_eq!(output["source"], "loki");
+    let entries = output["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["timestamp"], "2023-11-14T22:13:21Z");
+    assert_eq!(entries[0]["line"], "GET /api/health 200");
+
+    let queries = env.services.requests_to("GET", "/loki/api/v1/query_range");
+    assert_eq!(queries[0].query("query"), Some("{job=\"gateway\"}"));
+    assert_eq!(queries[0].query("limit"), Some("10"));
+
+    env.flatnet(&["logs", "gateway"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "2023-11-14T22:13:22Z GET /api/status 200",
+        ));
+}
+
+#[test]
+fn test_upgrade_check_without_releases() {
+    let env = TestEnv::new();
+    env.services.respond(
+        "/repos/khayashi4337/flatnet/releases/latest",
+        404,
+        json!({"message": "Not Found"}),
+    );
+
+    env.flatnet(&["upgrade", "--check"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No releases found"));
+}
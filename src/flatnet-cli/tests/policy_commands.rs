@@ -0,0 +1,176 @@
+//! End-to-end tests for `flatnet policy`
+//!
+//! `show`, `test` and `apply --dry-run` run against a scratch policy file,
+//! IPAM state and the mock Gateway registry; nothing is loaded into nftables.
+
+mod common;
+
+use common::TestEnv;
+use flatnet_mock_gateway::{Container, Fault};
+use predicates::prelude::*;
+
+const POLICY: &str = r#"
+[networks.flatnet]
+default = "deny"
+
+[[rules]]
+name = "web-to-db"
+action = "allow"
+containers = { names = ["db"] }
+peers = { names = ["web-*"] }
+ports = ["5432"]
+
+[[rules]]
+name = "no-host-3"
+action = "deny"
+direction = "both"
+peers = { hosts = [3] }
+
+[[rules]]
+name = "dns"
+action = "allow"
+direction = "egress"
+ports = ["53/udp"]
+"#;
+
+/// Local web and db containers, a remote web and one on the blocked host
+fn policy_env() -> TestEnv {
+    let env = TestEnv::new();
+    env.gateway
+        .add_container(Container::new("cccc3333dddd4444", "10.100.2.10", 2).with_hostname("web-2"));
+    env.gateway
+        .add_container(Container::new("eeee5555ffff6666", "10.100.3.10", 3).with_hostname("cache"));
+
+    let ipam_dir = env.home.join("ipam");
+    std::fs::create_dir_all(&ipam_dir).unwrap();
+    std::fs::write(
+        ipam_dir.join("allocations.json"),
+        serde_json::json!({
+            "subnet": "10.100.1.0/24",
+            "gateway": "10.100.1.1",
+            "range_start": "10.100.1.10",
+            "range_end": "10.100.1.254",
+            "allocations": {"aaaa1111": "10.100.1.10", "bbbb2222": "10.100.1.11"},
+            "names": {"aaaa1111": "web-1", "bbbb2222": "db"}
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let policy_file = env.home.join("policy.toml");
+    std::fs::write(&policy_file, POLICY).unwrap();
+    env.write_config(&format!(
+        "[policy]\nfile = \"{}\"\nstate_file = \"{}\"\nipam_dir = \"{}\"\n",
+        policy_file.display(),
+        env.home.join("policy.json").display(),
+        ipam_dir.display()
+    ));
+    env
+}
+
+#[test]
+fn test_policy_show() {
+    let env = policy_env();
+
+    env.flatnet(&["policy", "show"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "flatnet      bridge flatnet-br0    default deny",
+        ))
+        .stdout(predicate::str::contains(
+            " 1. allow web-to-db  web-* -> db  (5432/tcp)",
+        ))
+        .stdout(predicate::str::contains("containers: db (10.100.1.11)"))
+        .stdout(predicate::str::contains(
+            "peers: web-1 (10.100.1.10), web-2 (10.100.2.10)",
+        ))
+        .stdout(predicate::str::contains(
+            " 2. deny  no-host-3  any <-> host 3  (all ports)",
+        ));
+
+    env.flatnet(&["policy", "show", "--nft"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("table bridge flatnet_policy {"))
+        .stdout(predicate::str::contains(
+            "ip saddr @c1 ip daddr @c0 tcp dport { 5432 } accept comment \"web-to-db\"",
+        ));
+}
+
+#[test]
+fn test_policy_test() {
+    let env = policy_env();
+
+    env.flatnet(&["policy", "test", "web-2", "db", "5432"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "allow: web-2 (10.100.2.10) -> db (10.100.1.11) 5432/tcp (web-to-db)",
+        ));
+    env.flatnet(&["policy", "test", "db", "web-1", "5432"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("(default of network flatnet)"));
+    env.flatnet(&["policy", "test", "web-1", "cache", "53/udp"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("(no-host-3)"));
+    env.flatnet(&["policy", "test", "web-1", "db", "53/udp"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(dns)"));
+    env.flatnet(&["policy", "test", "db", "1.1.1.1", "443"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "1.1.1.1 is outside the policy scope",
+        ));
+
+    env.flatnet(&["policy", "test", "missing", "db", "5432"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown container: missing"));
+    env.flatnet(&["policy", "test", "web-1", "db", "5432/icmp"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_policy_apply_dry_run() {
+    let env = policy_env();
+
+    env.flatnet(&["policy", "apply", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "table inet flatnet_policy\ndelete table inet flatnet_policy\n",
+        ))
+        .stdout(predicate::str::contains(
+            "elements = { 10.100.1.10, 10.100.2.10 }",
+        ))
+        .stdout(predicate::str::contains(
+            "drop comment \"default of network flatnet\"",
+        ));
+    assert!(!env.home.join("policy.json").exists());
+
+    // The registry is optional; remote containers just drop out of the sets
+    env.gateway.fail("/api/containers", Fault::Status(503));
+    env.flatnet(&["policy", "apply", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("elements = { 10.100.1.10 }"))
+        .stderr(predicate::str::contains(
+            "Failed to list containers from the Gateway registry",
+        ));
+
+    std::fs::write(
+        env.home.join("policy.toml"),
+        "[[rules]]\naction = \"reject\"\n",
+    )
+    .unwrap();
+    env.flatnet(&["policy", "apply", "--dry-run"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid policy file"));
+}
@@ -9,6 +9,7 @@ use rtnetlink::{new_connection, Handle};
 use tokio::runtime::Runtime;
 
 use crate::error::{CniError, CniErrorCode};
+use crate::logger;
 
 /// Default bridge name
 pub const DEFAULT_BRIDGE_NAME: &str = "flatnet-br0";
@@ -55,7 +56,7 @@ async fn ensure_bridge_async(
 
     // Check if bridge already exists
     if let Some(index) = get_link_index(&handle, bridge_name).await? {
-        eprintln!("flatnet: bridge {} already exists (index {})", bridge_name, index);
+        logger::debug(&format!("bridge {} already exists (index {})", bridge_name, index));
         // Ensure IP address is configured (idempotent)
         add_address(&handle, index, gateway_ip, prefix_len).await?;
         // Ensure bridge is UP (idempotent)
@@ -64,7 +65,7 @@ async fn ensure_bridge_async(
     }
 
     // Create the bridge
-    eprintln!("flatnet: creating bridge {}", bridge_name);
+    logger::info(&format!("creating bridge {}", bridge_name));
     create_bridge(&handle, bridge_name).await?;
 
     // Get the bridge index
@@ -83,10 +84,10 @@ async fn ensure_bridge_async(
     // Bring the bridge up
     set_link_up(&handle, index).await?;
 
-    eprintln!(
-        "flatnet: bridge {} created with IP {}/{}",
+    logger::info(&format!(
+        "bridge {} created with IP {}/{}",
         bridge_name, gateway_ip, prefix_len
-    );
+    ));
 
     Ok(index)
 }
@@ -107,21 +108,25 @@ async fn get_link_index(handle: &Handle, name: &str) -> Result<Option<u32>, CniE
     }
 }
 
-/// Create a bridge interface
+/// Create a bridge interface (EEXIST means a concurrent ADD created it)
 async fn create_bridge(handle: &Handle, name: &str) -> Result<(), CniError> {
-    handle
-        .link()
-        .add()
-        .bridge(name.to_string())
-        .execute()
-        .await
-        .map_err(|e| {
-            CniError::new(
-                CniErrorCode::BridgeCreationFailed,
-                &format!("failed to create bridge {}", name),
-            )
-            .with_details(&e.to_string())
-        })
+    match handle.link().add().bridge(name.to_string()).execute().await {
+        Ok(()) => Ok(()),
+        Err(e) if is_exists(&e) => {
+            logger::debug(&format!("bridge {} created by another process", name));
+            Ok(())
+        }
+        Err(e) => Err(CniError::new(
+            CniErrorCode::BridgeCreationFailed,
+            &format!("failed to create bridge {}", name),
+        )
+        .with_details(&e.to_string())),
+    }
+}
+
+/// Whether a request failed because the link or address already exists
+fn is_exists(e: &rtnetlink::Error) -> bool {
+    matches!(e, rtnetlink::Error::NetlinkError(m) if m.raw_code() == -libc::EEXIST)
 }
 
 /// Add an IP address to an interface
@@ -140,25 +145,31 @@ async fn add_address(
         for nla in addr.attributes {
             if let netlink_packet_route::address::AddressAttribute::Address(existing) = nla {
                 if existing == std::net::IpAddr::V4(ip) {
-                    eprintln!("flatnet: address {}/{} already exists on bridge", ip, prefix_len);
+                    logger::debug(&format!(
+                        "address {}/{} already exists on bridge",
+                        ip, prefix_len
+                    ));
                     return Ok(());
                 }
             }
         }
     }
 
-    handle
+    // EEXIST: a concurrent ADD added it after the check above
+    match handle
         .address()
         .add(index, std::net::IpAddr::V4(ip), prefix_len)
         .execute()
         .await
-        .map_err(|e| {
-            CniError::new(
-                CniErrorCode::BridgeCreationFailed,
-                &format!("failed to add address {}/{} to bridge", ip, prefix_len),
-            )
-            .with_details(&e.to_string())
-        })
+    {
+        Ok(()) => Ok(()),
+        Err(e) if is_exists(&e) => Ok(()),
+        Err(e) => Err(CniError::new(
+            CniErrorCode::BridgeCreationFailed,
+            &format!("failed to add address {}/{} to bridge", ip, prefix_len),
+        )
+        .with_details(&e.to_string())),
+    }
 }
 
 /// Bring a network interface up
@@ -227,6 +238,8 @@ pub fn attach_to_bridge(bridge_index: u32, veth_index: u32) -> Result<(), CniErr
 #[cfg(test)]
 mod tests {
     use super::*;
+    use netlink_packet_core::ErrorMessage;
+    use std::num::NonZeroI32;
 
     #[test]
     fn test_default_values() {
@@ -234,4 +247,17 @@ mod tests {
         assert_eq!(DEFAULT_GATEWAY, "10.87.1.1");
         assert_eq!(DEFAULT_PREFIX_LEN, 24);
     }
+
+    #[test]
+    fn test_is_exists() {
+        let error = |code: i32| {
+            let mut message = ErrorMessage::default();
+            message.code = NonZeroI32::new(code);
+            rtnetlink::Error::NetlinkError(message)
+        };
+
+        assert!(is_exists(&error(-libc::EEXIST)));
+        assert!(!is_exists(&error(-libc::EPERM)));
+        assert!(!is_exists(&rtnetlink::Error::RequestFailed));
+    }
 }
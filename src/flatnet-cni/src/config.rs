@@ -3,8 +3,19 @@
 //! Handles parsing of the network configuration JSON passed via stdin.
 //! Supports multihost configuration with host ID and registry endpoints.
 
+use std::collections::{BTreeMap, HashMap};
+use std::net::Ipv4Addr;
+
 use serde::{Deserialize, Serialize};
 
+use crate::error::{CniError, CniErrorCode};
+use crate::logger::LogLevel;
+use crate::portmap::PortMapping;
+use crate::result::DnsResult;
+
+/// Domain the embedded DNS serves container names under
+const DEFAULT_DNS_DOMAIN: &str = "flatnet";
+
 /// Network configuration passed to the CNI plugin
 ///
 /// See: https://github.com/containernetworking/cni/blob/spec-v1.0.0/SPEC.md#network-configuration
@@ -25,6 +36,10 @@ pub struct NetworkConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prev_result: Option<serde_json::Value>,
 
+    /// Values the runtime supplies for the declared capabilities
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_config: Option<RuntimeConfig>,
+
     // Flatnet-specific configuration
 
     /// Bridge name (default: "flatnet-br0")
@@ -47,6 +62,10 @@ pub struct NetworkConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dns: Option<DnsConfig>,
 
+    /// Hand out the bridge IP as nameserver (for `flatnet dns serve`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_enabled: Option<bool>,
+
     /// Additional arguments
     #[serde(skip_serializing_if = "Option::is_none")]
     pub args: Option<serde_json::Value>,
@@ -64,6 +83,26 @@ pub struct NetworkConfig {
     /// Enable registry synchronization (default: true if endpoints provided)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub registry_enabled: Option<bool>,
+
+    // Logging configuration
+
+    /// JSON log file (default: /var/log/flatnet/cni.log)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<String>,
+
+    /// Minimum level to log: debug, info, warn, error (default: info)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<LogLevel>,
+
+    /// Prometheus textfile-collector file
+    /// (default: /var/lib/flatnet/metrics/flatnet_cni.prom)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics_file: Option<String>,
+
+    /// Network policy state written by `flatnet policy apply`
+    /// (default: /var/lib/flatnet/policy.json)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_file: Option<String>,
 }
 
 impl NetworkConfig {
@@ -86,6 +125,11 @@ impl NetworkConfig {
         self.host_id.unwrap_or(1)
     }
 
+    /// Host ID that turns on multihost mode (`hostId`, else `ipam.hostId`)
+    pub fn multihost_id(&self) -> Option<u8> {
+        self.host_id.or_else(|| self.ipam.as_ref().and_then(|ipam| ipam.host_id))
+    }
+
     /// Check if registry synchronization is enabled
     pub fn is_registry_enabled(&self) -> bool {
         self.registry_enabled.unwrap_or_else(|| self.registry_endpoints.is_some())
@@ -95,6 +139,154 @@ impl NetworkConfig {
     pub fn registry_endpoints(&self) -> &[String] {
         self.registry_endpoints.as_deref().unwrap_or(&[])
     }
+
+    /// DNS settings for the ADD result when the embedded DNS is enabled
+    ///
+    /// The bridge IP comes first and the flatnet domain (`dns.domain`,
+    /// default "flatnet") is searched first; other `dns` settings follow.
+    pub fn dns_result(&self, bridge_ip: Ipv4Addr) -> Option<DnsResult> {
+        if !self.dns_enabled.unwrap_or(false) {
+            return None;
+        }
+        let dns = self.dns.as_ref();
+        let domain = dns
+            .and_then(|d| d.domain.clone())
+            .unwrap_or_else(|| DEFAULT_DNS_DOMAIN.to_string());
+
+        Some(DnsResult {
+            nameservers: Some(prepend(
+                bridge_ip.to_string(),
+                dns.and_then(|d| d.nameservers.as_ref()),
+            )),
+            search: Some(prepend(domain.clone(), dns.and_then(|d| d.search.as_ref()))),
+            domain: Some(domain),
+            options: dns.and_then(|d| d.options.clone()),
+        })
+    }
+
+    /// Container labels passed by the runtime in `args.cni.labels`
+    ///
+    /// See: https://github.com/containernetworking/cni/blob/main/CONVENTIONS.md#args-in-network-config
+    pub fn labels(&self) -> BTreeMap<String, String> {
+        self.args
+            .as_ref()
+            .and_then(|args| args.pointer("/cni/labels"))
+            .and_then(|labels| labels.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|label| {
+                let key = label.get("key")?.as_str()?;
+                let value = label.get("value")?.as_str()?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+
+    /// Static IP requested through the `ips` capability or `CNI_ARGS` `IP=`
+    ///
+    /// Both may be given as long as they name the same address.
+    pub fn requested_ip(&self, args: &CniArgs) -> Result<Option<Ipv4Addr>, CniError> {
+        let from_runtime = self
+            .runtime_config
+            .as_ref()
+            .and_then(|r| r.ips.as_deref())
+            .unwrap_or(&[])
+            .iter()
+            .map(|ip| {
+                (
+                    CniErrorCode::InvalidNetworkConfig,
+                    "runtimeConfig ips",
+                    ip.as_str(),
+                )
+            });
+        let from_args = args
+            .get("IP")
+            .into_iter()
+            .flat_map(|ips| ips.split(','))
+            .map(|ip| (CniErrorCode::InvalidEnvironmentVariables, "CNI_ARGS IP", ip));
+
+        let mut requested: Option<Ipv4Addr> = None;
+        for (code, source, value) in from_runtime.chain(from_args) {
+            // Addresses may carry a prefix length; the subnet's is used
+            let address = value.trim().split('/').next().unwrap_or_default();
+            let ip: Ipv4Addr = address.parse().map_err(|_| {
+                CniError::new(
+                    code,
+                    &format!("invalid IPv4 address in {}: {}", source, value),
+                )
+            })?;
+            match requested {
+                Some(other) if other != ip => {
+                    return Err(CniError::new(
+                        code,
+                        &format!("conflicting static IPs requested: {} and {}", other, ip),
+                    ))
+                }
+                _ => requested = Some(ip),
+            }
+        }
+        Ok(requested)
+    }
+}
+
+/// Runtime configuration for the capabilities the plugin supports
+///
+/// See: https://github.com/containernetworking/cni/blob/main/CONVENTIONS.md
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeConfig {
+    /// Requested container IPs (`ips` capability), in CIDR notation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ips: Option<Vec<String>>,
+
+    /// Host port mappings (`portMappings` capability)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port_mappings: Option<Vec<PortMapping>>,
+}
+
+/// Arguments passed in `CNI_ARGS` (`KEY1=VAL1;KEY2=VAL2`)
+#[derive(Debug, Clone, Default)]
+pub struct CniArgs(HashMap<String, String>);
+
+impl CniArgs {
+    /// Parse `CNI_ARGS`; pairs without `=` are ignored
+    pub fn parse(args: &str) -> Self {
+        Self(
+            args.split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect(),
+        )
+    }
+
+    /// Arguments of the current invocation
+    pub fn from_env() -> Self {
+        Self::parse(&std::env::var("CNI_ARGS").unwrap_or_default())
+    }
+
+    /// Non-empty value of an argument
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .get(key)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Container name (Podman passes it as `K8S_POD_NAME`)
+    pub fn container_name(&self) -> Option<&str> {
+        self.get("K8S_POD_NAME")
+    }
+}
+
+/// `first` followed by the entries of `rest` other than it
+fn prepend(first: String, rest: Option<&Vec<String>>) -> Vec<String> {
+    let mut list = vec![first];
+    for entry in rest.into_iter().flatten() {
+        if !list.contains(entry) {
+            list.push(entry.clone());
+        }
+    }
+    list
 }
 
 /// IPAM (IP Address Management) configuration
@@ -124,6 +316,24 @@ pub struct IpamConfig {
     /// Host ID for multihost IP allocation (overrides network-level setting)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub host_id: Option<u8>,
+
+    /// Directory for the IPAM state (default: /var/lib/flatnet/ipam)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<String>,
+
+    /// Milliseconds to wait for the IPAM lock before failing with
+    /// TryAgainLater (default: 10000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_timeout_ms: Option<u64>,
+
+    /// Container name -> IP reserved for it (excluded from dynamic allocation)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reservations: Option<HashMap<String, String>>,
+
+    /// Seconds a released IP is kept for its container name before other
+    /// containers may get it (default: 600)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sticky_cooldown_secs: Option<u64>,
 }
 
 /// Route configuration
@@ -215,7 +425,8 @@ mod tests {
                 "gateway": "10.42.0.1",
                 "routes": [
                     {"dst": "0.0.0.0/0"}
-                ]
+                ],
+                "lockTimeoutMs": 2500
             },
             "dns": {
                 "nameservers": ["8.8.8.8", "8.8.4.4"],
@@ -232,6 +443,7 @@ mod tests {
         let ipam = config.ipam.unwrap();
         assert_eq!(ipam.plugin_type, "host-local");
         assert_eq!(ipam.subnet.unwrap(), "10.42.0.0/16");
+        assert_eq!(ipam.lock_timeout_ms, Some(2500));
 
         let dns = config.dns.unwrap();
         assert_eq!(dns.nameservers.unwrap().len(), 2);
@@ -256,6 +468,7 @@ mod tests {
 
         let config: NetworkConfig = serde_json::from_str(json).unwrap();
         assert_eq!(config.host_id_value(), 2);
+        assert_eq!(config.multihost_id(), Some(2));
         assert!(config.is_registry_enabled());
         assert_eq!(config.registry_endpoints().len(), 1);
 
@@ -264,6 +477,34 @@ mod tests {
         assert_eq!(ipam.host_id.unwrap(), 2);
     }
 
+    #[test]
+    fn test_parse_logging_config() {
+        let json = r#"{
+            "cniVersion": "1.0.0",
+            "name": "flatnet",
+            "type": "flatnet",
+            "logFile": "/var/log/flatnet/cni.log",
+            "logLevel": "debug",
+            "metricsFile": "/var/lib/flatnet/metrics/flatnet_cni.prom"
+        }"#;
+
+        let config: NetworkConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.log_file.as_deref(), Some("/var/log/flatnet/cni.log"));
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(
+            config.metrics_file.as_deref(),
+            Some("/var/lib/flatnet/metrics/flatnet_cni.prom")
+        );
+
+        let json = r#"{
+            "cniVersion": "1.0.0",
+            "name": "flatnet",
+            "type": "flatnet",
+            "logLevel": "verbose"
+        }"#;
+        assert!(serde_json::from_str::<NetworkConfig>(json).is_err());
+    }
+
     #[test]
     fn test_registry_enabled_with_endpoints() {
         let json = r#"{
@@ -277,4 +518,101 @@ mod tests {
         // Registry should be enabled when endpoints are provided
         assert!(config.is_registry_enabled());
     }
+
+    #[test]
+    fn test_dns_result() {
+        let bridge = Ipv4Addr::new(10, 87, 1, 1);
+        let mut config: NetworkConfig = serde_json::from_str(
+            r#"{
+            "cniVersion": "1.0.0",
+            "name": "flatnet",
+            "type": "flatnet",
+            "dns": {"nameservers": ["10.87.1.1", "1.1.1.1"], "search": ["lan"]}
+        }"#,
+        )
+        .unwrap();
+        assert!(config.dns_result(bridge).is_none());
+
+        config.dns_enabled = Some(true);
+        let dns = config.dns_result(bridge).unwrap();
+        assert_eq!(dns.nameservers.unwrap(), vec!["10.87.1.1", "1.1.1.1"]);
+        assert_eq!(dns.search.unwrap(), vec!["flatnet", "lan"]);
+        assert_eq!(dns.domain.as_deref(), Some("flatnet"));
+
+        config.dns = None;
+        let dns = config.dns_result(bridge).unwrap();
+        assert_eq!(dns.nameservers.unwrap(), vec!["10.87.1.1"]);
+        assert!(dns.options.is_none());
+    }
+
+    #[test]
+    fn test_labels() {
+        let config: NetworkConfig = serde_json::from_str(
+            r#"{
+            "cniVersion": "1.0.0",
+            "name": "flatnet",
+            "type": "flatnet",
+            "policyFile": "/run/flatnet/policy.json",
+            "args": {"cni": {"labels": [
+                {"key": "app", "value": "web"},
+                {"key": "tier", "value": "front"},
+                {"key": "broken"}
+            ]}}
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.policy_file.as_deref(),
+            Some("/run/flatnet/policy.json")
+        );
+        let labels = config.labels();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels.get("app").map(String::as_str), Some("web"));
+
+        let config: NetworkConfig = serde_json::from_str(
+            r#"{"cniVersion": "1.0.0", "name": "flatnet", "type": "flatnet"}"#,
+        )
+        .unwrap();
+        assert!(config.labels().is_empty());
+    }
+
+    #[test]
+    fn test_parse_cni_args() {
+        let args = CniArgs::parse("IgnoreUnknown=1;K8S_POD_NAME=web;IP=10.87.1.20;EMPTY=;junk");
+        assert_eq!(args.get("IgnoreUnknown"), Some("1"));
+        assert_eq!(args.container_name(), Some("web"));
+        assert_eq!(args.get("EMPTY"), None);
+        assert_eq!(args.get("junk"), None);
+        assert_eq!(CniArgs::parse("").container_name(), None);
+    }
+
+    #[test]
+    fn test_requested_ip() {
+        let json = r#"{
+            "cniVersion": "1.0.0",
+            "name": "flatnet",
+            "type": "flatnet",
+            "runtimeConfig": {"ips": ["10.87.1.20/24"]}
+        }"#;
+        let config: NetworkConfig = serde_json::from_str(json).unwrap();
+        let ip: Ipv4Addr = "10.87.1.20".parse().unwrap();
+
+        assert_eq!(config.requested_ip(&CniArgs::default()).unwrap(), Some(ip));
+        let args = CniArgs::parse("IP=10.87.1.20");
+        assert_eq!(config.requested_ip(&args).unwrap(), Some(ip));
+
+        let err = config
+            .requested_ip(&CniArgs::parse("IP=10.87.1.21"))
+            .unwrap_err();
+        assert!(err.message().contains("conflicting static IPs"));
+
+        let minimal: NetworkConfig =
+            serde_json::from_str(r#"{"cniVersion": "1.0.0", "name": "n", "type": "flatnet"}"#)
+                .unwrap();
+        assert_eq!(minimal.requested_ip(&CniArgs::default()).unwrap(), None);
+        let err = minimal
+            .requested_ip(&CniArgs::parse("IP=10.87.1"))
+            .unwrap_err();
+        assert_eq!(err.code(), CniErrorCode::InvalidEnvironmentVariables);
+    }
 }
@@ -51,6 +51,12 @@ pub enum CniErrorCode {
 
     /// 104: Route configuration failed
     RouteFailure = 104,
+
+    /// 105: Port mapping failed
+    PortMappingFailed = 105,
+
+    /// 106: Network policy sets could not be updated
+    PolicyFailed = 106,
 }
 
 /// CNI error with code, message, and optional details
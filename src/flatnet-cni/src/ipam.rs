@@ -8,16 +8,23 @@
 //!   - host-id: 1-254 (unique per host)
 //!   - container-id: 10-254 (first 10 reserved for infrastructure)
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::net::Ipv4Addr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use fs2::FileExt;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{CniError, CniErrorCode};
+use crate::logger;
+use crate::metrics;
+use crate::portmap::PortMapping;
 
 /// Default IPAM data directory
 pub const IPAM_DIR: &str = "/var/lib/flatnet/ipam";
@@ -25,9 +32,27 @@ pub const IPAM_DIR: &str = "/var/lib/flatnet/ipam";
 /// IPAM allocations file
 pub const ALLOCATIONS_FILE: &str = "allocations.json";
 
+/// Copy of the state as of the last checkpoint
+pub const BACKUP_FILE: &str = "allocations.backup.json";
+
+/// States committed since the last checkpoint, one JSON document per line
+pub const JOURNAL_FILE: &str = "allocations.journal";
+
+/// Journal entries written before the backup is refreshed
+const CHECKPOINT_INTERVAL: usize = 32;
+
+/// How long a released IP is kept for its container name
+pub const DEFAULT_STICKY_COOLDOWN: Duration = Duration::from_secs(600);
+
 /// Lock file for concurrent access
 pub const LOCK_FILE: &str = ".lock";
 
+/// Default time to wait for the IPAM lock
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Interval between attempts to take the IPAM lock
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Default host ID (for single-host deployments)
 pub const DEFAULT_HOST_ID: u8 = 1;
 
@@ -44,8 +69,42 @@ pub const MULTIHOST_CONTAINER_START: u8 = 10;
 /// Container ID end for multihost
 pub const MULTIHOST_CONTAINER_END: u8 = 254;
 
+/// IPAM directory from the network config's `ipam.dataDir`
+static DATA_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Lock timeout from the network config's `ipam.lockTimeoutMs`
+static LOCK_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+
+/// Sequence number making temp file names unique within the process
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Apply the network config's `ipam.dataDir` (default: IPAM_DIR) and
+/// `ipam.lockTimeoutMs` (default: DEFAULT_LOCK_TIMEOUT)
+pub fn configure(data_dir: Option<&str>, lock_timeout_ms: Option<u64>) {
+    *DATA_DIR.lock().unwrap_or_else(|e| e.into_inner()) = data_dir.map(PathBuf::from);
+    *LOCK_TIMEOUT.lock().unwrap_or_else(|e| e.into_inner()) =
+        lock_timeout_ms.map(Duration::from_millis);
+}
+
+/// Directory holding the IPAM state
+fn ipam_dir() -> PathBuf {
+    DATA_DIR
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(IPAM_DIR))
+}
+
+/// How long to wait for the IPAM lock
+fn lock_timeout() -> Duration {
+    LOCK_TIMEOUT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .unwrap_or(DEFAULT_LOCK_TIMEOUT)
+}
+
 /// IPAM state stored in file
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct IpamState {
     /// Subnet in CIDR notation
     pub subnet: String,
@@ -69,6 +128,31 @@ pub struct IpamState {
 
     /// Map of container ID to allocated IP
     pub allocations: HashMap<String, String>,
+
+    /// Map of container ID to container name, for sticky IPs
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub names: HashMap<String, String>,
+
+    /// Map of container name to the IP it released last
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub released: HashMap<String, ReleasedIp>,
+
+    /// Last dynamically allocated IP; the next search starts after it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_allocated: Option<String>,
+
+    /// Map of container ID to its host port mappings
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub port_mappings: HashMap<String, Vec<PortMapping>>,
+}
+
+/// An IP released by a named container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleasedIp {
+    pub ip: String,
+
+    /// Unix time of the release
+    pub released_at: u64,
 }
 
 fn default_host_id() -> u8 {
@@ -85,6 +169,10 @@ impl Default for IpamState {
             host_id: DEFAULT_HOST_ID,
             multihost: false,
             allocations: HashMap::new(),
+            names: HashMap::new(),
+            released: HashMap::new(),
+            last_allocated: None,
+            port_mappings: HashMap::new(),
         }
     }
 }
@@ -105,6 +193,10 @@ impl IpamState {
             host_id,
             multihost: true,
             allocations: HashMap::new(),
+            names: HashMap::new(),
+            released: HashMap::new(),
+            last_allocated: None,
+            port_mappings: HashMap::new(),
         }
     }
 
@@ -112,6 +204,55 @@ impl IpamState {
     pub fn is_multihost(&self) -> bool {
         self.multihost
     }
+
+    /// Number of addresses in the allocation range
+    pub fn capacity(&self) -> usize {
+        match (
+            self.range_start.parse::<Ipv4Addr>(),
+            self.range_end.parse::<Ipv4Addr>(),
+        ) {
+            (Ok(start), Ok(end)) => u32::from(end)
+                .checked_sub(u32::from(start))
+                .map_or(0, |n| n as usize + 1),
+            _ => 0,
+        }
+    }
+
+    /// Report pool usage to the metrics
+    fn record_usage(&self) {
+        metrics::ip_usage(self.allocations.len(), self.capacity());
+    }
+}
+
+/// What an ADD asks IPAM for
+pub struct IpRequest<'a> {
+    pub container_id: &'a str,
+
+    /// Host ID for the multihost scheme
+    pub host_id: Option<u8>,
+
+    /// Container name, used to look up reservations
+    pub name: Option<&'a str>,
+
+    /// Static IP from `CNI_ARGS` or the `ips` capability
+    pub static_ip: Option<Ipv4Addr>,
+
+    /// Container name -> reserved IP
+    pub reservations: &'a HashMap<String, Ipv4Addr>,
+
+    /// How long released IPs are kept for their container name
+    pub cooldown: Duration,
+}
+
+impl IpRequest<'_> {
+    /// The static IP if given, else the IP reserved for the container's name
+    fn wanted_ip(&self) -> Option<Ipv4Addr> {
+        self.static_ip.or_else(|| {
+            self.name
+                .and_then(|name| self.reservations.get(name))
+                .copied()
+        })
+    }
 }
 
 /// Result of IP allocation
@@ -129,42 +270,31 @@ pub struct IpAllocation {
     pub host_id: u8,
 }
 
-/// Ensure IPAM directory and files exist
+/// Ensure the IPAM directory exists
 pub fn ensure_ipam_dir() -> Result<(), CniError> {
-    let dir = Path::new(IPAM_DIR);
+    let dir = ipam_dir();
 
     if !dir.exists() {
-        fs::create_dir_all(dir).map_err(|e| {
+        fs::create_dir_all(&dir).map_err(|e| {
             CniError::new(CniErrorCode::IpamFailure, "failed to create IPAM directory")
                 .with_details(&e.to_string())
         })?;
     }
 
-    let allocations_path = dir.join(ALLOCATIONS_FILE);
-    if !allocations_path.exists() {
-        let state = IpamState::default();
-        let json = serde_json::to_string_pretty(&state).map_err(|e| {
-            CniError::new(CniErrorCode::IpamFailure, "failed to serialize IPAM state")
-                .with_details(&e.to_string())
-        })?;
-
-        fs::write(&allocations_path, json).map_err(|e| {
-            CniError::new(CniErrorCode::IpamFailure, "failed to write IPAM allocations file")
-                .with_details(&e.to_string())
-        })?;
-    }
-
     Ok(())
 }
 
 /// Execute a function while holding the IPAM lock
+///
+/// Gives up with `TryAgainLater` if the lock is not free within the
+/// configured timeout, so a stuck invocation cannot block every other one.
 fn with_ipam_lock<T, F>(f: F) -> Result<T, CniError>
 where
     F: FnOnce() -> Result<T, CniError>,
 {
     ensure_ipam_dir()?;
 
-    let lock_path = Path::new(IPAM_DIR).join(LOCK_FILE);
+    let lock_path = ipam_dir().join(LOCK_FILE);
     let lock_file = OpenOptions::new()
         .create(true)
         .write(true)
@@ -175,10 +305,7 @@ where
                 .with_details(&e.to_string())
         })?;
 
-    lock_file.lock_exclusive().map_err(|e| {
-        CniError::new(CniErrorCode::IpamFailure, "failed to acquire IPAM lock")
-            .with_details(&e.to_string())
-    })?;
+    acquire_lock(&lock_file, lock_timeout())?;
 
     let result = f();
 
@@ -190,61 +317,260 @@ where
     result
 }
 
-/// Load IPAM state from file
+/// Take the exclusive lock, polling until `timeout` has passed
+fn acquire_lock(lock_file: &File, timeout: Duration) -> Result<(), CniError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match lock_file.try_lock_exclusive() {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => {}
+            Err(e) => {
+                return Err(
+                    CniError::new(CniErrorCode::IpamFailure, "failed to acquire IPAM lock")
+                        .with_details(&e.to_string()),
+                )
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Err(CniError::new(
+                CniErrorCode::TryAgainLater,
+                "timed out waiting for the IPAM lock",
+            )
+            .with_details(&format!(
+                "another invocation has held {} for more than {}ms",
+                ipam_dir().join(LOCK_FILE).display(),
+                timeout.as_millis()
+            )));
+        }
+        thread::sleep(LOCK_POLL_INTERVAL);
+    }
+}
+
+/// Whether no state has ever been written (no allocations file, journal or
+/// backup)
+///
+/// A missing allocations file next to a journal or backup is not first use:
+/// the state is recovered from them instead of starting over.
+fn is_first_use() -> bool {
+    let dir = ipam_dir();
+    [ALLOCATIONS_FILE, JOURNAL_FILE, BACKUP_FILE]
+        .iter()
+        .all(|name| !dir.join(name).exists())
+}
+
+/// Load IPAM state, recovering it if the allocations file is missing or
+/// corrupt
+///
+/// On first use this is the empty single-host state; it is only written once
+/// something changes.
 fn load_state() -> Result<IpamState, CniError> {
-    let path = Path::new(IPAM_DIR).join(ALLOCATIONS_FILE);
+    if is_first_use() {
+        return Ok(IpamState::default());
+    }
 
-    let mut file = File::open(&path).map_err(|e| {
-        CniError::new(CniErrorCode::IpamFailure, "failed to open IPAM allocations file")
-            .with_details(&e.to_string())
-    })?;
+    match read_state(&ipam_dir().join(ALLOCATIONS_FILE)) {
+        Ok(state) => Ok(state),
+        Err(e) => recover_state(&e.to_string()),
+    }
+}
 
+/// Read and parse a state file
+fn read_state(path: &Path) -> io::Result<IpamState> {
     let mut contents = String::new();
-    file.read_to_string(&mut contents).map_err(|e| {
-        CniError::new(CniErrorCode::IpamFailure, "failed to read IPAM allocations file")
-            .with_details(&e.to_string())
-    })?;
+    File::open(path)?.read_to_string(&mut contents)?;
+    serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
 
-    serde_json::from_str(&contents).map_err(|e| {
-        CniError::new(CniErrorCode::IpamFailure, "failed to parse IPAM allocations file")
-            .with_details(&e.to_string())
-    })
+/// Rebuild the state from the journal or the backup
+///
+/// The last intact journal entry is the most recent committed state; the
+/// backup is the state at the last checkpoint. A corrupt file is copied
+/// aside for inspection and only replaced once the recovered state is on
+/// disk, so a crash during recovery loses nothing.
+fn recover_state(error: &str) -> Result<IpamState, CniError> {
+    let dir = ipam_dir();
+    let recovered = match last_journal_entry()? {
+        Some(state) => Some((state, JOURNAL_FILE)),
+        None => read_state(&dir.join(BACKUP_FILE))
+            .ok()
+            .map(|state| (state, BACKUP_FILE)),
+    };
+
+    let Some((state, source)) = recovered else {
+        return Err(CniError::new(
+            CniErrorCode::IpamFailure,
+            "IPAM allocations file is unreadable and no journal or backup is usable",
+        )
+        .with_details(&format!(
+            "{}: {}",
+            dir.join(ALLOCATIONS_FILE).display(),
+            error
+        )));
+    };
+
+    let path = dir.join(ALLOCATIONS_FILE);
+    let quarantine = dir.join(format!("{}.corrupt-{}", ALLOCATIONS_FILE, unix_now()));
+    let kept = match fs::copy(&path, &quarantine) {
+        Ok(_) => format!(" (corrupt copy kept as {})", quarantine.display()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(CniError::new(
+                CniErrorCode::IpamFailure,
+                "failed to copy corrupt IPAM allocations file",
+            )
+            .with_details(&e.to_string()))
+        }
+    };
+    checkpoint(&state)?;
+
+    logger::warn(&format!(
+        "IPAM: allocations file was unreadable ({}), recovered {} allocations from {}{}",
+        error,
+        state.allocations.len(),
+        source,
+        kept
+    ));
+    Ok(state)
 }
 
-/// Save IPAM state to file (atomic write using rename)
+/// Last journal entry that parses; a torn final line is skipped
+fn last_journal_entry() -> Result<Option<IpamState>, CniError> {
+    let contents = match fs::read_to_string(ipam_dir().join(JOURNAL_FILE)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(
+                CniError::new(CniErrorCode::IpamFailure, "failed to read IPAM journal")
+                    .with_details(&e.to_string()),
+            )
+        }
+    };
+
+    Ok(contents
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str(line).ok()))
+}
+
+/// Commit IPAM state
+///
+/// The state is appended to the journal before the allocations file is
+/// replaced. Every `CHECKPOINT_INTERVAL` commits the backup is refreshed
+/// and the journal emptied instead.
 fn save_state(state: &IpamState) -> Result<(), CniError> {
-    let path = Path::new(IPAM_DIR).join(ALLOCATIONS_FILE);
-    let tmp_path = Path::new(IPAM_DIR).join(".allocations.json.tmp");
+    let dir = ipam_dir();
+    let entries = match fs::read_to_string(dir.join(JOURNAL_FILE)) {
+        Ok(contents) => contents.lines().count(),
+        Err(_) => 0,
+    };
+    if entries >= CHECKPOINT_INTERVAL || !dir.join(BACKUP_FILE).exists() {
+        return checkpoint(state);
+    }
 
-    let json = serde_json::to_string_pretty(state).map_err(|e| {
+    let mut line = serde_json::to_string(state).map_err(|e| {
         CniError::new(CniErrorCode::IpamFailure, "failed to serialize IPAM state")
             .with_details(&e.to_string())
     })?;
+    line.push('\n');
 
-    // Write to temporary file first
-    let mut file = File::create(&tmp_path).map_err(|e| {
-        CniError::new(CniErrorCode::IpamFailure, "failed to create IPAM temp file")
-            .with_details(&e.to_string())
-    })?;
+    let mut journal = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(JOURNAL_FILE))
+        .map_err(|e| {
+            CniError::new(CniErrorCode::IpamFailure, "failed to open IPAM journal")
+                .with_details(&e.to_string())
+        })?;
+    journal
+        .write_all(line.as_bytes())
+        .and_then(|()| journal.sync_data())
+        .map_err(|e| {
+            CniError::new(CniErrorCode::IpamFailure, "failed to write IPAM journal")
+                .with_details(&e.to_string())
+        })?;
 
-    file.write_all(json.as_bytes()).map_err(|e| {
-        CniError::new(CniErrorCode::IpamFailure, "failed to write IPAM temp file")
-            .with_details(&e.to_string())
-    })?;
+    write_state(&dir.join(ALLOCATIONS_FILE), state)
+}
+
+/// Write the allocations file and the backup, then empty the journal
+fn checkpoint(state: &IpamState) -> Result<(), CniError> {
+    let dir = ipam_dir();
+    write_state(&dir.join(ALLOCATIONS_FILE), state)?;
+    write_state(&dir.join(BACKUP_FILE), state)?;
+
+    File::create(dir.join(JOURNAL_FILE))
+        .and_then(|journal| journal.sync_all())
+        .and_then(|()| sync_dir(&dir))
+        .map_err(|e| {
+            CniError::new(CniErrorCode::IpamFailure, "failed to truncate IPAM journal")
+                .with_details(&e.to_string())
+        })
+}
 
-    // Ensure data is flushed to disk
-    file.sync_all().map_err(|e| {
-        CniError::new(CniErrorCode::IpamFailure, "failed to sync IPAM temp file")
+/// Atomically replace a state file
+///
+/// Writes a temp file unique to this process, syncs it, renames it over
+/// `path` and syncs the directory so the rename itself is durable.
+fn write_state(path: &Path, state: &IpamState) -> Result<(), CniError> {
+    let json = serde_json::to_string_pretty(state).map_err(|e| {
+        CniError::new(CniErrorCode::IpamFailure, "failed to serialize IPAM state")
             .with_details(&e.to_string())
     })?;
 
-    // Atomic rename
-    fs::rename(&tmp_path, &path).map_err(|e| {
-        CniError::new(CniErrorCode::IpamFailure, "failed to rename IPAM allocations file")
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(ALLOCATIONS_FILE);
+    let tmp_path = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        name,
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(
+            CniError::new(CniErrorCode::IpamFailure, "failed to write IPAM temp file")
+                .with_details(&e.to_string()),
+        );
+    }
+
+    if let Err(e) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(CniError::new(
+            CniErrorCode::IpamFailure,
+            "failed to rename IPAM allocations file",
+        )
+        .with_details(&e.to_string()));
+    }
+
+    sync_dir(dir).map_err(|e| {
+        CniError::new(CniErrorCode::IpamFailure, "failed to sync IPAM directory")
             .with_details(&e.to_string())
-    })?;
+    })
+}
 
-    Ok(())
+/// Flush directory entries (renames, creations) to disk
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+/// Current Unix time in seconds
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
 }
 
 /// Parse an IP address
@@ -284,19 +610,51 @@ pub fn allocate(container_id: &str) -> Result<IpAllocation, CniError> {
 /// If host_id is provided, uses multihost IP scheme: 10.100.<host-id>.<container-id>
 /// Otherwise, uses legacy single-host scheme.
 pub fn allocate_with_host_id(container_id: &str, host_id: Option<u8>) -> Result<IpAllocation, CniError> {
+    allocate_request(&IpRequest {
+        container_id,
+        host_id,
+        name: None,
+        static_ip: None,
+        reservations: &HashMap::new(),
+        cooldown: DEFAULT_STICKY_COOLDOWN,
+    })
+}
+
+/// Allocate the requested IP, the container's reserved IP, the IP its name
+/// held before, or the next free one
+///
+/// A static IP must lie in the subnet and be neither allocated to another
+/// container nor reserved for another name. Reserved IPs are never handed
+/// out dynamically.
+pub fn allocate_request(request: &IpRequest) -> Result<IpAllocation, CniError> {
+    let container_id = request.container_id;
     with_ipam_lock(|| {
-        let mut state = load_or_init_state(host_id)?;
+        let mut state = load_or_init_state(request.host_id)?;
+        state.record_usage();
+
+        let prefix_len = parse_prefix_len(&state.subnet)?;
+        let gateway = parse_ip(&state.gateway)?;
+        let wanted = request.wanted_ip();
 
         // Check if container already has an allocation
         if let Some(ip_str) = state.allocations.get(container_id) {
             let ip = parse_ip(ip_str)?;
-            let prefix_len = parse_prefix_len(&state.subnet)?;
-            let gateway = parse_ip(&state.gateway)?;
+            if let Some(wanted) = wanted.filter(|w| *w != ip) {
+                return Err(CniError::new(
+                    CniErrorCode::IpamFailure,
+                    &format!(
+                        "container {} already has IP {}, not the requested {}",
+                        short_id(container_id),
+                        ip,
+                        wanted
+                    ),
+                ));
+            }
 
-            eprintln!(
-                "flatnet IPAM: container {} already has IP {}",
+            logger::debug(&format!(
+                "IPAM: container {} already has IP {}",
                 container_id, ip
-            );
+            ));
 
             return Ok(IpAllocation {
                 ip,
@@ -306,67 +664,211 @@ pub fn allocate_with_host_id(container_id: &str, host_id: Option<u8>) -> Result<
             });
         }
 
-        // Parse range
-        let range_start = parse_ip(&state.range_start)?;
-        let range_end = parse_ip(&state.range_end)?;
-        let prefix_len = parse_prefix_len(&state.subnet)?;
-        let gateway = parse_ip(&state.gateway)?;
+        let (ip, source) = match (wanted, sticky_ip(&state, request)) {
+            (Some(ip), _) => {
+                check_static_ip(&state, request, ip)?;
+                let source = if request.static_ip.is_some() {
+                    "static"
+                } else {
+                    "reserved"
+                };
+                (ip, source)
+            }
+            (None, Some(ip)) => (ip, "sticky"),
+            (None, None) => {
+                let ip = next_free_ip(&state, request.reservations, request.cooldown)?;
+                state.last_allocated = Some(ip.to_string());
+                (ip, "dynamic")
+            }
+        };
 
-        // Collect used IPs
-        let used_ips: std::collections::HashSet<Ipv4Addr> = state
-            .allocations
-            .values()
-            .filter_map(|ip_str| ip_str.parse().ok())
-            .collect();
+        let ip_str = ip.to_string();
+        if let Some(name) = request.name {
+            state
+                .names
+                .insert(container_id.to_string(), name.to_string());
+            state.released.remove(name);
+        }
+        // Another name's claim on the IP ends once it is reused
+        state.released.retain(|_, released| released.ip != ip_str);
+        state.allocations.insert(container_id.to_string(), ip_str);
+        save_state(&state)?;
+        state.record_usage();
 
-        // Find first available IP in range
-        let start: u32 = range_start.into();
-        let end: u32 = range_end.into();
-
-        for ip_int in start..=end {
-            let ip = Ipv4Addr::from(ip_int);
-            if !used_ips.contains(&ip) {
-                // Found an available IP
-                state
-                    .allocations
-                    .insert(container_id.to_string(), ip.to_string());
-                save_state(&state)?;
-
-                let mode = if state.multihost { "multihost" } else { "single-host" };
-                eprintln!(
-                    "flatnet IPAM ({}): allocated {} to container {} (host_id={})",
-                    mode, ip, container_id, state.host_id
-                );
+        let mode = if state.multihost { "multihost" } else { "single-host" };
+        logger::info(&format!(
+            "IPAM ({}): allocated {} IP {} to container {} (host_id={})",
+            mode, source, ip, container_id, state.host_id
+        ));
 
-                return Ok(IpAllocation {
-                    ip,
-                    prefix_len,
-                    gateway,
-                    host_id: state.host_id,
-                });
-            }
+        Ok(IpAllocation {
+            ip,
+            prefix_len,
+            gateway,
+            host_id: state.host_id,
+        })
+    })
+}
+
+/// Next free address after the last dynamic allocation (round-robin)
+///
+/// Skips allocated and reserved IPs. An IP released by a named container
+/// less than `cooldown` ago is only used when nothing else is free, the
+/// longest-released first.
+fn next_free_ip(
+    state: &IpamState,
+    reservations: &HashMap<String, Ipv4Addr>,
+    cooldown: Duration,
+) -> Result<Ipv4Addr, CniError> {
+    let start: u32 = parse_ip(&state.range_start)?.into();
+    let end: u32 = parse_ip(&state.range_end)?.into();
+
+    // Collect used IPs
+    let mut used_ips: HashSet<Ipv4Addr> = state
+        .allocations
+        .values()
+        .filter_map(|ip_str| ip_str.parse().ok())
+        .collect();
+    used_ips.extend(reservations.values());
+
+    let now = unix_now();
+    let cooling: HashMap<Ipv4Addr, u64> = state
+        .released
+        .values()
+        .filter(|released| now.saturating_sub(released.released_at) < cooldown.as_secs())
+        .filter_map(|released| Some((released.ip.parse().ok()?, released.released_at)))
+        .collect();
+
+    let after = state
+        .last_allocated
+        .as_deref()
+        .and_then(|ip| ip.parse::<Ipv4Addr>().ok())
+        .map(u32::from)
+        .filter(|ip| (start..end).contains(ip))
+        .map_or(start, |ip| ip + 1);
+
+    let mut cooling_free = Vec::new();
+    for ip in (after..=end).chain(start..after).map(Ipv4Addr::from) {
+        if used_ips.contains(&ip) {
+            continue;
         }
+        match cooling.get(&ip) {
+            None => return Ok(ip),
+            Some(&released_at) => cooling_free.push((released_at, ip)),
+        }
+    }
 
-        Err(CniError::new(
+    cooling_free
+        .into_iter()
+        .min_by_key(|(released_at, _)| *released_at)
+        .map(|(_, ip)| ip)
+        .ok_or_else(|| {
+            CniError::new(
+                CniErrorCode::IpamFailure,
+                "no available IP addresses in range",
+            )
+        })
+}
+
+/// The IP the container's name released earlier, if it can still be given out
+fn sticky_ip(state: &IpamState, request: &IpRequest) -> Option<Ipv4Addr> {
+    let released = state.released.get(request.name?)?;
+    let ip = released.ip.parse().ok()?;
+    check_static_ip(state, request, ip).ok().map(|()| ip)
+}
+
+/// Reject a static or reserved IP that cannot be given to the container
+fn check_static_ip(state: &IpamState, request: &IpRequest, ip: Ipv4Addr) -> Result<(), CniError> {
+    let gateway = parse_ip(&state.gateway)?;
+    let prefix_len = parse_prefix_len(&state.subnet)?;
+    let network = parse_ip(state.subnet.split('/').next().unwrap_or_default())?;
+    let mask = u32::MAX
+        .checked_shl(32 - u32::from(prefix_len))
+        .unwrap_or(0);
+    let network = u32::from(network) & mask;
+
+    let ip_int = u32::from(ip);
+    if ip_int & mask != network || ip_int == network || ip_int == network | !mask {
+        return Err(CniError::new(
+            CniErrorCode::InvalidNetworkConfig,
+            &format!(
+                "requested IP {} is not a host address in subnet {}",
+                ip, state.subnet
+            ),
+        ));
+    }
+    if ip == gateway {
+        return Err(CniError::new(
+            CniErrorCode::InvalidNetworkConfig,
+            &format!("requested IP {} is the subnet gateway", ip),
+        ));
+    }
+
+    if let Some((name, _)) = request
+        .reservations
+        .iter()
+        .find(|(name, reserved)| **reserved == ip && Some(name.as_str()) != request.name)
+    {
+        return Err(CniError::new(
             CniErrorCode::IpamFailure,
-            "no available IP addresses in range",
-        ))
-    })
+            &format!("requested IP {} is reserved for container {}", ip, name),
+        ));
+    }
+
+    let ip_str = ip.to_string();
+    if let Some((owner, _)) = state.allocations.iter().find(|(_, a)| **a == ip_str) {
+        return Err(CniError::new(
+            CniErrorCode::IpamFailure,
+            &format!(
+                "requested IP {} is already allocated to container {}",
+                ip,
+                short_id(owner)
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Container ID as shown by `podman ps`
+fn short_id(container_id: &str) -> &str {
+    container_id.get(..12).unwrap_or(container_id)
+}
+
+/// Parse the IPAM config's `reservations` (container name -> IP)
+pub fn parse_reservations(
+    reservations: Option<&HashMap<String, String>>,
+) -> Result<HashMap<String, Ipv4Addr>, CniError> {
+    let mut parsed: HashMap<String, Ipv4Addr> = HashMap::new();
+    for (name, ip) in reservations.into_iter().flatten() {
+        let ip: Ipv4Addr = ip.parse().map_err(|_| {
+            CniError::new(
+                CniErrorCode::InvalidNetworkConfig,
+                &format!("invalid IP {} reserved for {}", ip, name),
+            )
+        })?;
+        if let Some((other, _)) = parsed.iter().find(|(_, reserved)| **reserved == ip) {
+            return Err(CniError::new(
+                CniErrorCode::InvalidNetworkConfig,
+                &format!("IP {} is reserved for both {} and {}", ip, other, name),
+            ));
+        }
+        parsed.insert(name.clone(), ip);
+    }
+    Ok(parsed)
 }
 
 /// Load existing state or initialize for the given host ID
 fn load_or_init_state(host_id: Option<u8>) -> Result<IpamState, CniError> {
-    let path = Path::new(IPAM_DIR).join(ALLOCATIONS_FILE);
-
-    if path.exists() {
+    if !is_first_use() {
         let state = load_state()?;
         // If host_id is provided and differs from stored state, warn but continue
         if let Some(new_id) = host_id {
             if state.host_id != new_id && !state.allocations.is_empty() {
-                eprintln!(
-                    "flatnet IPAM: WARNING - host_id changed from {} to {}, existing allocations preserved",
+                logger::warn(&format!(
+                    "IPAM: host_id changed from {} to {}, existing allocations preserved",
                     state.host_id, new_id
-                );
+                ));
             }
         }
         Ok(state)
@@ -377,12 +879,12 @@ fn load_or_init_state(host_id: Option<u8>) -> Result<IpamState, CniError> {
             None => IpamState::default(),
         };
         save_state(&state)?;
-        eprintln!(
-            "flatnet IPAM: initialized {} mode (host_id={}, subnet={})",
+        logger::info(&format!(
+            "IPAM: initialized {} mode (host_id={}, subnet={})",
             if state.multihost { "multihost" } else { "single-host" },
             state.host_id,
             state.subnet
-        );
+        ));
         Ok(state)
     }
 }
@@ -393,22 +895,50 @@ pub fn release(container_id: &str) -> Result<(), CniError> {
         let mut state = load_state()?;
 
         if let Some(ip) = state.allocations.remove(container_id) {
+            state.port_mappings.remove(container_id);
+            // Keep the IP for the container's name until the cooldown passes
+            if let Some(name) = state.names.remove(container_id) {
+                state.released.insert(
+                    name,
+                    ReleasedIp {
+                        ip: ip.clone(),
+                        released_at: unix_now(),
+                    },
+                );
+            }
             save_state(&state)?;
-            eprintln!(
-                "flatnet IPAM: released {} from container {}",
+            state.record_usage();
+            logger::info(&format!(
+                "IPAM: released {} from container {}",
                 ip, container_id
-            );
+            ));
         } else {
-            eprintln!(
-                "flatnet IPAM: container {} had no allocation (idempotent)",
+            logger::debug(&format!(
+                "IPAM: container {} had no allocation (idempotent)",
                 container_id
-            );
+            ));
         }
 
         Ok(())
     })
 }
 
+/// Run `f` on the state while holding the lock, saving it if `f` changed it
+pub fn update_state<T, F>(f: F) -> Result<T, CniError>
+where
+    F: FnOnce(&mut IpamState) -> Result<T, CniError>,
+{
+    with_ipam_lock(|| {
+        let mut state = load_state()?;
+        let before = state.clone();
+        let result = f(&mut state)?;
+        if state != before {
+            save_state(&state)?;
+        }
+        Ok(result)
+    })
+}
+
 /// Get the current allocation for a container
 pub fn get_allocation(container_id: &str) -> Result<Option<IpAllocation>, CniError> {
     with_ipam_lock(|| {
@@ -475,6 +1005,40 @@ pub fn has_allocation(container_id: &str) -> Result<bool, CniError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::process::{Command, Stdio};
+
+    /// Worker parameters: `<dir>,<seed>,<index>,<ops>`
+    const WORKER_ENV: &str = "FLATNET_IPAM_TEST_WORKER";
+
+    /// The IPAM directory is process-wide, so tests using it run one at a time
+    static DIR_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Point IPAM and the logger at a fresh directory
+    fn use_dir(name: &str, lock_timeout_ms: Option<u64>) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("flatnet-ipam-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        configure(dir.to_str(), lock_timeout_ms);
+        logger::configure(dir.join("cni.log").to_str(), None);
+        dir
+    }
+
+    fn allocated_ip(container_id: &str) -> Option<Ipv4Addr> {
+        get_allocation(container_id).unwrap().map(|a| a.ip)
+    }
+
+    /// xorshift64, enough to drive reproducible random schedules
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
 
     #[test]
     fn test_default_state() {
@@ -497,6 +1061,7 @@ mod tests {
         assert_eq!(state.host_id, 5);
         assert!(state.multihost);
         assert!(state.allocations.is_empty());
+        assert_eq!(state.capacity(), 245);
     }
 
     #[test]
@@ -526,4 +1091,388 @@ mod tests {
         assert_eq!(parse_prefix_len("10.100.1.0/24").unwrap(), 24);
         assert!(parse_prefix_len("invalid").is_err());
     }
+
+    fn request<'a>(
+        container_id: &'a str,
+        name: Option<&'a str>,
+        static_ip: Option<&str>,
+        reservations: &'a HashMap<String, Ipv4Addr>,
+    ) -> IpRequest<'a> {
+        IpRequest {
+            container_id,
+            host_id: None,
+            name,
+            static_ip: static_ip.map(|ip| ip.parse().unwrap()),
+            reservations,
+            cooldown: DEFAULT_STICKY_COOLDOWN,
+        }
+    }
+
+    #[test]
+    fn test_sticky_and_round_robin() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = use_dir("sticky", None);
+        let state = IpamState {
+            range_end: "10.87.1.5".to_string(),
+            ..IpamState::default()
+        };
+        fs::write(
+            dir.join(ALLOCATIONS_FILE),
+            serde_json::to_string(&state).unwrap(),
+        )
+        .unwrap();
+        let none = HashMap::new();
+        let allocate = |id, name| allocate_request(&request(id, name, None, &none));
+        let ip = |id, name| allocate(id, name).unwrap().ip.to_string();
+
+        assert_eq!(ip("web-1", Some("web")), "10.87.1.2");
+        assert_eq!(ip("b", None), "10.87.1.3");
+        release("web-1").unwrap();
+
+        // Round-robin moves on, and .2 cools down for "web"
+        assert_eq!(ip("c", Some("c")), "10.87.1.4");
+        assert_eq!(ip("d", None), "10.87.1.5");
+        assert_eq!(ip("web-2", Some("web")), "10.87.1.2");
+
+        // Unnamed releases are free right away; the search wraps around
+        release("b").unwrap();
+        assert_eq!(ip("e", None), "10.87.1.3");
+
+        // A cooling IP is reused only when nothing else is free, which ends
+        // the old name's claim on it
+        release("c").unwrap();
+        assert_eq!(ip("f", None), "10.87.1.4");
+        let err = allocate("c-2", Some("c")).err().unwrap();
+        assert!(err.message().contains("no available"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_static_and_reserved_ips() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = use_dir("static", None);
+        let table: HashMap<String, String> = [("db".to_string(), "10.87.1.3".to_string())].into();
+        let reservations = parse_reservations(Some(&table)).unwrap();
+        let allocate = |id, name, ip| allocate_request(&request(id, name, ip, &reservations));
+        let ip = |id, name, static_ip| allocate(id, name, static_ip).unwrap().ip.to_string();
+
+        // Reserved IPs are skipped by dynamic allocation
+        assert_eq!(ip("a", None, None), "10.87.1.2");
+        assert_eq!(ip("b", None, None), "10.87.1.4");
+        assert_eq!(ip("c", Some("db"), None), "10.87.1.3");
+
+        // Static IPs may lie outside the dynamic range but not outside the subnet
+        let d = allocate("d", None, Some("10.87.1.255"));
+        assert_eq!(d.err().unwrap().code(), CniErrorCode::InvalidNetworkConfig);
+        let d = allocate("d", None, Some("10.87.2.5"));
+        assert!(d.err().unwrap().message().contains("not a host address"));
+        let d = allocate("d", None, Some("10.87.1.1"));
+        assert!(d.err().unwrap().message().contains("gateway"));
+        assert_eq!(ip("d", None, Some("10.87.1.200")), "10.87.1.200");
+
+        let e = allocate("e", None, Some("10.87.1.200")).err().unwrap();
+        assert_eq!(
+            e.message(),
+            "requested IP 10.87.1.200 is already allocated to container d"
+        );
+        let e = allocate("e", Some("web"), Some("10.87.1.3")).err().unwrap();
+        assert_eq!(
+            e.message(),
+            "requested IP 10.87.1.3 is reserved for container db"
+        );
+
+        // A repeated ADD must ask for the IP the container already has
+        assert!(allocate("d", None, Some("10.87.1.200")).is_ok());
+        assert!(allocate("d", None, Some("10.87.1.201")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_reservations() {
+        let table: HashMap<String, String> = [
+            ("web".to_string(), "10.87.1.20".to_string()),
+            ("api".to_string(), "10.87.1.20".to_string()),
+        ]
+        .into();
+        let err = parse_reservations(Some(&table)).unwrap_err();
+        assert!(err.message().contains("reserved for both"));
+
+        let table: HashMap<String, String> = [("web".to_string(), "10.87.1".to_string())].into();
+        assert!(parse_reservations(Some(&table)).is_err());
+        assert!(parse_reservations(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recovers_from_journal() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = use_dir("journal", None);
+
+        let ips: Vec<Ipv4Addr> = ["a", "b", "c"]
+            .iter()
+            .map(|id| allocate(id).unwrap().ip)
+            .collect();
+        fs::write(dir.join(ALLOCATIONS_FILE), "{\"subnet\": \"10.87").unwrap();
+        let mut journal = OpenOptions::new()
+            .append(true)
+            .open(dir.join(JOURNAL_FILE))
+            .unwrap();
+        journal.write_all(b"{\"subnet\": \"10.").unwrap();
+
+        // The next ADD recovers instead of failing, and keeps earlier allocations
+        let d = allocate("d").unwrap().ip;
+        assert!(!ips.contains(&d));
+        assert_eq!(allocated_ip("c"), Some(ips[2]));
+        assert_eq!(get_all_allocations().unwrap().len(), 4);
+
+        let quarantined = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().contains(".corrupt-"))
+            .count();
+        assert_eq!(quarantined, 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recovers_from_backup() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = use_dir("backup", None);
+
+        let a = allocate("a").unwrap().ip;
+        checkpoint(&load_state().unwrap()).unwrap();
+        fs::write(dir.join(ALLOCATIONS_FILE), "").unwrap();
+        fs::write(dir.join(JOURNAL_FILE), "{\"subnet\":").unwrap();
+
+        assert_eq!(allocated_ip("a"), Some(a));
+        assert!(has_allocation("a").unwrap());
+
+        // Without a journal or backup there is nothing safe to fall back to
+        fs::write(dir.join(ALLOCATIONS_FILE), "not json").unwrap();
+        fs::remove_file(dir.join(JOURNAL_FILE)).unwrap();
+        fs::remove_file(dir.join(BACKUP_FILE)).unwrap();
+        let err = allocate("b").err().unwrap();
+        assert_eq!(err.code(), CniErrorCode::IpamFailure);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recovery_keeps_releases() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = use_dir("releases", None);
+
+        // The backup still has both allocations, only the journal has the release
+        allocate("a").unwrap();
+        let b = allocate("b").unwrap().ip;
+        checkpoint(&load_state().unwrap()).unwrap();
+        release("a").unwrap();
+        fs::write(dir.join(ALLOCATIONS_FILE), "not json").unwrap();
+
+        assert!(!has_allocation("a").unwrap());
+        assert_eq!(allocated_ip("b"), Some(b));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recovers_missing_allocations_file() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = use_dir("missing", None);
+
+        // A crash after the corrupt file was moved away but before the
+        // recovered state was written must not reset IPAM to defaults
+        let a = allocate("a").unwrap().ip;
+        checkpoint(&load_state().unwrap()).unwrap();
+        let b = allocate("b").unwrap().ip;
+        fs::remove_file(dir.join(ALLOCATIONS_FILE)).unwrap();
+
+        assert_eq!(allocated_ip("a"), Some(a));
+        assert_eq!(allocated_ip("b"), Some(b));
+        assert!(dir.join(ALLOCATIONS_FILE).exists());
+
+        // Same from the backup alone
+        fs::remove_file(dir.join(ALLOCATIONS_FILE)).unwrap();
+        fs::remove_file(dir.join(JOURNAL_FILE)).unwrap();
+        let c = allocate("c").unwrap().ip;
+        assert!(![a, b].contains(&c));
+        assert_eq!(allocated_ip("b"), Some(b));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_first_use_initializes_multihost() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = use_dir("first-use", None);
+
+        // Lookups before the first ADD write nothing
+        assert_eq!(allocated_ip("a"), None);
+        release("a").unwrap();
+        assert!(!dir.join(ALLOCATIONS_FILE).exists());
+
+        let a = allocate_with_host_id("a", Some(5)).unwrap();
+        assert_eq!(a.ip, Ipv4Addr::new(10, 100, 5, 10));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_lock_timeout() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = use_dir("lock", Some(50));
+
+        let holder = File::create(dir.join(LOCK_FILE)).unwrap();
+        holder.lock_exclusive().unwrap();
+        let err = allocate("a").err().unwrap();
+        assert_eq!(err.code(), CniErrorCode::TryAgainLater);
+
+        holder.unlock().unwrap();
+        assert!(allocate("a").is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// One process of `test_parallel_processes_never_share_an_ip`
+    ///
+    /// After each allocation it claims a marker file named after the IP,
+    /// which fails if another process still holds that IP.
+    #[test]
+    #[ignore = "spawned by test_parallel_processes_never_share_an_ip"]
+    fn ipam_worker() {
+        let Ok(spec) = std::env::var(WORKER_ENV) else {
+            return;
+        };
+        let fields: Vec<&str> = spec.split(',').collect();
+        let dir = PathBuf::from(fields[0]);
+        let index: u64 = fields[2].parse().unwrap();
+        let ops: usize = fields[3].parse().unwrap();
+        let mut rng = Rng((fields[1].parse::<u64>().unwrap() ^ (index << 32)) | 1);
+        configure(dir.to_str(), None);
+        logger::configure(dir.join("cni.log").to_str(), None);
+
+        let marker = |ip: Ipv4Addr| dir.join("held").join(ip.to_string());
+        let mut held: Vec<(String, Ipv4Addr)> = Vec::new();
+        for n in 0..ops {
+            match rng.below(4) {
+                0 | 1 => {
+                    let id = format!("w{}-{}", index, n);
+                    match allocate(&id) {
+                        Ok(allocation) => {
+                            OpenOptions::new()
+                                .write(true)
+                                .create_new(true)
+                                .open(marker(allocation.ip))
+                                .unwrap_or_else(|e| {
+                                    panic!("{} allocated to two containers: {}", allocation.ip, e)
+                                });
+                            held.push((id, allocation.ip));
+                        }
+                        Err(e) if e.message().contains("no available") => {}
+                        Err(e) => panic!("allocate {}: {} {:?}", id, e, e.details()),
+                    }
+                }
+                2 if !held.is_empty() => {
+                    // Allocation is idempotent per container
+                    let (id, ip) = &held[rng.below(held.len())];
+                    assert_eq!(allocate(id).unwrap().ip, *ip);
+                }
+                _ if !held.is_empty() => {
+                    let (id, ip) = held.swap_remove(rng.below(held.len()));
+                    fs::remove_file(marker(ip)).unwrap();
+                    release(&id).unwrap();
+                }
+                _ => {}
+            }
+        }
+
+        for (id, ip) in held {
+            fs::remove_file(marker(ip)).unwrap();
+            release(&id).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parallel_processes_never_share_an_ip() {
+        const CASES: u64 = 3;
+        const WORKERS: u64 = 6;
+        const OPS: usize = 40;
+
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let seed = std::env::var("FLATNET_IPAM_TEST_SEED")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos() as u64
+            });
+
+        for case in 0..CASES {
+            let seed = seed.wrapping_add(case);
+            let dir = use_dir(&format!("parallel-{}", case), None);
+            fs::create_dir(dir.join("held")).unwrap();
+
+            // A 16-address pool, so workers contend for the same IPs
+            let state = IpamState {
+                range_end: "10.87.1.17".to_string(),
+                ..IpamState::default()
+            };
+            fs::write(
+                dir.join(ALLOCATIONS_FILE),
+                serde_json::to_string(&state).unwrap(),
+            )
+            .unwrap();
+
+            let workers: Vec<_> = (0..WORKERS)
+                .map(|index| {
+                    Command::new(std::env::current_exe().unwrap())
+                        .args(["--exact", "ipam::tests::ipam_worker", "--ignored"])
+                        .env(
+                            WORKER_ENV,
+                            format!("{},{},{},{}", dir.display(), seed, index, OPS),
+                        )
+                        .stdout(Stdio::piped())
+                        .spawn()
+                        .unwrap()
+                })
+                .collect();
+            for worker in workers {
+                let output = worker.wait_with_output().unwrap();
+                assert!(
+                    output.status.success(),
+                    "worker failed (FLATNET_IPAM_TEST_SEED={}):\n{}",
+                    seed,
+                    String::from_utf8_lossy(&output.stdout)
+                );
+            }
+
+            configure(dir.to_str(), None);
+            assert!(get_all_allocations().unwrap().is_empty());
+            let leftovers: Vec<_> = fs::read_dir(&dir)
+                .unwrap()
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".tmp"))
+                .collect();
+            assert!(leftovers.is_empty(), "temp files left: {:?}", leftovers);
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn test_data_dir() {
+        let _guard = DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir =
+            std::env::temp_dir().join(format!("flatnet-ipam-test-{}-data-dir", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        configure(dir.to_str(), None);
+        logger::configure(dir.join("cni.log").to_str(), None);
+        assert_eq!(ipam_dir(), dir);
+
+        // The directory is created on first use and holds the state
+        let allocation = allocate("abc123def456").unwrap();
+        let content = fs::read_to_string(dir.join(ALLOCATIONS_FILE)).unwrap();
+        assert!(content.contains(&allocation.ip.to_string()));
+        release("abc123def456").unwrap();
+
+        configure(None, None);
+        assert_eq!(ipam_dir(), PathBuf::from(IPAM_DIR));
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
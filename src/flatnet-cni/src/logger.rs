@@ -0,0 +1,386 @@
+//! Structured logging
+//!
+//! Writes JSON lines to a log file (network-config `logFile`, default
+//! `/var/log/flatnet/cni.log`) so promtail can ship them to Loki; Podman
+//! usually discards plugin stderr. Every line carries the invocation's
+//! operation ID, command, container ID, netns, and ifname; the final line
+//! of each invocation adds its duration, result, and error code.
+//!
+//! The operation ID is also sent to the Gateway with registry requests
+//! (see `registry.rs`), which logs it, so `flatnet logs --op <id>` can
+//! show both sides of one operation.
+//!
+//! The file is rotated by size. Logging never fails the CNI operation:
+//! if the file cannot be written, lines go to stderr only.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CniError;
+
+/// Default log file (promtail ships /var/log/flatnet/*.log)
+pub const DEFAULT_LOG_FILE: &str = "/var/log/flatnet/cni.log";
+
+/// Size at which the log file is rotated
+pub const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Rotated files kept (cni.log.1 .. cni.log.3)
+pub const MAX_ROTATED_FILES: u32 = 3;
+
+/// Value of the `component` field
+const COMPONENT: &str = "flatnet-cni";
+
+/// Log level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// The invocation a log line belongs to
+#[derive(Debug, Clone, Default)]
+struct Invocation {
+    op_id: Option<String>,
+    command: Option<String>,
+    container_id: Option<String>,
+    netns: Option<String>,
+    ifname: Option<String>,
+}
+
+/// Logger state for this process
+struct Logger {
+    file: Option<PathBuf>,
+    level: LogLevel,
+    invocation: Invocation,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    file: None,
+    level: LogLevel::Info,
+    invocation: Invocation {
+        op_id: None,
+        command: None,
+        container_id: None,
+        netns: None,
+        ifname: None,
+    },
+});
+
+/// One JSON log line
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    timestamp: String,
+    level: LogLevel,
+    component: &'static str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    op_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    container_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    netns: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ifname: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<u32>,
+}
+
+/// Outcome fields of an invocation's final line
+struct Outcome<'a> {
+    duration_ms: u64,
+    result: &'a str,
+    error_code: Option<u32>,
+}
+
+/// Record the invocation from the CNI environment variables
+///
+/// Also assigns the invocation a new operation ID.
+pub fn set_invocation(command: &str) {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let mut logger = lock();
+    logger.invocation = Invocation {
+        op_id: Some(new_operation_id()),
+        command: Some(command.to_string()),
+        container_id: var("CNI_CONTAINERID"),
+        netns: var("CNI_NETNS"),
+        ifname: var("CNI_IFNAME"),
+    };
+}
+
+/// Operation ID of the current invocation
+pub fn operation_id() -> Option<String> {
+    lock().invocation.op_id.clone()
+}
+
+/// Apply the network config's `logFile` and `logLevel`
+pub fn configure(file: Option<&str>, level: Option<LogLevel>) {
+    let mut logger = lock();
+    logger.file = file.map(PathBuf::from);
+    if let Some(level) = level {
+        logger.level = level;
+    }
+}
+
+pub fn debug(message: &str) {
+    log(LogLevel::Debug, message, None);
+}
+
+pub fn info(message: &str) {
+    log(LogLevel::Info, message, None);
+}
+
+pub fn warn(message: &str) {
+    log(LogLevel::Warn, message, None);
+}
+
+pub fn error(message: &str) {
+    log(LogLevel::Error, message, None);
+}
+
+/// Log the end of an invocation with its duration, result, and error code
+pub fn finish(started: Instant, result: &Result<(), CniError>) {
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let command = lock().invocation.command.clone().unwrap_or_default();
+
+    match result {
+        Ok(()) => log(
+            LogLevel::Info,
+            &format!("{} succeeded", command),
+            Some(Outcome {
+                duration_ms,
+                result: "ok",
+                error_code: None,
+            }),
+        ),
+        Err(e) => {
+            let message = match e.details() {
+                Some(details) => format!("{} failed: {}: {}", command, e.message(), details),
+                None => format!("{} failed: {}", command, e.message()),
+            };
+            log(
+                LogLevel::Error,
+                &message,
+                Some(Outcome {
+                    duration_ms,
+                    result: "error",
+                    error_code: Some(e.code() as u32),
+                }),
+            );
+        }
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Logger> {
+    // A panic while logging must not disable logging
+    LOGGER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn log(level: LogLevel, message: &str, outcome: Option<Outcome>) {
+    let logger = lock();
+    if level < logger.level {
+        return;
+    }
+
+    eprintln!("flatnet: {}", message);
+
+    let invocation = &logger.invocation;
+    let record = Record {
+        timestamp: timestamp(SystemTime::now()),
+        level,
+        component: COMPONENT,
+        message,
+        op_id: invocation.op_id.as_deref(),
+        command: invocation.command.as_deref(),
+        container_id: invocation.container_id.as_deref(),
+        netns: invocation.netns.as_deref(),
+        ifname: invocation.ifname.as_deref(),
+        duration_ms: outcome.as_ref().map(|o| o.duration_ms),
+        result: outcome.as_ref().map(|o| o.result),
+        error_code: outcome.as_ref().and_then(|o| o.error_code),
+    };
+
+    let Ok(mut line) = serde_json::to_string(&record) else {
+        return;
+    };
+    line.push('\n');
+
+    let path = logger
+        .file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_LOG_FILE));
+    if let Err(e) = append(&path, &line, MAX_LOG_SIZE) {
+        eprintln!("flatnet: cannot write log file {}: {}", path.display(), e);
+    }
+}
+
+/// Append a line, rotating the file first if it has reached `max_size`
+///
+/// Plugin invocations run concurrently, so appends and rotation happen
+/// under an exclusive lock on `<file>.lock`.
+fn append(path: &Path, line: &str, max_size: u64) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(suffixed(path, "lock"))?;
+    lock_file.lock_exclusive()?;
+
+    if fs::metadata(path).map(|m| m.len() >= max_size).unwrap_or(false) {
+        rotate(path)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())
+    // The lock is released when lock_file is dropped
+}
+
+/// Shift cni.log.N to cni.log.N+1 (dropping the oldest) and cni.log to cni.log.1
+fn rotate(path: &Path) -> io::Result<()> {
+    for n in (1..MAX_ROTATED_FILES).rev() {
+        let from = suffixed(path, &n.to_string());
+        if from.exists() {
+            fs::rename(&from, suffixed(path, &(n + 1).to_string()))?;
+        }
+    }
+    fs::rename(path, suffixed(path, "1"))
+}
+
+/// `path` with `.suffix` appended (cni.log -> cni.log.1)
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Generate a random 16-hex-digit operation ID
+///
+/// Falls back to the clock and PID if /dev/urandom cannot be read; the ID
+/// only needs to be unique enough to search logs by.
+fn new_operation_id() -> String {
+    let mut bytes = [0u8; 8];
+    let random = File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut bytes));
+    if random.is_err() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        bytes = (nanos ^ (u64::from(std::process::id()) << 32)).to_be_bytes();
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Format a time as RFC 3339 UTC with milliseconds
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timestamp() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(timestamp(time), "2023-11-14T22:13:20.123Z");
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let leap = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(timestamp(leap), "2000-02-29T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_new_operation_id() {
+        let id = new_operation_id();
+        assert_eq!(id.len(), 16);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(id, new_operation_id());
+    }
+
+    #[test]
+    fn test_append_rotates() {
+        let dir = std::env::temp_dir().join(format!("flatnet-cni-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("cni.log");
+
+        // Each line is 6 bytes; rotate once the file holds two
+        for i in 0..9 {
+            append(&path, &format!("line{}\n", i), 12).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line8\n");
+        assert_eq!(fs::read_to_string(suffixed(&path, "1")).unwrap(), "line6\nline7\n");
+        assert_eq!(fs::read_to_string(suffixed(&path, "3")).unwrap(), "line2\nline3\n");
+        assert!(!suffixed(&path, "4").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_fields() {
+        let record = Record {
+            timestamp: timestamp(UNIX_EPOCH),
+            level: LogLevel::Error,
+            component: COMPONENT,
+            message: "ADD failed: IPAM exhausted",
+            op_id: Some("3f9a2c1d5e7b8a40"),
+            command: Some("ADD"),
+            container_id: Some("abc123"),
+            netns: None,
+            ifname: Some("eth0"),
+            duration_ms: Some(42),
+            result: Some("error"),
+            error_code: Some(102),
+        };
+
+        let json: serde_json::Value = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["level"], "error");
+        assert_eq!(json["component"], "flatnet-cni");
+        assert_eq!(json["error_code"], 102);
+        assert_eq!(json["op_id"], "3f9a2c1d5e7b8a40");
+        assert!(json.get("netns").is_none());
+    }
+}
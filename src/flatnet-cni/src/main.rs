@@ -8,7 +8,12 @@ mod bridge;
 mod config;
 mod error;
 mod ipam;
+mod logger;
+mod metrics;
 mod netns;
+mod nft;
+mod policy;
+mod portmap;
 mod registry;
 mod result;
 mod veth;
@@ -16,8 +21,9 @@ mod veth;
 use std::env;
 use std::io::{self, Read};
 use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
 
-use config::NetworkConfig;
+use config::{CniArgs, NetworkConfig};
 use error::{CniError, CniErrorCode};
 use result::{CniResult, VersionResult};
 
@@ -31,30 +37,55 @@ const CNI_VERSION: &str = "1.0.0";
 const SUPPORTED_VERSIONS: &[&str] = &["0.3.0", "0.3.1", "0.4.0", "1.0.0"];
 
 fn main() {
-    if let Err(e) = run() {
-        // Output error in CNI format to stdout (per CNI spec)
-        let error_output = serde_json::json!({
-            "cniVersion": CNI_VERSION,
-            "code": e.code() as u32,
-            "msg": e.message(),
-            "details": e.details()
-        });
-        // Use compact JSON for CNI spec compliance; unwrap_or for robustness
-        println!(
-            "{}",
-            serde_json::to_string(&error_output).unwrap_or_else(|_| {
-                format!(
-                    r#"{{"cniVersion":"{}","code":{},"msg":"{}"}}"#,
-                    CNI_VERSION,
-                    e.code() as u32,
-                    e.message()
-                )
-            })
-        );
+    // Not part of the CNI protocol; lets `flatnet upgrade` discover the installed version
+    if env::args().nth(1).as_deref() == Some("--version") {
+        println!("flatnet-cni {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    // Not part of the CNI protocol either; lets `flatnet doctor` validate an
+    // installed conflist with the plugin's own parser
+    if env::args().nth(1).as_deref() == Some("--check-config") {
+        if let Err(e) = read_input().and_then(|input| check_config(&input)) {
+            print_error(&e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let started = Instant::now();
+    let result = run();
+    logger::finish(started, &result);
+    metrics::finish(started, &result);
+
+    if let Err(e) = result {
+        print_error(&e);
         std::process::exit(1);
     }
 }
 
+/// Output an error in CNI format to stdout (per CNI spec)
+fn print_error(e: &CniError) {
+    let error_output = serde_json::json!({
+        "cniVersion": CNI_VERSION,
+        "code": e.code() as u32,
+        "msg": e.message(),
+        "details": e.details()
+    });
+    // Use compact JSON for CNI spec compliance; unwrap_or for robustness
+    println!(
+        "{}",
+        serde_json::to_string(&error_output).unwrap_or_else(|_| {
+            format!(
+                r#"{{"cniVersion":"{}","code":{},"msg":"{}"}}"#,
+                CNI_VERSION,
+                e.code() as u32,
+                e.message()
+            )
+        })
+    );
+}
+
 fn run() -> Result<(), CniError> {
     // Get CNI command from environment
     let command = env::var("CNI_COMMAND").map_err(|_| {
@@ -64,15 +95,13 @@ fn run() -> Result<(), CniError> {
         )
     })?;
 
-    // Read network config from stdin (with size limit to prevent OOM)
-    let mut input = String::new();
-    io::stdin()
-        .take(MAX_INPUT_SIZE)
-        .read_to_string(&mut input)
-        .map_err(|e| {
-            CniError::new(CniErrorCode::IoFailure, "failed to read stdin")
-                .with_details(&e.to_string())
-        })?;
+    // VERSION is called often and carries nothing worth logging
+    if command != "VERSION" {
+        logger::set_invocation(&command);
+        metrics::set_operation(&command);
+    }
+
+    let input = read_input()?;
 
     match command.as_str() {
         "ADD" => cmd_add(&input),
@@ -94,12 +123,77 @@ fn run() -> Result<(), CniError> {
     }
 }
 
-/// Handle ADD command - create network interface
-fn cmd_add(input: &str) -> Result<(), CniError> {
-    let config: NetworkConfig = serde_json::from_str(input).map_err(|e| {
+/// Read the network config from stdin (with size limit to prevent OOM)
+fn read_input() -> Result<String, CniError> {
+    let mut input = String::new();
+    io::stdin()
+        .take(MAX_INPUT_SIZE)
+        .read_to_string(&mut input)
+        .map_err(|e| {
+            CniError::new(CniErrorCode::IoFailure, "failed to read stdin")
+                .with_details(&e.to_string())
+        })?;
+    Ok(input)
+}
+
+/// Decode the network config without applying any of its settings
+fn decode_config(input: &str) -> Result<NetworkConfig, CniError> {
+    serde_json::from_str(input).map_err(|e| {
         CniError::new(CniErrorCode::DecodingFailure, "failed to parse network config")
             .with_details(&e.to_string())
-    })?;
+    })
+}
+
+/// Parse the network config and apply its logging and metrics settings
+fn parse_config(input: &str) -> Result<NetworkConfig, CniError> {
+    let config = decode_config(input)?;
+
+    logger::configure(config.log_file.as_deref(), config.log_level);
+    metrics::configure(config.metrics_file.as_deref(), &config.name);
+    ipam::configure(
+        config.ipam.as_ref().and_then(|i| i.data_dir.as_deref()),
+        config.ipam.as_ref().and_then(|i| i.lock_timeout_ms),
+    );
+    Ok(config)
+}
+
+/// Validate a network config the way ADD would, without touching the host
+fn check_config(input: &str) -> Result<(), CniError> {
+    let config = decode_config(input)?;
+
+    gateway_ip(&config, config.multihost_id())?;
+    ipam::parse_reservations(
+        config.ipam.as_ref().and_then(|ipam| ipam.reservations.as_ref()),
+    )?;
+    Ok(())
+}
+
+/// Bridge gateway IP for the config (10.100.<host-id>.1 in multihost mode)
+fn gateway_ip(config: &NetworkConfig, host_id: Option<u8>) -> Result<Ipv4Addr, CniError> {
+    if let Some(hid) = host_id {
+        Ok(format!("10.100.{}.1", hid)
+            .parse()
+            .expect("multihost gateway IP is invalid"))
+    } else if let Some(ref ipam_config) = config.ipam {
+        ipam_config
+            .gateway
+            .as_deref()
+            .unwrap_or(bridge::DEFAULT_GATEWAY)
+            .parse()
+            .map_err(|e: std::net::AddrParseError| {
+                CniError::new(CniErrorCode::InvalidNetworkConfig, "invalid gateway IP")
+                    .with_details(&e.to_string())
+            })
+    } else {
+        Ok(bridge::DEFAULT_GATEWAY
+            .parse()
+            .expect("DEFAULT_GATEWAY constant is invalid"))
+    }
+}
+
+/// Handle ADD command - create network interface
+fn cmd_add(input: &str) -> Result<(), CniError> {
+    let config = parse_config(input)?;
 
     // Get required environment variables
     let container_id = env::var("CNI_CONTAINERID").map_err(|_| {
@@ -123,75 +217,112 @@ fn cmd_add(input: &str) -> Result<(), CniError> {
         )
     })?;
 
+    // Static IP (CNI_ARGS IP= or the `ips` capability) and reservations
+    let cni_args = CniArgs::from_env();
+    let static_ip = config.requested_ip(&cni_args)?;
+    let reservations = ipam::parse_reservations(
+        config.ipam.as_ref().and_then(|ipam| ipam.reservations.as_ref()),
+    )?;
+    let port_mappings = portmap::normalize(
+        config
+            .runtime_config
+            .as_ref()
+            .and_then(|runtime| runtime.port_mappings.as_deref())
+            .unwrap_or_default(),
+    )?;
+    let cooldown = config
+        .ipam
+        .as_ref()
+        .and_then(|ipam| ipam.sticky_cooldown_secs)
+        .map_or(ipam::DEFAULT_STICKY_COOLDOWN, Duration::from_secs);
+
     // Get multihost configuration
-    let host_id = config.host_id.or_else(|| {
-        config.ipam.as_ref().and_then(|ipam| ipam.host_id)
-    });
+    let host_id = config.multihost_id();
 
     let is_multihost = host_id.is_some();
 
-    eprintln!(
-        "flatnet ADD: container={}, netns={}, ifname={}, multihost={}, host_id={:?}",
-        container_id, netns, ifname, is_multihost, host_id
-    );
+    logger::debug(&format!("ADD: multihost={}, host_id={:?}", is_multihost, host_id));
 
     // Get configuration values
     let bridge_name = config.bridge_name();
     let mtu = config.mtu_value();
 
     // Determine gateway IP based on mode
-    let gateway_ip: Ipv4Addr = if is_multihost {
-        // Multihost mode: gateway is 10.100.<host-id>.1
-        let hid = host_id.unwrap_or(1);
-        format!("10.100.{}.1", hid)
-            .parse()
-            .expect("multihost gateway IP is invalid")
-    } else if let Some(ref ipam_config) = config.ipam {
-        ipam_config
-            .gateway
-            .as_deref()
-            .unwrap_or(bridge::DEFAULT_GATEWAY)
-            .parse()
-            .map_err(|e: std::net::AddrParseError| {
-                CniError::new(CniErrorCode::InvalidNetworkConfig, "invalid gateway IP")
-                    .with_details(&e.to_string())
-            })?
-    } else {
-        bridge::DEFAULT_GATEWAY
-            .parse()
-            .expect("DEFAULT_GATEWAY constant is invalid")
-    };
+    let gateway_ip = gateway_ip(&config, host_id)?;
 
     // Step 1: Ensure bridge exists
-    let _bridge_index = bridge::ensure_bridge(bridge_name, gateway_ip, bridge::DEFAULT_PREFIX_LEN)?;
+    let _bridge_index = metrics::step("bridge", || {
+        bridge::ensure_bridge(bridge_name, gateway_ip, bridge::DEFAULT_PREFIX_LEN)
+    })?;
 
     // Step 2: Allocate IP address (with host ID for multihost)
-    let allocation = ipam::allocate_with_host_id(&container_id, host_id)?;
+    let allocation = metrics::step("ipam", || {
+        ipam::allocate_request(&ipam::IpRequest {
+            container_id: &container_id,
+            host_id,
+            name: cni_args.container_name(),
+            static_ip,
+            reservations: &reservations,
+            cooldown,
+        })
+    })?;
 
     // Step 3: Create veth pair
-    let veth_pair = veth::create_veth_pair(&container_id, &netns, &ifname, mtu)?;
+    let veth_pair = metrics::step("veth", || {
+        veth::create_veth_pair(&container_id, &netns, &ifname, mtu)
+    })?;
 
     // Step 4: Attach host veth to bridge
-    veth::setup_host_veth(veth_pair.host_index, bridge_name)?;
+    metrics::step("attach", || {
+        veth::setup_host_veth(veth_pair.host_index, bridge_name)
+    })?;
 
     // Step 5: Configure container interface (in container namespace)
-    netns::with_netns(&netns, || {
-        veth::configure_container_interface(
-            &ifname,
+    metrics::step("configure", || {
+        netns::with_netns(&netns, || {
+            veth::configure_container_interface(
+                &ifname,
+                &veth_pair.peer_ifname,
+                allocation.ip,
+                allocation.prefix_len,
+                allocation.gateway,
+            )
+        })
+    })?;
+
+    // Step 6: Map host ports (portMappings capability)
+    if !port_mappings.is_empty() {
+        metrics::step("portmap", || {
+            portmap::add(&container_id, port_mappings.clone())
+        })?;
+    }
+
+    // Step 7: Join the network policy sets selecting the container
+    metrics::step("policy", || {
+        policy::attach(
+            config.policy_file.as_deref(),
             allocation.ip,
-            allocation.prefix_len,
-            allocation.gateway,
+            cni_args.container_name(),
+            &config.labels(),
         )
     })?;
 
-    // Step 6: Register container with Gateway (if registry enabled)
+    // Step 8: Register container with Gateway (if registry enabled)
     if config.is_registry_enabled() {
-        let container_info = registry::ContainerInfo::new(
+        let mut container_info = registry::ContainerInfo::new(
             container_id.clone(),
             allocation.ip.to_string(),
             allocation.host_id,
         );
-        registry::try_register(config.registry_endpoints(), &container_info);
+        if let Some(name) = cni_args.container_name() {
+            container_info = container_info.with_hostname(name.to_string());
+        }
+        if !port_mappings.is_empty() {
+            container_info = container_info.with_ports(portmap::container_ports(&port_mappings));
+        }
+        metrics::step("registry", || {
+            registry::try_register(config.registry_endpoints(), &container_info)
+        });
     }
 
     // Build the result
@@ -201,16 +332,21 @@ fn cmd_add(input: &str) -> Result<(), CniError> {
     // Build result with two interfaces:
     // 0: host-side veth (attached to bridge)
     // 1: container-side veth (inside container namespace)
-    let result = CniResult::new(config.cni_version.clone())
+    let mut result = CniResult::new(config.cni_version.clone())
         .with_interface(veth_pair.host_ifname.clone(), String::new(), None)
         .with_interface(ifname.clone(), veth_pair.mac_address, Some(netns.clone()))
         .with_ip(ip_with_prefix.clone(), Some(gateway_str.clone()), 1) // interface index 1 = container side
         .with_route("0.0.0.0/0".to_string(), Some(gateway_str.clone()));
 
-    eprintln!(
-        "flatnet ADD: success, ip={}, gateway={}, host_id={}",
+    // Embedded DNS (`flatnet dns serve`) listens on the bridge IP
+    if let Some(dns) = config.dns_result(allocation.gateway) {
+        result = result.with_dns(dns);
+    }
+
+    logger::info(&format!(
+        "ADD: ip={}, gateway={}, host_id={}",
         ip_with_prefix, gateway_str, allocation.host_id
-    );
+    ));
 
     // Output result to stdout
     println!(
@@ -226,41 +362,44 @@ fn cmd_add(input: &str) -> Result<(), CniError> {
 
 /// Handle DEL command - remove network interface
 fn cmd_del(input: &str) -> Result<(), CniError> {
-    let config: NetworkConfig = serde_json::from_str(input).map_err(|e| {
-        CniError::new(CniErrorCode::DecodingFailure, "failed to parse network config")
-            .with_details(&e.to_string())
-    })?;
+    let config = parse_config(input)?;
 
     // Get environment variables (some may be optional for DEL)
     let container_id = env::var("CNI_CONTAINERID").unwrap_or_default();
-    let ifname = env::var("CNI_IFNAME").unwrap_or_default();
-
-    eprintln!(
-        "flatnet DEL: container={}, ifname={}",
-        container_id, ifname
-    );
 
     if container_id.is_empty() {
         // Nothing to do without container ID
-        eprintln!("flatnet DEL: no container ID, nothing to clean up");
+        logger::debug("DEL: no container ID, nothing to clean up");
         return Ok(());
     }
 
     // Step 1: Deregister container from Gateway (if registry enabled)
     // Do this first while we still have network access
     if config.is_registry_enabled() {
-        registry::try_deregister(config.registry_endpoints(), &container_id);
+        metrics::step("registry", || {
+            registry::try_deregister(config.registry_endpoints(), &container_id)
+        });
     }
 
     // Step 2: Delete veth pair (if exists)
     // Deleting the host side automatically deletes the container side
     let host_ifname = veth::generate_host_ifname(&container_id);
-    veth::delete_veth(&host_ifname)?;
+    metrics::step("veth", || veth::delete_veth(&host_ifname))?;
 
-    // Step 3: Release IP address
-    ipam::release(&container_id)?;
+    // Step 3: Remove host port mappings
+    metrics::step("portmap", || portmap::remove(&container_id))?;
 
-    eprintln!("flatnet DEL: cleanup complete");
+    // Step 4: Leave the network policy sets (best effort)
+    if let Ok(Some(allocation)) = ipam::get_allocation(&container_id) {
+        metrics::step("policy", || {
+            policy::detach(config.policy_file.as_deref(), allocation.ip)
+        });
+    }
+
+    // Step 5: Release IP address
+    metrics::step("ipam", || ipam::release(&container_id))?;
+
+    logger::debug("DEL: cleanup complete");
 
     // DEL outputs nothing on success
     Ok(())
@@ -268,10 +407,7 @@ fn cmd_del(input: &str) -> Result<(), CniError> {
 
 /// Handle CHECK command - verify network interface exists
 fn cmd_check(input: &str) -> Result<(), CniError> {
-    let config: NetworkConfig = serde_json::from_str(input).map_err(|e| {
-        CniError::new(CniErrorCode::DecodingFailure, "failed to parse network config")
-            .with_details(&e.to_string())
-    })?;
+    let config = parse_config(input)?;
 
     // Required environment variables for CHECK per CNI spec
     let container_id = env::var("CNI_CONTAINERID").map_err(|_| {
@@ -281,25 +417,21 @@ fn cmd_check(input: &str) -> Result<(), CniError> {
         )
     })?;
 
-    let netns = env::var("CNI_NETNS").map_err(|_| {
+    // Not used by the checks below, but required (and logged)
+    env::var("CNI_NETNS").map_err(|_| {
         CniError::new(
             CniErrorCode::InvalidEnvironmentVariables,
             "CNI_NETNS not set",
         )
     })?;
 
-    let ifname = env::var("CNI_IFNAME").map_err(|_| {
+    env::var("CNI_IFNAME").map_err(|_| {
         CniError::new(
             CniErrorCode::InvalidEnvironmentVariables,
             "CNI_IFNAME not set",
         )
     })?;
 
-    eprintln!(
-        "flatnet CHECK: container={}, netns={}, ifname={}",
-        container_id, netns, ifname
-    );
-
     let bridge_name = config.bridge_name();
 
     // Check 1: Bridge exists
@@ -329,15 +461,13 @@ fn cmd_check(input: &str) -> Result<(), CniError> {
 
     // Check 4: Verify prevResult if present
     if let Some(ref prev_result) = config.prev_result {
-        eprintln!(
-            "flatnet CHECK: prevResult present, verifying..."
-        );
+        logger::debug("CHECK: prevResult present, verifying...");
         // In a full implementation, we would verify the prevResult matches actual state
         // For now, just log that we have it
         let _ = prev_result;
     }
 
-    eprintln!("flatnet CHECK: all checks passed");
+    logger::debug("CHECK: all checks passed");
 
     // CHECK outputs nothing on success
     Ok(())
@@ -377,4 +507,30 @@ mod tests {
     fn test_cni_version_constant() {
         assert_eq!(CNI_VERSION, "1.0.0");
     }
+
+    #[test]
+    fn test_check_config() {
+        let valid = r#"{
+            "cniVersion": "1.0.0",
+            "name": "flatnet",
+            "type": "flatnet",
+            "logLevel": "debug",
+            "ipam": {
+                "type": "flatnet-ipam",
+                "gateway": "10.87.1.1",
+                "reservations": {"db": "10.87.1.20"},
+                "stickyCooldownSecs": 60
+            }
+        }"#;
+        assert!(check_config(valid).is_ok());
+
+        let err = check_config(&valid.replace(r#""debug""#, r#""loud""#)).unwrap_err();
+        assert_eq!(err.code(), CniErrorCode::DecodingFailure);
+
+        let err = check_config(&valid.replace("10.87.1.1", "10.87.1")).unwrap_err();
+        assert_eq!(err.code(), CniErrorCode::InvalidNetworkConfig);
+
+        let err = check_config(&valid.replace("10.87.1.20", "db")).unwrap_err();
+        assert_eq!(err.code(), CniErrorCode::InvalidNetworkConfig);
+    }
 }
@@ -0,0 +1,455 @@
+//! Prometheus metrics
+//!
+//! The plugin only runs for the length of one CNI command, so there is
+//! nothing to scrape. Instead, each invocation adds its counts and timings
+//! to a state file and rewrites a textfile-collector file (network-config
+//! `metricsFile`, default `/var/lib/flatnet/metrics/flatnet_cni.prom`) that
+//! node-exporter serves. Both are updated under an exclusive lock, and the
+//! `.prom` file is replaced by rename so node-exporter never reads half of it.
+//!
+//! Like logging, metrics never fail the CNI operation.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CniError;
+use crate::logger;
+
+/// Default textfile-collector file
+pub const DEFAULT_METRICS_FILE: &str = "/var/lib/flatnet/metrics/flatnet_cni.prom";
+
+/// Histogram bucket upper bounds in seconds
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Commands that are counted (anything else would be an unbounded label)
+const OPERATIONS: &[&str] = &["ADD", "DEL", "CHECK"];
+
+/// Metric families: name, type, help
+const FAMILIES: &[(&str, &str, &str)] = &[
+    (
+        "flatnet_cni_operations_total",
+        "counter",
+        "CNI commands by operation, status, and CNI error code",
+    ),
+    (
+        "flatnet_cni_operation_duration_seconds",
+        "histogram",
+        "Duration of CNI commands",
+    ),
+    (
+        "flatnet_cni_step_duration_seconds",
+        "histogram",
+        "Duration of each step of a CNI command",
+    ),
+    (
+        "flatnet_cni_gateway_registrations_total",
+        "counter",
+        "Gateway registry requests by operation and status (one per endpoint)",
+    ),
+    (
+        "flatnet_cni_ip_allocated",
+        "gauge",
+        "IP addresses allocated from the network's pool",
+    ),
+    (
+        "flatnet_cni_ip_pool_size",
+        "gauge",
+        "IP addresses in the network's pool",
+    ),
+    (
+        "flatnet_cni_last_operation_timestamp_seconds",
+        "gauge",
+        "Unix time of the last CNI command by operation",
+    ),
+];
+
+/// What the current invocation has recorded so far
+struct Recorder {
+    file: Option<PathBuf>,
+    network: String,
+    operation: Option<&'static str>,
+    steps: Vec<(&'static str, f64)>,
+    registrations: Vec<(&'static str, bool)>,
+    ip_usage: Option<(usize, usize)>,
+}
+
+static RECORDER: Mutex<Recorder> = Mutex::new(Recorder {
+    file: None,
+    network: String::new(),
+    operation: None,
+    steps: Vec::new(),
+    registrations: Vec::new(),
+    ip_usage: None,
+});
+
+/// Cumulative metrics, kept between invocations
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    /// Counters and gauges by series (`name{labels}`)
+    #[serde(default)]
+    values: BTreeMap<String, f64>,
+    /// Histograms by series (`name{labels}`)
+    #[serde(default)]
+    histograms: BTreeMap<String, Histogram>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Histogram {
+    /// Cumulative count per bucket of DURATION_BUCKETS
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        self.buckets.resize(DURATION_BUCKETS.len(), 0);
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Record the CNI command being run (VERSION and unknown commands are not counted)
+pub fn set_operation(command: &str) {
+    lock().operation = OPERATIONS.iter().find(|op| **op == command).copied();
+}
+
+/// Apply the network config's `metricsFile` and name the network
+pub fn configure(file: Option<&str>, network: &str) {
+    let mut recorder = lock();
+    recorder.file = file.map(PathBuf::from);
+    recorder.network = network.to_string();
+}
+
+/// Run one step of the command, recording its duration
+pub fn step<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    lock().steps.push((name, started.elapsed().as_secs_f64()));
+    result
+}
+
+/// Record a registry request to one endpoint ("register" or "deregister")
+pub fn registration(operation: &'static str, success: bool) {
+    lock().registrations.push((operation, success));
+}
+
+/// Record how much of the IP pool is allocated
+pub fn ip_usage(allocated: usize, capacity: usize) {
+    lock().ip_usage = Some((allocated, capacity));
+}
+
+/// Add the invocation to the metrics files
+pub fn finish(started: Instant, result: &Result<(), CniError>) {
+    let recorder = lock();
+    let Some(operation) = recorder.operation else {
+        return;
+    };
+
+    let path = recorder
+        .file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_METRICS_FILE));
+    let duration = started.elapsed().as_secs_f64();
+    let update = |state: &mut State| recorder.apply(state, operation, duration, result);
+
+    if let Err(e) = update_files(&path, update) {
+        logger::warn(&format!(
+            "cannot write metrics file {}: {}",
+            path.display(),
+            e
+        ));
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Recorder> {
+    RECORDER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Recorder {
+    /// Add this invocation's measurements to the cumulative state
+    fn apply(
+        &self,
+        state: &mut State,
+        operation: &str,
+        duration: f64,
+        result: &Result<(), CniError>,
+    ) {
+        let op = [("operation", operation)];
+
+        let code;
+        let outcome = match result {
+            Ok(()) => vec![("operation", operation), ("status", "success")],
+            Err(e) => {
+                code = (e.code() as u32).to_string();
+                vec![
+                    ("operation", operation),
+                    ("status", "failure"),
+                    ("error_code", code.as_str()),
+                ]
+            }
+        };
+        state.add("flatnet_cni_operations_total", &outcome, 1.0);
+        state.observe("flatnet_cni_operation_duration_seconds", &op, duration);
+
+        for (step, seconds) in &self.steps {
+            let labels = [("operation", operation), ("step", *step)];
+            state.observe("flatnet_cni_step_duration_seconds", &labels, *seconds);
+        }
+
+        for (registration, success) in &self.registrations {
+            let status = if *success { "success" } else { "failure" };
+            let labels = [("operation", *registration), ("status", status)];
+            state.add("flatnet_cni_gateway_registrations_total", &labels, 1.0);
+        }
+
+        if let Some((allocated, capacity)) = self.ip_usage {
+            let network = [("network", self.network.as_str())];
+            state.set("flatnet_cni_ip_allocated", &network, allocated as f64);
+            state.set("flatnet_cni_ip_pool_size", &network, capacity as f64);
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        state.set("flatnet_cni_last_operation_timestamp_seconds", &op, now);
+    }
+}
+
+impl State {
+    fn add(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        *self.values.entry(series(name, labels)).or_insert(0.0) += value;
+    }
+
+    fn set(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.values.insert(series(name, labels), value);
+    }
+
+    fn observe(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.histograms
+            .entry(series(name, labels))
+            .or_default()
+            .observe(value);
+    }
+
+    /// Render in the Prometheus text exposition format
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        for (family, kind, help) in FAMILIES {
+            let values: Vec<_> = self
+                .values
+                .iter()
+                .filter(|(key, _)| split_series(key).0 == *family)
+                .collect();
+            let histograms: Vec<_> = self
+                .histograms
+                .iter()
+                .filter(|(key, _)| split_series(key).0 == *family)
+                .collect();
+            if values.is_empty() && histograms.is_empty() {
+                continue;
+            }
+
+            out.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} {}\n",
+                family, help, family, kind
+            ));
+            for (key, value) in values {
+                out.push_str(&format!("{} {}\n", key, value));
+            }
+            for (key, histogram) in histograms {
+                let (name, labels) = split_series(key);
+                let mut buckets = histogram.buckets.clone();
+                buckets.resize(DURATION_BUCKETS.len(), 0);
+                for (bound, count) in DURATION_BUCKETS.iter().zip(&buckets) {
+                    let le = format!("le=\"{}\"", bound);
+                    out.push_str(&format!(
+                        "{}_bucket{} {}\n",
+                        name,
+                        with_label(labels, &le),
+                        count
+                    ));
+                }
+                let le = "le=\"+Inf\"";
+                out.push_str(&format!(
+                    "{}_bucket{} {}\n",
+                    name,
+                    with_label(labels, le),
+                    histogram.count
+                ));
+                out.push_str(&format!("{}_sum{} {}\n", name, labels, histogram.sum));
+                out.push_str(&format!("{}_count{} {}\n", name, labels, histogram.count));
+            }
+        }
+
+        out
+    }
+}
+
+/// Series key: `name{a="x",b="y"}` (just `name` without labels)
+fn series(name: &str, labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!("{}{{{}}}", name, labels.join(","))
+}
+
+/// Split a series key into its name and `{labels}` (possibly empty)
+fn split_series(key: &str) -> (&str, &str) {
+    match key.find('{') {
+        Some(index) => key.split_at(index),
+        None => (key, ""),
+    }
+}
+
+/// Add a label to a `{labels}` string
+fn with_label(labels: &str, label: &str) -> String {
+    match labels.strip_suffix('}') {
+        Some(inner) => format!("{},{}}}", inner, label),
+        None => format!("{{{}}}", label),
+    }
+}
+
+/// Update the state file and rewrite the `.prom` file under the lock
+///
+/// The state lives next to the `.prom` file as `<name>.json`, which
+/// node-exporter ignores.
+fn update_files(path: &Path, update: impl FnOnce(&mut State)) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(path.with_extension("lock"))?;
+    lock_file.lock_exclusive()?;
+
+    let state_path = path.with_extension("json");
+    // A missing or unreadable state starts the counters over (a counter reset)
+    let mut state: State = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    update(&mut state);
+
+    let json = serde_json::to_string(&state).map_err(io::Error::other)?;
+    replace(&state_path, json.as_bytes())?;
+    replace(path, state.render().as_bytes())
+    // The lock is released when lock_file is dropped
+}
+
+/// Replace a file's contents atomically (write a temporary file, then rename)
+fn replace(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_os_string();
+    tmp.push(".tmp");
+
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CniErrorCode;
+
+    fn recorder() -> Recorder {
+        Recorder {
+            file: None,
+            network: "flatnet".to_string(),
+            operation: Some("ADD"),
+            steps: vec![("ipam", 0.02)],
+            registrations: vec![("register", true), ("register", false)],
+            ip_usage: Some((3, 245)),
+        }
+    }
+
+    #[test]
+    fn test_apply_and_render() {
+        let mut state = State::default();
+        let recorder = recorder();
+        recorder.apply(&mut state, "ADD", 0.3, &Ok(()));
+        let error = CniError::new(
+            CniErrorCode::IpamFailure,
+            "no available IP addresses in range",
+        );
+        recorder.apply(&mut state, "ADD", 0.01, &Err(error));
+
+        let text = state.render();
+        assert!(text.contains("# TYPE flatnet_cni_operations_total counter\n"));
+        assert!(
+            text.contains("flatnet_cni_operations_total{operation=\"ADD\",status=\"success\"} 1\n")
+        );
+        assert!(text.contains(
+            "flatnet_cni_operations_total{operation=\"ADD\",status=\"failure\",error_code=\"102\"} 1\n"
+        ));
+        assert!(text.contains(
+            "flatnet_cni_operation_duration_seconds_bucket{operation=\"ADD\",le=\"0.01\"} 1\n"
+        ));
+        assert!(text.contains(
+            "flatnet_cni_operation_duration_seconds_bucket{operation=\"ADD\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains(
+            "flatnet_cni_step_duration_seconds_count{operation=\"ADD\",step=\"ipam\"} 2\n"
+        ));
+        assert!(text.contains(
+            "flatnet_cni_gateway_registrations_total{operation=\"register\",status=\"failure\"} 2\n"
+        ));
+        assert!(text.contains("flatnet_cni_ip_pool_size{network=\"flatnet\"} 245\n"));
+    }
+
+    #[test]
+    fn test_series() {
+        assert_eq!(series("up", &[]), "up");
+        assert_eq!(
+            series("x", &[("network", "a\"b"), ("step", "ipam")]),
+            r#"x{network="a\"b",step="ipam"}"#
+        );
+        assert_eq!(split_series("x{a=\"1\"}"), ("x", "{a=\"1\"}"));
+        assert_eq!(with_label("{a=\"1\"}", "le=\"1\""), "{a=\"1\",le=\"1\"}");
+        assert_eq!(with_label("", "le=\"1\""), "{le=\"1\"}");
+    }
+
+    #[test]
+    fn test_update_files_accumulates() {
+        let dir = std::env::temp_dir().join(format!("flatnet-cni-metrics-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("flatnet_cni.prom");
+
+        for _ in 0..3 {
+            update_files(&path, |state| {
+                state.add("flatnet_cni_operations_total", &[], 1.0)
+            })
+            .unwrap();
+        }
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("flatnet_cni_operations_total 3\n"));
+        assert!(dir.join("flatnet_cni.json").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
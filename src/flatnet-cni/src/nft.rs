@@ -0,0 +1,11 @@
+//! nftables script runner
+//!
+//! Scripts go to `nft -f -`, which applies them as one transaction.
+
+use crate::error::{CniError, CniErrorCode};
+
+/// Run a script, failing with `code` and `message` (nft's stderr as details)
+pub fn run(script: &str, code: CniErrorCode, message: &str) -> Result<(), CniError> {
+    flatnet_policy::nft::run(script)
+        .map_err(|e| CniError::new(code, message).with_details(&e.to_string()))
+}
@@ -0,0 +1,168 @@
+//! Network policy container sets
+//!
+//! `flatnet policy apply` loads the policy into the `flatnet_policy`
+//! nftables tables (inet and bridge families) and writes the definitions of
+//! their container sets to a state file (network-config `policyFile`,
+//! default `/var/lib/flatnet/policy.json`). On ADD the new container's IP
+//! joins every set whose selector matches its name and labels (with the
+//! CLI's own matching, from `flatnet-policy`), so the rules apply from its
+//! first packet rather than from the next `policy apply`.
+//! On DEL the IP leaves all sets again.
+//!
+//! Without a state file there is no policy and nothing to do.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+
+use flatnet_policy::PolicyState;
+
+use crate::error::{CniError, CniErrorCode};
+use crate::logger;
+use crate::nft;
+
+/// Default state file written by `flatnet policy apply`
+pub const DEFAULT_POLICY_FILE: &str = "/var/lib/flatnet/policy.json";
+
+/// Families the policy table exists in
+const FAMILIES: &[&str] = &["inet", "bridge"];
+
+fn load(path: Option<&str>) -> Result<Option<PolicyState>, CniError> {
+    let path = path.unwrap_or(DEFAULT_POLICY_FILE);
+    let failed = |details: String| {
+        CniError::new(CniErrorCode::PolicyFailed, "failed to read policy state")
+            .with_details(&format!("{}: {}", path, details))
+    };
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| failed(e.to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(failed(e.to_string())),
+    }
+}
+
+/// Add a new container to the sets it is selected by
+///
+/// Returns the names of the sets joined.
+pub fn attach(
+    path: Option<&str>,
+    ip: Ipv4Addr,
+    name: Option<&str>,
+    labels: &BTreeMap<String, String>,
+) -> Result<Vec<String>, CniError> {
+    let Some(state) = load(path)? else {
+        return Ok(Vec::new());
+    };
+    let sets: Vec<String> = state
+        .container_sets
+        .iter()
+        .filter(|set| set.matches(name, labels))
+        .map(|set| set.name.clone())
+        .collect();
+    if sets.is_empty() {
+        return Ok(sets);
+    }
+
+    nft::run(
+        &elements_script(&state.table, &sets, ip, &["add"]),
+        CniErrorCode::PolicyFailed,
+        "failed to add container to network policy sets",
+    )?;
+    logger::info(&format!("policy: {} joined sets {}", ip, sets.join(", ")));
+    Ok(sets)
+}
+
+/// Remove a container from all sets
+///
+/// Never fails: a stale element only matters if the IP is reused, and the
+/// next `flatnet policy apply` rebuilds the sets anyway.
+pub fn detach(path: Option<&str>, ip: Ipv4Addr) {
+    let state = match load(path) {
+        Ok(Some(state)) => state,
+        Ok(None) => return,
+        Err(e) => {
+            logger::warn(&format!("policy: {}", e));
+            return;
+        }
+    };
+    let sets: Vec<String> = state.container_sets.into_iter().map(|s| s.name).collect();
+    if sets.is_empty() {
+        return;
+    }
+
+    // Adding first makes the delete succeed whether or not the IP was a member
+    let script = elements_script(&state.table, &sets, ip, &["add", "delete"]);
+    if let Err(e) = nft::run(
+        &script,
+        CniErrorCode::PolicyFailed,
+        "failed to remove container from network policy sets",
+    ) {
+        logger::warn(&format!(
+            "policy: {}: {}",
+            e,
+            e.details().unwrap_or_default()
+        ));
+    }
+}
+
+/// `add`/`delete element` commands for every set in both families
+fn elements_script(table: &str, sets: &[String], ip: Ipv4Addr, verbs: &[&str]) -> String {
+    let mut script = String::new();
+    for family in FAMILIES {
+        for set in sets {
+            for verb in verbs {
+                script.push_str(&format!(
+                    "{} element {} {} {} {{ {} }}\n",
+                    verb, family, table, set, ip
+                ));
+            }
+        }
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elements_script() {
+        let sets = vec!["c0".to_string(), "c2".to_string()];
+        let ip = Ipv4Addr::new(10, 87, 1, 5);
+        assert_eq!(
+            elements_script("flatnet_policy", &sets, ip, &["add"]),
+            "add element inet flatnet_policy c0 { 10.87.1.5 }\n\
+             add element inet flatnet_policy c2 { 10.87.1.5 }\n\
+             add element bridge flatnet_policy c0 { 10.87.1.5 }\n\
+             add element bridge flatnet_policy c2 { 10.87.1.5 }\n"
+        );
+        assert!(
+            elements_script("t", &sets[..1], ip, &["add", "delete"]).starts_with(
+                "add element inet t c0 { 10.87.1.5 }\ndelete element inet t c0 { 10.87.1.5 }\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_missing_state_is_no_policy() {
+        let path = std::env::temp_dir().join(format!("flatnet-policy-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let ip = Ipv4Addr::new(10, 87, 1, 5);
+        assert!(attach(Some(path), ip, Some("db"), &BTreeMap::new())
+            .unwrap()
+            .is_empty());
+
+        fs::write(path, "not json").unwrap();
+        let err = attach(Some(path), ip, Some("db"), &BTreeMap::new()).unwrap_err();
+        assert_eq!(err.code(), CniErrorCode::PolicyFailed);
+
+        // No set selects the container, so nft is never run
+        fs::write(path, r#"{"table": "flatnet_policy", "container_sets": []}"#).unwrap();
+        assert!(attach(Some(path), ip, Some("db"), &BTreeMap::new())
+            .unwrap()
+            .is_empty());
+        let _ = fs::remove_file(path);
+    }
+}
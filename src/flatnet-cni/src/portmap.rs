@@ -0,0 +1,423 @@
+//! Host port mappings (CNI `portMappings` capability)
+//!
+//! Mappings are stored per container in the IPAM state. Whenever they change
+//! the whole `ip flatnet_portmap` nftables table is regenerated from that
+//! state and replaced in one `nft -f` transaction. Entries of containers
+//! without an allocation are pruned first, so they neither block host ports
+//! nor keep their rules past the next ADD with mappings or DEL.
+
+use std::net::Ipv4Addr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CniError, CniErrorCode};
+use crate::ipam::{self, IpamState};
+use crate::logger;
+use crate::nft;
+
+/// nftables table holding the DNAT rules
+pub const NFT_TABLE: &str = "flatnet_portmap";
+
+/// Protocols a mapping may use
+const PROTOCOLS: &[&str] = &["tcp", "udp", "sctp"];
+
+/// One `host:port -> container:port` mapping
+///
+/// See: https://github.com/containernetworking/cni/blob/main/CONVENTIONS.md
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortMapping {
+    pub host_port: u16,
+
+    pub container_port: u16,
+
+    /// "tcp" (default), "udp" or "sctp"
+    #[serde(default = "default_protocol")]
+    pub protocol: String,
+
+    /// Host address to listen on (default: all local addresses)
+    #[serde(rename = "hostIP", default, skip_serializing_if = "Option::is_none")]
+    pub host_ip: Option<String>,
+}
+
+fn default_protocol() -> String {
+    "tcp".to_string()
+}
+
+impl PortMapping {
+    /// Whether both mappings claim the same host port
+    fn conflicts_with(&self, other: &PortMapping) -> bool {
+        self.protocol == other.protocol
+            && self.host_port == other.host_port
+            && (self.host_ip.is_none() || other.host_ip.is_none() || self.host_ip == other.host_ip)
+    }
+
+    fn host(&self) -> String {
+        format!(
+            "{}:{}/{}",
+            self.host_ip.as_deref().unwrap_or("*"),
+            self.host_port,
+            self.protocol
+        )
+    }
+}
+
+/// Validate mappings from the runtime and normalize protocol and host IP
+pub fn normalize(mappings: &[PortMapping]) -> Result<Vec<PortMapping>, CniError> {
+    let mut normalized: Vec<PortMapping> = Vec::new();
+    for mapping in mappings {
+        let protocol = mapping.protocol.to_ascii_lowercase();
+        if !PROTOCOLS.contains(&protocol.as_str()) {
+            return Err(CniError::config_error(&format!(
+                "unsupported port mapping protocol: {}",
+                mapping.protocol
+            )));
+        }
+        if mapping.host_port == 0 || mapping.container_port == 0 {
+            return Err(CniError::config_error("port mappings need non-zero ports"));
+        }
+
+        let host_ip = match mapping.host_ip.as_deref().map(str::trim) {
+            None | Some("") | Some("0.0.0.0") => None,
+            Some(ip) => {
+                let ip: Ipv4Addr = ip.parse().map_err(|_| {
+                    CniError::config_error(&format!("unsupported port mapping host IP: {}", ip))
+                })?;
+                Some(ip.to_string())
+            }
+        };
+
+        let mapping = PortMapping {
+            host_port: mapping.host_port,
+            container_port: mapping.container_port,
+            protocol,
+            host_ip,
+        };
+        if let Some(other) = normalized.iter().find(|m| m.conflicts_with(&mapping)) {
+            return Err(CniError::config_error(&format!(
+                "host port {} is mapped twice",
+                other.host()
+            )));
+        }
+        normalized.push(mapping);
+    }
+    Ok(normalized)
+}
+
+/// Record a container's mappings and install the rules
+///
+/// Fails without changing anything if another container already maps one
+/// of the host ports.
+pub fn add(container_id: &str, mappings: Vec<PortMapping>) -> Result<(), CniError> {
+    ipam::update_state(|state| {
+        if !state.allocations.contains_key(container_id) {
+            return Err(CniError::new(
+                CniErrorCode::PortMappingFailed,
+                &format!("container {} has no IP allocation", container_id),
+            ));
+        }
+
+        prune(state);
+        check_conflicts(state, container_id, &mappings)?;
+
+        let summary: Vec<String> = mappings
+            .iter()
+            .map(|m| format!("{} -> {}", m.host(), m.container_port))
+            .collect();
+        state
+            .port_mappings
+            .insert(container_id.to_string(), mappings);
+        apply(&ruleset(state))?;
+
+        logger::info(&format!(
+            "portmap: mapped {} for container {}",
+            summary.join(", "),
+            container_id
+        ));
+        Ok(())
+    })
+}
+
+/// Drop the mappings of containers without an allocation
+///
+/// Returns true if any were dropped.
+fn prune(state: &mut IpamState) -> bool {
+    let allocations = &state.allocations;
+    let before = state.port_mappings.len();
+    state
+        .port_mappings
+        .retain(|container_id, _| allocations.contains_key(container_id));
+    state.port_mappings.len() != before
+}
+
+/// Fail if another allocated container maps one of the host ports
+fn check_conflicts(
+    state: &IpamState,
+    container_id: &str,
+    mappings: &[PortMapping],
+) -> Result<(), CniError> {
+    for (owner, existing) in &state.port_mappings {
+        if owner == container_id || !state.allocations.contains_key(owner) {
+            continue;
+        }
+        if let Some(mapping) = mappings
+            .iter()
+            .find(|m| existing.iter().any(|e| e.conflicts_with(m)))
+        {
+            return Err(CniError::new(
+                CniErrorCode::PortMappingFailed,
+                &format!(
+                    "host port {} is already mapped by container {}",
+                    mapping.host(),
+                    owner.get(..12).unwrap_or(owner)
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Drop a container's mappings and their rules
+///
+/// Also prunes the mappings of containers without an allocation. Failing to
+/// update the rules only logs a warning, so DEL still succeeds; the stale
+/// rules go away at the next successful update.
+pub fn remove(container_id: &str) -> Result<(), CniError> {
+    ipam::update_state(|state| {
+        let removed = state.port_mappings.remove(container_id).is_some();
+        if !prune(state) && !removed {
+            return Ok(());
+        }
+
+        match apply(&ruleset(state)) {
+            Ok(()) => logger::info(&format!(
+                "portmap: removed mappings of container {}",
+                container_id
+            )),
+            Err(e) => logger::warn(&format!(
+                "portmap: failed to remove rules of container {}: {}",
+                container_id,
+                e.details().unwrap_or(e.message())
+            )),
+        }
+        Ok(())
+    })
+}
+
+/// nftables script replacing the table with rules for `state`
+///
+/// Connections to a mapped port on any local address (or `hostIP`) are
+/// DNATed to the container, both from outside and from the host itself.
+/// Hairpin connections from containers on the bridge are masqueraded so
+/// the replies pass back through the host.
+pub fn ruleset(state: &IpamState) -> String {
+    let mut containers: Vec<(&String, &Vec<PortMapping>)> = state.port_mappings.iter().collect();
+    containers.sort_by_key(|(container_id, _)| *container_id);
+
+    let mut rules = Vec::new();
+    for (container_id, mappings) in containers {
+        let Some(ip) = state.allocations.get(container_id) else {
+            continue;
+        };
+        for mapping in mappings {
+            let daddr = mapping
+                .host_ip
+                .as_ref()
+                .map(|ip| format!("ip daddr {} ", ip))
+                .unwrap_or_default();
+            rules.push(format!(
+                "        {}{} dport {} dnat to {}:{} comment \"{}\"",
+                daddr,
+                mapping.protocol,
+                mapping.host_port,
+                ip,
+                mapping.container_port,
+                container_id.get(..12).unwrap_or(container_id)
+            ));
+        }
+    }
+
+    let mut script = format!(
+        "table ip {table}\ndelete table ip {table}\ntable ip {table} {{\n",
+        table = NFT_TABLE
+    );
+    script.push_str("    chain hostports {\n");
+    for rule in &rules {
+        script.push_str(rule);
+        script.push('\n');
+    }
+    script.push_str("    }\n");
+    script.push_str(
+        "    chain prerouting {\n\
+         \x20       type nat hook prerouting priority dstnat; policy accept;\n\
+         \x20       fib daddr type local jump hostports\n\
+         \x20   }\n\
+         \x20   chain output {\n\
+         \x20       type nat hook output priority -100; policy accept;\n\
+         \x20       fib daddr type local jump hostports\n\
+         \x20   }\n",
+    );
+    script.push_str(&format!(
+        "    chain postrouting {{\n\
+         \x20       type nat hook postrouting priority srcnat; policy accept;\n\
+         \x20       ct status dnat ip saddr {subnet} ip daddr {subnet} masquerade\n\
+         \x20   }}\n",
+        subnet = state.subnet
+    ));
+    script.push_str("}\n");
+    script
+}
+
+/// Replace the table in one `nft -f` transaction
+fn apply(script: &str) -> Result<(), CniError> {
+    nft::run(
+        script,
+        CniErrorCode::PortMappingFailed,
+        "failed to update port mapping rules",
+    )
+}
+
+/// Container ports exposed through mappings, for Gateway registration
+pub fn container_ports(mappings: &[PortMapping]) -> Vec<u16> {
+    let mut ports: Vec<u16> = mappings.iter().map(|m| m.container_port).collect();
+    ports.sort_unstable();
+    ports.dedup();
+    ports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(
+        host_port: u16,
+        container_port: u16,
+        protocol: &str,
+        host_ip: Option<&str>,
+    ) -> PortMapping {
+        PortMapping {
+            host_port,
+            container_port,
+            protocol: protocol.to_string(),
+            host_ip: host_ip.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_runtime_mappings() {
+        let json = r#"[
+            {"hostPort": 8443, "containerPort": 443, "protocol": "TCP", "hostIP": ""},
+            {"hostPort": 2222, "containerPort": 22, "hostIP": "192.168.1.10"},
+            {"hostPort": 8443, "containerPort": 443, "protocol": "udp"}
+        ]"#;
+        let mappings: Vec<PortMapping> = serde_json::from_str(json).unwrap();
+        let mappings = normalize(&mappings).unwrap();
+
+        assert_eq!(mappings[0], mapping(8443, 443, "tcp", None));
+        assert_eq!(mappings[1], mapping(2222, 22, "tcp", Some("192.168.1.10")));
+        assert_eq!(mappings[2].protocol, "udp");
+        assert_eq!(container_ports(&mappings), vec![22, 443]);
+    }
+
+    #[test]
+    fn test_normalize_rejects_invalid_mappings() {
+        let err = normalize(&[mapping(80, 80, "icmp", None)]).unwrap_err();
+        assert!(err.message().contains("protocol"));
+        assert!(normalize(&[mapping(0, 80, "tcp", None)]).is_err());
+        assert!(normalize(&[mapping(80, 80, "tcp", Some("fe80::1"))]).is_err());
+
+        // A wildcard host IP overlaps a specific one
+        let err = normalize(&[
+            mapping(80, 80, "tcp", Some("192.168.1.10")),
+            mapping(80, 8080, "tcp", None),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.message(),
+            "host port 192.168.1.10:80/tcp is mapped twice"
+        );
+        assert!(normalize(&[
+            mapping(80, 80, "tcp", Some("192.168.1.10")),
+            mapping(80, 80, "tcp", Some("192.168.1.11")),
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn test_conflicts_across_containers() {
+        let mut state = IpamState::default();
+        let a = "a".repeat(64);
+        state.allocations.insert(a.clone(), "10.87.1.2".to_string());
+        state
+            .port_mappings
+            .insert(a.clone(), vec![mapping(8443, 443, "tcp", None)]);
+
+        let err = check_conflicts(&state, "b", &[mapping(8443, 8443, "tcp", Some("10.0.0.5"))])
+            .unwrap_err();
+        assert_eq!(err.code(), CniErrorCode::PortMappingFailed);
+        assert_eq!(
+            err.message(),
+            "host port 10.0.0.5:8443/tcp is already mapped by container aaaaaaaaaaaa"
+        );
+
+        // Other protocols and ports are free, and a re-ADD may keep its own
+        assert!(check_conflicts(&state, "b", &[mapping(8443, 443, "udp", None)]).is_ok());
+        assert!(check_conflicts(&state, "b", &[mapping(8444, 443, "tcp", None)]).is_ok());
+        assert!(check_conflicts(&state, &a, &[mapping(8443, 443, "tcp", None)]).is_ok());
+    }
+
+    #[test]
+    fn test_unallocated_owners_are_pruned() {
+        let mut state = IpamState::default();
+        state
+            .allocations
+            .insert("a".repeat(64), "10.87.1.2".to_string());
+        state
+            .port_mappings
+            .insert("a".repeat(64), vec![mapping(8443, 443, "tcp", None)]);
+        state
+            .port_mappings
+            .insert("b".repeat(64), vec![mapping(80, 80, "tcp", None)]);
+
+        // A released container does not hold on to its host port
+        assert!(check_conflicts(&state, "c", &[mapping(80, 8080, "tcp", None)]).is_ok());
+        assert!(check_conflicts(&state, "c", &[mapping(8443, 443, "tcp", None)]).is_err());
+
+        assert!(prune(&mut state));
+        assert!(!state.port_mappings.contains_key(&"b".repeat(64)));
+        assert!(state.port_mappings.contains_key(&"a".repeat(64)));
+        assert!(!prune(&mut state));
+    }
+
+    #[test]
+    fn test_ruleset() {
+        let mut state = IpamState::default();
+        state
+            .allocations
+            .insert("a".repeat(64), "10.87.1.2".to_string());
+        state.port_mappings.insert(
+            "a".repeat(64),
+            vec![
+                mapping(8443, 443, "tcp", None),
+                mapping(2222, 22, "tcp", Some("192.168.1.10")),
+            ],
+        );
+        // Mappings of containers without an allocation are dropped
+        state
+            .port_mappings
+            .insert("b".repeat(64), vec![mapping(80, 80, "tcp", None)]);
+
+        let script = ruleset(&state);
+        assert!(script.starts_with(
+            "table ip flatnet_portmap\ndelete table ip flatnet_portmap\ntable ip flatnet_portmap {\n"
+        ));
+        assert!(script
+            .contains("        tcp dport 8443 dnat to 10.87.1.2:443 comment \"aaaaaaaaaaaa\"\n"));
+        assert!(
+            script.contains("        ip daddr 192.168.1.10 tcp dport 2222 dnat to 10.87.1.2:22")
+        );
+        assert!(!script.contains("dport 80 "));
+        assert!(script
+            .contains("ct status dnat ip saddr 10.87.1.0/24 ip daddr 10.87.1.0/24 masquerade"));
+    }
+}
@@ -10,10 +10,15 @@ use std::time::Duration;
 use serde::{Deserialize, Serialize};
 
 use crate::error::{CniError, CniErrorCode};
+use crate::logger;
+use crate::metrics;
 
 /// Default timeout for registry operations (5 seconds)
 const REGISTRY_TIMEOUT_SECS: u64 = 5;
 
+/// Header carrying the CNI operation ID; the Gateway logs it
+const OPERATION_ID_HEADER: &str = "X-Flatnet-Operation-ID";
+
 /// Container information to register with the Gateway
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -82,6 +87,7 @@ fn chrono_now() -> String {
 pub struct RegistryClient {
     endpoints: Vec<String>,
     timeout: Duration,
+    operation_id: Option<String>,
 }
 
 impl RegistryClient {
@@ -90,6 +96,7 @@ impl RegistryClient {
         Self {
             endpoints,
             timeout: Duration::from_secs(REGISTRY_TIMEOUT_SECS),
+            operation_id: None,
         }
     }
 
@@ -99,13 +106,19 @@ impl RegistryClient {
         self
     }
 
+    /// Send an operation ID with every request
+    pub fn with_operation_id(mut self, operation_id: String) -> Self {
+        self.operation_id = Some(operation_id);
+        self
+    }
+
     /// Register a container with all endpoints
     ///
     /// Returns Ok(()) if at least one endpoint succeeds.
     /// This allows graceful degradation when some Gateways are down.
     pub fn register(&self, info: &ContainerInfo) -> Result<(), CniError> {
         if self.endpoints.is_empty() {
-            eprintln!("flatnet registry: no endpoints configured, skipping registration");
+            logger::debug("registry: no endpoints configured, skipping registration");
             return Ok(());
         }
 
@@ -118,20 +131,22 @@ impl RegistryClient {
         let mut last_error = None;
 
         for endpoint in &self.endpoints {
-            match self.post_to_endpoint(endpoint, &body) {
+            let result = self.post_to_endpoint(endpoint, &body);
+            metrics::registration("register", result.is_ok());
+            match result {
                 Ok(()) => {
-                    eprintln!(
-                        "flatnet registry: registered container {} at {}",
+                    logger::info(&format!(
+                        "registry: registered container {} at {}",
                         info.id, endpoint
-                    );
+                    ));
                     success_count += 1;
                 }
                 Err(e) => {
-                    eprintln!(
-                        "flatnet registry: failed to register at {}: {}",
+                    logger::warn(&format!(
+                        "registry: failed to register at {}: {}",
                         endpoint,
                         e.message()
-                    );
+                    ));
                     last_error = Some(e);
                 }
             }
@@ -151,7 +166,7 @@ impl RegistryClient {
     /// Returns Ok(()) if at least one endpoint succeeds.
     pub fn deregister(&self, container_id: &str) -> Result<(), CniError> {
         if self.endpoints.is_empty() {
-            eprintln!("flatnet registry: no endpoints configured, skipping deregistration");
+            logger::debug("registry: no endpoints configured, skipping deregistration");
             return Ok(());
         }
 
@@ -159,20 +174,22 @@ impl RegistryClient {
         let mut last_error = None;
 
         for endpoint in &self.endpoints {
-            match self.delete_from_endpoint(endpoint, container_id) {
+            let result = self.delete_from_endpoint(endpoint, container_id);
+            metrics::registration("deregister", result.is_ok());
+            match result {
                 Ok(()) => {
-                    eprintln!(
-                        "flatnet registry: deregistered container {} from {}",
+                    logger::info(&format!(
+                        "registry: deregistered container {} from {}",
                         container_id, endpoint
-                    );
+                    ));
                     success_count += 1;
                 }
                 Err(e) => {
-                    eprintln!(
-                        "flatnet registry: failed to deregister from {}: {}",
+                    logger::warn(&format!(
+                        "registry: failed to deregister from {}: {}",
                         endpoint,
                         e.message()
-                    );
+                    ));
                     last_error = Some(e);
                 }
             }
@@ -181,13 +198,14 @@ impl RegistryClient {
         if success_count > 0 {
             Ok(())
         } else {
-            // For DEL operations, we're more lenient - log warning but don't fail
+            // For DEL operations, we're more lenient - log an error but don't
+            // fail. The Gateway keeps a stale entry until it is cleaned up.
             if let Some(e) = last_error {
-                eprintln!(
-                    "flatnet registry: WARNING - could not deregister {}: {}",
+                logger::error(&format!(
+                    "registry: could not deregister {}: {}",
                     container_id,
                     e.message()
-                );
+                ));
             }
             Ok(())
         }
@@ -200,6 +218,7 @@ impl RegistryClient {
         let request = format!(
             "POST {} HTTP/1.1\r\n\
              Host: {}\r\n\
+             {}\
              Content-Type: application/json\r\n\
              Content-Length: {}\r\n\
              Connection: close\r\n\
@@ -207,6 +226,7 @@ impl RegistryClient {
              {}",
             path,
             host,
+            self.operation_header(),
             body.len(),
             body
         );
@@ -224,15 +244,25 @@ impl RegistryClient {
         let request = format!(
             "DELETE {} HTTP/1.1\r\n\
              Host: {}\r\n\
+             {}\
              Connection: close\r\n\
              \r\n",
             delete_path,
-            host
+            host,
+            self.operation_header()
         );
 
         self.send_request(&host, port, &request)
     }
 
+    /// Operation ID header line (empty without an operation ID)
+    fn operation_header(&self) -> String {
+        match &self.operation_id {
+            Some(id) => format!("{}: {}\r\n", OPERATION_ID_HEADER, id),
+            None => String::new(),
+        }
+    }
+
     /// Send HTTP request and check response
     fn send_request(&self, host: &str, port: u16, request: &str) -> Result<(), CniError> {
         let addr = format!("{}:{}", host, port);
@@ -314,6 +344,15 @@ fn parse_endpoint(endpoint: &str) -> Result<(String, u16, String), CniError> {
     Ok((host, port, path.to_string()))
 }
 
+/// Registry client for the current invocation, tagged with its operation ID
+fn invocation_client(endpoints: &[String]) -> RegistryClient {
+    let client = RegistryClient::new(endpoints.to_vec());
+    match logger::operation_id() {
+        Some(id) => client.with_operation_id(id),
+        None => client,
+    }
+}
+
 /// Try to register a container, but don't fail the CNI operation if it fails
 ///
 /// This is used for graceful degradation - local networking should work
@@ -323,12 +362,12 @@ pub fn try_register(endpoints: &[String], info: &ContainerInfo) {
         return;
     }
 
-    let client = RegistryClient::new(endpoints.to_vec());
+    let client = invocation_client(endpoints);
     if let Err(e) = client.register(info) {
-        eprintln!(
-            "flatnet registry: WARNING - registration failed (container will work locally): {}",
+        logger::error(&format!(
+            "registry: registration failed (container will work locally): {}",
             e.message()
-        );
+        ));
     }
 }
 
@@ -338,18 +377,16 @@ pub fn try_deregister(endpoints: &[String], container_id: &str) {
         return;
     }
 
-    let client = RegistryClient::new(endpoints.to_vec());
+    let client = invocation_client(endpoints);
     if let Err(e) = client.deregister(container_id) {
-        eprintln!(
-            "flatnet registry: WARNING - deregistration failed: {}",
-            e.message()
-        );
+        logger::warn(&format!("registry: deregistration failed: {}", e.message()));
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use flatnet_mock_gateway::{Fault, MockGateway};
 
     #[test]
     fn test_parse_endpoint() {
@@ -404,6 +441,18 @@ mod tests {
         assert_eq!(info.ports, Some(vec![80, 443]));
     }
 
+    #[test]
+    fn test_operation_header() {
+        let client = RegistryClient::new(vec![]);
+        assert_eq!(client.operation_header(), "");
+
+        let client = client.with_operation_id("3f9a2c1d5e7b8a40".to_string());
+        assert_eq!(
+            client.operation_header(),
+            "X-Flatnet-Operation-ID: 3f9a2c1d5e7b8a40\r\n"
+        );
+    }
+
     #[test]
     fn test_registry_client_empty_endpoints() {
         let client = RegistryClient::new(vec![]);
@@ -413,4 +462,65 @@ mod tests {
         assert!(client.register(&info).is_ok());
         assert!(client.deregister("test").is_ok());
     }
+
+    fn web_container() -> ContainerInfo {
+        ContainerInfo::new("abc123".to_string(), "10.100.1.10".to_string(), 1)
+            .with_hostname("web".to_string())
+    }
+
+    #[test]
+    fn test_register_with_gateway() {
+        let gateway = MockGateway::start();
+        let client = RegistryClient::new(vec![gateway.registry_endpoint()])
+            .with_operation_id("3f9a2c1d5e7b8a40".to_string());
+
+        client.register(&web_container()).unwrap();
+        let registered = gateway.containers();
+        assert_eq!(registered.len(), 1);
+        assert_eq!(registered[0].ip, "10.100.1.10");
+        assert_eq!(registered[0].hostname.as_deref(), Some("web"));
+        assert_eq!(registered[0].op_id.as_deref(), Some("3f9a2c1d5e7b8a40"));
+
+        client.deregister("abc123").unwrap();
+        assert!(gateway.containers().is_empty());
+        let deletes = gateway.requests_to("DELETE", "/api/containers/abc123");
+        assert_eq!(
+            deletes[0].header(OPERATION_ID_HEADER),
+            Some("3f9a2c1d5e7b8a40")
+        );
+    }
+
+    #[test]
+    fn test_register_fails_over() {
+        let down = MockGateway::start();
+        let up = MockGateway::start();
+        down.fail("/api/containers", Fault::Status(503));
+        let client = RegistryClient::new(vec![down.registry_endpoint(), up.registry_endpoint()]);
+
+        // One Gateway is enough
+        client.register(&web_container()).unwrap();
+        assert!(down.containers().is_empty());
+        assert_eq!(up.containers().len(), 1);
+
+        up.fail("/api/containers", Fault::Disconnect);
+        let err = client.register(&web_container()).unwrap_err();
+        assert_eq!(err.message(), "invalid response from registry");
+
+        let client = RegistryClient::new(vec![down.registry_endpoint()]);
+        let err = client.register(&web_container()).unwrap_err();
+        assert!(err.message().contains("503 Service Unavailable"));
+        // Deregistration failures never fail DEL
+        assert!(client.deregister("abc123").is_ok());
+    }
+
+    #[test]
+    fn test_registry_timeout() {
+        let gateway = MockGateway::start();
+        gateway.delay("/api/containers", Duration::from_secs(2));
+        let client = RegistryClient::new(vec![gateway.registry_endpoint()])
+            .with_timeout(Duration::from_millis(200));
+
+        let err = client.register(&web_container()).unwrap_err();
+        assert_eq!(err.message(), "failed to read response");
+    }
 }
@@ -12,6 +12,7 @@ use tokio::runtime::Runtime;
 
 use crate::bridge;
 use crate::error::{CniError, CniErrorCode};
+use crate::logger;
 
 /// Maximum length for interface names (Linux limit is 15 + null terminator)
 const MAX_IFNAME_LEN: usize = 15;
@@ -19,6 +20,11 @@ const MAX_IFNAME_LEN: usize = 15;
 /// Prefix for host-side veth names
 const HOST_VETH_PREFIX: &str = "fn-";
 
+/// Prefix for the container-side name while it is still in the host namespace
+///
+/// Concurrent ADDs would collide if every peer were created as "eth0".
+const PEER_VETH_PREFIX: &str = "fp-";
+
 /// Result of veth pair creation
 pub struct VethPair {
     /// Host-side interface name
@@ -27,6 +33,8 @@ pub struct VethPair {
     pub host_index: u32,
     /// Container-side interface name
     pub container_ifname: String,
+    /// Temporary container-side name, renamed to `container_ifname` in the container
+    pub peer_ifname: String,
     /// MAC address of container interface
     pub mac_address: String,
 }
@@ -70,25 +78,27 @@ async fn create_veth_pair_async(
 
     tokio::spawn(connection);
 
-    // Generate host-side interface name: fn-<first 8 chars of container id>
+    // Generate interface names: fn-/fp-<first 12 hex chars of container id>
     let host_ifname = generate_host_ifname(container_id);
+    let peer_ifname = generate_peer_ifname(container_id);
 
-    eprintln!(
-        "flatnet: creating veth pair {} <-> {}",
+    logger::info(&format!(
+        "creating veth pair {} <-> {}",
         host_ifname, container_ifname
-    );
+    ));
 
     // Check if host veth already exists (idempotent ADD)
     if let Some(existing_index) = get_link_index(&handle, &host_ifname).await? {
-        eprintln!(
-            "flatnet: veth {} already exists (index {}), reusing",
+        logger::debug(&format!(
+            "veth {} already exists (index {}), reusing",
             host_ifname, existing_index
-        );
+        ));
         let mac_address = generate_mac_address(container_id);
         return Ok(VethPair {
             host_ifname,
             host_index: existing_index,
             container_ifname: container_ifname.to_string(),
+            peer_ifname,
             mac_address,
         });
     }
@@ -97,16 +107,16 @@ async fn create_veth_pair_async(
     match handle
         .link()
         .add()
-        .veth(host_ifname.clone(), container_ifname.to_string())
+        .veth(host_ifname.clone(), peer_ifname.clone())
         .execute()
         .await
     {
         Ok(()) => {}
         Err(rtnetlink::Error::NetlinkError(e)) if e.raw_code() == -libc::EEXIST => {
-            eprintln!(
-                "flatnet: veth {} created by another process, reusing",
+            logger::debug(&format!(
+                "veth {} created by another process, reusing",
                 host_ifname
-            );
+            ));
         }
         Err(e) => {
             return Err(
@@ -126,7 +136,7 @@ async fn create_veth_pair_async(
 
     // Get container veth index (still in host namespace)
     let container_index =
-        get_link_index(&handle, container_ifname)
+        get_link_index(&handle, &peer_ifname)
             .await?
             .ok_or_else(|| {
                 CniError::new(
@@ -178,15 +188,16 @@ async fn create_veth_pair_async(
     // Generate MAC address for container interface
     let mac_address = generate_mac_address(container_id);
 
-    eprintln!(
-        "flatnet: veth pair created, host {} (index {})",
+    logger::info(&format!(
+        "veth pair created, host {} (index {})",
         host_ifname, host_index
-    );
+    ));
 
     Ok(VethPair {
         host_ifname,
         host_index,
         container_ifname: container_ifname.to_string(),
+        peer_ifname,
         mac_address,
     })
 }
@@ -197,11 +208,13 @@ async fn create_veth_pair_async(
 ///
 /// # Arguments
 /// * `ifname` - Interface name (e.g., "eth0")
+/// * `peer_ifname` - Temporary name of the moved veth, renamed to `ifname`
 /// * `ip` - IP address to assign
 /// * `prefix_len` - Subnet prefix length
 /// * `gateway` - Gateway IP address
 pub fn configure_container_interface(
     ifname: &str,
+    peer_ifname: &str,
     ip: Ipv4Addr,
     prefix_len: u8,
     gateway: Ipv4Addr,
@@ -212,12 +225,13 @@ pub fn configure_container_interface(
     })?;
 
     rt.block_on(async {
-        configure_container_interface_async(ifname, ip, prefix_len, gateway).await
+        configure_container_interface_async(ifname, peer_ifname, ip, prefix_len, gateway).await
     })
 }
 
 async fn configure_container_interface_async(
     ifname: &str,
+    peer_ifname: &str,
     ip: Ipv4Addr,
     prefix_len: u8,
     gateway: Ipv4Addr,
@@ -229,18 +243,37 @@ async fn configure_container_interface_async(
 
     tokio::spawn(connection);
 
-    // Get interface index
-    let index = get_link_index(&handle, ifname).await?.ok_or_else(|| {
-        CniError::new(
-            CniErrorCode::VethCreationFailed,
-            &format!("interface {} not found in container", ifname),
-        )
-    })?;
+    // Get interface index, renaming the moved veth on first configuration
+    let index = match get_link_index(&handle, ifname).await? {
+        Some(index) => index,
+        None => {
+            let index = get_link_index(&handle, peer_ifname).await?.ok_or_else(|| {
+                CniError::new(
+                    CniErrorCode::VethCreationFailed,
+                    &format!("interface {} not found in container", ifname),
+                )
+            })?;
+            handle
+                .link()
+                .set(index)
+                .name(ifname.to_string())
+                .execute()
+                .await
+                .map_err(|e| {
+                    CniError::new(
+                        CniErrorCode::VethCreationFailed,
+                        &format!("failed to rename {} to {}", peer_ifname, ifname),
+                    )
+                    .with_details(&e.to_string())
+                })?;
+            index
+        }
+    };
 
     // Bring loopback up (best-effort, log warning on failure)
     if let Some(lo_index) = get_link_index(&handle, "lo").await? {
         if let Err(e) = handle.link().set(lo_index).up().execute().await {
-            eprintln!("flatnet: warning: failed to bring loopback up: {}", e);
+            logger::warn(&format!("failed to bring loopback up: {}", e));
         }
     }
 
@@ -253,10 +286,10 @@ async fn configure_container_interface_async(
     {
         Ok(()) => {}
         Err(rtnetlink::Error::NetlinkError(e)) if e.raw_code() == -libc::EEXIST => {
-            eprintln!(
-                "flatnet: IP address {}/{} already exists on interface",
+            logger::debug(&format!(
+                "IP address {}/{} already exists on interface",
                 ip, prefix_len
-            );
+            ));
         }
         Err(e) => {
             return Err(CniError::new(
@@ -285,7 +318,7 @@ async fn configure_container_interface_async(
     {
         Ok(()) => {}
         Err(rtnetlink::Error::NetlinkError(e)) if e.raw_code() == -libc::EEXIST => {
-            eprintln!("flatnet: default route already exists");
+            logger::debug("default route already exists");
         }
         Err(e) => {
             return Err(CniError::new(
@@ -296,10 +329,10 @@ async fn configure_container_interface_async(
         }
     }
 
-    eprintln!(
-        "flatnet: container interface {} configured with {}/{}",
+    logger::info(&format!(
+        "container interface {} configured with {}/{}",
         ifname, ip, prefix_len
-    );
+    ));
 
     Ok(())
 }
@@ -328,10 +361,13 @@ pub fn delete_veth(host_ifname: &str) -> Result<(), CniError> {
                     CniError::new(CniErrorCode::VethCreationFailed, "failed to delete veth")
                         .with_details(&e.to_string())
                 })?;
-                eprintln!("flatnet: deleted veth {}", host_ifname);
+                logger::info(&format!("deleted veth {}", host_ifname));
             }
             None => {
-                eprintln!("flatnet: veth {} already deleted or never existed", host_ifname);
+                logger::debug(&format!(
+                    "veth {} already deleted or never existed",
+                    host_ifname
+                ));
             }
         }
 
@@ -360,13 +396,22 @@ pub fn veth_exists(host_ifname: &str) -> Result<bool, CniError> {
 
 /// Generate the host-side veth interface name from container ID
 pub fn generate_host_ifname(container_id: &str) -> String {
+    prefixed_ifname(HOST_VETH_PREFIX, container_id)
+}
+
+/// Generate the temporary container-side veth name from container ID
+fn generate_peer_ifname(container_id: &str) -> String {
+    prefixed_ifname(PEER_VETH_PREFIX, container_id)
+}
+
+fn prefixed_ifname(prefix: &str, container_id: &str) -> String {
     let id_part: String = container_id
         .chars()
         .filter(|c| c.is_ascii_hexdigit())
-        .take(MAX_IFNAME_LEN - HOST_VETH_PREFIX.len())
+        .take(MAX_IFNAME_LEN - prefix.len())
         .collect();
 
-    format!("{}{}", HOST_VETH_PREFIX, id_part)
+    format!("{}{}", prefix, id_part)
 }
 
 /// Generate a MAC address based on container ID
@@ -426,7 +471,10 @@ pub fn setup_host_veth(host_index: u32, bridge_name: &str) -> Result<(), CniErro
     // Attach to bridge
     bridge::attach_to_bridge(bridge_index, host_index)?;
 
-    eprintln!("flatnet: attached veth (index {}) to bridge {}", host_index, bridge_name);
+    logger::info(&format!(
+        "attached veth (index {}) to bridge {}",
+        host_index, bridge_name
+    ));
 
     Ok(())
 }
@@ -454,6 +502,20 @@ mod tests {
         assert!(name.starts_with("fn-"));
     }
 
+    #[test]
+    fn test_generate_peer_ifname() {
+        // Same ID part as the host side, so concurrent ADDs never collide
+        let name = generate_peer_ifname("abc123def456");
+        assert_eq!(name, "fp-abc123def456");
+        assert_ne!(name, generate_host_ifname("abc123def456"));
+        assert_ne!(name, generate_peer_ifname("abc123def457"));
+
+        // Truncated to the kernel's limit like the host side
+        let name = generate_peer_ifname("0123456789abcdef0123456789abcdef");
+        assert!(name.len() <= MAX_IFNAME_LEN);
+        assert_eq!(name, "fp-0123456789ab");
+    }
+
     #[test]
     fn test_generate_mac_address() {
         let mac = generate_mac_address("abc123def456");
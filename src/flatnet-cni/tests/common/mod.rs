@@ -0,0 +1,459 @@
+//! Harness for running the plugin binary in throwaway network namespaces
+//!
+//! Each `TestEnv` owns a "host" namespace where the plugin runs (and creates
+//! its bridge) and a scratch directory for the IPAM state, log and metrics
+//! files. Containers get their own namespaces. Everything is removed on drop,
+//! so tests never touch the real host network or `/var/lib/flatnet`.
+//!
+//! Needs CAP_NET_ADMIN and CAP_SYS_ADMIN (for `ip netns`); tests call
+//! `require_net_admin!()` to skip otherwise.
+
+#![allow(dead_code)] // Not every test binary uses every helper
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use flatnet_mock_gateway::MockGateway;
+use nix::sched::{setns, CloneFlags};
+use serde_json::Value;
+
+const CAP_NET_ADMIN: u32 = 12;
+const CAP_SYS_ADMIN: u32 = 21;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Skip the current test unless it can create network namespaces
+#[allow(unused_macros)]
+macro_rules! require_net_admin {
+    () => {
+        if !common::has_net_admin() {
+            eprintln!("skipping: needs CAP_NET_ADMIN, CAP_SYS_ADMIN and iproute2");
+            return;
+        }
+    };
+}
+
+/// Check the effective capabilities and that `ip` is installed
+pub fn has_net_admin() -> bool {
+    let Ok(status) = fs::read_to_string("/proc/self/status") else {
+        return false;
+    };
+    let caps = status
+        .lines()
+        .find_map(|l| l.strip_prefix("CapEff:"))
+        .and_then(|v| u64::from_str_radix(v.trim(), 16).ok())
+        .unwrap_or(0);
+    let has = |cap: u32| caps & (1 << cap) != 0;
+
+    has(CAP_NET_ADMIN)
+        && has(CAP_SYS_ADMIN)
+        && Command::new("ip")
+            .arg("-V")
+            .stdout(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+}
+
+/// Whether the `nft` CLI is installed (needed for port mappings)
+pub fn has_nft() -> bool {
+    Command::new("nft")
+        .arg("-v")
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Unique name for namespaces and directories of this test process
+fn unique(kind: &str) -> String {
+    format!(
+        "fntest-{}-{}-{}",
+        kind,
+        std::process::id(),
+        NEXT_ID.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+/// Run `ip` and return its stdout, panicking on failure
+fn ip(args: &[&str]) -> String {
+    let output = Command::new("ip")
+        .args(args)
+        .output()
+        .expect("failed to run ip");
+    assert!(
+        output.status.success(),
+        "ip {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// A named network namespace, deleted on drop
+pub struct Netns {
+    pub name: String,
+}
+
+impl Netns {
+    pub fn new(kind: &str) -> Self {
+        let name = unique(kind);
+        ip(&["netns", "add", &name]);
+        Self { name }
+    }
+
+    /// Path passed as CNI_NETNS
+    pub fn path(&self) -> String {
+        format!("/var/run/netns/{}", self.name)
+    }
+
+    /// `ip -j <args>` in this namespace, parsed as JSON
+    pub fn ip_json(&self, args: &[&str]) -> Vec<Value> {
+        let mut full = vec!["-n", &self.name, "-j"];
+        full.extend_from_slice(args);
+        let out = ip(&full);
+        if out.trim().is_empty() {
+            return Vec::new();
+        }
+        serde_json::from_str(&out).expect("ip -j returned invalid JSON")
+    }
+
+    /// Link by name, if present
+    pub fn link(&self, name: &str) -> Option<Value> {
+        self.ip_json(&["link", "show"])
+            .into_iter()
+            .find(|l| l["ifname"] == name)
+    }
+
+    /// IPv4 addresses of a link in CIDR notation
+    pub fn addresses(&self, dev: &str) -> Vec<String> {
+        self.ip_json(&["-4", "addr", "show", "dev", dev])
+            .iter()
+            .flat_map(|l| l["addr_info"].as_array().cloned().unwrap_or_default())
+            .map(|a| format!("{}/{}", a["local"].as_str().unwrap_or(""), a["prefixlen"]))
+            .collect()
+    }
+
+    /// IPv4 routes of the main table
+    pub fn routes(&self) -> Vec<Value> {
+        self.ip_json(&["-4", "route", "show"])
+    }
+
+    /// Start a mock Gateway listening on this namespace's loopback
+    ///
+    /// The plugin runs inside the namespace, so it cannot reach a listener
+    /// on the test process's own loopback.
+    pub fn start_gateway(&self) -> MockGateway {
+        ip(&["-n", &self.name, "link", "set", "lo", "up"]);
+        let path = self.path();
+        // Only this thread switches namespaces; the listener stays in it
+        thread::spawn(move || {
+            let ns = fs::File::open(&path).expect("namespace missing");
+            setns(&ns, CloneFlags::CLONE_NEWNET).expect("setns failed");
+            MockGateway::start()
+        })
+        .join()
+        .expect("failed to start the mock Gateway")
+    }
+
+    /// Ruleset of an nftables table in this namespace, empty if it is missing
+    pub fn nft_table(&self, family: &str, table: &str) -> String {
+        let output = Command::new("ip")
+            .args([
+                "netns", "exec", &self.name, "nft", "list", "table", family, table,
+            ])
+            .output()
+            .expect("failed to run nft");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Load an nftables script into this namespace
+    pub fn nft(&self, script: &str) {
+        let mut child = Command::new("ip")
+            .args(["netns", "exec", &self.name, "nft", "-f", "-"])
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("failed to run nft");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(script.as_bytes())
+            .unwrap();
+        assert!(child.wait().unwrap().success(), "nft rejected:\n{}", script);
+    }
+
+    /// Names of the links enslaved to a bridge
+    pub fn bridge_ports(&self, bridge: &str) -> Vec<String> {
+        self.ip_json(&["link", "show", "master", bridge])
+            .iter()
+            .filter_map(|l| l["ifname"].as_str().map(String::from))
+            .collect()
+    }
+}
+
+impl Drop for Netns {
+    fn drop(&mut self) {
+        let _ = Command::new("ip")
+            .args(["netns", "del", &self.name])
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+/// Plugin invocation output
+pub struct PluginOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl PluginOutput {
+    fn from(output: Output) -> Self {
+        Self {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+
+    /// Parse stdout as a CNI result or error
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.stdout)
+            .unwrap_or_else(|e| panic!("invalid plugin output {:?}: {}", self.stdout, e))
+    }
+
+    /// Panic with the plugin's output unless it succeeded
+    pub fn expect_success(self, what: &str) -> Self {
+        assert!(
+            self.success,
+            "{} failed: stdout={} stderr={}",
+            what, self.stdout, self.stderr
+        );
+        self
+    }
+}
+
+/// A host namespace plus scratch directory for one test
+pub struct TestEnv {
+    pub host: Netns,
+    pub dir: PathBuf,
+    /// Gateway registry endpoint (registration is disabled without one)
+    pub registry: Option<String>,
+    /// Extra `ipam` settings for the network config
+    pub ipam: serde_json::Map<String, Value>,
+    /// Extra top-level settings for the network config
+    pub settings: serde_json::Map<String, Value>,
+}
+
+impl TestEnv {
+    pub fn new() -> Self {
+        Self {
+            host: Netns::new("host"),
+            dir: scratch_dir(),
+            registry: None,
+            ipam: serde_json::Map::new(),
+            settings: serde_json::Map::new(),
+        }
+    }
+
+    /// Register containers with a Gateway registry endpoint
+    pub fn with_registry(mut self, endpoint: String) -> Self {
+        self.registry = Some(endpoint);
+        self
+    }
+
+    /// Add a setting to the network config's `ipam` section
+    pub fn with_ipam(mut self, key: &str, value: Value) -> Self {
+        self.ipam.insert(key.to_string(), value);
+        self
+    }
+
+    /// Add a top-level setting to the network config
+    pub fn with_setting(mut self, key: &str, value: Value) -> Self {
+        self.settings.insert(key.to_string(), value);
+        self
+    }
+
+    /// Network config pointing IPAM, logs and metrics at the scratch directory
+    pub fn config(&self) -> String {
+        let config = network_config(&self.dir, self.registry.as_deref());
+        if self.ipam.is_empty() && self.settings.is_empty() {
+            return config;
+        }
+        let mut config: Value = serde_json::from_str(&config).unwrap();
+        for (key, value) in &self.ipam {
+            config["ipam"][key] = value.clone();
+        }
+        for (key, value) in &self.settings {
+            config[key] = value.clone();
+        }
+        config.to_string()
+    }
+
+    /// Run the plugin in the host namespace
+    pub fn run(
+        &self,
+        command: &str,
+        container_id: &str,
+        netns: &Netns,
+        ifname: &str,
+    ) -> PluginOutput {
+        self.run_with_args(command, container_id, netns, ifname, "")
+    }
+
+    /// Run the plugin in the host namespace with `CNI_ARGS`
+    pub fn run_with_args(
+        &self,
+        command: &str,
+        container_id: &str,
+        netns: &Netns,
+        ifname: &str,
+        cni_args: &str,
+    ) -> PluginOutput {
+        self.invoke(
+            command,
+            container_id,
+            netns,
+            ifname,
+            cni_args,
+            &self.config(),
+        )
+    }
+
+    /// Run ADD with a `runtimeConfig` section, as a runtime passes it
+    pub fn add_with_runtime_config(
+        &self,
+        container_id: &str,
+        netns: &Netns,
+        runtime_config: Value,
+    ) -> PluginOutput {
+        let mut config: Value = serde_json::from_str(&self.config()).unwrap();
+        config["runtimeConfig"] = runtime_config;
+        self.invoke("ADD", container_id, netns, "eth0", "", &config.to_string())
+    }
+
+    fn invoke(
+        &self,
+        command: &str,
+        container_id: &str,
+        netns: &Netns,
+        ifname: &str,
+        cni_args: &str,
+        config: &str,
+    ) -> PluginOutput {
+        let mut cmd = Command::new("ip");
+        cmd.args([
+            "netns",
+            "exec",
+            &self.host.name,
+            env!("CARGO_BIN_EXE_flatnet"),
+        ])
+        .env("CNI_ARGS", cni_args);
+        invoke(cmd, command, container_id, &netns.path(), ifname, config)
+    }
+
+    pub fn add(&self, container_id: &str, netns: &Netns) -> PluginOutput {
+        self.run("ADD", container_id, netns, "eth0")
+    }
+
+    pub fn check(&self, container_id: &str, netns: &Netns) -> PluginOutput {
+        self.run("CHECK", container_id, netns, "eth0")
+    }
+
+    pub fn del(&self, container_id: &str, netns: &Netns) -> PluginOutput {
+        self.run("DEL", container_id, netns, "eth0")
+    }
+
+    /// IPAM allocations (container ID -> IP)
+    pub fn allocations(&self) -> serde_json::Map<String, Value> {
+        let path = self.dir.join("ipam").join("allocations.json");
+        let content = fs::read_to_string(&path).expect("IPAM state missing");
+        let state: Value = serde_json::from_str(&content).expect("IPAM state is invalid JSON");
+        state["allocations"]
+            .as_object()
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Fresh scratch directory for IPAM state, logs and metrics
+pub fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(unique("dir"));
+    fs::create_dir_all(&dir).expect("failed to create test directory");
+    dir
+}
+
+/// Network config keeping IPAM state, logs and metrics under `dir`
+pub fn network_config(dir: &Path, registry: Option<&str>) -> String {
+    serde_json::json!({
+        "cniVersion": "1.0.0",
+        "name": "flatnet-test",
+        "type": "flatnet",
+        "bridge": "flatnet-br0",
+        "ipam": {
+            "type": "flatnet-ipam",
+            "subnet": "10.87.1.0/24",
+            "gateway": "10.87.1.1",
+            "dataDir": dir.join("ipam"),
+        },
+        "registryEnabled": registry.is_some(),
+        "registryEndpoints": registry.into_iter().collect::<Vec<_>>(),
+        "logFile": dir.join("cni.log"),
+        "logLevel": "debug",
+        "metricsFile": dir.join("flatnet_cni.prom"),
+        "policyFile": dir.join("policy.json"),
+    })
+    .to_string()
+}
+
+/// Run the plugin with the CNI environment, writing `config` to its stdin
+///
+/// `cmd` is the plugin binary itself or a wrapper such as `ip netns exec`.
+pub fn invoke(
+    mut cmd: Command,
+    command: &str,
+    container_id: &str,
+    netns: &str,
+    ifname: &str,
+    config: &str,
+) -> PluginOutput {
+    let mut child = cmd
+        .env("CNI_COMMAND", command)
+        .env("CNI_CONTAINERID", container_id)
+        .env("CNI_NETNS", netns)
+        .env("CNI_IFNAME", ifname)
+        .env("CNI_PATH", "/opt/cni/bin")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run the plugin");
+
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(config.as_bytes())
+        .expect("failed to write the network config");
+
+    PluginOutput::from(child.wait_with_output().expect("plugin did not exit"))
+}
+
+/// Container ID as generated by Podman (64 hex chars, unique in the first 12)
+pub fn container_id(seed: usize) -> String {
+    format!("{:08x}{}", seed, "ab".repeat(28))
+}
+
+/// Host-side veth name of a container (see `veth::generate_host_ifname`)
+pub fn host_ifname(container_id: &str) -> String {
+    format!("fn-{}", &container_id[..12])
+}
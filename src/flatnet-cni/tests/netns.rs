@@ -0,0 +1,327 @@
+//! Plugin integration tests in throwaway network namespaces
+//!
+//! Runs the real binary for ADD/CHECK/DEL and inspects the resulting links,
+//! addresses, routes and IPAM state. Skipped without CAP_NET_ADMIN; run with
+//! `sudo -E cargo test --test netns`.
+
+#[macro_use]
+mod common;
+
+use common::{container_id, host_ifname, Netns, TestEnv};
+use flatnet_mock_gateway::Fault;
+use serde_json::json;
+use std::collections::HashSet;
+
+#[test]
+fn test_add_configures_host_and_container() {
+    require_net_admin!();
+    let env = TestEnv::new();
+    let container = Netns::new("ctr");
+    let id = container_id(1);
+
+    let result = env.add(&id, &container).expect_success("ADD").json();
+    assert_eq!(result["cniVersion"], "1.0.0");
+    assert_eq!(result["ips"][0]["address"], "10.87.1.2/24");
+    assert_eq!(result["ips"][0]["gateway"], "10.87.1.1");
+    assert_eq!(result["interfaces"][0]["name"], host_ifname(&id));
+    assert_eq!(result["interfaces"][1]["sandbox"], container.path());
+
+    // Host side: bridge with the gateway address, veth attached to it
+    let bridge = env.host.link("flatnet-br0").expect("bridge missing");
+    assert!(bridge["flags"].as_array().unwrap().contains(&"UP".into()));
+    assert_eq!(env.host.addresses("flatnet-br0"), vec!["10.87.1.1/24"]);
+    assert_eq!(env.host.bridge_ports("flatnet-br0"), vec![host_ifname(&id)]);
+    // The temporary peer name never stays behind
+    assert!(env.host.link(&format!("fp-{}", &id[..12])).is_none());
+
+    // Container side: eth0 with the allocated address and a default route
+    assert!(container.link("eth0").is_some());
+    assert_eq!(container.addresses("eth0"), vec!["10.87.1.2/24"]);
+    let routes = container.routes();
+    let default = routes
+        .iter()
+        .find(|r| r["dst"] == "default")
+        .expect("default route missing");
+    assert_eq!(default["gateway"], "10.87.1.1");
+    assert_eq!(default["dev"], "eth0");
+
+    assert_eq!(env.allocations()[&id], "10.87.1.2");
+}
+
+#[test]
+fn test_check_and_del() {
+    require_net_admin!();
+    let env = TestEnv::new();
+    let container = Netns::new("ctr");
+    let id = container_id(2);
+
+    env.add(&id, &container).expect_success("ADD");
+    let check = env.check(&id, &container).expect_success("CHECK");
+    assert!(check.stdout.trim().is_empty());
+
+    let del = env.del(&id, &container).expect_success("DEL");
+    assert!(del.stdout.trim().is_empty());
+    assert!(env.host.link(&host_ifname(&id)).is_none());
+    assert!(container.link("eth0").is_none());
+    assert!(!env.allocations().contains_key(&id));
+
+    // CHECK reports the missing veth as a CNI error
+    let check = env.check(&id, &container);
+    assert!(!check.success);
+    assert!(check.json()["msg"]
+        .as_str()
+        .unwrap()
+        .contains("does not exist"));
+
+    // DEL is idempotent
+    env.del(&id, &container).expect_success("second DEL");
+}
+
+#[test]
+fn test_readd_is_idempotent() {
+    require_net_admin!();
+    let env = TestEnv::new();
+    let container = Netns::new("ctr");
+    let id = container_id(3);
+
+    let first = env.add(&id, &container).expect_success("ADD").json();
+    let second = env.add(&id, &container).expect_success("re-ADD").json();
+    assert_eq!(first["ips"], second["ips"]);
+
+    assert_eq!(env.host.bridge_ports("flatnet-br0"), vec![host_ifname(&id)]);
+    assert_eq!(container.addresses("eth0"), vec!["10.87.1.2/24"]);
+    assert_eq!(env.allocations().len(), 1);
+    env.check(&id, &container)
+        .expect_success("CHECK after re-ADD");
+}
+
+#[test]
+fn test_add_static_and_reserved_ips() {
+    require_net_admin!();
+    let env = TestEnv::new().with_ipam("reservations", json!({"db": "10.87.1.2"}));
+    let containers: Vec<Netns> = (0..4).map(|_| Netns::new("ctr")).collect();
+    let ids: Vec<String> = (0..4).map(|i| container_id(200 + i)).collect();
+
+    // The reserved address is skipped for other containers...
+    let result = env
+        .add(&ids[0], &containers[0])
+        .expect_success("ADD")
+        .json();
+    assert_eq!(result["ips"][0]["address"], "10.87.1.3/24");
+
+    // ...and given to the container with the reserved name
+    let result = env
+        .run_with_args(
+            "ADD",
+            &ids[1],
+            &containers[1],
+            "eth0",
+            "IgnoreUnknown=1;K8S_POD_NAME=db",
+        )
+        .expect_success("ADD db")
+        .json();
+    assert_eq!(result["ips"][0]["address"], "10.87.1.2/24");
+    assert_eq!(containers[1].addresses("eth0"), vec!["10.87.1.2/24"]);
+
+    let result = env
+        .run_with_args("ADD", &ids[2], &containers[2], "eth0", "IP=10.87.1.100")
+        .expect_success("ADD static")
+        .json();
+    assert_eq!(result["ips"][0]["address"], "10.87.1.100/24");
+
+    // Conflicts are CNI errors naming the owner
+    let conflict = env.run_with_args("ADD", &ids[3], &containers[3], "eth0", "IP=10.87.1.100");
+    assert!(!conflict.success);
+    let msg = conflict.json()["msg"].as_str().unwrap().to_string();
+    assert!(msg.contains("already allocated to container"), "{}", msg);
+    assert!(!env.allocations().contains_key(&ids[3]));
+}
+
+#[test]
+fn test_add_maps_host_ports() {
+    require_net_admin!();
+    if !common::has_nft() {
+        eprintln!("skipping: needs nft");
+        return;
+    }
+    let env = TestEnv::new();
+    let containers: Vec<Netns> = (0..2).map(|_| Netns::new("ctr")).collect();
+    let ids: Vec<String> = (0..2).map(|i| container_id(300 + i)).collect();
+    let mappings = json!({"portMappings": [
+        {"hostPort": 8080, "containerPort": 80, "protocol": "tcp"},
+        {"hostPort": 5353, "containerPort": 53, "protocol": "udp"},
+    ]});
+
+    env.add_with_runtime_config(&ids[0], &containers[0], mappings.clone())
+        .expect_success("ADD");
+    let table = env.host.nft_table("ip", "flatnet_portmap");
+    assert!(
+        table.contains("tcp dport 8080 dnat to 10.87.1.2:80"),
+        "{}",
+        table
+    );
+    assert!(table.contains("udp dport 5353 dnat to 10.87.1.2:53"));
+
+    // A second container cannot claim the same host port
+    let conflict = env.add_with_runtime_config(&ids[1], &containers[1], mappings);
+    assert!(!conflict.success);
+    let error = conflict.json();
+    assert_eq!(error["code"], 105);
+    assert!(error["msg"].as_str().unwrap().contains("8080"), "{}", error);
+
+    // The runtime cleans up with DEL, which leaves the owner's rules alone
+    env.del(&ids[1], &containers[1])
+        .expect_success("DEL after failed ADD");
+    assert!(!env.allocations().contains_key(&ids[1]));
+    let table = env.host.nft_table("ip", "flatnet_portmap");
+    assert!(table.contains("tcp dport 8080 dnat to 10.87.1.2:80"));
+
+    env.del(&ids[0], &containers[0]).expect_success("DEL");
+    let table = env.host.nft_table("ip", "flatnet_portmap");
+    assert!(!table.contains("dnat"), "{}", table);
+}
+
+#[test]
+fn test_concurrent_adds() {
+    require_net_admin!();
+    const CONTAINERS: usize = 8;
+    let env = TestEnv::new();
+    let containers: Vec<Netns> = (0..CONTAINERS).map(|_| Netns::new("ctr")).collect();
+    let ids: Vec<String> = (0..CONTAINERS).map(|i| container_id(100 + i)).collect();
+
+    // Start from a host namespace without a bridge, so the ADDs also race
+    // to create it
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = ids
+            .iter()
+            .zip(&containers)
+            .map(|(id, container)| scope.spawn(|| env.add(id, container)))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut addresses = HashSet::new();
+    for (i, result) in results.into_iter().enumerate() {
+        let result = result.expect_success(&format!("ADD #{}", i)).json();
+        let address = result["ips"][0]["address"].as_str().unwrap().to_string();
+        assert_eq!(containers[i].addresses("eth0"), vec![address.clone()]);
+        addresses.insert(address);
+    }
+    assert_eq!(addresses.len(), CONTAINERS, "duplicate addresses allocated");
+
+    let allocations = env.allocations();
+    assert_eq!(allocations.len(), CONTAINERS);
+    let mut ports = env.host.bridge_ports("flatnet-br0");
+    ports.sort();
+    let mut expected: Vec<String> = ids.iter().map(|id| host_ifname(id)).collect();
+    expected.sort();
+    assert_eq!(ports, expected);
+}
+
+#[test]
+fn test_add_registers_with_gateway() {
+    require_net_admin!();
+    let env = TestEnv::new();
+    let gateway = env.host.start_gateway();
+    let env = env.with_registry(gateway.registry_endpoint());
+    let container = Netns::new("ctr");
+    let id = container_id(4);
+
+    env.add(&id, &container).expect_success("ADD");
+    let registered = gateway.containers();
+    assert_eq!(registered.len(), 1);
+    assert_eq!(registered[0].id, id);
+    assert_eq!(registered[0].ip, "10.87.1.2");
+    assert!(registered[0].op_id.is_some());
+
+    env.del(&id, &container).expect_success("DEL");
+    assert!(gateway.containers().is_empty());
+
+    // A failing registry does not fail ADD; the container works locally
+    // (on the next address, as allocation is round-robin)
+    gateway.fail("/api/containers", Fault::Status(503));
+    env.add(&id, &container)
+        .expect_success("ADD without registry");
+    assert_eq!(container.addresses("eth0"), vec!["10.87.1.3/24"]);
+}
+
+#[test]
+fn test_add_hands_out_dns_and_registers_name() {
+    require_net_admin!();
+    let env = TestEnv::new().with_setting("dnsEnabled", json!(true));
+    let gateway = env.host.start_gateway();
+    let env = env.with_registry(gateway.registry_endpoint());
+    let container = Netns::new("ctr");
+    let id = container_id(5);
+
+    // `flatnet dns serve` answers on the bridge IP
+    let result = env
+        .run_with_args("ADD", &id, &container, "eth0", "K8S_POD_NAME=web")
+        .expect_success("ADD")
+        .json();
+    assert_eq!(result["dns"]["nameservers"], json!(["10.87.1.1"]));
+    assert_eq!(result["dns"]["search"], json!(["flatnet"]));
+
+    // The name is registered for hosts resolving it remotely
+    let registered = gateway.containers();
+    assert_eq!(registered[0].hostname.as_deref(), Some("web"));
+}
+
+#[test]
+fn test_add_joins_policy_sets() {
+    require_net_admin!();
+    if !common::has_nft() {
+        eprintln!("skipping: needs nft");
+        return;
+    }
+    // Sets as `flatnet policy apply` leaves them: c0 for db*, c1 for app=web
+    let env = TestEnv::new();
+    for family in ["inet", "bridge"] {
+        env.host.nft(&format!(
+            "table {} flatnet_policy {{\n    set c0 {{ type ipv4_addr; }}\n    set c1 {{ type ipv4_addr; }}\n}}\n",
+            family
+        ));
+    }
+    std::fs::write(
+        env.dir.join("policy.json"),
+        json!({
+            "table": "flatnet_policy",
+            "container_sets": [
+                {"name": "c0", "names": ["db*"], "labels": {}},
+                {"name": "c1", "names": [], "labels": {"app": "web"}}
+            ]
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let containers: Vec<Netns> = (0..2).map(|_| Netns::new("ctr")).collect();
+    let ids: Vec<String> = (0..2).map(|i| container_id(400 + i)).collect();
+
+    env.run_with_args("ADD", &ids[0], &containers[0], "eth0", "K8S_POD_NAME=db-1")
+        .expect_success("ADD");
+    let labelled = env.with_setting(
+        "args",
+        json!({"cni": {"labels": [{"key": "app", "value": "web"}]}}),
+    );
+    labelled
+        .add(&ids[1], &containers[1])
+        .expect_success("ADD with labels");
+
+    for family in ["inet", "bridge"] {
+        let table = labelled.host.nft_table(family, "flatnet_policy");
+        let c1 = table.find("set c1").unwrap();
+        assert!(table[..c1].contains("10.87.1.2"), "{}", table);
+        assert!(!table[..c1].contains("10.87.1.3"), "{}", table);
+        assert!(table[c1..].contains("10.87.1.3"), "{}", table);
+        assert!(!table[c1..].contains("10.87.1.2"), "{}", table);
+    }
+
+    for (id, container) in ids.iter().zip(&containers) {
+        labelled.del(id, container).expect_success("DEL");
+    }
+    for family in ["inet", "bridge"] {
+        let table = labelled.host.nft_table(family, "flatnet_policy");
+        assert!(table.contains("set c0"), "{}", table);
+        assert!(!table.contains("10.87.1."), "{}", table);
+    }
+}
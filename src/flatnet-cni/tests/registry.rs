@@ -0,0 +1,80 @@
+//! Gateway deregistration by the plugin binary, against a mock Gateway
+//!
+//! DEL needs no privileges once the container's veth is gone, so these run
+//! unprivileged. Registration on ADD is covered in `netns.rs`.
+
+mod common;
+
+use common::{container_id, invoke, network_config, scratch_dir, PluginOutput};
+use flatnet_mock_gateway::{Container, Fault, MockGateway};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const OPERATION_ID_HEADER: &str = "X-Flatnet-Operation-ID";
+
+/// Run DEL for a container whose namespace and veth are already gone
+fn del(dir: &Path, registry: &str, id: &str) -> PluginOutput {
+    invoke(
+        Command::new(env!("CARGO_BIN_EXE_flatnet")),
+        "DEL",
+        id,
+        "/var/run/netns/fntest-gone",
+        "eth0",
+        &network_config(dir, Some(registry)),
+    )
+}
+
+#[test]
+fn test_del_deregisters() {
+    let gateway = MockGateway::start();
+    let dir = scratch_dir();
+    let id = container_id(1);
+    gateway.add_container(Container::new(&id, "10.87.1.2", 1));
+
+    let output = del(&dir, &gateway.registry_endpoint(), &id).expect_success("DEL");
+    assert!(output.stdout.trim().is_empty());
+    assert!(gateway.containers().is_empty());
+
+    // The request carries the invocation's operation ID, as do the log lines
+    let deletes = gateway.requests_to("DELETE", &format!("/api/containers/{}", id));
+    assert_eq!(deletes.len(), 1);
+    let op_id = deletes[0]
+        .header(OPERATION_ID_HEADER)
+        .expect("operation ID header missing");
+    let log = fs::read_to_string(dir.join("cni.log")).unwrap();
+    assert!(
+        log.contains(op_id),
+        "operation {} not in log:\n{}",
+        op_id,
+        log
+    );
+
+    // DEL is idempotent on the Gateway side too
+    del(&dir, &gateway.registry_endpoint(), &id).expect_success("second DEL");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_del_survives_gateway_failures() {
+    let dir = scratch_dir();
+    let id = container_id(2);
+
+    for fault in [Fault::Status(500), Fault::Disconnect, Fault::Malformed] {
+        let gateway = MockGateway::start();
+        gateway.fail("/api/containers", fault.clone());
+        let output = del(&dir, &gateway.registry_endpoint(), &id);
+        assert!(
+            output.success,
+            "DEL failed with {:?}: {}",
+            fault, output.stdout
+        );
+        assert_eq!(gateway.requests().len(), 1);
+    }
+
+    // Nothing listens on port 1
+    del(&dir, "http://127.0.0.1:1/api/containers", &id).expect_success("DEL");
+    let log = fs::read_to_string(dir.join("cni.log")).unwrap();
+    assert!(log.contains("registry: failed to deregister"));
+    let _ = fs::remove_dir_all(&dir);
+}
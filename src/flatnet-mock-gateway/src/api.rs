@@ -0,0 +1,460 @@
+//! Gateway API endpoints
+//!
+//! Mirrors the handlers in `config/openresty/conf.d/api.conf` and
+//! `lualib/flatnet/api/containers.lua`, backed by in-memory state.
+
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::http::{Request, Response};
+use crate::{Container, Peer};
+
+/// Escalation state of an IP without one (`escalation.get_state`)
+const DEFAULT_STATE: &str = "GATEWAY_ONLY";
+
+/// Escalation states counted by `/api/escalation/stats`
+const STATES: [&str; 4] = [
+    "GATEWAY_ONLY",
+    "P2P_ATTEMPTING",
+    "P2P_ACTIVE",
+    "GATEWAY_FALLBACK",
+];
+
+/// Registry TTL reported by `GET /api/containers`
+const CONTAINER_TTL: u64 = 300;
+
+/// Gateway state
+#[derive(Debug)]
+pub(crate) struct State {
+    pub host_id: u8,
+    pub sync_interval: u64,
+    pub containers: Vec<Container>,
+    pub peers: Vec<Peer>,
+    /// Peers whose pulls fail, with the error to record
+    pub failing_peers: BTreeMap<String, String>,
+    pub last_sync: Option<i64>,
+    /// Escalation state by container IP
+    pub escalation: BTreeMap<String, String>,
+    /// Consecutive healthcheck failures by container IP
+    pub health_failures: BTreeMap<String, u32>,
+    pub healthcheck_enabled: bool,
+    pub failure_threshold: u32,
+    /// Responses by status code, for `/metrics`
+    pub responses: BTreeMap<u16, u64>,
+}
+
+impl State {
+    pub fn new(host_id: u8) -> Self {
+        Self {
+            host_id,
+            sync_interval: 30,
+            containers: Vec::new(),
+            peers: Vec::new(),
+            failing_peers: BTreeMap::new(),
+            last_sync: None,
+            escalation: BTreeMap::new(),
+            health_failures: BTreeMap::new(),
+            healthcheck_enabled: true,
+            failure_threshold: 3,
+            responses: BTreeMap::new(),
+        }
+    }
+
+    /// Route an API request
+    pub fn handle(&mut self, req: &Request) -> Response {
+        let method = req.method.as_str();
+        let path = req.path.as_str();
+
+        if let Some(id) = path.strip_prefix("/api/containers/") {
+            return self.container(method, id);
+        }
+
+        match (method, path) {
+            ("GET", "/api/health") => Response::text(200, "OK\n"),
+            ("GET", "/api/status") => Response::json(200, self.status()),
+            ("GET", "/api/containers") => Response::json(200, self.list_containers()),
+            ("POST", "/api/containers") => self.register(req),
+            ("DELETE", "/api/containers") => {
+                Response::error(400, "container ID required for DELETE")
+            }
+            ("GET", "/api/sync/status") => Response::json(200, self.sync_status()),
+            (_, "/api/sync/pull") => self.sync_pull(req),
+            (_, "/api/sync/peers") => self.sync_peers(req),
+            ("GET", "/api/escalation/state") => self.escalation_state(req),
+            ("GET", "/api/escalation/states") => Response::json(200, self.escalation_states()),
+            ("GET", "/api/escalation/stats") => Response::json(200, self.escalation_stats()),
+            (_, "/api/escalation/attempt") => self.escalation_update(req, "P2P_ACTIVE"),
+            (_, "/api/escalation/reset") => self.escalation_update(req, DEFAULT_STATE),
+            ("GET", "/api/routing/routes") => Response::json(200, self.routes()),
+            ("GET", "/api/routing/stats") => Response::json(200, self.routing_stats()),
+            ("GET", "/api/healthcheck/status") => Response::json(200, self.healthcheck()),
+            ("GET", "/metrics") => Response::text(200, self.metrics()),
+            _ => Response::error(404, "not found"),
+        }
+    }
+
+    fn status(&self) -> Value {
+        json!({
+            "status": "running",
+            "service": "flatnet-gateway-api",
+            "stage": "4",
+            "registry": {
+                "count": self.containers.len(),
+                "shared_dict_name": "flatnet_containers",
+            },
+            "sync": self.sync_status(),
+            "escalation": self.escalation_stats(),
+            "routing": self.routing_stats(),
+            "healthcheck": self.healthcheck(),
+        })
+    }
+
+    fn list_containers(&self) -> Value {
+        let containers: Vec<Value> = self
+            .containers
+            .iter()
+            .map(|c| {
+                json!({
+                    "id": c.id,
+                    "ip": c.ip,
+                    "hostname": c.hostname,
+                    "ports": c.ports,
+                    "hostId": c.host_id,
+                    "createdAt": c.created_at,
+                    "ttl": CONTAINER_TTL,
+                })
+            })
+            .collect();
+        Value::Array(containers)
+    }
+
+    /// `GET`, `POST` and `DELETE /api/containers/:id`
+    fn container(&mut self, method: &str, id: &str) -> Response {
+        if id.is_empty()
+            || id.len() > 128
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Response::error(400, "invalid container ID");
+        }
+
+        match method {
+            "GET" => match self.containers.iter().find(|c| c.id == id) {
+                Some(container) => Response::json(200, json!(container)),
+                None => Response::json(404, json!({ "error": "container not found", "id": id })),
+            },
+            "DELETE" => {
+                // Idempotent: deleting an unknown container succeeds
+                let before = self.containers.len();
+                self.containers.retain(|c| c.id != id);
+                if self.containers.len() == before {
+                    return Response::json(
+                        200,
+                        json!({ "success": true, "id": id, "note": "already deleted" }),
+                    );
+                }
+                Response::json(200, json!({ "success": true, "id": id }))
+            }
+            _ => Response::error(405, "method not allowed"),
+        }
+    }
+
+    /// `POST /api/containers`
+    fn register(&mut self, req: &Request) -> Response {
+        let data = req.json();
+        if !data.is_object() {
+            return Response::json(
+                400,
+                json!({ "error": "invalid request body", "details": "invalid JSON" }),
+            );
+        }
+        if !data["id"].is_string() || !data["ip"].is_string() {
+            return Response::error(400, "missing required fields: id, ip");
+        }
+
+        let mut container: Container = match serde_json::from_value(data.clone()) {
+            Ok(container) => container,
+            Err(e) => {
+                return Response::json(
+                    400,
+                    json!({ "error": "invalid request body", "details": e.to_string() }),
+                )
+            }
+        };
+        // Peers forward the operation ID in the body
+        container.op_id = req
+            .header("X-Flatnet-Operation-ID")
+            .map(String::from)
+            .or(container.op_id);
+
+        let id = container.id.clone();
+        match self.containers.iter_mut().find(|c| c.id == id) {
+            Some(existing) => *existing = container,
+            None => self.containers.push(container),
+        }
+        Response::json(201, json!({ "success": true, "id": id }))
+    }
+
+    fn sync_status(&self) -> Value {
+        let endpoints: Vec<&str> = self.peers.iter().map(|p| p.endpoint.as_str()).collect();
+        json!({
+            "enabled": !self.peers.is_empty(),
+            "host_id": self.host_id,
+            "peer_count": self.peers.len(),
+            "peers": endpoints,
+            "last_sync": self.last_sync,
+            "sync_interval": self.sync_interval,
+            "sync_ttl": CONTAINER_TTL,
+            "local_containers": self.containers.len(),
+        })
+    }
+
+    /// `POST /api/sync/pull[?peer=<url>]`
+    fn sync_pull(&mut self, req: &Request) -> Response {
+        if req.method != "POST" {
+            return Response::error(405, "method not allowed");
+        }
+
+        let only = req.query("peer");
+        if let Some(peer) = only {
+            if !self.peers.iter().any(|p| p.endpoint == peer) {
+                return Response::json(404, json!({ "error": "peer not found", "endpoint": peer }));
+            }
+        }
+
+        let now = now();
+        let (mut success, mut failure) = (0, 0);
+        for peer in &mut self.peers {
+            if only.is_some_and(|o| o != peer.endpoint) {
+                continue;
+            }
+            peer.last_attempt = Some(now);
+            match self.failing_peers.get(&peer.endpoint) {
+                Some(error) => {
+                    peer.last_error = Some(error.clone());
+                    peer.consecutive_failures += 1;
+                    failure += 1;
+                }
+                None => {
+                    peer.last_success = Some(now);
+                    peer.last_error = None;
+                    peer.consecutive_failures = 0;
+                    success += 1;
+                }
+            }
+        }
+        self.last_sync = Some(now);
+
+        Response::json(
+            200,
+            json!({ "action": "pull", "success_count": success, "failure_count": failure }),
+        )
+    }
+
+    /// `GET`, `POST` and `DELETE /api/sync/peers`
+    fn sync_peers(&mut self, req: &Request) -> Response {
+        match req.method.as_str() {
+            "GET" => Response::json(200, json!(self.peers)),
+            "POST" => {
+                let data = req.json();
+                let endpoint = match data["endpoint"].as_str() {
+                    Some(e) if e.starts_with("http://") || e.starts_with("https://") => {
+                        e.trim_end_matches('/').to_string()
+                    }
+                    _ => return Response::error(400, "endpoint must be an http(s) base URL"),
+                };
+
+                let added = !self.peers.iter().any(|p| p.endpoint == endpoint);
+                if added {
+                    self.peers.push(Peer::new(&endpoint));
+                }
+                Response::json(
+                    if added { 201 } else { 200 },
+                    json!({ "success": true, "endpoint": endpoint, "added": added }),
+                )
+            }
+            "DELETE" => {
+                let Some(endpoint) = req.query("endpoint") else {
+                    return Response::error(400, "endpoint parameter required");
+                };
+                let before = self.peers.len();
+                self.peers.retain(|p| p.endpoint != endpoint);
+                if self.peers.len() == before {
+                    return Response::json(
+                        404,
+                        json!({ "error": "peer not found", "endpoint": endpoint }),
+                    );
+                }
+                Response::json(200, json!({ "success": true, "endpoint": endpoint }))
+            }
+            _ => Response::error(405, "method not allowed"),
+        }
+    }
+
+    fn state_of(&self, ip: &str) -> &str {
+        self.escalation
+            .get(ip)
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_STATE)
+    }
+
+    /// `GET /api/escalation/state?ip=<ip>`
+    fn escalation_state(&self, req: &Request) -> Response {
+        let Some(ip) = req.query("ip") else {
+            return Response::error(400, "ip parameter required");
+        };
+        Response::json(200, self.escalation_entry(ip, Some(ip)))
+    }
+
+    fn escalation_entry(&self, ip: &str, with_ip: Option<&str>) -> Value {
+        let mut entry = json!({
+            "state": self.state_of(ip),
+            "retry_count": 0,
+            "last_check": self.last_sync,
+        });
+        if let Some(ip) = with_ip {
+            entry["ip"] = json!(ip);
+        }
+        entry
+    }
+
+    fn escalation_states(&self) -> Value {
+        let states: serde_json::Map<String, Value> = self
+            .escalation
+            .keys()
+            .map(|ip| (ip.clone(), self.escalation_entry(ip, None)))
+            .collect();
+        Value::Object(states)
+    }
+
+    fn escalation_stats(&self) -> Value {
+        let mut counts: BTreeMap<&str, u32> = STATES.iter().map(|s| (*s, 0)).collect();
+        for state in self.escalation.values() {
+            if let Some(count) = counts.get_mut(state.as_str()) {
+                *count += 1;
+            }
+        }
+        json!({
+            "total": counts.values().sum::<u32>(),
+            "states": counts,
+            "config": {
+                "healthcheck_interval": 10,
+                "latency_warning": 100,
+                "latency_fallback": 500,
+            },
+        })
+    }
+
+    /// `POST /api/escalation/attempt` and `/api/escalation/reset` (`?ip=<ip>`)
+    ///
+    /// Attempts always succeed; script a fault to make them fail.
+    fn escalation_update(&mut self, req: &Request, state: &str) -> Response {
+        if req.method != "POST" {
+            return Response::error(405, "method not allowed");
+        }
+        let Some(ip) = req.query("ip") else {
+            return Response::error(400, "ip parameter required");
+        };
+
+        if state == DEFAULT_STATE {
+            self.escalation.remove(ip);
+        } else {
+            self.escalation.insert(ip.to_string(), state.to_string());
+        }
+        Response::json(200, json!({ "success": true, "ip": ip, "state": state }))
+    }
+
+    /// Route for every registered container, as `routing.get_route` picks it
+    fn routes(&self) -> Value {
+        let routes: serde_json::Map<String, Value> = self
+            .containers
+            .iter()
+            .map(|c| {
+                let state = self.state_of(&c.ip);
+                let route = if c.host_id == self.host_id {
+                    json!({ "type": "local", "target": c.ip, "state": "LOCAL" })
+                } else if state == "P2P_ACTIVE" {
+                    json!({ "type": "p2p", "target": c.ip, "state": state })
+                } else {
+                    let gateway = format!("10.100.{}.1", c.host_id);
+                    json!({ "type": "gateway", "target": gateway, "state": state })
+                };
+                (c.ip.clone(), route)
+            })
+            .collect();
+        Value::Object(routes)
+    }
+
+    fn routing_stats(&self) -> Value {
+        json!({
+            "local_host_id": self.host_id,
+            "gateway_count": self.peers.len(),
+            "default_gateway": "10.100.0.1",
+            "local_subnet": format!("10.100.{}.0/24", self.host_id),
+            "escalation": self.escalation_stats(),
+        })
+    }
+
+    fn healthcheck(&self) -> Value {
+        let active: Vec<&String> = self
+            .escalation
+            .iter()
+            .filter(|(_, state)| *state == "P2P_ACTIVE")
+            .map(|(ip, _)| ip)
+            .collect();
+        let failures: BTreeMap<&String, &u32> = self
+            .health_failures
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .collect();
+        json!({
+            "enabled": self.healthcheck_enabled,
+            "worker_started": self.healthcheck_enabled,
+            "interval": 10,
+            "failure_threshold": self.failure_threshold,
+            "active_p2p_count": active.len(),
+            "active_ips": active,
+            "failure_counts": failures,
+        })
+    }
+
+    /// Prometheus text format, as `metrics.export` writes it
+    fn metrics(&self) -> String {
+        let total: u64 = self.responses.values().sum();
+        let mut out = vec![
+            "# HELP flatnet_gateway_info Gateway information".to_string(),
+            "# TYPE flatnet_gateway_info gauge".to_string(),
+            r#"flatnet_gateway_info{version="1.0.0",phase="4"} 1"#.to_string(),
+            String::new(),
+            "# HELP flatnet_active_connections Current number of active connections".to_string(),
+            "# TYPE flatnet_active_connections gauge".to_string(),
+            "flatnet_active_connections 1".to_string(),
+            String::new(),
+            "# HELP flatnet_http_requests_total Total number of HTTP requests".to_string(),
+            "# TYPE flatnet_http_requests_total counter".to_string(),
+            format!("flatnet_http_requests_total {}", total),
+            String::new(),
+            "# HELP flatnet_http_requests_by_status_total HTTP requests by status code".to_string(),
+            "# TYPE flatnet_http_requests_by_status_total counter".to_string(),
+        ];
+        for (status, count) in &self.responses {
+            out.push(format!(
+                r#"flatnet_http_requests_total{{status="{}"}} {}"#,
+                status, count
+            ));
+        }
+        out.push(String::new());
+        out.join("\n")
+    }
+}
+
+/// Current Unix time in seconds
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,513 @@
+//! In-process mock of the Flatnet Gateway API for tests
+//!
+//! Serves `/api/containers`, `/api/status`, `/api/health`, `/api/sync/*`,
+//! `/api/escalation/*`, `/api/routing/*`, `/api/healthcheck/status` and
+//! `/metrics` from in-memory state on a random local port, the way the
+//! OpenResty handlers answer them. Failures and latency can be scripted per
+//! path, and other paths can be given canned responses, so the same server
+//! also stands in for Prometheus, Loki, Alertmanager or the GitHub API.
+//!
+//! ```no_run
+//! use flatnet_mock_gateway::{Container, Fault, MockGateway};
+//!
+//! let gateway = MockGateway::start();
+//! gateway.add_container(Container::new("abc123", "10.100.2.10", 2));
+//! gateway.fail("/api/sync/peers", Fault::Status(500));
+//! println!("FLATNET_GATEWAY_URL={}", gateway.url());
+//! ```
+
+mod api;
+mod http;
+
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use api::State;
+use http::Response;
+
+pub use http::Request;
+
+/// Host ID of the mock Gateway unless set with `with_host_id`
+pub const DEFAULT_HOST_ID: u8 = 1;
+
+/// A container in the registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Container {
+    pub id: String,
+    pub ip: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ports: Option<Vec<u16>>,
+    /// Host ID (the Gateway defaults it to 1)
+    #[serde(default = "default_host_id")]
+    pub host_id: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// CNI operation ID that registered the container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op_id: Option<String>,
+}
+
+fn default_host_id() -> u8 {
+    1
+}
+
+impl Container {
+    pub fn new(id: &str, ip: &str, host_id: u8) -> Self {
+        Self {
+            id: id.to_string(),
+            ip: ip.to_string(),
+            hostname: None,
+            ports: None,
+            host_id,
+            created_at: None,
+            op_id: None,
+        }
+    }
+
+    pub fn with_hostname(mut self, hostname: &str) -> Self {
+        self.hostname = Some(hostname.to_string());
+        self
+    }
+}
+
+/// Per-peer sync state, as `GET /api/sync/peers` reports it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Peer {
+    pub endpoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_attempt: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub container_count: u32,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+}
+
+impl Peer {
+    /// A peer that has not been pulled yet
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            last_attempt: None,
+            last_success: None,
+            last_error: None,
+            container_count: 0,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// A peer pulled successfully `age_secs` ago
+    pub fn synced(endpoint: &str, age_secs: i64, container_count: u32) -> Self {
+        let at = api::now() - age_secs;
+        Self {
+            last_attempt: Some(at),
+            last_success: Some(at),
+            container_count,
+            ..Self::new(endpoint)
+        }
+    }
+}
+
+/// A scripted failure
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Answer with this status and a JSON error body
+    Status(u16),
+    /// Answer 200 with a body that is not JSON
+    Malformed,
+    /// Close the connection without answering
+    Disconnect,
+}
+
+/// What a rule does to a matching request
+#[derive(Debug, Clone)]
+enum Action {
+    Fault(Fault),
+    Delay(Duration),
+    Respond(Response),
+}
+
+/// A scripted behavior for requests under a path
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Path prefix for faults and delays, exact path for canned responses
+    path: String,
+    action: Action,
+    /// Matching requests left (unlimited if None)
+    remaining: Option<usize>,
+}
+
+impl Rule {
+    fn matches(&self, path: &str) -> bool {
+        match self.action {
+            Action::Respond(_) => path == self.path,
+            _ => path.starts_with(&self.path),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: State,
+    rules: Vec<Rule>,
+    requests: Vec<Request>,
+}
+
+/// Mock Gateway listening on 127.0.0.1; stops when dropped
+pub struct MockGateway {
+    addr: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockGateway {
+    /// Start a mock Gateway on a random port
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind mock Gateway");
+        let addr = listener.local_addr().expect("mock Gateway has no address");
+        let shared = Arc::new(Mutex::new(Shared {
+            state: State::new(DEFAULT_HOST_ID),
+            rules: Vec::new(),
+            requests: Vec::new(),
+        }));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let shared = Arc::clone(&shared);
+            let shutdown = Arc::clone(&shutdown);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let shared = Arc::clone(&shared);
+                    // One thread per connection, so a delayed request does
+                    // not hold up the others
+                    thread::spawn(move || serve(&shared, stream));
+                }
+            })
+        };
+
+        Self {
+            addr,
+            shared,
+            shutdown,
+            thread: Some(thread),
+        }
+    }
+
+    /// Set this Gateway's host ID (default 1)
+    pub fn with_host_id(self, host_id: u8) -> Self {
+        self.lock().state.host_id = host_id;
+        self
+    }
+
+    /// Base URL, e.g. `http://127.0.0.1:41234`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Registry endpoint as the CNI plugin is configured with it
+    pub fn registry_endpoint(&self) -> String {
+        format!("{}/api/containers", self.url())
+    }
+
+    /// Listening address
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Gateway state
+
+    /// Add or replace a registry entry
+    pub fn add_container(&self, container: Container) {
+        let containers = &mut self.lock().state.containers;
+        containers.retain(|c| c.id != container.id);
+        containers.push(container);
+    }
+
+    /// Current registry entries, in registration order
+    pub fn containers(&self) -> Vec<Container> {
+        self.lock().state.containers.clone()
+    }
+
+    /// Add or replace a sync peer
+    pub fn add_peer(&self, peer: Peer) {
+        let peers = &mut self.lock().state.peers;
+        peers.retain(|p| p.endpoint != peer.endpoint);
+        peers.push(peer);
+    }
+
+    /// Current sync peers
+    pub fn peers(&self) -> Vec<Peer> {
+        self.lock().state.peers.clone()
+    }
+
+    /// Make pulls from a peer fail with an error
+    pub fn fail_peer(&self, endpoint: &str, error: &str) {
+        self.lock()
+            .state
+            .failing_peers
+            .insert(endpoint.to_string(), error.to_string());
+    }
+
+    /// Set the sync interval reported in `/api/status` (default 30s)
+    pub fn set_sync_interval(&self, secs: u64) {
+        self.lock().state.sync_interval = secs;
+    }
+
+    /// Set the escalation state of a container IP (e.g., "P2P_ACTIVE")
+    pub fn set_escalation(&self, ip: &str, state: &str) {
+        self.lock()
+            .state
+            .escalation
+            .insert(ip.to_string(), state.to_string());
+    }
+
+    /// Set the consecutive healthcheck failures of a container IP
+    pub fn set_health_failures(&self, ip: &str, failures: u32) {
+        self.lock()
+            .state
+            .health_failures
+            .insert(ip.to_string(), failures);
+    }
+
+    // Scripting
+    //
+    // When several faults or canned responses match a request, the one added
+    // last applies.
+
+    /// Fail every request whose path starts with `path`
+    pub fn fail(&self, path: &str, fault: Fault) {
+        self.add_rule(path, Action::Fault(fault), None);
+    }
+
+    /// Fail the next `times` requests whose path starts with `path`
+    pub fn fail_times(&self, path: &str, fault: Fault, times: usize) {
+        self.add_rule(path, Action::Fault(fault), Some(times));
+    }
+
+    /// Delay every response for paths starting with `path`
+    pub fn delay(&self, path: &str, latency: Duration) {
+        self.add_rule(path, Action::Delay(latency), None);
+    }
+
+    /// Answer requests for exactly `path` with a canned JSON response
+    pub fn respond(&self, path: &str, status: u16, body: serde_json::Value) {
+        self.add_rule(path, Action::Respond(Response::json(status, body)), None);
+    }
+
+    /// Answer requests for exactly `path` with a canned text response
+    pub fn respond_text(&self, path: &str, status: u16, body: &str) {
+        self.add_rule(path, Action::Respond(Response::text(status, body)), None);
+    }
+
+    /// Remove all faults, delays and canned responses
+    pub fn reset_rules(&self) {
+        self.lock().rules.clear();
+    }
+
+    fn add_rule(&self, path: &str, action: Action, remaining: Option<usize>) {
+        self.lock().rules.push(Rule {
+            path: path.to_string(),
+            action,
+            remaining,
+        });
+    }
+
+    // Inspection
+
+    /// Every request received so far
+    pub fn requests(&self) -> Vec<Request> {
+        self.lock().requests.clone()
+    }
+
+    /// Requests received with a method and path
+    pub fn requests_to(&self, method: &str, path: &str) -> Vec<Request> {
+        self.lock()
+            .requests
+            .iter()
+            .filter(|r| r.method == method && r.path == path)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Drop for MockGateway {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the accept loop. A listener in another network namespace
+        // cannot be reached from here; its thread ends with the process.
+        if TcpStream::connect(self.addr).is_ok() {
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Answer one connection
+fn serve(shared: &Mutex<Shared>, stream: TcpStream) {
+    let Some(request) = http::read_request(&stream) else {
+        return;
+    };
+
+    // Decide under the lock, sleep and write outside of it
+    let (delay, outcome) = {
+        let mut shared = shared.lock().unwrap_or_else(|e| e.into_inner());
+        shared.requests.push(request.clone());
+
+        let mut delay = Duration::ZERO;
+        let mut scripted = None;
+        // Delays add up; of the faults and canned responses, the most
+        // recently added one wins
+        for rule in shared.rules.iter_mut().rev() {
+            if !rule.matches(&request.path) || rule.remaining == Some(0) {
+                continue;
+            }
+            match &rule.action {
+                Action::Delay(latency) => delay += *latency,
+                action if scripted.is_none() => scripted = Some(action.clone()),
+                _ => continue,
+            }
+            if let Some(remaining) = rule.remaining.as_mut() {
+                *remaining -= 1;
+            }
+        }
+
+        let outcome = match scripted {
+            Some(Action::Fault(Fault::Disconnect)) => None,
+            Some(Action::Fault(Fault::Status(status))) => {
+                Some(Response::error(status, "scripted failure"))
+            }
+            Some(Action::Fault(Fault::Malformed)) => {
+                Some(Response::text(200, "<html>not json</html>"))
+            }
+            Some(Action::Respond(response)) => Some(response),
+            _ => Some(shared.state.handle(&request)),
+        };
+        if let Some(response) = &outcome {
+            *shared.state.responses.entry(response.status).or_default() += 1;
+        }
+        (delay, outcome)
+    };
+
+    if !delay.is_zero() {
+        thread::sleep(delay);
+    }
+    if let Some(response) = outcome {
+        http::write_response(&stream, &response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    /// Send a raw request and return the status code and body
+    fn send(gateway: &MockGateway, method: &str, target: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(gateway.addr()).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: x\r\nX-Flatnet-Operation-ID: op1\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            target,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or("").to_string();
+        (status, body)
+    }
+
+    #[test]
+    fn test_registry_round_trip() {
+        let gateway = MockGateway::start();
+        let (status, _) = send(
+            &gateway,
+            "POST",
+            "/api/containers",
+            r#"{"id":"abc","ip":"10.100.1.10","hostId":1}"#,
+        );
+        assert_eq!(status, 201);
+        assert_eq!(gateway.containers()[0].op_id.as_deref(), Some("op1"));
+
+        let (_, body) = send(&gateway, "GET", "/api/status", "");
+        let status: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(status["registry"]["count"], 1);
+
+        assert_eq!(send(&gateway, "DELETE", "/api/containers/abc", "").0, 200);
+        assert_eq!(send(&gateway, "DELETE", "/api/containers/abc", "").0, 200);
+        assert!(gateway.containers().is_empty());
+        assert_eq!(
+            gateway.requests_to("DELETE", "/api/containers/abc").len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_sync_peers() {
+        let gateway = MockGateway::start();
+        let body = r#"{"endpoint":"http://10.100.2.1:8080/"}"#;
+        assert_eq!(send(&gateway, "POST", "/api/sync/peers", body).0, 201);
+        assert_eq!(send(&gateway, "POST", "/api/sync/peers", body).0, 200);
+
+        gateway.fail_peer("http://10.100.2.1:8080", "connection refused");
+        let (_, body) = send(&gateway, "POST", "/api/sync/pull", "");
+        assert!(body.contains(r#""failure_count":1"#));
+        assert_eq!(gateway.peers()[0].consecutive_failures, 1);
+
+        let target = "/api/sync/peers?endpoint=http%3A%2F%2F10.100.2.1%3A8080";
+        assert_eq!(send(&gateway, "DELETE", target, "").0, 200);
+        assert_eq!(send(&gateway, "DELETE", target, "").0, 404);
+    }
+
+    #[test]
+    fn test_scripted_faults() {
+        let gateway = MockGateway::start();
+        gateway.fail_times("/api/", Fault::Status(503), 1);
+        gateway.respond("/api/v2/alerts", 200, serde_json::json!([]));
+
+        assert_eq!(send(&gateway, "GET", "/api/health", "").0, 503);
+        assert_eq!(send(&gateway, "GET", "/api/health", "").0, 200);
+        assert_eq!(
+            send(&gateway, "GET", "/api/v2/alerts", ""),
+            (200, "[]\n".to_string())
+        );
+        // The later fault overrides the canned response
+        gateway.fail("/api/v2", Fault::Status(500));
+        assert_eq!(send(&gateway, "GET", "/api/v2/alerts", "").0, 500);
+
+        gateway.fail("/metrics", Fault::Malformed);
+        assert!(send(&gateway, "GET", "/metrics", "").1.contains("not json"));
+        gateway.reset_rules();
+        let (_, metrics) = send(&gateway, "GET", "/metrics", "");
+        assert!(metrics.contains(r#"flatnet_http_requests_total{status="503"} 1"#));
+        assert!(metrics.contains(r#"flatnet_http_requests_total{status="500"} 1"#));
+    }
+}
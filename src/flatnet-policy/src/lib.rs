@@ -0,0 +1,222 @@
+//! Network policy pieces shared by flatnet-cli and flatnet-cni
+//!
+//! `flatnet policy apply` writes the definitions of the policy's container
+//! sets to a state file, and flatnet-cni reads it on ADD to put a new
+//! container into the sets that select it. Both sides use the types and the
+//! selector matching here, so a container joins exactly the sets the CLI
+//! would have put it in. Both also load their nftables scripts with
+//! [`nft::run`].
+
+pub mod nft;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// Makes temp file names unique within the process
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Container set definition, as written to the state file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerSet {
+    pub name: String,
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl ContainerSet {
+    /// Whether a container with this name and these labels belongs in the set
+    pub fn matches(&self, name: Option<&str>, labels: &BTreeMap<String, String>) -> bool {
+        selects_container(&self.names, &self.labels, name, labels)
+    }
+}
+
+/// State file written by `flatnet policy apply` and read by flatnet-cni
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyState {
+    pub table: String,
+    #[serde(default)]
+    pub container_sets: Vec<ContainerSet>,
+}
+
+impl PolicyState {
+    /// Write the state file atomically
+    ///
+    /// Writes a uniquely named temp file in the same directory, syncs it,
+    /// renames it over `path` and syncs the directory, so a crash leaves
+    /// either the old or the new state and concurrent writers never share
+    /// a temp file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)?;
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("policy.json");
+        let tmp_path = dir.join(format!(
+            ".{}.{}.{}.tmp",
+            name,
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let written = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(json.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp_path, path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+
+        File::open(dir)?.sync_all()
+    }
+}
+
+/// Whether a container matches name globs and labels
+///
+/// It matches if its name matches one of `names` (when given) and it has
+/// all of `labels` (when given). With neither, no container matches.
+pub fn selects_container(
+    names: &[String],
+    labels: &BTreeMap<String, String>,
+    name: Option<&str>,
+    container_labels: &BTreeMap<String, String>,
+) -> bool {
+    let name_matches =
+        names.is_empty() || name.is_some_and(|name| names.iter().any(|g| glob_match(g, name)));
+    (!names.is_empty() || !labels.is_empty())
+        && name_matches
+        && labels
+            .iter()
+            .all(|(key, value)| container_labels.get(key) == Some(value))
+}
+
+/// Match `*` (any run) and `?` (any one character) globs
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("web-*", "web-1"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(glob_match("db?", "db1"));
+        assert!(!glob_match("db?", "db"));
+        assert!(!glob_match("web-*", "api-web-1"));
+        assert!(!glob_match("a*b", "acbc"));
+    }
+
+    #[test]
+    fn test_container_set_matches() {
+        let state: PolicyState = serde_json::from_str(
+            r#"{
+                "table": "flatnet_policy",
+                "container_sets": [
+                    {"name": "c0", "names": ["db", "db-*"], "labels": {}},
+                    {"name": "c1", "names": [], "labels": {"app": "web", "tier": "front"}},
+                    {"name": "c3", "names": ["api-?"], "labels": {"app": "api"}},
+                    {"name": "c4"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let labels = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let joined = |name: Option<&str>, labels: BTreeMap<String, String>| -> Vec<&str> {
+            state
+                .container_sets
+                .iter()
+                .filter(|set| set.matches(name, &labels))
+                .map(|set| set.name.as_str())
+                .collect()
+        };
+
+        assert_eq!(joined(Some("db-1"), labels(&[])), vec!["c0"]);
+        assert_eq!(joined(None, labels(&[])), Vec::<&str>::new());
+        assert_eq!(
+            joined(None, labels(&[("app", "web"), ("tier", "front")])),
+            vec!["c1"]
+        );
+        assert!(joined(None, labels(&[("app", "web")])).is_empty());
+        assert_eq!(joined(Some("api-1"), labels(&[("app", "api")])), vec!["c3"]);
+        assert!(joined(Some("api-10"), labels(&[("app", "api")])).is_empty());
+        assert!(joined(Some("api-1"), labels(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_save() {
+        let dir = std::env::temp_dir().join(format!("flatnet-policy-save-{}", std::process::id()));
+        let path = dir.join("state").join("policy.json");
+        let state = PolicyState {
+            table: "flatnet_policy".to_string(),
+            container_sets: vec![ContainerSet {
+                name: "c0".to_string(),
+                names: vec!["db".to_string()],
+                labels: BTreeMap::new(),
+            }],
+        };
+
+        state.save(&path).unwrap();
+        let mut state2 = state.clone();
+        state2.container_sets.clear();
+        state2.save(&path).unwrap();
+
+        let saved: PolicyState = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved, state2);
+        // No temp files left behind
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
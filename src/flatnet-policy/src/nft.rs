@@ -0,0 +1,30 @@
+//! nftables script runner
+//!
+//! Scripts go to `nft -f -`, which applies them as one transaction.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Run a script with `nft -f -`
+///
+/// If nft exits with an error, the error message is nft's stderr.
+pub fn run(script: &str) -> io::Result<()> {
+    let mut child = Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to execute nft: {}", e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(script.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}